@@ -0,0 +1,89 @@
+//! Command to back up the catalog, snapshots, parquet files and WAL files of a host
+
+use std::{error::Error, path::PathBuf, sync::Arc};
+
+use clap_blocks::object_store::{make_object_store, ObjectStoreConfig};
+use influxdb3_write::backup::{backup, BackupManifest};
+use object_store::{local::LocalFileSystem, prefix::PrefixStore, ObjectStore};
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    /// object store options for the host being backed up
+    #[clap(flatten)]
+    object_store_config: ObjectStoreConfig,
+
+    /// The host identifier prefix of the host to back up
+    #[clap(long = "host-id", env = "INFLUXDB3_HOST_IDENTIFIER_PREFIX", action)]
+    host_identifier_prefix: String,
+
+    #[clap(flatten)]
+    backup_store_config: BackupStoreConfig,
+
+    /// Copy every object, rather than only those that were not copied by the previous backup
+    #[clap(long = "full", action)]
+    full: bool,
+}
+
+/// Where backups are stored: either a local directory, or a prefix in the object store of the
+/// host
+#[derive(Debug, clap::Parser)]
+#[group(required = true, multiple = false)]
+pub struct BackupStoreConfig {
+    /// The local directory that backups are stored in
+    #[clap(long = "backup-dir", action)]
+    backup_dir: Option<PathBuf>,
+
+    /// The prefix in the host's object store that backups are stored under
+    #[clap(long = "backup-prefix", action)]
+    backup_prefix: Option<String>,
+}
+
+impl BackupStoreConfig {
+    pub(crate) fn backup_store(
+        &self,
+        object_store: Arc<dyn ObjectStore>,
+    ) -> Result<Arc<dyn ObjectStore>, Box<dyn Error>> {
+        match (&self.backup_dir, &self.backup_prefix) {
+            (Some(dir), _) => {
+                std::fs::create_dir_all(dir)?;
+                Ok(Arc::new(LocalFileSystem::new_with_prefix(dir)?))
+            }
+            (None, Some(prefix)) => Ok(Arc::new(PrefixStore::new(object_store, prefix.as_str()))),
+            (None, None) => Err("one of --backup-dir or --backup-prefix is required".into()),
+        }
+    }
+}
+
+pub(crate) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let object_store = make_object_store(&config.object_store_config)?;
+    let backup_store = config
+        .backup_store_config
+        .backup_store(Arc::clone(&object_store))?;
+
+    let manifest = backup(
+        object_store,
+        &config.host_identifier_prefix,
+        backup_store,
+        config.full,
+    )
+    .await?;
+    print_manifest(&manifest);
+
+    Ok(())
+}
+
+pub(crate) fn print_manifest(manifest: &BackupManifest) {
+    let size_bytes: u64 = manifest.files.iter().map(|f| f.size_bytes).sum();
+    println!(
+        "backup {} of host {}: catalog sequence number {}, snapshot sequence number {}, \
+        {} files ({size_bytes} bytes)",
+        manifest.backup_sequence_number,
+        manifest.host_identifier_prefix,
+        manifest.catalog_sequence_number.as_u32(),
+        manifest
+            .snapshot_sequence_number
+            .map(|s| s.as_u64().to_string())
+            .unwrap_or_else(|| "-".to_string()),
+        manifest.files.len(),
+    );
+}
@@ -0,0 +1,80 @@
+//! Command to check the consistency of the catalog, snapshots, parquet files and WAL files of a
+//! host, and optionally repair the problems found
+
+use std::{error::Error, sync::Arc};
+
+use clap_blocks::object_store::{make_object_store, ObjectStoreConfig};
+use influxdb3_write::check::{check, repair};
+use iox_time::{SystemProvider, TimeProvider};
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    /// object store options for the host being checked
+    #[clap(flatten)]
+    object_store_config: ObjectStoreConfig,
+
+    /// The host identifier prefix of the host to check
+    #[clap(long = "host-id", env = "INFLUXDB3_HOST_IDENTIFIER_PREFIX", action)]
+    host_identifier_prefix: String,
+
+    /// Repair the problems that are found. The server for the host must not be running, which
+    /// has to be confirmed with --server-stopped.
+    #[clap(long = "repair", action)]
+    repair: bool,
+
+    /// Confirm that the server for the host is not running, which is required to --repair, as
+    /// repairs made while it is running can delete files that it is still using
+    #[clap(long = "server-stopped", action)]
+    server_stopped: bool,
+
+    /// Parquet files that are not referenced by any snapshot are only reported as orphaned once
+    /// they are older than this, as they may belong to a snapshot that is still being persisted
+    #[clap(long = "min-orphan-age", default_value = "1h", action)]
+    min_orphan_age: humantime::Duration,
+}
+
+pub(crate) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let object_store = make_object_store(&config.object_store_config)?;
+    let report = check(
+        Arc::clone(&object_store),
+        &config.host_identifier_prefix,
+        config.min_orphan_age.into(),
+        SystemProvider::new().now(),
+    )
+    .await?;
+
+    for problem in &report.problems {
+        println!("{problem}");
+    }
+    println!(
+        "checked {} catalogs, {} snapshots, {} parquet files and {} WAL files: {} problems found",
+        report.catalogs_checked,
+        report.snapshots_checked,
+        report.parquet_files_checked,
+        report.wal_files_checked,
+        report.problems.len(),
+    );
+    if report.problems.is_empty() {
+        return Ok(());
+    }
+
+    if !config.repair {
+        return Err(format!(
+            "{} problems found, stop the server and run with --repair --server-stopped to fix them",
+            report.problems.len()
+        )
+        .into());
+    }
+    if !config.server_stopped {
+        return Err(
+            "stop the server for the host, then confirm that it is not running with \
+             --server-stopped to repair the problems"
+                .into(),
+        );
+    }
+    for action in repair(object_store, &config.host_identifier_prefix, &report).await? {
+        println!("repaired: {action}");
+    }
+
+    Ok(())
+}
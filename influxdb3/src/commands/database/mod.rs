@@ -0,0 +1,31 @@
+use std::error::Error;
+
+pub mod create;
+pub mod delete;
+pub mod retention;
+
+#[derive(Debug, clap::Parser)]
+pub(crate) struct Config {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// Create a new, empty database
+    Create(create::Config),
+
+    /// Delete a database, along with all of its tables and data
+    Delete(delete::Config),
+
+    /// Set how long data is retained in a database
+    Retention(retention::Config),
+}
+
+pub(crate) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    match config.command {
+        Command::Create(config) => create::command(config).await,
+        Command::Delete(config) => delete::command(config).await,
+        Command::Retention(config) => retention::command(config).await,
+    }
+}
@@ -0,0 +1,59 @@
+use std::error::Error;
+
+use secrecy::ExposeSecret;
+
+use crate::commands::common::InfluxDb3Config;
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(flatten)]
+    influxdb3_config: InfluxDb3Config,
+
+    #[clap(flatten)]
+    retention: RetentionConfig,
+}
+
+/// The retention period to set: either a duration, or none, to retain data indefinitely
+#[derive(Debug, clap::Parser)]
+#[group(required = true, multiple = false)]
+pub struct RetentionConfig {
+    /// How long data is retained in the database, e.g., "30d"
+    #[clap(long = "retention-period")]
+    retention_period: Option<humantime::Duration>,
+
+    /// Retain data in the database indefinitely, clearing its retention period
+    #[clap(long = "indefinite", action)]
+    indefinite: bool,
+}
+
+pub(super) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let InfluxDb3Config {
+        host_url,
+        database_name,
+        auth_token,
+    } = config.influxdb3_config;
+    let mut client = influxdb3_client::Client::new(host_url)?;
+    if let Some(t) = auth_token {
+        client = client.with_auth_token(t.expose_secret());
+    }
+    let RetentionConfig {
+        retention_period,
+        indefinite,
+    } = config.retention;
+    let retention_period = retention_period.filter(|_| !indefinite);
+    client
+        .api_v3_configure_database_retention_period(
+            &database_name,
+            retention_period.map(|d| d.as_secs()),
+        )
+        .await?;
+
+    match retention_period {
+        Some(retention_period) => {
+            println!("retention period of database {database_name} set to {retention_period}")
+        }
+        None => println!("data in database {database_name} is retained indefinitely"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Command to export the data of a database's tables from a running server as line protocol
+
+use clap::Parser;
+use secrecy::ExposeSecret;
+use tokio::{
+    fs::File,
+    io::{self, AsyncWrite, AsyncWriteExt},
+};
+
+use super::common::InfluxDb3Config;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)]
+    Client(#[from] influxdb3_client::Error),
+
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Parser)]
+pub struct Config {
+    /// Common InfluxDB 3.0 config
+    #[clap(flatten)]
+    influxdb3_config: InfluxDb3Config,
+
+    /// The table to export, can be given more than once. All tables in the database are
+    /// exported if none are given.
+    #[clap(short = 't', long = "table")]
+    tables: Vec<String>,
+
+    /// Only export rows at or after this time, either an RFC3339 timestamp or nanoseconds
+    /// since the epoch
+    #[clap(long = "start")]
+    start: Option<String>,
+
+    /// Only export rows before this time, either an RFC3339 timestamp or nanoseconds since the
+    /// epoch
+    #[clap(long = "end")]
+    end: Option<String>,
+
+    /// Write the line protocol gzip-compressed
+    #[clap(long = "gzip")]
+    gzip: bool,
+
+    /// The file to write the line protocol to, defaults to stdout
+    #[clap(short = 'o', long = "output")]
+    output_file_path: Option<String>,
+}
+
+pub(crate) async fn command(config: Config) -> Result<()> {
+    let InfluxDb3Config {
+        host_url,
+        database_name,
+        auth_token,
+    } = config.influxdb3_config;
+    let mut client = influxdb3_client::Client::new(host_url)?;
+    if let Some(t) = auth_token {
+        client = client.with_auth_token(t.expose_secret());
+    }
+
+    let mut req = client.api_v3_export(database_name).gzip(config.gzip);
+    for table in config.tables {
+        req = req.table(table);
+    }
+    if let Some(start) = config.start {
+        req = req.start(start);
+    }
+    if let Some(end) = config.end {
+        req = req.end(end);
+    }
+    let mut resp = req.send().await?;
+
+    // the export is streamed, so write each chunk out as it arrives, rather than buffering
+    // the whole export in memory:
+    let mut out: Box<dyn AsyncWrite + Unpin> = match config.output_file_path {
+        Some(path) => Box::new(File::create(path).await?),
+        None => Box::new(io::stdout()),
+    };
+    while let Some(chunk) = resp.chunk().await? {
+        out.write_all(&chunk).await?;
+    }
+    out.flush().await?;
+
+    Ok(())
+}
@@ -0,0 +1,201 @@
+//! Command to bulk import Parquet or CSV files into a table of a running server
+
+use std::{path::Path, str::FromStr};
+
+use clap::{Parser, ValueEnum};
+use influxdb3_client::{FieldType, ImportFormat, Precision};
+use secrecy::ExposeSecret;
+use tokio::{fs, io};
+
+use super::common::{InfluxDb3Config, SeparatedList};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)]
+    Client(#[from] influxdb3_client::Error),
+
+    #[error("error reading file {path}: {source}")]
+    Io { path: String, source: io::Error },
+
+    #[error(
+        "the format of {0} could not be determined from its extension, \
+        provide it with --format"
+    )]
+    UnknownFormat(String),
+}
+
+pub(crate) type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Parser)]
+pub struct Config {
+    /// Common InfluxDB 3.0 config
+    #[clap(flatten)]
+    influxdb3_config: InfluxDb3Config,
+
+    /// The table to import the files into
+    #[clap(short = 't', long = "table")]
+    table: String,
+
+    /// The format of the files, inferred from their extension if not given
+    #[clap(long = "format")]
+    format: Option<FormatArg>,
+
+    /// The columns of the files to import as tags
+    #[clap(long = "tags")]
+    tags: Option<SeparatedList<String>>,
+
+    /// The columns of the files to import as fields, each optionally followed by ':' and a type
+    /// of string, integer, uinteger, float, or boolean, e.g., `usage:float,count`. Fields
+    /// without a type take their type from the table, if it already exists, or from the file.
+    ///
+    /// Required for CSV files, of which only the tags, fields and time column are imported. All
+    /// columns of Parquet files are imported.
+    #[clap(long = "fields")]
+    fields: Option<SeparatedList<FieldArg>>,
+
+    /// The column of the files holding the time of each row
+    #[clap(long = "time-column", default_value = "time")]
+    time_column: String,
+
+    /// The precision of integer times in the files
+    #[clap(long = "precision", default_value = "ns")]
+    precision: PrecisionArg,
+
+    /// The files to import
+    #[clap(required = true, num_args = 1..)]
+    file_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Parquet,
+    Csv,
+}
+
+impl From<FormatArg> for ImportFormat {
+    fn from(format: FormatArg) -> Self {
+        match format {
+            FormatArg::Parquet => Self::Parquet,
+            FormatArg::Csv => Self::Csv,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PrecisionArg {
+    #[value(name = "s")]
+    Second,
+    #[value(name = "ms")]
+    Millisecond,
+    #[value(name = "us")]
+    Microsecond,
+    #[value(name = "ns")]
+    Nanosecond,
+}
+
+impl From<PrecisionArg> for Precision {
+    fn from(precision: PrecisionArg) -> Self {
+        match precision {
+            PrecisionArg::Second => Self::Second,
+            PrecisionArg::Millisecond => Self::Millisecond,
+            PrecisionArg::Microsecond => Self::Microsecond,
+            PrecisionArg::Nanosecond => Self::Nanosecond,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FieldArg {
+    name: String,
+    field_type: Option<FieldType>,
+}
+
+impl FromStr for FieldArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let Some((name, field_type)) = s.rsplit_once(':') else {
+            return Ok(Self {
+                name: s.to_string(),
+                field_type: None,
+            });
+        };
+        let field_type = match field_type {
+            "string" => FieldType::String,
+            "integer" => FieldType::Integer,
+            "uinteger" => FieldType::UInteger,
+            "float" => FieldType::Float,
+            "boolean" | "bool" => FieldType::Boolean,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "invalid field type '{field_type}', expected one of string, integer, \
+                    uinteger, float, or boolean"
+                ))
+            }
+        };
+        Ok(Self {
+            name: name.to_string(),
+            field_type: Some(field_type),
+        })
+    }
+}
+
+pub(crate) async fn command(config: Config) -> Result<()> {
+    let InfluxDb3Config {
+        host_url,
+        database_name,
+        auth_token,
+    } = config.influxdb3_config;
+    let mut client = influxdb3_client::Client::new(host_url)?;
+    if let Some(t) = auth_token {
+        client = client.with_auth_token(t.expose_secret());
+    }
+    let tags: Vec<String> = config.tags.into_iter().flatten().collect();
+    let fields: Vec<FieldArg> = config.fields.into_iter().flatten().collect();
+
+    let (mut row_count, mut skipped_row_count) = (0, 0);
+    for path in config.file_paths {
+        let format = match config.format {
+            Some(format) => format,
+            None => format_from_extension(&path)?,
+        };
+        let body = fs::read(&path).await.map_err(|source| Error::Io {
+            path: path.clone(),
+            source,
+        })?;
+
+        let mut req = client
+            .api_v3_import(&database_name, &config.table)
+            .format(format.into())
+            .time_column(&config.time_column)
+            .precision(config.precision.into());
+        for tag in &tags {
+            req = req.tag(tag);
+        }
+        for FieldArg { name, field_type } in &fields {
+            req = req.field(name, *field_type);
+        }
+        let summary = req.body(body).send().await?;
+
+        println!(
+            "imported {} rows from {path}, skipped {}",
+            summary.row_count, summary.skipped_row_count
+        );
+        row_count += summary.row_count;
+        skipped_row_count += summary.skipped_row_count;
+    }
+    println!(
+        "imported {row_count} rows into table {}, skipped {skipped_row_count}",
+        config.table
+    );
+
+    Ok(())
+}
+
+fn format_from_extension(path: &str) -> Result<FormatArg> {
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("parquet") => Ok(FormatArg::Parquet),
+        Some("csv") => Ok(FormatArg::Csv),
+        _ => Err(Error::UnknownFormat(path.to_string())),
+    }
+}
@@ -0,0 +1,113 @@
+use std::error::Error;
+
+use secrecy::ExposeSecret;
+
+use crate::commands::common::{InfluxDb3Config, SeparatedList};
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(flatten)]
+    influxdb3_config: InfluxDb3Config,
+
+    #[clap(flatten)]
+    meta_cache_config: MetaCacheConfig,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct MetaCacheConfig {
+    /// The table name for which the cache is being created
+    #[clap(short = 't', long = "table")]
+    table: String,
+
+    /// Give a name for the cache.
+    #[clap(long = "cache-name")]
+    cache_name: Option<String>,
+
+    /// Which columns in the table to cache distinct values for, as a comma-separated list of the
+    /// column names.
+    ///
+    /// The cache is a hierarchical structure, with a level for each column specified; the order
+    /// specified here will determine the order of the levels from top-to-bottom of the cache
+    /// hierarchy.
+    #[clap(long = "columns")]
+    columns: SeparatedList<String>,
+
+    /// The maximum number of distinct value combinations to hold in the cache
+    #[clap(long = "max-cardinality")]
+    max_cardinality: Option<usize>,
+
+    /// The maximum age of an entry in the cache, in seconds
+    #[clap(long = "max-age")]
+    max_age: Option<u64>,
+}
+
+pub(super) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let InfluxDb3Config {
+        host_url,
+        database_name,
+        auth_token,
+    } = config.influxdb3_config;
+    let MetaCacheConfig {
+        table,
+        cache_name,
+        columns,
+        max_cardinality,
+        max_age,
+    } = config.meta_cache_config;
+    let mut client = influxdb3_client::Client::new(host_url)?;
+    if let Some(t) = auth_token {
+        client = client.with_auth_token(t.expose_secret());
+    }
+    let mut b = client.api_v3_configure_meta_cache_create(database_name, table, columns);
+
+    // Add optional parameters:
+    if let Some(name) = cache_name {
+        b = b.name(name);
+    }
+    if let Some(max_cardinality) = max_cardinality {
+        b = b.max_cardinality(max_cardinality);
+    }
+    if let Some(max_age) = max_age {
+        b = b.max_age(max_age);
+    }
+
+    // Make the request:
+    match b.send().await? {
+        Some(def) => println!(
+            "new cache created: {}",
+            serde_json::to_string_pretty(&def).expect("serialize meta cache definition as JSON")
+        ),
+        None => println!("a cache already exists for the provided parameters"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use crate::commands::meta_cache::create::MetaCacheConfig;
+
+    #[test]
+    fn parse_args() {
+        let args = MetaCacheConfig::parse_from([
+            "meta_cache_create",
+            "--table",
+            "foo",
+            "--cache-name",
+            "bar",
+            "--columns",
+            "tag1,tag2,tag3",
+            "--max-cardinality",
+            "1000",
+            "--max-age",
+            "3600",
+        ]);
+        assert_eq!("foo", args.table);
+        assert!(args.cache_name.is_some_and(|n| n == "bar"));
+        assert_eq!(["tag1", "tag2", "tag3"], args.columns.0.as_slice());
+        assert!(args.max_cardinality.is_some_and(|c| c == 1000));
+        assert!(args.max_age.is_some_and(|a| a == 3600));
+    }
+}
@@ -1,6 +1,9 @@
+use std::fmt::Write as _;
 use std::str::Utf8Error;
+use std::time::Duration;
 
 use clap::{Parser, ValueEnum};
+use influxdb3_client::{ExplainAnalyze, PlanNode};
 use secrecy::ExposeSecret;
 use tokio::{
     fs::OpenOptions,
@@ -35,7 +38,10 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(Debug, Parser)]
 #[clap(visible_alias = "q", trailing_var_arg = true)]
 pub struct Config {
-    /// Common InfluxDB 3.0 config
+    #[clap(subcommand)]
+    cmd: Option<SubCommand>,
+
+    /// Common InfluxDB 3.0 config, which is also used by the subcommands
     #[clap(flatten)]
     influxdb3_config: InfluxDb3Config,
 
@@ -59,15 +65,37 @@ pub struct Config {
     #[clap(short = 'o', long = "output")]
     output_file_path: Option<String>,
 
+    /// Run the query to completion, and print the plan that was executed, with the metrics
+    /// from its execution, instead of the results of the query
+    #[clap(long = "explain", conflicts_with = "output_format")]
+    explain: bool,
+
     /// The query string to execute
     query: Vec<String>,
 }
 
+#[derive(Debug, clap::Subcommand)]
+enum SubCommand {
+    /// Kill a running query, which is then shown as cancelled in `system.queries`
+    ///
+    /// The host, token and database name are given before the subcommand, as for a query, e.g.,
+    /// `influxdb3 query --dbname foo kill <ID>`
+    Kill(KillConfig),
+}
+
+#[derive(Debug, Parser)]
+struct KillConfig {
+    /// The ID of the query to kill, as shown in the `id` column of `system.queries`
+    query_id: String,
+}
+
 #[derive(Debug, ValueEnum, Clone)]
 #[clap(rename_all = "snake_case")]
 enum Format {
     Pretty,
     Json,
+    #[clap(alias = "jsonl")]
+    JsonLines,
     Csv,
     Parquet,
 }
@@ -83,6 +111,7 @@ impl From<Format> for influxdb3_client::Format {
         match this {
             Format::Pretty => Self::Pretty,
             Format::Json => Self::Json,
+            Format::JsonLines => Self::JsonLines,
             Format::Csv => Self::Csv,
             Format::Parquet => Self::Parquet,
         }
@@ -105,9 +134,28 @@ pub(crate) async fn command(config: Config) -> Result<()> {
     if let Some(t) = auth_token {
         client = client.with_auth_token(t.expose_secret());
     }
+    if let Some(SubCommand::Kill(config)) = config.cmd {
+        return kill(&client, config).await;
+    }
 
     let query = parse_query(config.query)?;
 
+    if config.explain {
+        let explain = match config.language {
+            QueryLanguage::Sql => client.api_v3_query_sql(database_name, query),
+            QueryLanguage::Influxql => client.api_v3_query_influxql(database_name, query),
+        }
+        .explain_analyze()
+        .await?;
+        let output = format_explain(&explain);
+        if let Some(path) = &config.output_file_path {
+            tokio::fs::write(path, output).await?;
+        } else {
+            print!("{output}");
+        }
+        return Ok(());
+    }
+
     // make the query using the client
     let mut resp_bytes = match config.language {
         QueryLanguage::Sql => {
@@ -145,6 +193,57 @@ pub(crate) async fn command(config: Config) -> Result<()> {
     Ok(())
 }
 
+async fn kill(client: &influxdb3_client::Client, config: KillConfig) -> Result<()> {
+    client.api_v3_query_kill(&config.query_id).await?;
+    println!("Query {} killed", config.query_id);
+
+    Ok(())
+}
+
+/// Format the plan returned by `explain=analyze` as a tree of operators, each followed by the
+/// metrics from its execution
+fn format_explain(explain: &ExplainAnalyze) -> String {
+    let mut output = format!(
+        "rows: {}, elapsed: {}, buffer chunks: {}, parquet chunks: {}\n\n",
+        explain.rows,
+        humantime::format_duration(Duration::from_nanos(explain.elapsed_ns)),
+        explain.buffer_chunks,
+        explain.parquet_chunks,
+    );
+    format_plan_node(&mut output, &explain.plan, 0);
+    output
+}
+
+fn format_plan_node(output: &mut String, node: &PlanNode, depth: usize) {
+    let indent = depth * 2;
+    writeln!(output, "{:indent$}{}", "", node.details).unwrap();
+
+    let mut metrics = vec![];
+    if let Some(rows) = node.output_rows {
+        metrics.push(format!("output_rows={rows}"));
+    }
+    if let Some(nanos) = node.elapsed_compute_ns {
+        let elapsed = humantime::format_duration(Duration::from_nanos(nanos as u64));
+        metrics.push(format!("elapsed_compute={elapsed}"));
+    }
+    if let Some(bytes) = node.bytes_scanned {
+        metrics.push(format!("bytes_scanned={bytes}"));
+    }
+    if let Some(chunks) = node.buffer_chunks {
+        metrics.push(format!("buffer_chunks={chunks}"));
+    }
+    if let Some(chunks) = node.parquet_chunks {
+        metrics.push(format!("parquet_chunks={chunks}"));
+    }
+    if !metrics.is_empty() {
+        writeln!(output, "{:indent$}  metrics: {}", "", metrics.join(", ")).unwrap();
+    }
+
+    for child in &node.children {
+        format_plan_node(output, child, depth + 1);
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum QueryError {
     #[error("no query provided")]
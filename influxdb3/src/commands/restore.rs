@@ -0,0 +1,65 @@
+//! Command to restore a backup taken with `influxdb3 backup` into a new host
+
+use std::{error::Error, sync::Arc};
+
+use clap_blocks::object_store::{make_object_store, ObjectStoreConfig};
+use influxdb3_write::backup::{list_backups, restore};
+
+use super::backup::{print_manifest, BackupStoreConfig};
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    /// object store options for the host being restored
+    #[clap(flatten)]
+    object_store_config: ObjectStoreConfig,
+
+    /// The host identifier prefix to restore the backup as, which must not have any objects
+    /// in the object store yet
+    #[clap(
+        long = "host-id",
+        env = "INFLUXDB3_HOST_IDENTIFIER_PREFIX",
+        required_unless_present = "list",
+        action
+    )]
+    host_identifier_prefix: Option<String>,
+
+    #[clap(flatten)]
+    backup_store_config: BackupStoreConfig,
+
+    /// The sequence number of the backup to restore, defaults to the latest backup
+    #[clap(long = "backup", action)]
+    backup_sequence_number: Option<u64>,
+
+    /// List the backups in the backup store instead of restoring one
+    #[clap(long = "list", action)]
+    list: bool,
+}
+
+pub(crate) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let object_store = make_object_store(&config.object_store_config)?;
+    let backup_store = config
+        .backup_store_config
+        .backup_store(Arc::clone(&object_store))?;
+
+    if config.list {
+        for manifest in list_backups(backup_store.as_ref()).await? {
+            print_manifest(&manifest);
+        }
+        return Ok(());
+    }
+
+    let host_identifier_prefix = config
+        .host_identifier_prefix
+        .ok_or("--host-id is required to restore a backup")?;
+    let manifest = restore(
+        backup_store,
+        config.backup_sequence_number,
+        object_store,
+        &host_identifier_prefix,
+    )
+    .await?;
+    print!("restored as host {host_identifier_prefix} from ");
+    print_manifest(&manifest);
+
+    Ok(())
+}
@@ -8,22 +8,29 @@ use clap_blocks::{
     tokio::TokioDatafusionConfig,
 };
 use datafusion_util::config::register_iox_object_store;
+use influxdb3_cache::meta_cache::MetaCacheProvider;
 use influxdb3_process::{
     build_malloc_conf, setup_metric_registry, INFLUXDB3_GIT_HASH, INFLUXDB3_VERSION, PROCESS_UUID,
 };
 use influxdb3_server::{
-    auth::AllOrNothingAuthorizer,
+    auth::CatalogAuthorizer,
     builder::ServerBuilder,
-    query_executor::{CreateQueryExecutorArgs, QueryExecutorImpl},
+    query_executor::{
+        CreateQueryExecutorArgs, QueryExecutorImpl, QueryLimitBehavior, SlowQueryLogConfig,
+        SlowQuerySink,
+    },
     serve, CommonServerState,
 };
 use influxdb3_telemetry::store::TelemetryStore;
-use influxdb3_wal::{Gen1Duration, WalConfig};
+use influxdb3_wal::{Gen1Duration, WalCompression, WalConfig};
 use influxdb3_write::{
     last_cache::LastCacheProvider,
     parquet_cache::create_cached_obj_store_and_oracle,
     persister::Persister,
-    write_buffer::{persisted_files::PersistedFiles, WriteBufferImpl},
+    write_buffer::{
+        background_retention_process, compactor::background_compaction_process,
+        persisted_files::PersistedFiles, WriteBufferImpl,
+    },
     WriteBuffer,
 };
 use iox_query::exec::{DedicatedExecutor, Executor, ExecutorConfig};
@@ -32,7 +39,12 @@ use object_store::ObjectStore;
 use observability_deps::tracing::*;
 use panic_logging::SendPanicsToTracing;
 use parquet_file::storage::{ParquetStorage, StorageId};
-use std::{collections::HashMap, path::Path, str::FromStr};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 use std::{num::NonZeroUsize, sync::Arc};
 use thiserror::Error;
 use tokio::net::TcpListener;
@@ -79,6 +91,9 @@ pub enum Error {
 
     #[error("failed to initialize last cache: {0}")]
     InitializeLastCache(#[source] influxdb3_write::last_cache::Error),
+
+    #[error("failed to initialize metadata cache: {0}")]
+    InitializeMetaCache(#[source] influxdb3_cache::meta_cache::ProviderError),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -110,6 +125,17 @@ pub struct Config {
     )]
     pub max_http_request_size: usize,
 
+    /// Maximum size of an import to the `/api/v3/import` API, which is not limited by the
+    /// maximum HTTP request size. Applies to both the compressed and decompressed size of the
+    /// imported file.
+    #[clap(
+    long = "max-import-size",
+    env = "INFLUXDB3_MAX_IMPORT_SIZE",
+    default_value = "10737418240", // 10 GiB
+    action,
+    )]
+    pub max_import_size: usize,
+
     /// The address on which InfluxDB will serve HTTP API requests
     #[clap(
     long = "http-bind",
@@ -151,14 +177,16 @@ pub struct Config {
     )]
     pub datafusion_config: HashMap<String, String>,
 
-    /// bearer token to be set for requests
+    /// The hashed admin token, which is granted access to every request and can be used to
+    /// manage named tokens with `influxdb3 token`
     #[clap(long = "bearer-token", env = "INFLUXDB3_BEARER_TOKEN", action)]
     pub bearer_token: Option<String>,
 
     /// Duration that the Parquet files get arranged into. The data timestamps will land each
-    /// row into a file of this duration. 1m, 5m, and 10m are supported. These are known as
-    /// "generation 1" files. The compactor in Pro can compact these into larger and longer
-    /// generations.
+    /// row into a file of this duration. Any whole number of seconds that evenly divides a day
+    /// is supported, expressed as a human-readable time, e.g., "30s", "10m", "1h". These are
+    /// known as "generation 1" files. See `--compaction-generation-duration` to compact these
+    /// into larger and longer generations.
     #[clap(
         long = "gen1-duration",
         env = "INFLUXDB3_GEN1_DURATION",
@@ -187,6 +215,15 @@ pub struct Config {
     )]
     pub wal_snapshot_size: usize,
 
+    /// The compression applied to WAL files, one of none, snappy, or zstd
+    #[clap(
+        long = "wal-compression",
+        env = "INFLUXDB3_WAL_COMPRESSION",
+        default_value = "zstd",
+        action
+    )]
+    pub wal_compression: WalCompression,
+
     /// The maximum number of writes requests that can be buffered before a flush must be run
     /// and succeed.
     #[clap(
@@ -197,6 +234,12 @@ pub struct Config {
     )]
     pub wal_max_write_buffer_size: usize,
 
+    /// A directory on local disk to write the WAL to. Writes are acknowledged once they are
+    /// synced to this directory and WAL files are shipped to object store in the background.
+    /// If not set, WAL files are written directly to object store.
+    #[clap(long = "wal-dir", env = "INFLUXDB3_WAL_DIR", action)]
+    pub wal_dir: Option<PathBuf>,
+
     // TODO - tune this default:
     /// The size of the query log. Up to this many queries will remain in the log before
     /// old queries are evicted to make room for new ones.
@@ -208,6 +251,95 @@ pub struct Config {
     )]
     pub query_log_size: usize,
 
+    /// The maximum number of queries, made through either the HTTP or Flight APIs, that can run
+    /// at once.
+    #[clap(
+        long = "query-concurrency-limit",
+        env = "INFLUXDB3_QUERY_CONCURRENCY_LIMIT",
+        default_value = "10",
+        action
+    )]
+    pub query_concurrency_limit: usize,
+
+    /// What to do with queries that arrive once the query concurrency limit has been reached:
+    /// `queue` them until a running query completes, or `reject` them. Rejected HTTP queries
+    /// fail with a 429 response, and rejected Flight queries with a RESOURCE_EXHAUSTED status.
+    #[clap(
+        long = "query-limit-behavior",
+        env = "INFLUXDB3_QUERY_LIMIT_BEHAVIOR",
+        default_value = "queue",
+        action
+    )]
+    pub query_limit_behavior: QueryLimitBehavior,
+
+    /// The maximum wall-clock time a query can take, including any time spent queued, after
+    /// which its execution is cancelled. Queued HTTP queries that time out fail with a 503
+    /// response. Queries have no timeout if this is not given.
+    ///
+    /// Enter as a human-readable time, e.g., "30s", "5m", etc.
+    #[clap(long = "query-timeout", env = "INFLUXDB3_QUERY_TIMEOUT", action)]
+    pub query_timeout: Option<humantime::Duration>,
+
+    /// Write the queries that exceed the slow query thresholds, as JSON lines, to files in this
+    /// local directory. The file is rotated once it reaches `--slow-query-log-max-file-size`.
+    #[clap(
+        long = "slow-query-log-dir",
+        env = "INFLUXDB3_SLOW_QUERY_LOG_DIR",
+        conflicts_with = "slow_query_log_db",
+        action
+    )]
+    pub slow_query_log_dir: Option<PathBuf>,
+
+    /// Write the queries that exceed the slow query thresholds to the `slow_queries` table of
+    /// this database, where they can be queried with SQL or InfluxQL.
+    #[clap(
+        long = "slow-query-log-db",
+        env = "INFLUXDB3_SLOW_QUERY_LOG_DB",
+        action
+    )]
+    pub slow_query_log_db: Option<String>,
+
+    /// Queries that take at least this long, from when they are received until their results
+    /// are complete, are written to the slow query log, if one is configured.
+    ///
+    /// Enter as a human-readable time, e.g., "10s", "1m", etc.
+    #[clap(
+        long = "slow-query-duration-threshold",
+        env = "INFLUXDB3_SLOW_QUERY_DURATION_THRESHOLD",
+        default_value = "10s",
+        action
+    )]
+    pub slow_query_duration_threshold: humantime::Duration,
+
+    /// Queries that use at least this much memory are written to the slow query log, if one is
+    /// configured, regardless of how long they take.
+    ///
+    /// Can be given as absolute value or in percentage of the total available memory (e.g. `10%`).
+    #[clap(
+        long = "slow-query-memory-threshold-bytes",
+        env = "INFLUXDB3_SLOW_QUERY_MEMORY_THRESHOLD_BYTES",
+        action
+    )]
+    pub slow_query_memory_threshold_bytes: Option<MemorySize>,
+
+    /// The size, in bytes, at which the slow query log file is rotated.
+    #[clap(
+        long = "slow-query-log-max-file-size",
+        env = "INFLUXDB3_SLOW_QUERY_LOG_MAX_FILE_SIZE",
+        default_value = "104857600",  // 100MB
+        action
+    )]
+    pub slow_query_log_max_file_size: u64,
+
+    /// The maximum number of slow query log files to keep, including the one being written to.
+    #[clap(
+        long = "slow-query-log-max-files",
+        env = "INFLUXDB3_SLOW_QUERY_LOG_MAX_FILES",
+        default_value = "10",
+        action
+    )]
+    pub slow_query_log_max_files: usize,
+
     // TODO - make this default to 70% of available memory:
     /// The size limit of the buffered data. If this limit is passed a snapshot will be forced.
     #[clap(
@@ -281,6 +413,47 @@ pub struct Config {
         action
     )]
     pub last_cache_eviction_interval: humantime::Duration,
+
+    /// The interval on which to evict expired entries from the Metadata cache, and bring caches
+    /// under their max cardinality, expressed as a human-readable time, e.g., "20s", "1m", "1h".
+    #[clap(
+        long = "meta-cache-eviction-interval",
+        env = "INFLUXDB3_META_CACHE_EVICTION_INTERVAL",
+        default_value = "10s",
+        action
+    )]
+    pub meta_cache_eviction_interval: humantime::Duration,
+
+    /// The interval on which to check for, and remove, persisted data that is outside of the
+    /// retention period of its database.
+    #[clap(
+        long = "retention-check-interval",
+        env = "INFLUXDB3_RETENTION_CHECK_INTERVAL",
+        default_value = "1m",
+        action
+    )]
+    pub retention_check_interval: humantime::Duration,
+
+    /// Enables compaction of the gen1 parquet files of each table into larger files, one per
+    /// generation of this duration, expressed as a human-readable time, e.g., "1h", "1d".
+    ///
+    /// Compaction is disabled if this is not set.
+    #[clap(
+        long = "compaction-generation-duration",
+        env = "INFLUXDB3_COMPACTION_GENERATION_DURATION",
+        action
+    )]
+    pub compaction_generation_duration: Option<CompactionGenerationDuration>,
+
+    /// The interval on which to check for gen1 parquet files to compact, expressed as a
+    /// human-readable time, e.g., "20s", "1m", "1h".
+    #[clap(
+        long = "compaction-check-interval",
+        env = "INFLUXDB3_COMPACTION_CHECK_INTERVAL",
+        default_value = "5m",
+        action
+    )]
+    pub compaction_check_interval: humantime::Duration,
 }
 
 /// Specified size of the Parquet cache in megabytes (MB)
@@ -327,6 +500,28 @@ impl FromStr for ParquetCachePrunePercent {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct CompactionGenerationDuration(Duration);
+
+impl From<CompactionGenerationDuration> for Duration {
+    fn from(value: CompactionGenerationDuration) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for CompactionGenerationDuration {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::prelude::v1::Result<Self, Self::Err> {
+        let d = humantime::parse_duration(s)
+            .context("failed to parse compaction generation duration")?;
+        if d.is_zero() {
+            bail!("compaction generation duration must be greater than zero");
+        }
+        Ok(Self(d))
+    }
+}
+
 /// If `p` does not exist, try to create it as a directory.
 ///
 /// panic's if the directory does not exist and can not be created
@@ -437,6 +632,7 @@ pub async fn command(config: Config) -> Result<()> {
         max_write_buffer_size: config.wal_max_write_buffer_size,
         flush_interval: config.wal_flush_interval.into(),
         snapshot_size: config.wal_snapshot_size,
+        compression: config.wal_compression,
     };
 
     let catalog = Arc::new(
@@ -451,6 +647,13 @@ pub async fn command(config: Config) -> Result<()> {
         config.last_cache_eviction_interval.into(),
     )
     .map_err(Error::InitializeLastCache)?;
+
+    let meta_cache = MetaCacheProvider::new_from_catalog_with_background_prune(
+        Arc::<SystemProvider>::clone(&time_provider),
+        Arc::clone(&catalog),
+        config.meta_cache_eviction_interval.into(),
+    )
+    .map_err(Error::InitializeMetaCache)?;
     info!(instance_id = ?catalog.instance_id(), "Catalog initialized with");
 
     let write_buffer_impl = Arc::new(
@@ -458,10 +661,13 @@ pub async fn command(config: Config) -> Result<()> {
             Arc::clone(&persister),
             Arc::clone(&catalog),
             last_cache,
+            meta_cache,
             Arc::<SystemProvider>::clone(&time_provider),
             Arc::clone(&exec),
             wal_config,
+            config.wal_dir,
             parquet_cache,
+            Arc::clone(&metrics),
         )
         .await
         .map_err(|e| Error::WriteBufferInit(e.into()))?,
@@ -476,6 +682,18 @@ pub async fn command(config: Config) -> Result<()> {
     )
     .await;
 
+    background_retention_process(
+        Arc::clone(&write_buffer_impl),
+        config.retention_check_interval.into(),
+    );
+    if let Some(generation_duration) = config.compaction_generation_duration {
+        background_compaction_process(
+            Arc::clone(&write_buffer_impl),
+            generation_duration.into(),
+            config.compaction_check_interval.into(),
+        );
+    }
+
     let write_buffer: Arc<dyn WriteBuffer> = write_buffer_impl;
 
     let common_state = CommonServerState::new(
@@ -485,15 +703,36 @@ pub async fn command(config: Config) -> Result<()> {
         Arc::clone(&telemetry_store),
     )?;
 
+    let slow_query_sink = match (config.slow_query_log_dir, config.slow_query_log_db) {
+        (Some(dir), _) => Some(SlowQuerySink::Files {
+            dir,
+            max_file_size: config.slow_query_log_max_file_size,
+            max_files: config.slow_query_log_max_files,
+        }),
+        (None, Some(db)) => Some(SlowQuerySink::Database(db)),
+        (None, None) => None,
+    };
+    let slow_query_log = slow_query_sink.map(|sink| SlowQueryLogConfig {
+        duration_threshold: config.slow_query_duration_threshold.into(),
+        memory_threshold: config
+            .slow_query_memory_threshold_bytes
+            .map(|size| size.bytes() as u64),
+        sink,
+    });
+
     let query_executor = Arc::new(QueryExecutorImpl::new(CreateQueryExecutorArgs {
         catalog: write_buffer.catalog(),
         write_buffer: Arc::clone(&write_buffer),
         exec: Arc::clone(&exec),
         metrics: Arc::clone(&metrics),
         datafusion_config: Arc::new(config.datafusion_config),
-        concurrent_query_limit: 10,
+        concurrent_query_limit: config.query_concurrency_limit,
+        query_limit_behavior: config.query_limit_behavior,
+        query_timeout: config.query_timeout.map(Into::into),
         query_log_size: config.query_log_size,
+        slow_query_log,
         telemetry_store: Arc::clone(&telemetry_store),
+        time_provider: Arc::<SystemProvider>::clone(&time_provider),
     }));
 
     let listener = TcpListener::bind(*config.http_bind_address)
@@ -502,19 +741,23 @@ pub async fn command(config: Config) -> Result<()> {
 
     let builder = ServerBuilder::new(common_state)
         .max_request_size(config.max_http_request_size)
+        .max_import_size(config.max_import_size)
         .write_buffer(write_buffer)
         .query_executor(query_executor)
-        .time_provider(time_provider)
+        .time_provider(Arc::<SystemProvider>::clone(&time_provider))
         .persister(persister)
         .tcp_listener(listener);
 
-    let server = if let Some(token) = config.bearer_token.map(hex::decode).transpose()? {
-        builder
-            .authorizer(Arc::new(AllOrNothingAuthorizer::new(token)))
-            .build()
-    } else {
-        builder.build()
-    };
+    // the catalog authorizer is always installed: it allows all requests until an admin token is
+    // given or named tokens have been created, which can happen while the server is running
+    let admin_token = config.bearer_token.map(hex::decode).transpose()?;
+    let server = builder
+        .authorizer(Arc::new(CatalogAuthorizer::new(
+            Arc::clone(&catalog),
+            admin_token,
+            time_provider,
+        )))
+        .build();
     serve(server, frontend_shutdown).await?;
 
     Ok(())
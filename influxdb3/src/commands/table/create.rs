@@ -0,0 +1,87 @@
+use std::{error::Error, str::FromStr};
+
+use influxdb3_client::FieldType;
+use secrecy::ExposeSecret;
+
+use crate::commands::common::{InfluxDb3Config, SeparatedList};
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(flatten)]
+    influxdb3_config: InfluxDb3Config,
+
+    /// The name of the table being created
+    #[clap(short = 't', long = "table")]
+    table: String,
+
+    /// The tag columns of the table
+    #[clap(long = "tags")]
+    tags: Option<SeparatedList<String>>,
+
+    /// The field columns of the table, given as `name:type` pairs, where the type is one of
+    /// string, integer, uinteger, float, or boolean
+    #[clap(long = "fields")]
+    fields: Option<SeparatedList<FieldArg>>,
+}
+
+#[derive(Debug, Clone)]
+struct FieldArg {
+    name: String,
+    field_type: FieldType,
+}
+
+impl FromStr for FieldArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, field_type)) = s.rsplit_once(':') else {
+            return Err(anyhow::anyhow!(
+                "invalid field '{s}', expected a name and type, e.g., usage:float"
+            ));
+        };
+        let field_type = match field_type {
+            "string" => FieldType::String,
+            "integer" => FieldType::Integer,
+            "uinteger" => FieldType::UInteger,
+            "float" => FieldType::Float,
+            "boolean" | "bool" => FieldType::Boolean,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "invalid field type '{field_type}', expected one of string, integer, \
+                    uinteger, float, or boolean"
+                ))
+            }
+        };
+        Ok(Self {
+            name: name.to_string(),
+            field_type,
+        })
+    }
+}
+
+pub(super) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let InfluxDb3Config {
+        host_url,
+        database_name,
+        auth_token,
+    } = config.influxdb3_config;
+    let mut client = influxdb3_client::Client::new(host_url)?;
+    if let Some(t) = auth_token {
+        client = client.with_auth_token(t.expose_secret());
+    }
+    let mut request = client.api_v3_configure_table_create(&database_name, &config.table);
+    for tag in config.tags.into_iter().flatten() {
+        request = request.tag(tag);
+    }
+    for FieldArg { name, field_type } in config.fields.into_iter().flatten() {
+        request = request.field(name, field_type);
+    }
+    request.send().await?;
+
+    println!(
+        "table {table} created in database {database_name} successfully",
+        table = config.table
+    );
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::str::FromStr;
+
+use influxdb3_client::TokenAction;
+use influxdb3_server::auth::{generate_token, hash_token};
+
+use super::TokenClientConfig;
+use crate::commands::common::SeparatedList;
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(flatten)]
+    client_config: TokenClientConfig,
+
+    #[clap(flatten)]
+    token_config: TokenConfig,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct TokenConfig {
+    /// The name of the token to create on the server
+    ///
+    /// If no name is given, a token is generated locally, without contacting the server, that
+    /// can be used as the admin token with `influxdb3 serve --bearer-token`.
+    #[clap(short = 'n', long = "name")]
+    name: Option<String>,
+
+    /// A description of what the token is used for
+    #[clap(long = "description", requires = "name")]
+    description: Option<String>,
+
+    /// The databases that the token is granted access to, as a comma-separated list of
+    /// database names, or `*` for all databases
+    #[clap(short = 'd', long = "databases", requires = "name")]
+    databases: Option<SeparatedList<String>>,
+
+    /// The actions that the token is granted on those databases, as a comma-separated list of
+    /// `read`, `write`, or `admin`
+    #[clap(long = "actions", default_value = "read", requires = "name")]
+    actions: SeparatedList<ActionArg>,
+
+    /// The number of seconds after which the token expires; by default it never expires
+    #[clap(long = "expires-in", requires = "name")]
+    expires_in: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActionArg(TokenAction);
+
+impl FromStr for ActionArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Self(TokenAction::Read)),
+            "write" => Ok(Self(TokenAction::Write)),
+            "admin" => Ok(Self(TokenAction::Admin)),
+            _ => Err(anyhow::anyhow!(
+                "invalid action '{s}', expected one of read, write, or admin"
+            )),
+        }
+    }
+}
+
+pub(super) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let TokenConfig {
+        name,
+        description,
+        databases,
+        actions,
+        expires_in,
+    } = config.token_config;
+    let Some(name) = name else {
+        print_admin_token();
+        return Ok(());
+    };
+    let Some(databases) = databases else {
+        return Err("the --databases to grant the token access to must be provided".into());
+    };
+
+    let client = config.client_config.client()?;
+    let actions = actions.into_iter().map(|a| a.0).collect::<Vec<_>>();
+    let mut b = client.api_v3_configure_token_create(name);
+    for database in databases {
+        b = b.permission(database, actions.iter().copied());
+    }
+    if let Some(description) = description {
+        b = b.description(description);
+    }
+    if let Some(expires_in) = expires_in {
+        b = b.expires_in(expires_in);
+    }
+    let resp = b.send().await?;
+
+    println!(
+        "\
+        Token: {token}\n\n\
+        {info}\n\n\
+        HTTP requests require the following header: \"Authorization: Bearer {token}\"\n\
+        The token is not stored by the server, and cannot be retrieved again",
+        token = resp.token,
+        info = serde_json::to_string_pretty(&resp.info).expect("serialize token info as JSON"),
+    );
+
+    Ok(())
+}
+
+/// Generate a token locally, to be used as the admin token of a server
+fn print_admin_token() {
+    let token = generate_token();
+    println!(
+        "\
+        Token: {token}\n\
+        Hashed Token: {hashed}\n\n\
+        Start the server with `influxdb3 serve --bearer-token {hashed}`\n\n\
+        HTTP requests require the following header: \"Authorization: Bearer {token}\"\n\
+        This will grant you access to every HTTP endpoint or deny it otherwise
+    ",
+        hashed = hash_token(&token)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use influxdb3_client::TokenAction;
+
+    use super::TokenConfig;
+
+    #[test]
+    fn parse_args() {
+        let args = TokenConfig::parse_from([
+            "token_create",
+            "--name",
+            "ingest",
+            "--databases",
+            "foo,bar",
+            "--actions",
+            "read,write",
+            "--expires-in",
+            "3600",
+        ]);
+        assert!(args.name.is_some_and(|n| n == "ingest"));
+        assert_eq!(["foo", "bar"], args.databases.unwrap().0.as_slice());
+        assert_eq!(
+            vec![TokenAction::Read, TokenAction::Write],
+            args.actions.0.iter().map(|a| a.0).collect::<Vec<_>>()
+        );
+        assert!(args.expires_in.is_some_and(|e| e == 3600));
+    }
+
+    #[test]
+    fn parse_args_requires_name() {
+        assert!(TokenConfig::try_parse_from(["token_create", "--databases", "foo"]).is_err());
+        assert!(TokenConfig::try_parse_from(["token_create", "--actions", "bad"]).is_err());
+    }
+}
@@ -0,0 +1,21 @@
+use std::error::Error;
+
+use super::TokenClientConfig;
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(flatten)]
+    client_config: TokenClientConfig,
+}
+
+pub(super) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let client = config.client_config.client()?;
+    let tokens = client.api_v3_configure_token_list().await?;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&tokens).expect("serialize tokens as JSON")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::error::Error;
+
+use clap::Parser;
+use secrecy::{ExposeSecret, Secret};
+use url::Url;
+
+pub mod create;
+pub mod list;
+pub mod revoke;
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(subcommand)]
+    cmd: SubCommand,
+}
+
+#[derive(Debug, clap::Parser)]
+pub enum SubCommand {
+    /// Create a new auth token
+    Create(create::Config),
+    /// List the named tokens stored on the server
+    List(list::Config),
+    /// Revoke a named token, so that it can no longer be used
+    Revoke(revoke::Config),
+}
+
+pub async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    match config.cmd {
+        SubCommand::Create(config) => create::command(config).await,
+        SubCommand::List(config) => list::command(config).await,
+        SubCommand::Revoke(config) => revoke::command(config).await,
+    }
+}
+
+/// The configuration used to connect to the server to manage tokens
+#[derive(Debug, Parser)]
+pub struct TokenClientConfig {
+    /// The host URL of the running InfluxDB 3.0 server
+    #[clap(
+        short = 'h',
+        long = "host",
+        env = "INFLUXDB3_HOST_URL",
+        default_value = "http://127.0.0.1:8181"
+    )]
+    pub host_url: Url,
+
+    /// The admin token for authentication with the InfluxDB 3.0 server
+    #[clap(long = "token", env = "INFLUXDB3_AUTH_TOKEN")]
+    pub auth_token: Option<Secret<String>>,
+}
+
+impl TokenClientConfig {
+    fn client(self) -> Result<influxdb3_client::Client, influxdb3_client::Error> {
+        let mut client = influxdb3_client::Client::new(self.host_url)?;
+        if let Some(t) = self.auth_token {
+            client = client.with_auth_token(t.expose_secret());
+        }
+        Ok(client)
+    }
+}
@@ -0,0 +1,22 @@
+use std::error::Error;
+
+use super::TokenClientConfig;
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(flatten)]
+    client_config: TokenClientConfig,
+
+    /// The name of the token being revoked
+    #[clap(short = 'n', long = "name")]
+    name: String,
+}
+
+pub(super) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let client = config.client_config.client()?;
+    client.api_v3_configure_token_delete(config.name).await?;
+
+    println!("token revoked successfully");
+
+    Ok(())
+}
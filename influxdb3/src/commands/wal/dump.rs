@@ -0,0 +1,95 @@
+use std::error::Error;
+
+use clap::ValueEnum;
+use influxdb3_wal::{WalContents, WalFileSequenceNumber, WalOp};
+
+use super::{read_wal_file, WalStoreConfig};
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(flatten)]
+    store_config: WalStoreConfig,
+
+    /// The sequence number of the WAL file to print
+    #[clap(required = true)]
+    wal_file_number: u64,
+
+    /// The format to print the WAL file contents in
+    #[clap(value_enum, long = "fmt", default_value = "summary")]
+    output_format: Format,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+#[clap(rename_all = "snake_case")]
+enum Format {
+    Summary,
+    Json,
+}
+
+pub(super) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let object_store = config.store_config.object_store()?;
+    let contents = read_wal_file(
+        object_store.as_ref(),
+        &config.store_config.host_identifier_prefix,
+        WalFileSequenceNumber::new(config.wal_file_number),
+    )
+    .await?;
+
+    match config.output_format {
+        Format::Summary => print_summary(&contents),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&contents)?),
+    }
+
+    Ok(())
+}
+
+fn print_summary(contents: &WalContents) {
+    println!("wal file number: {}", contents.wal_file_number);
+    println!("min timestamp (ns): {}", contents.min_timestamp_ns);
+    println!("max timestamp (ns): {}", contents.max_timestamp_ns);
+    match &contents.snapshot {
+        Some(snapshot) => println!(
+            "snapshot: sequence number {}, end time marker {}, last wal file number {}",
+            snapshot.snapshot_sequence_number.as_u64(),
+            snapshot.end_time_marker,
+            snapshot.last_wal_sequence_number,
+        ),
+        None => println!("snapshot: none"),
+    }
+    println!("ops: {}", contents.ops.len());
+
+    for op in &contents.ops {
+        match op {
+            WalOp::Catalog(batch) => {
+                println!(
+                    "  catalog batch: database {} ({} ops)",
+                    batch.database_name,
+                    batch.ops.len()
+                );
+                for catalog_op in &batch.ops {
+                    // catalog ops are externally tagged, so the only key is the variant name
+                    let name = serde_json::to_value(catalog_op)
+                        .ok()
+                        .and_then(|v| v.as_object().and_then(|o| o.keys().next().cloned()))
+                        .unwrap_or_else(|| "unknown".to_string());
+                    println!("    {name}");
+                }
+            }
+            WalOp::Write(batch) => {
+                let rows: usize = batch
+                    .table_chunks
+                    .iter()
+                    .flat_map(|(_, chunks)| chunks.chunk_time_to_chunk.values())
+                    .map(|chunk| chunk.rows.len())
+                    .sum();
+                println!(
+                    "  write batch: database {} ({} tables, {rows} rows, time range {} to {})",
+                    batch.database_name,
+                    batch.table_chunks.len(),
+                    batch.min_time_ns,
+                    batch.max_time_ns,
+                );
+            }
+        }
+    }
+}
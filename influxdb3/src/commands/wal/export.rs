@@ -0,0 +1,272 @@
+use std::{
+    error::Error,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use influxdb3_catalog::catalog::{Catalog, TableDefinition};
+use influxdb3_wal::{
+    object_store::list_wal_files, FieldData, Row, WalFileSequenceNumber, WalOp, WriteBatch,
+};
+use influxdb3_write::line_protocol::{FieldValue, LineBuilder};
+use influxdb3_write::persister::Persister;
+
+use super::{read_wal_file, WalStoreConfig};
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(flatten)]
+    store_config: WalStoreConfig,
+
+    /// The sequence number of the WAL file to export
+    #[clap(required = true)]
+    wal_file_number: u64,
+
+    /// The file to write the line protocol to, defaults to stdout
+    #[clap(short = 'o', long = "output")]
+    output: Option<PathBuf>,
+}
+
+pub(super) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let object_store = config.store_config.object_store()?;
+    let host_identifier_prefix = config.store_config.host_identifier_prefix.as_str();
+    let wal_file_number = WalFileSequenceNumber::new(config.wal_file_number);
+
+    // WAL files only refer to tables and columns by id, so the names are resolved from the
+    // persisted catalog, brought up to date with the catalog ops in the WAL up to this file
+    let persister = Persister::new(Arc::clone(&object_store), host_identifier_prefix);
+    let catalog = match persister.load_catalog().await? {
+        Some(inner) => Catalog::from_inner(inner),
+        None => Catalog::new(host_identifier_prefix.into(), "wal-export".into()),
+    };
+    for file in list_wal_files(object_store.as_ref(), host_identifier_prefix).await? {
+        let earlier_file_number = match WalFileSequenceNumber::try_from(&file.location) {
+            Ok(n) if n < wal_file_number => n,
+            _ => continue,
+        };
+        let contents = read_wal_file(
+            object_store.as_ref(),
+            host_identifier_prefix,
+            earlier_file_number,
+        )
+        .await?;
+        apply_catalog_ops(&catalog, &contents.ops)?;
+    }
+
+    let contents = read_wal_file(
+        object_store.as_ref(),
+        host_identifier_prefix,
+        wal_file_number,
+    )
+    .await?;
+    apply_catalog_ops(&catalog, &contents.ops)?;
+
+    let mut out: Box<dyn Write> = match config.output {
+        Some(path) => Box::new(BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(BufWriter::new(std::io::stdout().lock())),
+    };
+    for op in &contents.ops {
+        if let WalOp::Write(batch) = op {
+            out.write_all(write_batch_to_line_protocol(&catalog, batch)?.as_bytes())?;
+        }
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+fn apply_catalog_ops(catalog: &Catalog, ops: &[WalOp]) -> Result<(), Box<dyn Error>> {
+    for op in ops {
+        if let WalOp::Catalog(batch) = op {
+            catalog.apply_catalog_batch(batch)?;
+        }
+    }
+    Ok(())
+}
+
+/// Converts the rows of a write batch to line protocol, one line per row. Rows for tables with
+/// a series key are written in the v3 line protocol format.
+fn write_batch_to_line_protocol(
+    catalog: &Catalog,
+    batch: &WriteBatch,
+) -> Result<String, Box<dyn Error>> {
+    let db_schema = catalog
+        .db_schema_by_id(&batch.database_id)
+        .ok_or_else(|| format!("database {} not found in catalog", batch.database_name))?;
+
+    let mut lp = String::new();
+    for (table_id, table_chunks) in batch.table_chunks.iter() {
+        let table_def = db_schema.table_definition_by_id(table_id).ok_or_else(|| {
+            format!(
+                "table with id {table_id} not found in database {}",
+                batch.database_name
+            )
+        })?;
+
+        let mut chunk_times: Vec<_> = table_chunks.chunk_time_to_chunk.keys().collect();
+        chunk_times.sort();
+        for chunk_time in chunk_times {
+            for row in &table_chunks.chunk_time_to_chunk[chunk_time].rows {
+                write_row(&mut lp, &table_def, row)?;
+            }
+        }
+    }
+
+    Ok(lp)
+}
+
+fn write_row(
+    lp: &mut String,
+    table_def: &TableDefinition,
+    row: &Row,
+) -> Result<(), Box<dyn Error>> {
+    let column_name = |id| {
+        table_def.column_id_to_name(id).ok_or_else(|| {
+            format!(
+                "column with id {id} not found in table {}",
+                table_def.table_name
+            )
+        })
+    };
+
+    let mut line = LineBuilder::new(table_def);
+    match &table_def.series_key {
+        Some(series_key) => {
+            for id in series_key {
+                let value = row.fields.iter().find_map(|f| match &f.value {
+                    FieldData::Key(v) if &f.id == id => Some(v),
+                    _ => None,
+                });
+                if let Some(value) = value {
+                    line.tag(&column_name(id)?, value);
+                }
+            }
+        }
+        None => {
+            for field in &row.fields {
+                if let FieldData::Tag(value) = &field.value {
+                    line.tag(&column_name(&field.id)?, value);
+                }
+            }
+        }
+    }
+
+    for field in &row.fields {
+        let value = match &field.value {
+            FieldData::Timestamp(_) | FieldData::Key(_) | FieldData::Tag(_) => continue,
+            FieldData::String(s) => FieldValue::String(s),
+            FieldData::Integer(i) => FieldValue::Integer(*i),
+            FieldData::UInteger(u) => FieldValue::UInteger(*u),
+            FieldData::Float(f) => FieldValue::Float(*f),
+            FieldData::Boolean(b) => FieldValue::Boolean(*b),
+        };
+        line.field(&column_name(&field.id)?, value);
+    }
+    line.finish_line(lp, row.time);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use influxdb3_id::{ColumnId, DbId, TableId};
+    use influxdb3_wal::{
+        create::{catalog_batch_op, create_table_op, field_def},
+        Field, FieldDataType, TableChunk, TableChunks,
+    };
+
+    use super::*;
+
+    #[test]
+    fn write_batch_exported_as_line_protocol() {
+        let catalog = Catalog::new("host".into(), "instance".into());
+        let db_id = DbId::new();
+        let table_id = TableId::new();
+        let (host, region, usage, label, time) = (
+            ColumnId::from(0),
+            ColumnId::from(1),
+            ColumnId::from(2),
+            ColumnId::from(3),
+            ColumnId::from(4),
+        );
+        let WalOp::Catalog(batch) = catalog_batch_op(
+            db_id,
+            "foo",
+            0,
+            [create_table_op(
+                db_id,
+                "foo",
+                table_id,
+                "cpu load",
+                [
+                    field_def(host, "host", FieldDataType::Tag),
+                    field_def(region, "region", FieldDataType::Tag),
+                    field_def(usage, "usage", FieldDataType::Float),
+                    field_def(label, "label", FieldDataType::String),
+                    field_def(time, "time", FieldDataType::Timestamp),
+                ],
+            )],
+        ) else {
+            unreachable!()
+        };
+        catalog.apply_catalog_batch(&batch).unwrap();
+
+        let row = |time_ns: i64, host_value: &str, fields: Vec<Field>| Row {
+            time: time_ns,
+            fields: [
+                vec![Field::new(host, FieldData::Tag(host_value.to_string()))],
+                fields,
+                vec![Field::new(time, FieldData::Timestamp(time_ns))],
+            ]
+            .concat(),
+        };
+        let batch = WriteBatch::new(
+            db_id,
+            "foo".into(),
+            [(
+                table_id,
+                TableChunks {
+                    min_time: 1,
+                    max_time: 2,
+                    chunk_time_to_chunk: [(
+                        0,
+                        TableChunk {
+                            rows: vec![
+                                row(
+                                    1,
+                                    "a,b",
+                                    vec![
+                                        Field::new(region, FieldData::Tag("us west".to_string())),
+                                        Field::new(usage, FieldData::Float(0.5)),
+                                    ],
+                                ),
+                                row(
+                                    2,
+                                    "c",
+                                    vec![
+                                        Field::new(usage, FieldData::Float(1.0)),
+                                        Field::new(
+                                            label,
+                                            FieldData::String("say \"hi\"".to_string()),
+                                        ),
+                                    ],
+                                ),
+                            ],
+                        },
+                    )]
+                    .into_iter()
+                    .collect(),
+                },
+            )]
+            .into_iter()
+            .collect(),
+        );
+
+        assert_eq!(
+            write_batch_to_line_protocol(&catalog, &batch).unwrap(),
+            "cpu\\ load,host=a\\,b,region=us\\ west usage=0.5 1\n\
+            cpu\\ load,host=c usage=1,label=\"say \\\"hi\\\"\" 2\n"
+        );
+    }
+}
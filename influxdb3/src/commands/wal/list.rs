@@ -0,0 +1,40 @@
+use std::error::Error;
+
+use influxdb3_wal::{object_store::list_wal_files, WalFileSequenceNumber};
+
+use super::WalStoreConfig;
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(flatten)]
+    store_config: WalStoreConfig,
+}
+
+pub(super) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let object_store = config.store_config.object_store()?;
+    let files = list_wal_files(
+        object_store.as_ref(),
+        &config.store_config.host_identifier_prefix,
+    )
+    .await?;
+
+    if files.is_empty() {
+        println!(
+            "no WAL files found for host {}",
+            config.store_config.host_identifier_prefix
+        );
+        return Ok(());
+    }
+
+    for file in files {
+        let wal_file_number = WalFileSequenceNumber::try_from(&file.location)
+            .map(|n| n.to_string())
+            .unwrap_or_else(|_| "-".to_string());
+        println!(
+            "{wal_file_number}\t{}\t{} bytes\t{}",
+            file.location, file.size, file.last_modified
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Commands for inspecting the WAL files that a server has written to object store
+
+use std::{error::Error, sync::Arc};
+
+use clap_blocks::object_store::{make_object_store, ObjectStoreConfig};
+use influxdb3_wal::{
+    object_store::wal_path, serialize::verify_file_type_and_deserialize, WalContents,
+    WalFileSequenceNumber,
+};
+use object_store::ObjectStore;
+
+pub mod dump;
+pub mod export;
+pub mod list;
+pub mod verify;
+
+#[derive(Debug, clap::Parser)]
+pub(crate) struct Config {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// List the WAL files for a host
+    List(list::Config),
+
+    /// Print the contents of a WAL file
+    Dump(dump::Config),
+
+    /// Verify the checksums of a range of WAL files
+    Verify(verify::Config),
+
+    /// Export the writes in a WAL file as line protocol
+    Export(export::Config),
+}
+
+pub(crate) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    match config.command {
+        Command::List(config) => list::command(config).await,
+        Command::Dump(config) => dump::command(config).await,
+        Command::Verify(config) => verify::command(config).await,
+        Command::Export(config) => export::command(config).await,
+    }
+}
+
+/// The configuration used to locate the WAL files of a host in object store
+#[derive(Debug, clap::Parser)]
+pub struct WalStoreConfig {
+    /// object store options
+    #[clap(flatten)]
+    object_store_config: ObjectStoreConfig,
+
+    /// The host identifier prefix that the server was run with
+    #[clap(long = "host-id", env = "INFLUXDB3_HOST_IDENTIFIER_PREFIX", action)]
+    pub host_identifier_prefix: String,
+}
+
+impl WalStoreConfig {
+    fn object_store(&self) -> Result<Arc<dyn ObjectStore>, Box<dyn Error>> {
+        Ok(make_object_store(&self.object_store_config)?)
+    }
+}
+
+/// Reads the WAL file with the given sequence number, verifying its checksum
+async fn read_wal_file(
+    object_store: &dyn ObjectStore,
+    host_identifier_prefix: &str,
+    wal_file_number: WalFileSequenceNumber,
+) -> Result<WalContents, Box<dyn Error>> {
+    let path = wal_path(host_identifier_prefix, wal_file_number);
+    let bytes = object_store.get(&path).await?.bytes().await?;
+    Ok(verify_file_type_and_deserialize(bytes)?)
+}
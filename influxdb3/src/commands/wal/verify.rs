@@ -0,0 +1,83 @@
+use std::error::Error;
+
+use influxdb3_wal::{
+    object_store::list_wal_files, serialize::verify_file_type_and_deserialize,
+    WalFileSequenceNumber,
+};
+
+use super::WalStoreConfig;
+
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(flatten)]
+    store_config: WalStoreConfig,
+
+    /// The sequence number of the first WAL file to verify, defaults to the first file
+    #[clap(long = "start")]
+    start: Option<u64>,
+
+    /// The sequence number of the last WAL file to verify, defaults to the last file
+    #[clap(long = "end")]
+    end: Option<u64>,
+}
+
+pub(super) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
+    let object_store = config.store_config.object_store()?;
+    let start = config.start.unwrap_or(u64::MIN);
+    let end = config.end.unwrap_or(u64::MAX);
+
+    let mut verified = 0;
+    let mut failed = 0;
+    let mut previous: Option<u64> = None;
+    for file in list_wal_files(
+        object_store.as_ref(),
+        &config.store_config.host_identifier_prefix,
+    )
+    .await?
+    {
+        let wal_file_number = match WalFileSequenceNumber::try_from(&file.location) {
+            Ok(n) => n.as_u64(),
+            Err(e) => {
+                println!("{}\tFAILED: {e}", file.location);
+                failed += 1;
+                continue;
+            }
+        };
+        if !(start..=end).contains(&wal_file_number) {
+            continue;
+        }
+        if let Some(previous) = previous.filter(|p| wal_file_number != p + 1) {
+            println!(
+                "WARNING: missing WAL files {} to {}",
+                previous + 1,
+                wal_file_number - 1
+            );
+        }
+        previous = Some(wal_file_number);
+
+        let result = match object_store.get(&file.location).await {
+            Ok(get) => match get.bytes().await {
+                Ok(bytes) => verify_file_type_and_deserialize(bytes).map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            },
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(_) => {
+                println!("{wal_file_number}\tOK");
+                verified += 1;
+            }
+            Err(e) => {
+                println!("{wal_file_number}\tFAILED: {e}");
+                failed += 1;
+            }
+        }
+    }
+
+    println!("{verified} WAL files verified, {failed} failed");
+    if failed > 0 {
+        return Err(format!("{failed} WAL files failed verification").into());
+    }
+
+    Ok(())
+}
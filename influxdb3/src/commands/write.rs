@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use influxdb3_client::ContentEncoding;
 use secrecy::ExposeSecret;
 use tokio::{
     fs::File,
@@ -36,6 +37,35 @@ pub struct Config {
     /// Invalid lines in the input data will be ignored by the server.
     #[clap(long = "accept-partial")]
     accept_partial_writes: bool,
+
+    /// Compress the write data before sending it to the server
+    #[clap(long = "compression")]
+    compression: Option<Compression>,
+
+    /// Flag to request the server write the data in slices as it streams in
+    ///
+    /// This allows writes larger than the server's maximum request size, which are read from
+    /// the file as they are sent, unless they are compressed. Slices are written separately, so
+    /// a streamed write that fails part way through is not rolled back.
+    #[clap(long = "stream")]
+    stream: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Compression {
+    Gzip,
+    Zstd,
+    Snappy,
+}
+
+impl From<Compression> for ContentEncoding {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::Gzip => Self::Gzip,
+            Compression::Zstd => Self::Zstd,
+            Compression::Snappy => Self::Snappy,
+        }
+    }
 }
 
 pub(crate) async fn command(config: Config) -> Result<()> {
@@ -50,14 +80,26 @@ pub(crate) async fn command(config: Config) -> Result<()> {
     }
 
     let mut f = File::open(config.file_path).await?;
-    let mut writes = Vec::new();
-    f.read_to_end(&mut writes).await?;
 
     let mut req = client.api_v3_write_lp(database_name);
     if config.accept_partial_writes {
         req = req.accept_partial(true);
     }
-    req.body(writes).send().await?;
+    if config.stream {
+        req = req.stream(true);
+    }
+    match config.compression {
+        // a streamed write that is not compressed is sent straight from the file:
+        None if config.stream => req.body(f).send().await?,
+        compression => {
+            let mut writes = Vec::new();
+            f.read_to_end(&mut writes).await?;
+            if let Some(compression) = compression {
+                req = req.content_encoding(compression.into());
+            }
+            req.body(writes).send().await?
+        }
+    }
 
     println!("success");
 
@@ -25,11 +25,20 @@ use trogging::{
 };
 
 mod commands {
+    pub mod backup;
+    pub mod check;
     pub(crate) mod common;
+    pub mod database;
+    pub mod export;
+    pub mod import;
     pub mod last_cache;
+    pub mod meta_cache;
     pub mod query;
+    pub mod restore;
     pub mod serve;
+    pub mod table;
     pub mod token;
+    pub mod wal;
     pub mod write;
 }
 
@@ -86,11 +95,38 @@ enum Command {
     /// Perform a set of writes to a running InfluxDB 3.0 server
     Write(commands::write::Config),
 
+    /// Export the data of a database from a running InfluxDB 3.0 server as line protocol
+    Export(commands::export::Config),
+
+    /// Bulk import Parquet or CSV files into a table of a running InfluxDB 3.0 server
+    Import(commands::import::Config),
+
     /// Manage tokens for your InfluxDB 3.0 server
     Token(commands::token::Config),
 
     /// Manage last-n-value caches
     LastCache(commands::last_cache::Config),
+
+    /// Manage metadata caches
+    MetaCache(commands::meta_cache::Config),
+
+    /// Manage databases
+    Database(commands::database::Config),
+
+    /// Manage tables
+    Table(commands::table::Config),
+
+    /// Inspect the WAL files of a host in object store
+    Wal(commands::wal::Config),
+
+    /// Back up the catalog, snapshots and data files of a host
+    Backup(commands::backup::Config),
+
+    /// Restore a backup into a new host
+    Restore(commands::restore::Config),
+
+    /// Check the consistency of the persisted state of a host, and repair it
+    Check(commands::check::Config),
 }
 
 fn main() -> Result<(), std::io::Error> {
@@ -136,8 +172,20 @@ fn main() -> Result<(), std::io::Error> {
                     std::process::exit(ReturnCode::Failure as _)
                 }
             }
+            Some(Command::Export(config)) => {
+                if let Err(e) = commands::export::command(config).await {
+                    eprintln!("Export command failed: {e}");
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
+            Some(Command::Import(config)) => {
+                if let Err(e) = commands::import::command(config).await {
+                    eprintln!("Import command failed: {e}");
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
             Some(Command::Token(config)) => {
-                if let Err(e) = commands::token::command(config) {
+                if let Err(e) = commands::token::command(config).await {
                     eprintln!("Token command failed: {e}");
                     std::process::exit(ReturnCode::Failure as _)
                 }
@@ -148,6 +196,48 @@ fn main() -> Result<(), std::io::Error> {
                     std::process::exit(ReturnCode::Failure as _)
                 }
             }
+            Some(Command::MetaCache(config)) => {
+                if let Err(e) = commands::meta_cache::command(config).await {
+                    eprintln!("Metadata Cache command failed: {e}");
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
+            Some(Command::Database(config)) => {
+                if let Err(e) = commands::database::command(config).await {
+                    eprintln!("Database command failed: {e}");
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
+            Some(Command::Table(config)) => {
+                if let Err(e) = commands::table::command(config).await {
+                    eprintln!("Table command failed: {e}");
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
+            Some(Command::Wal(config)) => {
+                if let Err(e) = commands::wal::command(config).await {
+                    eprintln!("WAL command failed: {e}");
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
+            Some(Command::Backup(config)) => {
+                if let Err(e) = commands::backup::command(config).await {
+                    eprintln!("Backup command failed: {e}");
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
+            Some(Command::Restore(config)) => {
+                if let Err(e) = commands::restore::command(config).await {
+                    eprintln!("Restore command failed: {e}");
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
+            Some(Command::Check(config)) => {
+                if let Err(e) = commands::check::command(config).await {
+                    eprintln!("Check command failed: {e}");
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
         }
     });
 
@@ -1,6 +1,6 @@
 use arrow_flight::error::FlightError;
 use arrow_util::assert_batches_sorted_eq;
-use influxdb3_client::Precision;
+use influxdb3_client::{Precision, TokenAction};
 use reqwest::StatusCode;
 
 use crate::{collect_stream, ConfigProvider, TestServer};
@@ -336,3 +336,137 @@ async fn v1_password_parameter() {
         StatusCode::OK,
     );
 }
+
+#[tokio::test]
+async fn auth_named_tokens() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+
+    let server = TestServer::configure()
+        .with_auth_token(HASHED_TOKEN, TOKEN)
+        .spawn()
+        .await;
+    let admin = influxdb3_client::Client::new(server.client_addr())
+        .unwrap()
+        .with_auth_token(TOKEN);
+
+    // the admin token creates a token that can only write to the foo database:
+    let created = admin
+        .api_v3_configure_token_create("ingest")
+        .description("telegraf agents")
+        .permission("foo", [TokenAction::Write])
+        .send()
+        .await
+        .expect("create token");
+    assert_eq!("ingest", created.info.name);
+    assert!(matches!(
+        admin
+            .api_v3_configure_token_create("ingest")
+            .permission("foo", [TokenAction::Write])
+            .send()
+            .await,
+        Err(influxdb3_client::Error::ApiError {
+            code: StatusCode::CONFLICT,
+            ..
+        })
+    ));
+
+    // tokens are listed without the token or its hash:
+    let tokens = admin.api_v3_configure_token_list().await.unwrap();
+    assert_eq!(1, tokens.len());
+    assert_eq!(Some("telegraf agents"), tokens[0].description.as_deref());
+    assert!(tokens[0].expires_at.is_none());
+
+    let client = reqwest::Client::new();
+    let base = server.client_addr();
+    let write = |db: &'static str| {
+        client
+            .post(format!("{base}/api/v3/write_lp"))
+            .query(&[("db", db)])
+            .body("cpu,host=a val=1i 123")
+            .bearer_auth(&created.token)
+            .send()
+    };
+    assert_eq!(write("foo").await.unwrap().status(), StatusCode::OK);
+    assert_eq!(write("bar").await.unwrap().status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        client
+            .get(format!("{base}/api/v3/query_sql"))
+            .query(&[("db", "foo"), ("q", "select * from cpu")])
+            .bearer_auth(&created.token)
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+
+    // the named token cannot manage tokens:
+    let ingest = influxdb3_client::Client::new(server.client_addr())
+        .unwrap()
+        .with_auth_token(&created.token);
+    assert!(matches!(
+        ingest.api_v3_configure_token_list().await,
+        Err(influxdb3_client::Error::ApiError {
+            code: StatusCode::FORBIDDEN,
+            ..
+        })
+    ));
+
+    // revoking the token takes effect immediately:
+    admin.api_v3_configure_token_delete("ingest").await.unwrap();
+    assert_eq!(
+        write("foo").await.unwrap().status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert!(admin
+        .api_v3_configure_token_list()
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        admin.api_v3_configure_token_delete("ingest").await,
+        Err(influxdb3_client::Error::ApiError {
+            code: StatusCode::NOT_FOUND,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn auth_is_enabled_by_the_first_named_token() {
+    let server = TestServer::configure().spawn().await;
+    let anonymous = influxdb3_client::Client::new(server.client_addr()).unwrap();
+
+    // without an admin token, requests are allowed until a token is created:
+    let created = anonymous
+        .api_v3_configure_token_create("admin")
+        .permission("*", [TokenAction::Admin])
+        .send()
+        .await
+        .expect("create token");
+
+    // from then on, requests require a token, including those that manage tokens:
+    assert!(matches!(
+        anonymous.api_v3_configure_token_list().await,
+        Err(influxdb3_client::Error::ApiError {
+            code: StatusCode::UNAUTHORIZED,
+            ..
+        })
+    ));
+    assert!(matches!(
+        anonymous
+            .api_v3_configure_token_create("other")
+            .permission("*", [TokenAction::Admin])
+            .send()
+            .await,
+        Err(influxdb3_client::Error::ApiError {
+            code: StatusCode::UNAUTHORIZED,
+            ..
+        })
+    ));
+    let admin = influxdb3_client::Client::new(server.client_addr())
+        .unwrap()
+        .with_auth_token(&created.token);
+    assert_eq!(1, admin.api_v3_configure_token_list().await.unwrap().len());
+}
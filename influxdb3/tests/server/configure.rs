@@ -1,4 +1,5 @@
 use hyper::StatusCode;
+use serde_json::json;
 
 use crate::TestServer;
 
@@ -367,3 +368,621 @@ async fn api_v3_configure_last_cache_delete() {
         }
     }
 }
+
+#[tokio::test]
+async fn api_v3_configure_meta_cache_create() {
+    let server = TestServer::spawn().await;
+
+    // Write some LP to the database to initialize the catalog:
+    let db_name = "db";
+    let tbl_name = "tbl";
+    server
+        .write_lp_to_db(
+            db_name,
+            format!("{tbl_name},t1=a,t2=b,t3=c f1=true,f2=\"hello\",f3=4i,f4=4u,f5=5 1000"),
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write to db");
+
+    struct TestCase {
+        description: &'static str,
+        request: serde_json::Value,
+        // This is the status code expected in the response:
+        expected: StatusCode,
+    }
+
+    let test_cases = [
+        TestCase {
+            description: "no parameters specified",
+            request: json!({}),
+            expected: StatusCode::BAD_REQUEST,
+        },
+        TestCase {
+            description: "missing columns",
+            request: json!({ "db": db_name, "table": tbl_name }),
+            expected: StatusCode::BAD_REQUEST,
+        },
+        TestCase {
+            description: "empty set of columns",
+            request: json!({ "db": db_name, "table": tbl_name, "columns": [] }),
+            expected: StatusCode::BAD_REQUEST,
+        },
+        TestCase {
+            description: "good, will use defaults for everything omitted, and get back a 201",
+            request: json!({ "db": db_name, "table": tbl_name, "columns": ["t1", "t2"] }),
+            expected: StatusCode::CREATED,
+        },
+        TestCase {
+            description: "same as before, will be successful, but with 204",
+            request: json!({ "db": db_name, "table": tbl_name, "columns": ["t1", "t2"] }),
+            expected: StatusCode::NO_CONTENT,
+        },
+        TestCase {
+            description: "use a specific cache name, will succeed and create new cache",
+            request: json!({
+                "db": db_name,
+                "table": tbl_name,
+                "name": "my_cache",
+                "columns": ["t3"],
+            }),
+            expected: StatusCode::CREATED,
+        },
+        TestCase {
+            description: "same name, but different parameters, results in a bad request",
+            request: json!({
+                "db": db_name,
+                "table": tbl_name,
+                "name": "my_cache",
+                "columns": ["t3"],
+                "max_age": 10,
+            }),
+            expected: StatusCode::BAD_REQUEST,
+        },
+        TestCase {
+            description: "column that does not exist is a bad request",
+            request: json!({
+                "db": db_name,
+                "table": tbl_name,
+                "columns": ["not_a_column"],
+            }),
+            expected: StatusCode::BAD_REQUEST,
+        },
+        TestCase {
+            description: "column of unsupported type is a bad request",
+            request: json!({ "db": db_name, "table": tbl_name, "columns": ["f1"] }),
+            expected: StatusCode::BAD_REQUEST,
+        },
+        TestCase {
+            description: "zero max cardinality is a bad request",
+            request: json!({
+                "db": db_name,
+                "table": tbl_name,
+                "columns": ["t1"],
+                "max_cardinality": 0,
+            }),
+            expected: StatusCode::BAD_REQUEST,
+        },
+    ];
+
+    for (i, t) in test_cases.iter().enumerate() {
+        let status = server
+            .api_v3_configure_meta_cache_create(&t.request)
+            .await
+            .status();
+        assert_eq!(
+            t.expected,
+            status,
+            "test case ({i}) failed, {description}",
+            description = t.description
+        );
+    }
+}
+
+#[tokio::test]
+async fn api_v3_configure_meta_cache_query_and_delete() {
+    let server = TestServer::spawn().await;
+
+    let db_name = "db";
+    let tbl_name = "cpu";
+    let cache_name = "cpu_cache";
+    server
+        .write_lp_to_db(
+            db_name,
+            format!(
+                "{tbl_name},region=us-east,host=a usage=0.1\n\
+                {tbl_name},region=us-east,host=b usage=0.2\n\
+                {tbl_name},region=us-west,host=c usage=0.3\n\
+                {tbl_name},region=ca-cent,host=d usage=0.4"
+            ),
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write to db");
+
+    let resp = server
+        .api_v3_configure_meta_cache_create(&json!({
+            "db": db_name,
+            "table": tbl_name,
+            "name": cache_name,
+            "columns": ["region", "host"],
+        }))
+        .await;
+    assert_eq!(StatusCode::CREATED, resp.status());
+
+    // Write more data, which will be added to the cache. Timestamps are omitted so that rows are
+    // given the current time, and will therefore not be outside the cache's max age. Only data
+    // written after the cache was created will be in the cache:
+    server
+        .write_lp_to_db(
+            db_name,
+            format!(
+                "{tbl_name},region=us-east,host=a usage=0.5\n\
+                {tbl_name},region=us-west,host=e usage=0.6\n\
+                {tbl_name},region=ca-cent,host=f usage=0.7"
+            ),
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write to db");
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", db_name),
+            (
+                "q",
+                "SELECT * FROM meta_cache('cpu') WHERE region != 'ca-cent' ORDER BY region, host",
+            ),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+---------+------+\n\
+        | region  | host |\n\
+        +---------+------+\n\
+        | us-east | a    |\n\
+        | us-west | e    |\n\
+        +---------+------+",
+        resp
+    );
+
+    let resp = server
+        .api_v3_configure_meta_cache_delete(&json!({
+            "db": db_name,
+            "table": tbl_name,
+            "name": cache_name,
+        }))
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+
+    // Deleting again gives a 404:
+    let resp = server
+        .api_v3_configure_meta_cache_delete(&json!({
+            "db": db_name,
+            "table": tbl_name,
+            "name": cache_name,
+        }))
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    // The cache can no longer be queried:
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", db_name),
+            ("q", "SELECT * FROM meta_cache('cpu')"),
+            ("format", "pretty"),
+        ])
+        .await;
+    assert!(!resp.status().is_success());
+}
+
+#[tokio::test]
+async fn api_v3_configure_database_retention_period() {
+    let server = TestServer::spawn().await;
+    let db_name = "foo";
+
+    // An invalid database name is rejected:
+    let resp = server
+        .api_v3_configure_database_retention_period(&json!({
+            "db": "",
+            "retention_period": 3600,
+        }))
+        .await;
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+    // The database must exist:
+    let resp = server
+        .api_v3_configure_database_retention_period(&json!({
+            "db": db_name,
+            "retention_period": 3600,
+        }))
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    let resp = server
+        .api_v3_configure_database_create(&json!({ "db": db_name }))
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+    let resp = server
+        .api_v3_configure_database_retention_period(&json!({
+            "db": db_name,
+            "retention_period": 3600,
+        }))
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+
+    let resp = server
+        .api_v3_query_influxql(&[
+            ("q", "SHOW RETENTION POLICIES ON foo"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+---------------+---------+---------------+\n\
+        | iox::database | name    | duration      |\n\
+        +---------------+---------+---------------+\n\
+        | foo           | autogen | 3600000000000 |\n\
+        +---------------+---------+---------------+",
+        resp
+    );
+
+    // Lines older than the retention period are rejected, while the rest are written:
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let err = server
+        .write_lp_to_db(
+            db_name,
+            format!(
+                "cpu,host=a usage=1 {old}\n\
+                cpu,host=b usage=2 {now}",
+                old = now - 7200
+            ),
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect_err("write of line outside retention period should fail");
+    assert!(
+        err.to_string()
+            .contains("line timestamp is older than the database retention period"),
+        "unexpected error: {err}"
+    );
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", db_name),
+            ("q", "SELECT host, usage FROM cpu"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | b    | 2.0   |\n\
+        +------+-------+",
+        resp
+    );
+
+    // The retention period can be cleared again:
+    let resp = server
+        .api_v3_configure_database_retention_period(&json!({
+            "db": db_name,
+            "retention_period": null,
+        }))
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+    server
+        .write_lp_to_db(
+            db_name,
+            format!("cpu,host=a usage=1 {old}", old = now - 7200),
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write line after clearing retention period");
+}
+
+#[tokio::test]
+async fn api_v3_configure_database_and_table_delete() {
+    let server = TestServer::spawn().await;
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a usage=1 1\n\
+            mem,host=a usage=2 1",
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write to db");
+
+    // Tables and databases that do not exist cannot be deleted:
+    let resp = server
+        .api_v3_configure_table_delete(&json!({ "db": "foo", "table": "disk" }))
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    let resp = server
+        .api_v3_configure_database_delete(&json!({ "db": "bar" }))
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    // Deleting a table removes it from queries:
+    let resp = server
+        .api_v3_configure_table_delete(&json!({ "db": "foo", "table": "cpu" }))
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+    let resp = server
+        .api_v3_query_influxql(&[
+            ("db", "foo"),
+            ("q", "SHOW MEASUREMENTS"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+------------------+------+\n\
+        | iox::measurement | name |\n\
+        +------------------+------+\n\
+        | measurements     | mem  |\n\
+        +------------------+------+",
+        resp
+    );
+
+    // The table name can be reused, without any of the old data:
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=b usage=3 2",
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write to new table");
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT host, usage FROM cpu"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | b    | 3.0   |\n\
+        +------+-------+",
+        resp
+    );
+
+    // Deleting the database removes it, and frees up its name:
+    let resp = server
+        .api_v3_configure_database_delete(&json!({ "db": "foo" }))
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+    let resp = server
+        .api_v3_query_influxql(&[("q", "SHOW DATABASES"), ("format", "pretty")])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(!resp.contains("foo"), "deleted database was listed: {resp}");
+    server
+        .write_lp_to_db(
+            "foo",
+            "mem,host=c usage=4 3",
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write to new database");
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT host, usage FROM mem"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | c    | 4.0   |\n\
+        +------+-------+",
+        resp
+    );
+}
+
+#[tokio::test]
+async fn api_v3_configure_database_and_table_create() {
+    let server = TestServer::spawn().await;
+
+    let resp = server
+        .api_v3_configure_database_create(&json!({ "db": "foo" }))
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+    let resp = server
+        .api_v3_configure_database_create(&json!({ "db": "foo" }))
+        .await;
+    assert_eq!(StatusCode::CONFLICT, resp.status());
+
+    // The new database is listed, even though nothing has been written to it:
+    let resp = server
+        .api_v3_query_influxql(&[("q", "SHOW DATABASES"), ("format", "pretty")])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(
+        resp.contains("foo"),
+        "created database was not listed: {resp}"
+    );
+
+    // A database can be created with a retention period:
+    let resp = server
+        .api_v3_configure_database_create(&json!({ "db": "baz", "retention_period": 3600 }))
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+    let resp = server
+        .api_v3_query_influxql(&[
+            ("q", "SHOW RETENTION POLICIES ON baz"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+---------------+---------+---------------+\n\
+        | iox::database | name    | duration      |\n\
+        +---------------+---------+---------------+\n\
+        | baz           | autogen | 3600000000000 |\n\
+        +---------------+---------+---------------+",
+        resp
+    );
+
+    let table = json!({
+        "db": "foo",
+        "table": "cpu",
+        "tags": ["region", "host"],
+        "fields": [
+            { "name": "usage", "type": "float" },
+            { "name": "up", "type": "boolean" },
+        ],
+    });
+    let resp = server
+        .api_v3_configure_table_create(&json!({ "db": "bar", "table": "cpu" }))
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    let resp = server
+        .api_v3_configure_table_create(&json!({
+            "db": "foo",
+            "table": "cpu",
+            "tags": ["host"],
+            "fields": [{ "name": "host", "type": "string" }],
+        }))
+        .await;
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+    let resp = server.api_v3_configure_table_create(&table).await;
+    assert_eq!(StatusCode::OK, resp.status());
+    let resp = server.api_v3_configure_table_create(&table).await;
+    assert_eq!(StatusCode::CONFLICT, resp.status());
+
+    // Line protocol can be written to the table:
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,region=us,host=a usage=1,up=true 1",
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write to table");
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT * FROM cpu"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+------+--------+---------------------+------+-------+\n\
+        | host | region | time                | up   | usage |\n\
+        +------+--------+---------------------+------+-------+\n\
+        | a    | us     | 1970-01-01T00:00:01 | true | 1.0   |\n\
+        +------+--------+---------------------+------+-------+",
+        resp
+    );
+}
+
+#[tokio::test]
+async fn api_v3_configure_schema_lock() {
+    let server = TestServer::spawn().await;
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a usage=1 1",
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write to db");
+
+    let resp = server
+        .api_v3_configure_schema_lock(&json!({ "db": "foo", "table": "mem", "locked": true }))
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    let resp = server
+        .api_v3_configure_schema_lock(&json!({ "db": "foo", "locked": true }))
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+
+    // Writes that would add a column or table are rejected, while others are accepted:
+    for lp in [
+        "cpu,host=a usage=2,temp=3 2",
+        "cpu,host=a,region=us usage=2 2",
+        "mem,host=a usage=2 2",
+    ] {
+        let result = server
+            .write_lp_to_db("foo", lp, influxdb3_client::Precision::Second)
+            .await;
+        assert!(result.is_err(), "write to locked schema was accepted: {lp}");
+    }
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=b usage=2 2",
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write within the locked schema");
+
+    // Unlocking the database allows the schema to change again:
+    let resp = server
+        .api_v3_configure_schema_lock(&json!({ "db": "foo", "locked": false }))
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=c usage=3,temp=4 3",
+            influxdb3_client::Precision::Second,
+        )
+        .await
+        .expect("write to unlocked schema");
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT host, temp, usage FROM cpu ORDER BY host"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+------+------+-------+\n\
+        | host | temp | usage |\n\
+        +------+------+-------+\n\
+        | a    |      | 1.0   |\n\
+        | b    |      | 2.0   |\n\
+        | c    | 4.0  | 3.0   |\n\
+        +------+------+-------+",
+        resp
+    );
+}
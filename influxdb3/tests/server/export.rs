@@ -0,0 +1,99 @@
+use influxdb3_client::Precision;
+use pretty_assertions::assert_eq;
+use reqwest::StatusCode;
+
+use crate::TestServer;
+
+#[tokio::test]
+async fn api_v3_export() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a,region=us\\ east usage=0.5,count=1i,total=2u,ok=true 1\n\
+            cpu,host=b label=\"say \\\"hi\\\"\" 2\n\
+            cpu,host=a usage=0.7 3\n\
+            mem,host=a free=10i 1",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    struct TestCase<'a> {
+        params: Vec<(&'a str, &'a str)>,
+        expected: &'a str,
+    }
+
+    let test_cases = [
+        TestCase {
+            params: vec![("db", "foo"), ("table", "cpu")],
+            expected: "cpu,host=a,region=us\\ east count=1i,ok=true,total=2u,usage=0.5 1\n\
+                cpu,host=b label=\"say \\\"hi\\\"\" 2\n\
+                cpu,host=a usage=0.7 3\n",
+        },
+        TestCase {
+            params: vec![
+                ("db", "foo"),
+                ("table", "cpu,mem"),
+                ("start", "2"),
+                ("end", "3"),
+            ],
+            expected: "cpu,host=b label=\"say \\\"hi\\\"\" 2\n",
+        },
+        TestCase {
+            params: vec![
+                ("db", "foo"),
+                ("table", "mem"),
+                ("end", "1970-01-01T00:00:01Z"),
+            ],
+            expected: "mem,host=a free=10i 1\n",
+        },
+    ];
+
+    for t in test_cases {
+        let resp = server.api_v3_export(&t.params).await;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(
+            t.expected,
+            resp.text().await.unwrap(),
+            "params: {:?}",
+            t.params
+        );
+    }
+
+    // the exported line protocol can be written into another database:
+    let lp = server
+        .api_v3_export(&[("db", "foo")])
+        .await
+        .text()
+        .await
+        .unwrap();
+    server
+        .write_lp_to_db("bar", &lp, Precision::Nanosecond)
+        .await
+        .unwrap();
+    let exported = server
+        .api_v3_export(&[("db", "bar")])
+        .await
+        .text()
+        .await
+        .unwrap();
+    let mut expected: Vec<_> = lp.lines().collect();
+    let mut actual: Vec<_> = exported.lines().collect();
+    expected.sort();
+    actual.sort();
+    assert_eq!(expected, actual);
+
+    // missing tables and databases are not found:
+    let resp = server
+        .api_v3_export(&[("db", "foo"), ("table", "disk")])
+        .await;
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    let resp = server.api_v3_export(&[("db", "baz")]).await;
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    let resp = server
+        .api_v3_export(&[("db", "foo"), ("start", "yesterday")])
+        .await;
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+}
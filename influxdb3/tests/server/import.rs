@@ -0,0 +1,211 @@
+use influxdb3_client::Precision;
+use pretty_assertions::assert_eq;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use crate::TestServer;
+
+#[tokio::test]
+async fn api_v3_import_csv() {
+    let server = TestServer::spawn().await;
+
+    let csv = "host,usage,count,ts,ignored\n\
+        a,0.5,1,1,x\n\
+        b,,2,2,y\n\
+        c,0.7,3,,z\n";
+    let resp = server
+        .api_v3_import(
+            &[
+                ("db", "foo"),
+                ("table", "cpu"),
+                ("format", "csv"),
+                ("tags", "host"),
+                ("fields", "usage:float,count:integer"),
+                ("time", "ts"),
+                ("precision", "second"),
+            ],
+            csv,
+        )
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+    // the row without a time is skipped:
+    assert_eq!(
+        json!({"row_count": 2, "skipped_row_count": 1}),
+        resp.json::<Value>().await.unwrap()
+    );
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            (
+                "q",
+                "SELECT host, count, usage, time FROM cpu ORDER BY time",
+            ),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+------+-------+-------+---------------------+\n\
+        | host | count | usage | time                |\n\
+        +------+-------+-------+---------------------+\n\
+        | a    | 1     | 0.5   | 1970-01-01T00:00:01 |\n\
+        | b    | 2     |       | 1970-01-01T00:00:02 |\n\
+        +------+-------+-------+---------------------+",
+        resp
+    );
+
+    // values that cannot be converted to the type of their field fail the import:
+    let resp = server
+        .api_v3_import(
+            &[
+                ("db", "foo"),
+                ("table", "cpu"),
+                ("format", "csv"),
+                ("tags", "host"),
+                ("fields", "usage"),
+                ("time", "ts"),
+            ],
+            "host,usage,ts\na,high,10\n",
+        )
+        .await;
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+    // the format must be given, either as a parameter or by the content type:
+    let resp = server
+        .api_v3_import(&[("db", "foo"), ("table", "cpu")], csv)
+        .await;
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+}
+
+#[tokio::test]
+async fn api_v3_import_parquet() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "mem,host=a free=10i,used=0.5 1\n\
+            mem,host=b free=20i,used=0.7 2",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+    let parquet = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT host, free, used, time FROM mem"),
+            ("format", "parquet"),
+        ])
+        .await
+        .bytes()
+        .await
+        .unwrap();
+
+    // all columns of a Parquet file are imported, with types from their Arrow data types:
+    let resp = server
+        .api_v3_import(
+            &[
+                ("db", "bar"),
+                ("table", "mem"),
+                ("format", "parquet"),
+                ("tags", "host"),
+            ],
+            parquet,
+        )
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(
+        json!({"row_count": 2, "skipped_row_count": 0}),
+        resp.json::<Value>().await.unwrap()
+    );
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "bar"),
+            ("q", "SELECT host, free, used, time FROM mem ORDER BY time"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "+------+------+------+-------------------------------+\n\
+        | host | free | used | time                          |\n\
+        +------+------+------+-------------------------------+\n\
+        | a    | 10   | 0.5  | 1970-01-01T00:00:00.000000001 |\n\
+        | b    | 20   | 0.7  | 1970-01-01T00:00:00.000000002 |\n\
+        +------+------+------+-------------------------------+",
+        resp
+    );
+}
+
+#[tokio::test]
+async fn api_v3_import_larger_than_max_request_size() {
+    let server = TestServer::configure()
+        .with_max_http_request_size(1024)
+        .spawn()
+        .await;
+
+    let csv = std::iter::once("host,usage,time\n".to_string())
+        .chain((0..1000).map(|i| format!("host-{i},{i}.5,{i}\n")))
+        .collect::<String>();
+    assert!(csv.len() > 1024);
+    let resp = server
+        .api_v3_import(
+            &[
+                ("db", "foo"),
+                ("table", "cpu"),
+                ("format", "csv"),
+                ("tags", "host"),
+                ("fields", "usage:float"),
+            ],
+            csv,
+        )
+        .await;
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(
+        json!({"row_count": 1000, "skipped_row_count": 0}),
+        resp.json::<Value>().await.unwrap()
+    );
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT count(*) AS count FROM cpu"),
+            ("format", "json"),
+        ])
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(json!([{"count": 1000}]), resp);
+}
+
+#[tokio::test]
+async fn api_v3_import_larger_than_max_import_size() {
+    let server = TestServer::configure()
+        .with_max_import_size(1024)
+        .spawn()
+        .await;
+
+    let csv = std::iter::once("host,usage,time\n".to_string())
+        .chain((0..1000).map(|i| format!("host-{i},{i}.5,{i}\n")))
+        .collect::<String>();
+    let resp = server
+        .api_v3_import(
+            &[
+                ("db", "foo"),
+                ("table", "cpu"),
+                ("format", "csv"),
+                ("tags", "host"),
+                ("fields", "usage:float"),
+            ],
+            csv,
+        )
+        .await;
+    assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+}
@@ -16,7 +16,9 @@ use reqwest::Response;
 mod auth;
 mod client;
 mod configure;
+mod export;
 mod flight;
+mod import;
 mod limits;
 mod ping;
 mod query;
@@ -47,6 +49,8 @@ trait ConfigProvider {
 pub struct TestConfig {
     auth_token: Option<(String, String)>,
     host_id: Option<String>,
+    max_http_request_size: Option<usize>,
+    max_import_size: Option<usize>,
 }
 
 impl TestConfig {
@@ -65,6 +69,18 @@ impl TestConfig {
         self.host_id = Some(host_id.into());
         self
     }
+
+    /// Set the maximum size of an HTTP request body on the spawned [`TestServer`]
+    pub fn with_max_http_request_size(mut self, bytes: usize) -> Self {
+        self.max_http_request_size = Some(bytes);
+        self
+    }
+
+    /// Set the maximum size of an import on the spawned [`TestServer`]
+    pub fn with_max_import_size(mut self, bytes: usize) -> Self {
+        self.max_import_size = Some(bytes);
+        self
+    }
 }
 
 impl ConfigProvider for TestConfig {
@@ -79,6 +95,18 @@ impl ConfigProvider for TestConfig {
         } else {
             args.push("test-server".to_string());
         }
+        if let Some(bytes) = self.max_http_request_size {
+            args.append(&mut vec![
+                "--max-http-request-size".to_string(),
+                bytes.to_string(),
+            ]);
+        }
+        if let Some(bytes) = self.max_import_size {
+            args.append(&mut vec![
+                "--max-import-size".to_string(),
+                bytes.to_string(),
+            ]);
+        }
         args.append(&mut vec![
             "--object-store".to_string(),
             "memory".to_string(),
@@ -252,6 +280,40 @@ impl TestServer {
             .expect("send /api/v3/query_influxql request to server")
     }
 
+    pub async fn api_v3_query_kill(&self, id: &str) -> Response {
+        self.http_client
+            .delete(format!(
+                "{base}/api/v3/query/{id}",
+                base = self.client_addr()
+            ))
+            .send()
+            .await
+            .expect("send request to kill a query to server")
+    }
+
+    pub async fn api_v3_export(&self, params: &[(&str, &str)]) -> Response {
+        self.http_client
+            .get(format!("{base}/api/v3/export", base = self.client_addr()))
+            .query(params)
+            .send()
+            .await
+            .expect("send /api/v3/export request to server")
+    }
+
+    pub async fn api_v3_import(
+        &self,
+        params: &[(&str, &str)],
+        body: impl Into<reqwest::Body>,
+    ) -> Response {
+        self.http_client
+            .post(format!("{base}/api/v3/import", base = self.client_addr()))
+            .query(params)
+            .body(body)
+            .send()
+            .await
+            .expect("send /api/v3/import request to server")
+    }
+
     pub async fn api_v1_query(
         &self,
         params: &[(&str, &str)],
@@ -306,6 +368,111 @@ impl TestServer {
             .await
             .expect("failed to send request to delete last cache")
     }
+
+    pub async fn api_v3_configure_meta_cache_create(
+        &self,
+        request: &serde_json::Value,
+    ) -> Response {
+        self.http_client
+            .post(format!(
+                "{base}/api/v3/configure/meta_cache",
+                base = self.client_addr()
+            ))
+            .json(request)
+            .send()
+            .await
+            .expect("failed to send request to create metadata cache")
+    }
+
+    pub async fn api_v3_configure_meta_cache_delete(
+        &self,
+        request: &serde_json::Value,
+    ) -> Response {
+        self.http_client
+            .delete(format!(
+                "{base}/api/v3/configure/meta_cache",
+                base = self.client_addr()
+            ))
+            .json(request)
+            .send()
+            .await
+            .expect("failed to send request to delete metadata cache")
+    }
+
+    pub async fn api_v3_configure_database_retention_period(
+        &self,
+        request: &serde_json::Value,
+    ) -> Response {
+        self.http_client
+            .post(format!(
+                "{base}/api/v3/configure/database/retention_period",
+                base = self.client_addr()
+            ))
+            .json(request)
+            .send()
+            .await
+            .expect("failed to send request to set database retention period")
+    }
+
+    pub async fn api_v3_configure_schema_lock(&self, request: &serde_json::Value) -> Response {
+        self.http_client
+            .post(format!(
+                "{base}/api/v3/configure/schema_lock",
+                base = self.client_addr()
+            ))
+            .json(request)
+            .send()
+            .await
+            .expect("failed to send request to set schema lock")
+    }
+
+    pub async fn api_v3_configure_database_create(&self, request: &serde_json::Value) -> Response {
+        self.http_client
+            .post(format!(
+                "{base}/api/v3/configure/database",
+                base = self.client_addr()
+            ))
+            .json(request)
+            .send()
+            .await
+            .expect("failed to send request to create database")
+    }
+
+    pub async fn api_v3_configure_table_create(&self, request: &serde_json::Value) -> Response {
+        self.http_client
+            .post(format!(
+                "{base}/api/v3/configure/table",
+                base = self.client_addr()
+            ))
+            .json(request)
+            .send()
+            .await
+            .expect("failed to send request to create table")
+    }
+
+    pub async fn api_v3_configure_database_delete(&self, request: &serde_json::Value) -> Response {
+        self.http_client
+            .delete(format!(
+                "{base}/api/v3/configure/database",
+                base = self.client_addr()
+            ))
+            .json(request)
+            .send()
+            .await
+            .expect("failed to send request to delete database")
+    }
+
+    pub async fn api_v3_configure_table_delete(&self, request: &serde_json::Value) -> Response {
+        self.http_client
+            .delete(format!(
+                "{base}/api/v3/configure/table",
+                base = self.client_addr()
+            ))
+            .json(request)
+            .send()
+            .await
+            .expect("failed to send request to delete table")
+    }
 }
 
 /// Get an available bind address on localhost
@@ -2,6 +2,7 @@ use crate::TestServer;
 use futures::StreamExt;
 use influxdb3_client::Precision;
 use pretty_assertions::assert_eq;
+use reqwest::StatusCode;
 use serde_json::{json, Value};
 use test_helpers::assert_contains;
 
@@ -679,6 +680,76 @@ async fn api_v3_query_json_format() {
     }
 }
 
+#[tokio::test]
+async fn api_v3_query_jsonl_format() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a,region=us-east usage=0.9 1
+            cpu,host=b,region=us-east usage=0.50 1
+            cpu,host=a,region=us-east usage=0.80 2
+            cpu,host=b,region=us-east usage=0.60 2",
+            Precision::Second,
+        )
+        .await
+        .unwrap();
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            (
+                "q",
+                "SELECT host, region, time, usage FROM cpu ORDER BY time, host",
+            ),
+            ("format", "jsonl"),
+        ])
+        .await;
+    assert_eq!(
+        "application/jsonl",
+        resp.headers()
+            .get("content-type")
+            .unwrap()
+            .to_str()
+            .unwrap()
+    );
+    let resp = resp.text().await.unwrap();
+    assert_eq!(
+        "{\"host\":\"a\",\"region\":\"us-east\",\"time\":\"1970-01-01T00:00:01\",\"usage\":0.9}\n\
+        {\"host\":\"b\",\"region\":\"us-east\",\"time\":\"1970-01-01T00:00:01\",\"usage\":0.5}\n\
+        {\"host\":\"a\",\"region\":\"us-east\",\"time\":\"1970-01-01T00:00:02\",\"usage\":0.8}\n\
+        {\"host\":\"b\",\"region\":\"us-east\",\"time\":\"1970-01-01T00:00:02\",\"usage\":0.6}\n",
+        resp
+    );
+}
+
+#[tokio::test]
+async fn api_v3_query_empty_result_formats() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a,region=us-east usage=0.9 1",
+            Precision::Second,
+        )
+        .await
+        .unwrap();
+
+    // a query that returns no rows should still produce valid output for streamed formats:
+    let query = "SELECT host, usage FROM cpu WHERE host = 'z'";
+    for (format, expected) in [("json", "[]"), ("jsonl", ""), ("csv", "")] {
+        let resp = server
+            .api_v3_query_sql(&[("db", "foo"), ("q", query), ("format", format)])
+            .await
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(expected, resp, "unexpected response for format: {format}");
+    }
+}
+
 #[tokio::test]
 async fn api_v1_query_json_format() {
     let server = TestServer::spawn().await;
@@ -1249,3 +1320,395 @@ async fn api_v1_query_data_conversion() {
         assert_eq!(t.expected, resp, "query failed: {q}", q = t.query);
     }
 }
+
+#[tokio::test]
+async fn api_v1_query_multiple_statements() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a usage=0.9 1\n\
+            cpu,host=a usage=0.89 2\n\
+            mem,host=a usage=0.5 4\n\
+            mem,host=a usage=0.6 5",
+            Precision::Second,
+        )
+        .await
+        .unwrap();
+
+    struct TestCase<'a> {
+        database: Option<&'a str>,
+        chunked: bool,
+        query: &'a str,
+        expected: Vec<Value>,
+    }
+
+    let test_cases = [
+        // Each statement has its own result:
+        TestCase {
+            database: Some("foo"),
+            chunked: false,
+            query: "SELECT time, host, usage FROM cpu; SELECT time, host, usage FROM mem",
+            expected: vec![json!({
+              "results": [
+                {
+                  "series": [
+                    {
+                      "name": "cpu",
+                      "columns": ["time", "host", "usage"],
+                      "values": [
+                        [1000, "a", 0.9],
+                        [2000, "a", 0.89]
+                      ]
+                    }
+                  ],
+                  "statement_id": 0
+                },
+                {
+                  "series": [
+                    {
+                      "name": "mem",
+                      "columns": ["time", "host", "usage"],
+                      "values": [
+                        [4000, "a", 0.5],
+                        [5000, "a", 0.6]
+                      ]
+                    }
+                  ],
+                  "statement_id": 1
+                }
+              ]
+            })],
+        },
+        // Statements that produce no results still have a result:
+        TestCase {
+            database: Some("foo"),
+            chunked: false,
+            query: "SELECT time, host, usage FROM cpu WHERE host = 'z'; \
+                SELECT time, host, usage FROM mem",
+            expected: vec![json!({
+              "results": [
+                {
+                  "statement_id": 0
+                },
+                {
+                  "series": [
+                    {
+                      "name": "mem",
+                      "columns": ["time", "host", "usage"],
+                      "values": [
+                        [4000, "a", 0.5],
+                        [5000, "a", 0.6]
+                      ]
+                    }
+                  ],
+                  "statement_id": 1
+                }
+              ]
+            })],
+        },
+        // A failed statement has an error as its result, and the statements after it
+        // are not run:
+        TestCase {
+            database: None,
+            chunked: false,
+            query: "SELECT time, host, usage FROM foo.autogen.mem; \
+                SELECT usage FROM bar.autogen.cpu; \
+                SELECT time, host, usage FROM foo.autogen.cpu",
+            expected: vec![json!({
+              "results": [
+                {
+                  "series": [
+                    {
+                      "name": "mem",
+                      "columns": ["time", "host", "usage"],
+                      "values": [
+                        [4000, "a", 0.5],
+                        [5000, "a", 0.6]
+                      ]
+                    }
+                  ],
+                  "statement_id": 0
+                },
+                {
+                  "error": "query error: database not found: bar",
+                  "statement_id": 1
+                }
+              ]
+            })],
+        },
+        // The first statement failing is also returned as its result:
+        TestCase {
+            database: None,
+            chunked: false,
+            query: "SELECT usage FROM bar.autogen.cpu; \
+                SELECT time, host, usage FROM foo.autogen.cpu",
+            expected: vec![json!({
+              "results": [
+                {
+                  "error": "query error: database not found: bar",
+                  "statement_id": 0
+                }
+              ]
+            })],
+        },
+        // Each statement is chunked separately:
+        TestCase {
+            database: Some("foo"),
+            chunked: true,
+            query: "SELECT time, host, usage FROM cpu; SELECT time, host, usage FROM mem",
+            expected: vec![
+                json!({
+                  "results": [
+                    {
+                      "series": [
+                        {
+                          "name": "cpu",
+                          "columns": ["time", "host", "usage"],
+                          "values": [
+                            [1000, "a", 0.9],
+                            [2000, "a", 0.89]
+                          ]
+                        }
+                      ],
+                      "statement_id": 0
+                    }
+                  ]
+                }),
+                json!({
+                  "results": [
+                    {
+                      "series": [
+                        {
+                          "name": "mem",
+                          "columns": ["time", "host", "usage"],
+                          "values": [
+                            [4000, "a", 0.5],
+                            [5000, "a", 0.6]
+                          ]
+                        }
+                      ],
+                      "statement_id": 1
+                    }
+                  ]
+                }),
+            ],
+        },
+    ];
+
+    for t in test_cases {
+        let mut params = vec![("q", t.query), ("epoch", "ms")];
+        if let Some(db) = t.database {
+            params.push(("db", db));
+        }
+        if t.chunked {
+            params.push(("chunked", "true"));
+        }
+        let resp = server.api_v1_query(&params, None).await;
+        assert_eq!(
+            StatusCode::OK,
+            resp.status(),
+            "query failed: {q}",
+            q = t.query
+        );
+        let values = resp
+            .bytes_stream()
+            .map(|chunk| serde_json::from_slice(chunk.unwrap().as_ref()).unwrap())
+            .collect::<Vec<Value>>()
+            .await;
+        println!("\n{q}", q = t.query);
+        assert_eq!(t.expected, values, "query failed: {q}", q = t.query);
+    }
+
+    // the results of each statement are separated by an empty line in CSV:
+    let resp = server
+        .api_v1_query(
+            &[
+                ("db", "foo"),
+                ("epoch", "s"),
+                (
+                    "q",
+                    "SELECT time, host, usage FROM cpu; SELECT time, usage FROM mem",
+                ),
+            ],
+            Some(&[("Accept", "application/csv")]),
+        )
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        "name,tags,time,host,usage\n\
+        cpu,,1,a,0.9\n\
+        cpu,,2,a,0.89\n\
+        \n\
+        name,tags,time,usage\n\
+        mem,,4,0.5\n\
+        mem,,5,0.6\n\r\n",
+        resp
+    );
+}
+
+#[tokio::test]
+async fn api_query_compressed_responses() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a usage=0.5 1\n\
+            cpu,host=b usage=0.7 2",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    // the client requests compressed responses by default, and decompresses them:
+    let client = influxdb3_client::Client::new(server.client_addr()).unwrap();
+    let compressed = client
+        .api_v3_query_sql("foo", "SELECT host, usage FROM cpu ORDER BY time")
+        .format(influxdb3_client::Format::Csv)
+        .send()
+        .await
+        .unwrap();
+    let uncompressed = client
+        .with_compressed_responses(false)
+        .api_v3_query_sql("foo", "SELECT host, usage FROM cpu ORDER BY time")
+        .format(influxdb3_client::Format::Csv)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(compressed, "host,usage\na,0.5\nb,0.7\n");
+    assert_eq!(compressed, uncompressed);
+
+    let http_client = reqwest::Client::new();
+    let urls = [
+        format!(
+            "{}/api/v3/query_sql?db=foo&q=SELECT%20*%20FROM%20cpu",
+            server.client_addr()
+        ),
+        format!(
+            "{}/api/v3/query_influxql?db=foo&q=SELECT%20*%20FROM%20cpu",
+            server.client_addr()
+        ),
+        format!(
+            "{}/query?db=foo&q=SELECT%20*%20FROM%20cpu",
+            server.client_addr()
+        ),
+    ];
+    let cases = [
+        ("gzip", Some("gzip")),
+        ("gzip, deflate, br", Some("br")),
+        ("gzip, zstd, br", Some("zstd")),
+        ("zstd;q=0.1, gzip", Some("gzip")),
+        ("identity", None),
+    ];
+    for url in &urls {
+        for (accept_encoding, expected) in cases {
+            let resp = http_client
+                .get(url)
+                .header("Accept-Encoding", accept_encoding)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), reqwest::StatusCode::OK);
+            assert_eq!(
+                resp.headers()
+                    .get("Content-Encoding")
+                    .map(|v| v.to_str().unwrap()),
+                expected,
+                "url: {url}, accept-encoding: {accept_encoding}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn api_v3_query_kill() {
+    let server = TestServer::spawn().await;
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=0.5 1", Precision::Nanosecond)
+        .await
+        .unwrap();
+
+    // queries that have completed are no longer running, so cannot be killed:
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", "SELECT * FROM cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    resp.text().await.unwrap();
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            (
+                "q",
+                "SELECT id FROM system.queries WHERE query_text = 'SELECT * FROM cpu'",
+            ),
+            ("format", "json"),
+        ])
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    let id = resp[0]["id"].as_str().unwrap().to_string();
+    let resp = server.api_v3_query_kill(&id).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_contains!(resp.text().await.unwrap(), "no running query found");
+
+    let resp = server
+        .api_v3_query_influxql(&[("q", &format!("KILL QUERY '{id}'"))])
+        .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = server.api_v3_query_influxql(&[("q", "KILL QUERY")]).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn api_v3_query_explain_analyze() {
+    let server = TestServer::spawn().await;
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a usage=0.5 1\n\
+            cpu,host=b usage=0.6 2",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    // the data has not been persisted, so is all read from the write buffer:
+    let check_explain = |explain: &Value| {
+        assert_eq!(explain["rows"], 2);
+        assert!(explain["buffer_chunks"].as_u64().unwrap() > 0);
+        assert_eq!(explain["parquet_chunks"], 0);
+        assert!(explain["plan"]["operator"].is_string());
+        assert!(explain["plan"]["children"].is_array());
+    };
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT * FROM cpu"),
+            ("explain", "analyze"),
+        ])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    check_explain(&resp.json::<Value>().await.unwrap());
+
+    let resp = server
+        .api_v3_query_influxql(&[
+            ("db", "foo"),
+            ("q", "SELECT usage FROM cpu"),
+            ("explain", "analyze"),
+        ])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    check_explain(&resp.json::<Value>().await.unwrap());
+
+    // statements that do not select data cannot be explained:
+    let resp = server
+        .api_v3_query_influxql(&[("q", "SHOW DATABASES"), ("explain", "analyze")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
@@ -1,5 +1,5 @@
 use hyper::StatusCode;
-use influxdb3_client::Precision;
+use influxdb3_client::{ContentEncoding, Precision};
 use pretty_assertions::assert_eq;
 use test_helpers::assert_contains;
 
@@ -429,3 +429,66 @@ async fn writes_with_different_schema_should_fail() {
         "the request should hae failed with an API Error"
     );
 }
+
+#[tokio::test]
+async fn api_v3_write_lp_streamed() {
+    let server = TestServer::configure()
+        .with_max_http_request_size(1024)
+        .spawn()
+        .await;
+    let client = influxdb3_client::Client::new(server.client_addr()).unwrap();
+
+    let lp = (0..200)
+        .map(|i| format!("cpu,host=h{i} usage={i} {i}\n"))
+        .collect::<String>();
+    assert!(lp.len() > 1024);
+
+    // the write is larger than the maximum request size, so can only be made when streamed:
+    let err = client
+        .api_v3_write_lp("foo")
+        .body(lp.clone())
+        .send()
+        .await
+        .unwrap_err();
+    assert_contains!(err.to_string(), "max request size (1024 bytes) exceeded");
+
+    for (db, encoding) in [
+        ("plain", None),
+        ("gzip", Some(ContentEncoding::Gzip)),
+        ("zstd", Some(ContentEncoding::Zstd)),
+    ] {
+        let mut req = client.api_v3_write_lp(db).stream(true);
+        if let Some(encoding) = encoding {
+            req = req.content_encoding(encoding);
+        }
+        req.body(lp.clone()).send().await.unwrap();
+
+        let resp = server
+            .api_v3_query_sql(&[
+                ("db", db),
+                ("q", "SELECT count(*) AS rows, sum(usage) AS total FROM cpu"),
+                ("format", "json"),
+            ])
+            .await
+            .json::<serde_json::Value>()
+            .await
+            .unwrap();
+        assert_eq!(
+            resp,
+            serde_json::json!([{"rows": 200, "total": 19900.0}]),
+            "database: {db}"
+        );
+    }
+
+    // invalid lines are reported with their line number in the whole write, not in the slice
+    // that they were written in:
+    let lp = format!("{lp}cpu,host=h0 usage=\"high\" 0\n");
+    let err = client
+        .api_v3_write_lp("plain")
+        .stream(true)
+        .body(lp)
+        .send()
+        .await
+        .unwrap_err();
+    assert_contains!(err.to_string(), "\"line_number\":201");
+}
@@ -15,11 +15,16 @@ use arrow::{
     error::ArrowError,
 };
 use influxdb3_catalog::catalog::TableDefinition;
-use influxdb3_id::ColumnId;
-use influxdb3_wal::{FieldData, Row};
+use influxdb3_id::{ColumnId, TableId};
+use influxdb3_wal::{FieldData, MetaCacheDefinition, Row};
 use iox_time::TimeProvider;
 use schema::{InfluxColumnType, InfluxFieldType};
 
+mod provider;
+pub use provider::{MetaCacheProvider, ProviderError};
+mod table_function;
+pub use table_function::MetaCacheFunction;
+
 /// A metadata cache for storing distinct values for a set of columns in a table
 #[derive(Debug)]
 pub struct MetaCache {
@@ -58,15 +63,22 @@ pub struct CreateMetaCacheArgs {
 #[derive(Debug, Clone, Copy)]
 pub struct MaxCardinality(NonZeroUsize);
 
-#[cfg(test)]
 impl MaxCardinality {
-    fn try_new(size: usize) -> Result<Self, anyhow::Error> {
+    pub fn try_new(size: usize) -> Result<Self, anyhow::Error> {
         Ok(Self(
             NonZeroUsize::try_from(size).context("invalid size provided")?,
         ))
     }
 }
 
+impl TryFrom<usize> for MaxCardinality {
+    type Error = anyhow::Error;
+
+    fn try_from(size: usize) -> Result<Self, Self::Error> {
+        Self::try_new(size)
+    }
+}
+
 impl Default for MaxCardinality {
     fn default() -> Self {
         Self(NonZeroUsize::new(100_000).unwrap())
@@ -235,6 +247,64 @@ impl MetaCache {
         }
     }
 
+    /// Get the Arrow schema for the [`RecordBatch`]es produced by this cache
+    pub fn arrow_schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    /// Compare the configuration of this cache to that of another, returning an error describing
+    /// the first difference found, if any.
+    fn compare_config(&self, other: &Self) -> Result<(), anyhow::Error> {
+        if self.max_cardinality != other.max_cardinality {
+            bail!(
+                "incompatible max cardinality, expected {}, got {}",
+                self.max_cardinality,
+                other.max_cardinality
+            );
+        }
+        if self.max_age != other.max_age {
+            bail!(
+                "incompatible max age, expected {}s, got {}s",
+                self.max_age.as_secs(),
+                other.max_age.as_secs()
+            );
+        }
+        if self.column_ids != other.column_ids {
+            bail!(
+                "incompatible column id selection, expected {}, got {}",
+                self.column_ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                other
+                    .column_ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+        }
+        Ok(())
+    }
+
+    /// Convert this cache into a [`MetaCacheDefinition`]
+    fn to_definition(
+        &self,
+        table_id: TableId,
+        table_name: Arc<str>,
+        cache_name: Arc<str>,
+    ) -> MetaCacheDefinition {
+        MetaCacheDefinition {
+            table_id,
+            table_name,
+            cache_name,
+            column_ids: self.column_ids.clone(),
+            max_cardinality: self.max_cardinality,
+            max_age_seconds: self.max_age.as_secs(),
+        }
+    }
+
     /// Get the nanosecond timestamp as an `i64`, before which, entries that have not been seen
     /// since are considered expired.
     fn expired_time_ns(&self) -> i64 {
@@ -433,7 +503,6 @@ pub struct Predicate {
     kind: PredicateKind,
 }
 
-#[cfg(test)]
 impl Predicate {
     fn new_eq(column_id: ColumnId, rhs: impl Into<Arc<str>>) -> Self {
         Self {
@@ -464,8 +533,6 @@ impl Predicate {
     }
 }
 
-// TODO: remove this when fully implemented, for now just suppressing compiler warnings
-#[allow(dead_code)]
 #[derive(Debug)]
 enum PredicateKind {
     Eq(Value),
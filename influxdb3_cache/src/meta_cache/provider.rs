@@ -0,0 +1,350 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use arrow::datatypes::SchemaRef;
+use influxdb3_catalog::catalog::{Catalog, TableDefinition};
+use influxdb3_id::{DbId, TableId};
+use influxdb3_wal::{MetaCacheDefinition, WalContents, WalOp};
+use iox_time::TimeProvider;
+use observability_deps::tracing::debug;
+use parking_lot::RwLock;
+
+use super::{CreateMetaCacheArgs, MetaCache};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProviderError {
+    #[error("invalid metadata cache configuration: {0:#}")]
+    InvalidConfiguration(anyhow::Error),
+    #[error(
+        "metadata cache already exists for database and table, but it was configured \
+        differently: {0:#}"
+    )]
+    CacheAlreadyExists(anyhow::Error),
+    #[error("requested metadata cache does not exist")]
+    CacheDoesNotExist,
+}
+
+/// A three level hashmap storing DbId -> TableId -> Cache Name -> MetaCache
+type CacheMap = RwLock<HashMap<DbId, HashMap<TableId, HashMap<Arc<str>, MetaCache>>>>;
+
+/// Provides all metadata caches for the entire database
+pub struct MetaCacheProvider {
+    pub(crate) time_provider: Arc<dyn TimeProvider>,
+    pub(crate) catalog: Arc<Catalog>,
+    pub(crate) cache_map: CacheMap,
+}
+
+impl std::fmt::Debug for MetaCacheProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MetaCacheProvider")
+    }
+}
+
+impl MetaCacheProvider {
+    /// Initialize a [`MetaCacheProvider`] from a [`Catalog`], populating the provider's
+    /// `cache_map` from the definitions in the catalog.
+    pub fn new_from_catalog(
+        time_provider: Arc<dyn TimeProvider>,
+        catalog: Arc<Catalog>,
+    ) -> Result<Arc<Self>, ProviderError> {
+        let provider = Arc::new(MetaCacheProvider {
+            time_provider: Arc::clone(&time_provider),
+            catalog: Arc::clone(&catalog),
+            cache_map: Default::default(),
+        });
+        for db_schema in catalog.list_db_schema() {
+            for table_def in db_schema.tables() {
+                for (cache_name, cache_def) in table_def.meta_caches() {
+                    debug!(%cache_name, ?cache_def, "adding metadata cache from catalog");
+                    assert!(
+                        provider
+                            .create_cache(
+                                db_schema.id,
+                                Some(cache_name),
+                                CreateMetaCacheArgs {
+                                    time_provider: Arc::clone(&time_provider),
+                                    table_def: Arc::clone(&table_def),
+                                    max_cardinality: cache_def
+                                        .max_cardinality
+                                        .try_into()
+                                        .map_err(ProviderError::InvalidConfiguration)?,
+                                    max_age: Duration::from_secs(cache_def.max_age_seconds).into(),
+                                    column_ids: cache_def.column_ids.to_vec(),
+                                },
+                            )?
+                            .is_some(),
+                        "catalog should not contain duplicate metadata cache definitions"
+                    );
+                }
+            }
+        }
+
+        Ok(provider)
+    }
+
+    /// Initialize a [`MetaCacheProvider`] from a [`Catalog`] and run a background process to
+    /// prune expired entries from the caches, and bring them under their maximum cardinality
+    pub fn new_from_catalog_with_background_prune(
+        time_provider: Arc<dyn TimeProvider>,
+        catalog: Arc<Catalog>,
+        prune_interval: Duration,
+    ) -> Result<Arc<Self>, ProviderError> {
+        let provider = Self::new_from_catalog(time_provider, catalog)?;
+
+        background_prune_process(Arc::clone(&provider), prune_interval);
+
+        Ok(provider)
+    }
+
+    /// Get a particular cache's Arrow schema
+    ///
+    /// This is used for the implementation of DataFusion's `TableFunctionImpl` and `TableProvider`
+    /// traits.
+    pub(crate) fn get_cache_schema(
+        &self,
+        db_id: DbId,
+        table_id: TableId,
+        cache_name: &str,
+    ) -> Option<SchemaRef> {
+        self.cache_map
+            .read()
+            .get(&db_id)
+            .and_then(|db| db.get(&table_id))
+            .and_then(|table| table.get(cache_name))
+            .map(|cache| cache.arrow_schema())
+    }
+
+    /// Get the [`MetaCacheDefinition`] for all caches contained in a database
+    pub fn get_cache_definitions_for_db(&self, db_id: &DbId) -> Vec<MetaCacheDefinition> {
+        let Some(db_schema) = self.catalog.db_schema_by_id(db_id) else {
+            return vec![];
+        };
+        self.cache_map
+            .read()
+            .get(db_id)
+            .map(|db| {
+                db.iter()
+                    .flat_map(|(table_id, table)| {
+                        let table_name =
+                            db_schema.table_id_to_name(table_id).expect("table exists");
+                        table.iter().map(move |(cache_name, cache)| {
+                            cache.to_definition(
+                                *table_id,
+                                Arc::clone(&table_name),
+                                Arc::clone(cache_name),
+                            )
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Create a new entry in the metadata cache for a given database and parameters.
+    ///
+    /// If a new cache is created, this will return the [`MetaCacheDefinition`] for the created
+    /// cache; otherwise, if the provided arguments are identical to an existing cache, along with
+    /// any defaults, then `None` will be returned. It is an error to attempt to create a cache that
+    /// would overwrite an existing one with different parameters.
+    ///
+    /// The cache name is optional; if not provided, it will be of the form:
+    /// ```text
+    /// <table_name>_<column_names>_meta_cache
+    /// ```
+    /// Where `<column_names>` is an `_`-separated list of the column names used in the cache.
+    pub fn create_cache(
+        &self,
+        db_id: DbId,
+        cache_name: Option<Arc<str>>,
+        args: CreateMetaCacheArgs,
+    ) -> Result<Option<MetaCacheDefinition>, ProviderError> {
+        let table_def = Arc::clone(&args.table_def);
+        let cache = MetaCache::new(args).map_err(ProviderError::InvalidConfiguration)?;
+        // Generate the cache name if it was not provided
+        let cache_name = cache_name.unwrap_or_else(|| {
+            format!(
+                "{table_name}_{cols}_meta_cache",
+                table_name = table_def.table_name,
+                cols = cache
+                    .column_ids
+                    .iter()
+                    .map(|id| {
+                        table_def
+                            .column_id_to_name(id)
+                            .expect("column id should be valid")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("_")
+            )
+            .into()
+        });
+
+        // Check to see if there is already a cache for the same database/table/cache name, and with
+        // the exact same configuration. If so, we return None, indicating that the operation did
+        // not fail, but that a cache was not created because it already exists. If the underlying
+        // configuration of the newly created cache is different than the one that already exists,
+        // then this is an error.
+        let mut lock = self.cache_map.write();
+        if let Some(existing) = lock
+            .get(&db_id)
+            .and_then(|db| db.get(&table_def.table_id))
+            .and_then(|table| table.get(&cache_name))
+        {
+            return existing
+                .compare_config(&cache)
+                .map(|_| None)
+                .map_err(ProviderError::CacheAlreadyExists);
+        }
+
+        let definition = cache.to_definition(
+            table_def.table_id,
+            Arc::clone(&table_def.table_name),
+            Arc::clone(&cache_name),
+        );
+
+        lock.entry(db_id)
+            .or_default()
+            .entry(table_def.table_id)
+            .or_default()
+            .insert(cache_name, cache);
+
+        Ok(Some(definition))
+    }
+
+    /// Create a new cache given the database schema and WAL definition. This is useful during WAL
+    /// replay.
+    pub fn create_from_definition(
+        &self,
+        db_id: DbId,
+        table_def: Arc<TableDefinition>,
+        definition: &MetaCacheDefinition,
+    ) {
+        let meta_cache = MetaCache::new(CreateMetaCacheArgs {
+            time_provider: Arc::clone(&self.time_provider),
+            table_def,
+            max_cardinality: definition
+                .max_cardinality
+                .try_into()
+                .expect("definition should have a valid max_cardinality"),
+            max_age: Duration::from_secs(definition.max_age_seconds).into(),
+            column_ids: definition.column_ids.to_vec(),
+        })
+        .expect("definition should be valid coming from the WAL");
+        self.cache_map
+            .write()
+            .entry(db_id)
+            .or_default()
+            .entry(definition.table_id)
+            .or_default()
+            .insert(Arc::clone(&definition.cache_name), meta_cache);
+    }
+
+    /// Delete a cache from the provider
+    ///
+    /// This will also clean up empty levels in the provider hierarchy, so if there are no more
+    /// caches for a given table, that table's entry will be removed from the parent map for that
+    /// table's database; likewise for the database's entry in the provider's cache map.
+    pub fn delete_cache(
+        &self,
+        db_id: &DbId,
+        table_id: &TableId,
+        cache_name: &str,
+    ) -> Result<(), ProviderError> {
+        let mut lock = self.cache_map.write();
+
+        let Some(db) = lock.get_mut(db_id) else {
+            return Err(ProviderError::CacheDoesNotExist);
+        };
+
+        let Some(table) = db.get_mut(table_id) else {
+            return Err(ProviderError::CacheDoesNotExist);
+        };
+
+        if table.remove(cache_name).is_none() {
+            return Err(ProviderError::CacheDoesNotExist);
+        }
+
+        if table.is_empty() {
+            db.remove(table_id);
+        }
+
+        if db.is_empty() {
+            lock.remove(db_id);
+        }
+
+        Ok(())
+    }
+
+    /// Delete all caches for a database, e.g., when the database is deleted
+    pub fn delete_caches_for_db(&self, db_id: &DbId) {
+        self.cache_map.write().remove(db_id);
+    }
+
+    /// Delete all caches for a table, e.g., when the table is deleted
+    pub fn delete_caches_for_table(&self, db_id: &DbId, table_id: &TableId) {
+        let mut lock = self.cache_map.write();
+        if let Some(db) = lock.get_mut(db_id) {
+            db.remove(table_id);
+            if db.is_empty() {
+                lock.remove(db_id);
+            }
+        }
+    }
+
+    /// Write the contents of a WAL file to the cache by iterating over its database and table
+    /// batches to find entries that belong in each cache.
+    pub fn write_wal_contents_to_cache(&self, wal_contents: &WalContents) {
+        let mut lock = self.cache_map.write();
+        for op in &wal_contents.ops {
+            match op {
+                WalOp::Write(batch) => {
+                    if let Some(db_caches) = lock.get_mut(&batch.database_id) {
+                        if db_caches.is_empty() {
+                            continue;
+                        }
+                        for (table_id, table_chunks) in &batch.table_chunks {
+                            if let Some(table_caches) = db_caches.get_mut(table_id) {
+                                if table_caches.is_empty() {
+                                    continue;
+                                }
+                                for (_, cache) in table_caches.iter_mut() {
+                                    for chunk in table_chunks.chunk_time_to_chunk.values() {
+                                        for row in &chunk.rows {
+                                            cache.push(row);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                WalOp::Catalog(_) => (),
+            }
+        }
+    }
+
+    /// Run the prune process for all caches in the provider
+    pub fn prune_caches(&self) {
+        let mut lock = self.cache_map.write();
+        lock.iter_mut().for_each(|(_, db_caches)| {
+            db_caches.iter_mut().for_each(|(_, table_caches)| {
+                table_caches.iter_mut().for_each(|(_, cache)| cache.prune())
+            })
+        });
+    }
+}
+
+fn background_prune_process(
+    provider: Arc<MetaCacheProvider>,
+    prune_interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(prune_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+
+            provider.prune_caches();
+        }
+    })
+}
@@ -0,0 +1,216 @@
+use std::{any::Any, collections::HashMap, sync::Arc};
+
+use arrow::datatypes::SchemaRef;
+use async_trait::async_trait;
+use datafusion::{
+    catalog::Session,
+    common::{plan_err, Result},
+    datasource::{function::TableFunctionImpl, TableProvider, TableType},
+    logical_expr::{expr::InList, BinaryExpr, Expr, Operator, TableProviderFilterPushDown},
+    physical_plan::{memory::MemoryExec, ExecutionPlan},
+    scalar::ScalarValue,
+};
+use influxdb3_catalog::catalog::TableDefinition;
+use influxdb3_id::{ColumnId, DbId};
+
+use super::{MetaCacheProvider, Predicate};
+
+struct MetaCacheFunctionProvider {
+    db_id: DbId,
+    table_def: Arc<TableDefinition>,
+    cache_name: Arc<str>,
+    schema: SchemaRef,
+    provider: Arc<MetaCacheProvider>,
+}
+
+#[async_trait]
+impl TableProvider for MetaCacheFunctionProvider {
+    fn as_any(&self) -> &dyn Any {
+        self as &dyn Any
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Temporary
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        Ok(vec![TableProviderFilterPushDown::Inexact; filters.len()])
+    }
+
+    async fn scan(
+        &self,
+        ctx: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let read = self.provider.cache_map.read();
+        let batches = if let Some(cache) = read
+            .get(&self.db_id)
+            .and_then(|db| db.get(&self.table_def.table_id))
+            .and_then(|tbl| tbl.get(&self.cache_name))
+        {
+            let predicates = convert_filter_exprs(&self.table_def, &cache.column_ids, filters);
+            vec![cache.to_record_batch(&predicates)?]
+        } else {
+            // If there is no cache, it means that it was removed, in which case, we just return
+            // an empty set of record batches.
+            vec![]
+        };
+        let mut exec = MemoryExec::try_new(&[batches], self.schema(), projection.cloned())?;
+
+        let show_sizes = ctx.config_options().explain.show_sizes;
+        exec = exec.with_show_sizes(show_sizes);
+
+        Ok(Arc::new(exec))
+    }
+}
+
+/// Convert the filter expressions provided by DataFusion into a set of [`Predicate`]s that can be
+/// evaluated against the cache.
+///
+/// Only `=`, `!=`, `IN`, and `NOT IN` on string literals are supported; any other expressions are
+/// ignored, which is fine since filters are pushed down as `Inexact` and will be re-applied by
+/// DataFusion. The cache requires at most one predicate per column, so only the first supported
+/// predicate encountered for a given column is used.
+fn convert_filter_exprs(
+    table_def: &TableDefinition,
+    cache_column_ids: &[ColumnId],
+    exprs: &[Expr],
+) -> Vec<Predicate> {
+    let column_id = |expr: &Expr| -> Option<ColumnId> {
+        let Expr::Column(c) = expr else {
+            return None;
+        };
+        table_def
+            .column_name_to_id(c.name())
+            .filter(|id| cache_column_ids.contains(id))
+    };
+    let mut predicates: HashMap<ColumnId, Predicate> = HashMap::new();
+    for expr in exprs {
+        let predicate = match expr {
+            Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                let Some(col_id) = column_id(left) else {
+                    continue;
+                };
+                let Some(value) = string_literal(right) else {
+                    continue;
+                };
+                match op {
+                    Operator::Eq => Predicate::new_eq(col_id, value),
+                    Operator::NotEq => Predicate::new_not_eq(col_id, value),
+                    _ => continue,
+                }
+            }
+            Expr::InList(InList {
+                expr,
+                list,
+                negated,
+            }) => {
+                let Some(col_id) = column_id(expr) else {
+                    continue;
+                };
+                let Some(values) = list.iter().map(string_literal).collect::<Option<Vec<_>>>()
+                else {
+                    continue;
+                };
+                if *negated {
+                    Predicate::new_not_in(col_id, values)
+                } else {
+                    Predicate::new_in(col_id, values)
+                }
+            }
+            _ => continue,
+        };
+        predicates.entry(predicate.column_id).or_insert(predicate);
+    }
+    predicates.into_values().collect()
+}
+
+fn string_literal(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Literal(
+            ScalarValue::Utf8(Some(v))
+            | ScalarValue::Utf8View(Some(v))
+            | ScalarValue::LargeUtf8(Some(v)),
+        ) => Some(v.as_str()),
+        _ => None,
+    }
+}
+
+/// Implementation of the `meta_cache` table function, which takes the name of a table, and
+/// optionally the name of a metadata cache on that table, as arguments
+pub struct MetaCacheFunction {
+    db_id: DbId,
+    provider: Arc<MetaCacheProvider>,
+}
+
+impl MetaCacheFunction {
+    pub fn new(db_id: DbId, provider: Arc<MetaCacheProvider>) -> Self {
+        Self { db_id, provider }
+    }
+}
+
+impl TableFunctionImpl for MetaCacheFunction {
+    fn call(&self, args: &[Expr]) -> Result<Arc<dyn TableProvider>> {
+        let Some(Expr::Literal(ScalarValue::Utf8(Some(table_name)))) = args.first() else {
+            return plan_err!("first argument must be the table name as a string");
+        };
+        let cache_name = match args.get(1) {
+            Some(Expr::Literal(ScalarValue::Utf8(Some(name)))) => Some(name),
+            Some(_) => {
+                return plan_err!("second argument, if passed, must be the cache name as a string")
+            }
+            None => None,
+        };
+
+        let Some(table_def) = self
+            .provider
+            .catalog
+            .db_schema_by_id(&self.db_id)
+            .and_then(|db| db.table_definition(table_name.as_str()))
+        else {
+            return plan_err!("provided table name ({}) is invalid", table_name);
+        };
+        let cache_name: Arc<str> = match cache_name {
+            Some(name) => name.as_str().into(),
+            None => {
+                let mut names = table_def.meta_caches().map(|(name, _)| name);
+                match (names.next(), names.next()) {
+                    (Some(name), None) => name,
+                    (None, _) => {
+                        return plan_err!("no metadata cache exists for table ({})", table_name)
+                    }
+                    (Some(_), Some(_)) => {
+                        return plan_err!(
+                            "there are multiple metadata caches for table ({}), the cache name \
+                            must be provided as the second argument",
+                            table_name
+                        )
+                    }
+                }
+            }
+        };
+        let Some(schema) =
+            self.provider
+                .get_cache_schema(self.db_id, table_def.table_id, cache_name.as_ref())
+        else {
+            return plan_err!("could not find meta cache for the given arguments");
+        };
+
+        Ok(Arc::new(MetaCacheFunctionProvider {
+            db_id: self.db_id,
+            table_def,
+            cache_name,
+            schema,
+            provider: Arc::clone(&self.provider),
+        }))
+    }
+}
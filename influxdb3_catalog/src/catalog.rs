@@ -7,14 +7,18 @@ use indexmap::IndexMap;
 use influxdb3_id::{ColumnId, DbId, SerdeVecMap, TableId};
 use influxdb3_wal::{
     CatalogBatch, CatalogOp, FieldAdditions, LastCacheDefinition, LastCacheDelete,
+    MetaCacheDefinition, MetaCacheDelete,
 };
+pub use influxdb3_wal::{TokenAction, TokenDefinition, TokenPermission};
 use influxdb_line_protocol::FieldValue;
+use iox_time::Time;
 use observability_deps::tracing::info;
 use parking_lot::RwLock;
 use schema::{InfluxColumnType, InfluxFieldType, Schema, SchemaBuilder};
 use serde::{Deserialize, Serialize, Serializer};
 use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error, Clone)]
@@ -69,6 +73,12 @@ pub enum Error {
         table_name: String,
         existing: String,
     },
+
+    #[error("a token with the name {0} already exists")]
+    TokenAlreadyExists(Arc<str>),
+
+    #[error("a token with the name {0} does not exist")]
+    TokenNotFound(Arc<str>),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -145,7 +155,7 @@ impl Catalog {
             None => {
                 let mut inner = self.inner.write();
 
-                if inner.databases.len() >= Self::NUM_DBS_LIMIT {
+                if inner.database_count() >= Self::NUM_DBS_LIMIT {
                     return Err(Error::TooManyDbs);
                 }
 
@@ -189,16 +199,30 @@ impl Catalog {
             .map(|db| (*db_id, Arc::clone(db)))
     }
 
+    /// The names of the databases that have not been deleted
     pub fn db_names(&self) -> Vec<String> {
         self.inner
             .read()
             .databases
             .values()
+            .filter(|db| !db.deleted)
             .map(|db| db.name.to_string())
             .collect()
     }
 
+    /// The schemas of the databases that have not been deleted
     pub fn list_db_schema(&self) -> Vec<Arc<DatabaseSchema>> {
+        self.inner
+            .read()
+            .databases
+            .values()
+            .filter(|db| !db.deleted)
+            .cloned()
+            .collect()
+    }
+
+    /// The schemas of all databases, including those that have been deleted
+    pub fn list_db_schema_with_deleted(&self) -> Vec<Arc<DatabaseSchema>> {
         self.inner.read().databases.values().cloned().collect()
     }
 
@@ -252,6 +276,92 @@ impl Catalog {
         inner.updated = true;
     }
 
+    pub fn add_meta_cache(&self, db_id: DbId, table_id: TableId, meta_cache: MetaCacheDefinition) {
+        let mut inner = self.inner.write();
+        let mut db = inner
+            .databases
+            .get(&db_id)
+            .expect("db should exist")
+            .as_ref()
+            .clone();
+        let mut table = db
+            .tables
+            .get(&table_id)
+            .expect("table should exist")
+            .as_ref()
+            .clone();
+        table.add_meta_cache(meta_cache);
+        db.tables.insert(table_id, Arc::new(table));
+        inner.databases.insert(db_id, Arc::new(db));
+        inner.sequence = inner.sequence.next();
+        inner.updated = true;
+    }
+
+    pub fn delete_meta_cache(&self, db_id: DbId, table_id: TableId, name: &str) {
+        let mut inner = self.inner.write();
+        let mut db = inner
+            .databases
+            .get(&db_id)
+            .expect("db should exist")
+            .as_ref()
+            .clone();
+        let mut table = db
+            .tables
+            .get(&table_id)
+            .expect("table should exist")
+            .as_ref()
+            .clone();
+        table.remove_meta_cache(name);
+        db.tables.insert(table_id, Arc::new(table));
+        inner.databases.insert(db_id, Arc::new(db));
+        inner.sequence = inner.sequence.next();
+        inner.updated = true;
+    }
+
+    /// Add a named token to the catalog
+    ///
+    /// Token names, and the tokens themselves, must be unique.
+    pub fn add_token(&self, token: TokenDefinition) -> Result<()> {
+        let mut inner = self.inner.write();
+        if inner
+            .tokens
+            .iter()
+            .any(|t| t.name == token.name || t.hash == token.hash)
+        {
+            return Err(Error::TokenAlreadyExists(token.name));
+        }
+        inner.tokens.push(Arc::new(token));
+        inner.sequence = inner.sequence.next();
+        inner.updated = true;
+        Ok(())
+    }
+
+    /// Get the token with the given hex encoded SHA-512 hash
+    pub fn token_by_hash(&self, hash: &str) -> Option<Arc<TokenDefinition>> {
+        self.inner
+            .read()
+            .tokens
+            .iter()
+            .find(|t| t.hash.as_ref() == hash)
+            .cloned()
+    }
+
+    pub fn list_tokens(&self) -> Vec<Arc<TokenDefinition>> {
+        self.inner.read().tokens.clone()
+    }
+
+    /// Remove the token with the given name from the catalog, returning its definition
+    pub fn remove_token(&self, name: &str) -> Result<Arc<TokenDefinition>> {
+        let mut inner = self.inner.write();
+        let Some(idx) = inner.tokens.iter().position(|t| t.name.as_ref() == name) else {
+            return Err(Error::TokenNotFound(name.into()));
+        };
+        let token = inner.tokens.remove(idx);
+        inner.sequence = inner.sequence.next();
+        inner.updated = true;
+        Ok(token)
+    }
+
     pub fn instance_id(&self) -> Arc<str> {
         Arc::clone(&self.inner.read().instance_id)
     }
@@ -307,6 +417,9 @@ pub struct InnerCatalog {
     updated: bool,
     #[serde_as(as = "DbMapAsArray")]
     db_map: BiHashMap<DbId, Arc<str>>,
+    /// The named tokens that can be used to access the server
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tokens: Vec<Arc<TokenDefinition>>,
 }
 
 serde_with::serde_conv!(
@@ -371,6 +484,7 @@ impl InnerCatalog {
             instance_id,
             updated: false,
             db_map: BiHashMap::new(),
+            tokens: vec![],
         }
     }
 
@@ -378,20 +492,34 @@ impl InnerCatalog {
         self.sequence
     }
 
+    /// The number of tables, across all databases, that have not been deleted
     pub fn table_count(&self) -> usize {
-        self.databases.values().map(|db| db.tables.len()).sum()
+        self.databases
+            .values()
+            .filter(|db| !db.deleted)
+            .map(|db| db.table_count())
+            .sum()
+    }
+
+    /// The number of databases that have not been deleted
+    pub fn database_count(&self) -> usize {
+        self.databases.values().filter(|db| !db.deleted).count()
     }
 
     /// Applies the `CatalogBatch` while validating that all updates are compatible. If updates
     /// have already been applied, the sequence number and updated tracker are not updated.
     pub fn apply_catalog_batch(&mut self, catalog_batch: &CatalogBatch) -> Result<()> {
+        if catalog_batch.is_token_batch() {
+            return self.apply_token_ops(&catalog_batch.ops);
+        }
+
         let table_count = self.table_count();
 
         if let Some(db) = self.databases.get(&catalog_batch.database_id) {
-            let existing_table_count = db.tables.len();
+            let existing_table_count = db.table_count();
 
             if let Some(new_db) = db.new_if_updated_from_batch(catalog_batch)? {
-                let new_table_count = new_db.tables.len() - existing_table_count;
+                let new_table_count = new_db.table_count().saturating_sub(existing_table_count);
                 if table_count + new_table_count > Catalog::NUM_TABLES_LIMIT {
                     return Err(Error::TooManyTables);
                 }
@@ -402,12 +530,12 @@ impl InnerCatalog {
                 self.db_map.insert(new_db.id, Arc::clone(&new_db.name));
             }
         } else {
-            if self.databases.len() >= Catalog::NUM_DBS_LIMIT {
+            if self.database_count() >= Catalog::NUM_DBS_LIMIT {
                 return Err(Error::TooManyDbs);
             }
 
             let new_db = DatabaseSchema::new_from_batch(catalog_batch)?;
-            if table_count + new_db.tables.len() > Catalog::NUM_TABLES_LIMIT {
+            if table_count + new_db.table_count() > Catalog::NUM_TABLES_LIMIT {
                 return Err(Error::TooManyTables);
             }
 
@@ -421,6 +549,44 @@ impl InnerCatalog {
         Ok(())
     }
 
+    /// Applies operations on named tokens, which are not scoped to a database
+    ///
+    /// Tokens that have already been added or removed are ignored, as happens when the WAL is
+    /// replayed.
+    fn apply_token_ops(&mut self, ops: &[CatalogOp]) -> Result<()> {
+        let mut updated = false;
+        for op in ops {
+            match op {
+                CatalogOp::CreateToken(token) => {
+                    match self
+                        .tokens
+                        .iter()
+                        .find(|t| t.name == token.name || t.hash == token.hash)
+                    {
+                        Some(existing) if existing.as_ref() == token => (),
+                        Some(_) => return Err(Error::TokenAlreadyExists(Arc::clone(&token.name))),
+                        None => {
+                            self.tokens.push(Arc::new(token.clone()));
+                            updated = true;
+                        }
+                    }
+                }
+                CatalogOp::DeleteToken(definition) => {
+                    if let Some(idx) = self.tokens.iter().position(|t| t.name == definition.name) {
+                        self.tokens.remove(idx);
+                        updated = true;
+                    }
+                }
+                _ => unreachable!("only token operations are applied to the catalog's tokens"),
+            }
+        }
+        if updated {
+            self.sequence = self.sequence.next();
+            self.updated = true;
+        }
+        Ok(())
+    }
+
     pub fn db_exists(&self, db_id: DbId) -> bool {
         self.databases.contains_key(&db_id)
     }
@@ -433,6 +599,13 @@ pub struct DatabaseSchema {
     /// The database is a map of tables
     pub tables: SerdeVecMap<TableId, Arc<TableDefinition>>,
     pub table_map: BiHashMap<TableId, Arc<str>>,
+    /// How long data is retained in the database, or `None` if it is retained indefinitely
+    pub retention_period: Option<Duration>,
+    /// Whether the database has been deleted; deleted databases are renamed, so that a new
+    /// database can be created with the original name
+    pub deleted: bool,
+    /// Whether writes are prevented from adding new tables, or new columns to existing tables
+    pub schema_locked: bool,
 }
 
 impl DatabaseSchema {
@@ -442,14 +615,29 @@ impl DatabaseSchema {
             name,
             tables: Default::default(),
             table_map: BiHashMap::new(),
+            retention_period: None,
+            deleted: false,
+            schema_locked: false,
         }
     }
 
+    /// The minimum timestamp, in nanoseconds, of data that is retained in this database at the
+    /// given time, or `None` if the database has no retention period
+    pub fn retention_cutoff_ns(&self, now_ns: i64) -> Option<i64> {
+        self.retention_period.map(|period| {
+            now_ns.saturating_sub(i64::try_from(period.as_nanos()).unwrap_or(i64::MAX))
+        })
+    }
+
     /// Validates the updates in the `CatalogBatch` are compatible with this schema. If
     /// everything is compatible and there are no updates to the existing schema, None will be
     /// returned, otherwise a new `DatabaseSchema` will be returned with the updates applied.
     pub fn new_if_updated_from_batch(&self, catalog_batch: &CatalogBatch) -> Result<Option<Self>> {
         let mut updated_or_new_tables = SerdeVecMap::new();
+        let mut retention_period = self.retention_period;
+        let mut deleted = self.deleted;
+        let mut schema_locked = self.schema_locked;
+        let mut name = Arc::clone(&self.name);
 
         for catalog_op in &catalog_batch.ops {
             match catalog_op {
@@ -517,10 +705,98 @@ impl DatabaseSchema {
                         updated_or_new_tables.insert(new_table.table_id, Arc::new(new_table));
                     }
                 }
+                CatalogOp::CreateMetaCache(meta_cache_definition) => {
+                    let new_or_existing_table = updated_or_new_tables
+                        .get(&meta_cache_definition.table_id)
+                        .or_else(|| self.tables.get(&meta_cache_definition.table_id));
+
+                    let table = new_or_existing_table.ok_or(TableNotFound {
+                        db_name: Arc::clone(&self.name),
+                        table_name: Arc::clone(&meta_cache_definition.table_name),
+                    })?;
+
+                    if let Some(new_table) =
+                        table.new_if_meta_cache_definition_is_new(meta_cache_definition)
+                    {
+                        updated_or_new_tables.insert(new_table.table_id, Arc::new(new_table));
+                    }
+                }
+                CatalogOp::DeleteMetaCache(meta_cache_deletion) => {
+                    let new_or_existing_table = updated_or_new_tables
+                        .get(&meta_cache_deletion.table_id)
+                        .or_else(|| self.tables.get(&meta_cache_deletion.table_id));
+
+                    let table = new_or_existing_table.ok_or(TableNotFound {
+                        db_name: Arc::clone(&self.name),
+                        table_name: Arc::clone(&meta_cache_deletion.table_name),
+                    })?;
+
+                    if let Some(new_table) =
+                        table.new_if_meta_cache_deletes_existing(meta_cache_deletion)
+                    {
+                        updated_or_new_tables.insert(new_table.table_id, Arc::new(new_table));
+                    }
+                }
+                CatalogOp::SetRetentionPeriod(definition) => {
+                    retention_period = definition.retention_period_seconds.map(Duration::from_secs);
+                }
+                CatalogOp::DeleteDatabase(definition) => {
+                    // the database may already have been deleted when the batch is replayed:
+                    if !self.deleted {
+                        deleted = true;
+                        name = deleted_name(&self.name, self.id, definition.deletion_time);
+                    }
+                }
+                CatalogOp::DeleteTable(definition) => {
+                    let new_or_existing_table = updated_or_new_tables
+                        .get(&definition.table_id)
+                        .or_else(|| self.tables.get(&definition.table_id));
+
+                    let table = new_or_existing_table.ok_or(TableNotFound {
+                        db_name: Arc::clone(&self.name),
+                        table_name: Arc::clone(&definition.table_name),
+                    })?;
+
+                    if !table.deleted {
+                        let mut new_table = table.as_ref().clone();
+                        new_table.deleted = true;
+                        new_table.table_name = deleted_name(
+                            &table.table_name,
+                            table.table_id,
+                            definition.deletion_time,
+                        );
+                        updated_or_new_tables.insert(new_table.table_id, Arc::new(new_table));
+                    }
+                }
+                // tokens are not scoped to a database, see `InnerCatalog::apply_token_ops`:
+                CatalogOp::CreateToken(_) | CatalogOp::DeleteToken(_) => (),
+                CatalogOp::SetSchemaLocked(definition) => match &definition.table {
+                    None => schema_locked = definition.locked,
+                    Some((table_id, table_name)) => {
+                        let new_or_existing_table = updated_or_new_tables
+                            .get(table_id)
+                            .or_else(|| self.tables.get(table_id));
+
+                        let table = new_or_existing_table.ok_or(TableNotFound {
+                            db_name: Arc::clone(&self.name),
+                            table_name: Arc::clone(table_name),
+                        })?;
+
+                        if table.schema_locked != definition.locked {
+                            let mut new_table = table.as_ref().clone();
+                            new_table.schema_locked = definition.locked;
+                            updated_or_new_tables.insert(new_table.table_id, Arc::new(new_table));
+                        }
+                    }
+                },
             }
         }
 
-        if updated_or_new_tables.is_empty() {
+        if updated_or_new_tables.is_empty()
+            && retention_period == self.retention_period
+            && deleted == self.deleted
+            && schema_locked == self.schema_locked
+        {
             Ok(None)
         } else {
             for (table_id, table_def) in &self.tables {
@@ -537,9 +813,12 @@ impl DatabaseSchema {
 
             Ok(Some(Self {
                 id: self.id,
-                name: Arc::clone(&self.name),
+                name,
                 tables: updated_or_new_tables,
                 table_map: new_table_maps,
+                retention_period,
+                deleted,
+                schema_locked,
             }))
         }
     }
@@ -549,6 +828,14 @@ impl DatabaseSchema {
             catalog_batch.database_id,
             Arc::clone(&catalog_batch.database_name),
         );
+        // a database created through the API, without any other settings, has nothing to update:
+        if catalog_batch
+            .ops
+            .iter()
+            .all(|op| matches!(op, CatalogOp::CreateDatabase(_)))
+        {
+            return Ok(db_schema);
+        }
         let new_db = db_schema
             .new_if_updated_from_batch(catalog_batch)?
             .expect("database must be new");
@@ -614,23 +901,31 @@ impl DatabaseSchema {
             .map(|table_def| (*table_id, Arc::clone(table_def)))
     }
 
+    /// The ids of the tables in the database that have not been deleted
     pub fn table_ids(&self) -> Vec<TableId> {
-        self.tables.keys().cloned().collect()
+        self.tables().map(|td| td.table_id).collect()
     }
 
+    /// The names of the tables in the database that have not been deleted
     pub fn table_names(&self) -> Vec<Arc<str>> {
-        self.tables
-            .values()
-            .map(|td| Arc::clone(&td.table_name))
-            .collect()
+        self.tables().map(|td| Arc::clone(&td.table_name)).collect()
+    }
+
+    /// The number of tables in the database that have not been deleted
+    pub fn table_count(&self) -> usize {
+        self.tables.values().filter(|td| !td.deleted).count()
     }
 
     pub fn table_exists(&self, table_id: &TableId) -> bool {
         self.tables.contains_key(table_id)
     }
 
+    /// The tables in the database that have not been deleted
     pub fn tables(&self) -> impl Iterator<Item = Arc<TableDefinition>> + use<'_> {
-        self.tables.values().map(Arc::clone)
+        self.tables
+            .values()
+            .filter(|td| !td.deleted)
+            .map(Arc::clone)
     }
 
     pub fn table_name_to_id(&self, table_name: impl Into<Arc<str>>) -> Option<TableId> {
@@ -651,6 +946,12 @@ pub struct TableDefinition {
     pub column_map: BiHashMap<ColumnId, Arc<str>>,
     pub series_key: Option<Vec<ColumnId>>,
     pub last_caches: HashMap<Arc<str>, LastCacheDefinition>,
+    pub meta_caches: HashMap<Arc<str>, MetaCacheDefinition>,
+    /// Whether the table has been deleted; deleted tables are renamed, so that a new table can
+    /// be created with the original name
+    pub deleted: bool,
+    /// Whether writes are prevented from adding new columns to the table
+    pub schema_locked: bool,
 }
 
 impl TableDefinition {
@@ -705,6 +1006,9 @@ impl TableDefinition {
             column_map,
             series_key,
             last_caches: HashMap::new(),
+            meta_caches: HashMap::new(),
+            deleted: false,
+            schema_locked: false,
         })
     }
 
@@ -832,6 +1136,35 @@ impl TableDefinition {
         }
     }
 
+    pub(crate) fn new_if_meta_cache_definition_is_new(
+        &self,
+        meta_cache_definition: &MetaCacheDefinition,
+    ) -> Option<Self> {
+        if self
+            .meta_caches
+            .contains_key(&meta_cache_definition.cache_name)
+        {
+            None
+        } else {
+            let mut new_table = self.clone();
+            new_table.add_meta_cache(meta_cache_definition.clone());
+            Some(new_table)
+        }
+    }
+
+    pub(crate) fn new_if_meta_cache_deletes_existing(
+        &self,
+        meta_cache_delete: &MetaCacheDelete,
+    ) -> Option<Self> {
+        if self.meta_caches.contains_key(&meta_cache_delete.cache_name) {
+            let mut new_table = self.clone();
+            new_table.remove_meta_cache(&meta_cache_delete.cache_name);
+            Some(new_table)
+        } else {
+            None
+        }
+    }
+
     /// Check if the column exists in the [`TableDefinition`]
     pub fn column_exists(&self, column: impl Into<Arc<str>>) -> bool {
         self.column_map.get_by_right(&column.into()).is_some()
@@ -933,6 +1266,23 @@ impl TableDefinition {
             .map(|(name, def)| (Arc::clone(name), def))
     }
 
+    /// Add a new metadata cache to this table definition
+    pub fn add_meta_cache(&mut self, meta_cache: MetaCacheDefinition) {
+        self.meta_caches
+            .insert(Arc::clone(&meta_cache.cache_name), meta_cache);
+    }
+
+    /// Remove a metadata cache from the table definition
+    pub fn remove_meta_cache(&mut self, name: &str) {
+        self.meta_caches.remove(name);
+    }
+
+    pub fn meta_caches(&self) -> impl Iterator<Item = (Arc<str>, &MetaCacheDefinition)> {
+        self.meta_caches
+            .iter()
+            .map(|(name, def)| (Arc::clone(name), def))
+    }
+
     pub fn column_name_to_id(&self, name: impl Into<Arc<str>>) -> Option<ColumnId> {
         self.column_map.get_by_right(&name.into()).copied()
     }
@@ -990,6 +1340,20 @@ impl ColumnDefinition {
     }
 }
 
+/// The name given to a database or table when it is deleted, which frees up the original name
+///
+/// The id is included so that deleting the same name more than once, within the same second,
+/// does not produce a name that is already taken.
+fn deleted_name(name: &str, id: impl std::fmt::Display, deletion_time: i64) -> Arc<str> {
+    format!(
+        "{name}-{}-{id}",
+        Time::from_timestamp_nanos(deletion_time)
+            .date_time()
+            .format("%Y%m%dT%H%M%S")
+    )
+    .into()
+}
+
 pub fn influx_column_type_from_field_value(fv: &FieldValue<'_>) -> InfluxColumnType {
     match fv {
         FieldValue::I64(_) => InfluxColumnType::Field(InfluxFieldType::Integer),
@@ -1026,6 +1390,9 @@ mod tests {
                 map.insert(TableId::from(2), "test_table_2".into());
                 map
             },
+            retention_period: None,
+            deleted: false,
+            schema_locked: false,
         };
         use InfluxColumnType::*;
         use InfluxFieldType::*;
@@ -1223,6 +1590,9 @@ mod tests {
             name: "test".into(),
             tables: SerdeVecMap::new(),
             table_map: BiHashMap::new(),
+            retention_period: None,
+            deleted: false,
+            schema_locked: false,
         };
         database.tables.insert(
             TableId::from(0),
@@ -1279,6 +1649,9 @@ mod tests {
                 map.insert(TableId::from(1), "test_table_1".into());
                 map
             },
+            retention_period: None,
+            deleted: false,
+            schema_locked: false,
         };
         use InfluxColumnType::*;
         use InfluxFieldType::*;
@@ -1337,6 +1710,9 @@ mod tests {
                 map.insert(TableId::from(0), "test".into());
                 map
             },
+            retention_period: None,
+            deleted: false,
+            schema_locked: false,
         };
         use InfluxColumnType::*;
         use InfluxFieldType::*;
@@ -1398,6 +1774,355 @@ mod tests {
         assert_eq!(host_id, catalog.host_id());
     }
 
+    #[test]
+    fn retention_period_is_set_and_serialized() {
+        let catalog = Catalog::new(Arc::from("host"), Arc::from("instance"));
+        let db_id = DbId::new();
+        // a batch that only sets the retention period will create the database if needed:
+        let catalog_batch = create::catalog_batch_op(
+            db_id,
+            "foo",
+            0,
+            [create::set_retention_period_op(db_id, "foo", Some(3600))],
+        );
+        catalog
+            .apply_catalog_batch(catalog_batch.as_catalog().unwrap())
+            .unwrap();
+        let db = catalog.db_schema("foo").unwrap();
+        assert_eq!(Some(Duration::from_secs(3600)), db.retention_period);
+        assert_eq!(
+            Some(1_000_000_000_000 - 3_600_000_000_000),
+            db.retention_cutoff_ns(1_000_000_000_000)
+        );
+
+        // the retention period survives a round trip through serialization:
+        let serialized = serde_json::to_string(&catalog).unwrap();
+        let deserialized_inner: InnerCatalog = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(catalog, Catalog::from_inner(deserialized_inner));
+
+        // and can be cleared again:
+        let sequence = catalog.sequence_number();
+        let catalog_batch = create::catalog_batch_op(
+            db_id,
+            "foo",
+            0,
+            [create::set_retention_period_op(db_id, "foo", None)],
+        );
+        catalog
+            .apply_catalog_batch(catalog_batch.as_catalog().unwrap())
+            .unwrap();
+        let db = catalog.db_schema("foo").unwrap();
+        assert!(db.retention_period.is_none());
+        assert!(db.retention_cutoff_ns(1_000_000_000_000).is_none());
+        assert_eq!(sequence.next(), catalog.sequence_number());
+    }
+
+    #[test]
+    fn schema_locks_are_set_and_serialized() {
+        let catalog = Catalog::new(Arc::from("host"), Arc::from("instance"));
+        let db_id = DbId::new();
+        let cpu_id = TableId::new();
+        let catalog_batch = create::catalog_batch_op(
+            db_id,
+            "foo",
+            0,
+            [
+                create::create_table_op(
+                    db_id,
+                    "foo",
+                    cpu_id,
+                    "cpu",
+                    [
+                        create::field_def(ColumnId::new(), "host", FieldDataType::Tag),
+                        create::field_def(ColumnId::new(), "time", FieldDataType::Timestamp),
+                    ],
+                ),
+                create::set_schema_locked_op(db_id, "foo", Some((cpu_id, "cpu")), true),
+            ],
+        );
+        catalog
+            .apply_catalog_batch(catalog_batch.as_catalog().unwrap())
+            .unwrap();
+        let db = catalog.db_schema("foo").unwrap();
+        assert!(!db.schema_locked);
+        assert!(db.table_definition("cpu").unwrap().schema_locked);
+
+        // locking the database is separate from locking its tables:
+        let catalog_batch = create::catalog_batch_op(
+            db_id,
+            "foo",
+            0,
+            [
+                create::set_schema_locked_op(db_id, "foo", None, true),
+                create::set_schema_locked_op(db_id, "foo", Some((cpu_id, "cpu")), false),
+            ],
+        );
+        catalog
+            .apply_catalog_batch(catalog_batch.as_catalog().unwrap())
+            .unwrap();
+        let db = catalog.db_schema("foo").unwrap();
+        assert!(db.schema_locked);
+        assert!(!db.table_definition("cpu").unwrap().schema_locked);
+
+        // the locks survive a round trip through serialization:
+        let serialized = serde_json::to_string(&catalog).unwrap();
+        let deserialized_inner: InnerCatalog = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(catalog, Catalog::from_inner(deserialized_inner));
+
+        // and re-applying the same lock is a no-op:
+        let sequence = catalog.sequence_number();
+        let catalog_batch = create::catalog_batch_op(
+            db_id,
+            "foo",
+            0,
+            [create::set_schema_locked_op(db_id, "foo", None, true)],
+        );
+        catalog
+            .apply_catalog_batch(catalog_batch.as_catalog().unwrap())
+            .unwrap();
+        assert_eq!(sequence, catalog.sequence_number());
+    }
+
+    #[test]
+    fn tokens_are_added_and_serialized() {
+        let catalog = Catalog::new(Arc::from("host"), Arc::from("instance"));
+        let token = TokenDefinition {
+            name: "ingest".into(),
+            hash: "abc123".into(),
+            permissions: vec![TokenPermission {
+                database: "foo".into(),
+                actions: vec![TokenAction::Write],
+            }],
+            description: None,
+            created_at_ns: 0,
+            expires_at_ns: None,
+        };
+        catalog.add_token(token.clone()).unwrap();
+        assert!(matches!(
+            catalog.add_token(token.clone()),
+            Err(Error::TokenAlreadyExists(_))
+        ));
+        let admin = TokenDefinition {
+            name: "admin".into(),
+            hash: "def456".into(),
+            permissions: vec![TokenPermission {
+                database: TokenPermission::ALL_DATABASES.into(),
+                actions: vec![TokenAction::Admin],
+            }],
+            description: None,
+            created_at_ns: 0,
+            expires_at_ns: None,
+        };
+        catalog.add_token(admin).unwrap();
+
+        let ingest = catalog.token_by_hash("abc123").unwrap();
+        assert!(ingest.allows("foo", TokenAction::Write));
+        assert!(!ingest.allows("foo", TokenAction::Read));
+        assert!(!ingest.allows("bar", TokenAction::Write));
+        assert!(!ingest.allows(TokenPermission::ALL_DATABASES, TokenAction::Write));
+        let admin = catalog.token_by_hash("def456").unwrap();
+        assert!(admin.allows("foo", TokenAction::Read));
+        assert!(admin.allows("bar", TokenAction::Admin));
+        assert!(admin.allows(TokenPermission::ALL_DATABASES, TokenAction::Write));
+        assert!(catalog.token_by_hash("nope").is_none());
+
+        let serialized = serde_json::to_string(&catalog).unwrap();
+        let deserialized_inner: InnerCatalog = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(catalog, Catalog::from_inner(deserialized_inner));
+        assert_eq!(2, catalog.list_tokens().len());
+    }
+
+    #[test]
+    fn tokens_are_removed_and_expire() {
+        let catalog = Catalog::new(Arc::from("host"), Arc::from("instance"));
+        let token = TokenDefinition {
+            name: "temp".into(),
+            hash: "abc123".into(),
+            permissions: vec![TokenPermission {
+                database: "foo".into(),
+                actions: vec![TokenAction::Read],
+            }],
+            description: Some("temporary access".into()),
+            created_at_ns: 100,
+            expires_at_ns: Some(200),
+        };
+        catalog.add_token(token).unwrap();
+        let temp = catalog.token_by_hash("abc123").unwrap();
+        assert!(!temp.is_expired(199));
+        assert!(temp.is_expired(200));
+
+        let sequence = catalog.sequence_number();
+        assert_eq!("temp", catalog.remove_token("temp").unwrap().name.as_ref());
+        assert_eq!(sequence.next(), catalog.sequence_number());
+        assert!(catalog.token_by_hash("abc123").is_none());
+        assert!(matches!(
+            catalog.remove_token("temp"),
+            Err(Error::TokenNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn tables_and_databases_are_soft_deleted() {
+        let catalog = Catalog::new(Arc::from("host"), Arc::from("instance"));
+        let db_id = DbId::new();
+        let cpu_id = TableId::new();
+        let mem_id = TableId::new();
+        let table_op = |table_id, name| {
+            create::create_table_op(
+                db_id,
+                "foo",
+                table_id,
+                name,
+                [
+                    create::field_def(ColumnId::new(), "host", FieldDataType::Tag),
+                    create::field_def(ColumnId::new(), "time", FieldDataType::Timestamp),
+                ],
+            )
+        };
+        let catalog_batch = create::catalog_batch_op(
+            db_id,
+            "foo",
+            0,
+            [table_op(cpu_id, "cpu"), table_op(mem_id, "mem")],
+        );
+        catalog
+            .apply_catalog_batch(catalog_batch.as_catalog().unwrap())
+            .unwrap();
+
+        // deleting a table renames it, so that the name can be reused:
+        let deletion_time = 1_700_000_000_000_000_000;
+        let delete_table = create::catalog_batch_op(
+            db_id,
+            "foo",
+            deletion_time,
+            [create::delete_table_op(
+                db_id,
+                "foo",
+                cpu_id,
+                "cpu",
+                deletion_time,
+            )],
+        );
+        catalog
+            .apply_catalog_batch(delete_table.as_catalog().unwrap())
+            .unwrap();
+        let db = catalog.db_schema("foo").unwrap();
+        assert!(db.table_definition("cpu").is_none());
+        let deleted = db.table_definition_by_id(&cpu_id).unwrap();
+        assert!(deleted.deleted);
+        assert_eq!(
+            format!("cpu-20231114T221320-{cpu_id}"),
+            deleted.table_name.as_ref()
+        );
+        assert_eq!(vec![Arc::<str>::from("mem")], db.table_names());
+        assert_eq!(1, db.table_count());
+
+        // replaying the deletion does not change the catalog:
+        let sequence = catalog.sequence_number();
+        catalog
+            .apply_catalog_batch(delete_table.as_catalog().unwrap())
+            .unwrap();
+        assert_eq!(sequence, catalog.sequence_number());
+
+        // the deletion is preserved through serialization:
+        let serialized = serde_json::to_string(&catalog).unwrap();
+        let deserialized_inner: InnerCatalog = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(catalog, Catalog::from_inner(deserialized_inner));
+
+        // deleting the database frees up its name for a new database:
+        let delete_db = create::catalog_batch_op(
+            db_id,
+            "foo",
+            deletion_time,
+            [create::delete_database_op(db_id, "foo", deletion_time)],
+        );
+        catalog
+            .apply_catalog_batch(delete_db.as_catalog().unwrap())
+            .unwrap();
+        assert!(catalog.db_schema("foo").is_none());
+        assert!(catalog.db_names().is_empty());
+        assert!(catalog.list_db_schema().is_empty());
+        assert_eq!(1, catalog.list_db_schema_with_deleted().len());
+        let deleted = catalog.db_schema_by_id(&db_id).unwrap();
+        assert!(deleted.deleted);
+        assert_eq!(
+            format!("foo-20231114T221320-{db_id}"),
+            deleted.name.as_ref()
+        );
+
+        let new_db = catalog.db_or_create("foo").unwrap();
+        assert_ne!(db_id, new_db.id);
+        assert_eq!(vec!["foo".to_string()], catalog.db_names());
+    }
+
+    #[test]
+    fn deleting_the_same_name_twice_gives_unique_names() {
+        let catalog = Catalog::new(Arc::from("host"), Arc::from("instance"));
+        let deletion_time = 1_700_000_000_000_000_000;
+
+        // delete a table named cpu twice, in the same second:
+        let db_id = catalog.db_or_create("foo").unwrap().id;
+        let mut table_ids = vec![];
+        for _ in 0..2 {
+            let table_id = TableId::new();
+            let create_and_delete = create::catalog_batch_op(
+                db_id,
+                "foo",
+                deletion_time,
+                [
+                    create::create_table_op(
+                        db_id,
+                        "foo",
+                        table_id,
+                        "cpu",
+                        [create::field_def(
+                            ColumnId::new(),
+                            "time",
+                            FieldDataType::Timestamp,
+                        )],
+                    ),
+                    create::delete_table_op(db_id, "foo", table_id, "cpu", deletion_time),
+                ],
+            );
+            catalog
+                .apply_catalog_batch(create_and_delete.as_catalog().unwrap())
+                .unwrap();
+            table_ids.push(table_id);
+        }
+        let db = catalog.db_schema_by_id(&db_id).unwrap();
+        let deleted_tables = table_ids
+            .iter()
+            .map(|id| db.table_definition_by_id(id).unwrap())
+            .collect::<Vec<_>>();
+        assert!(deleted_tables.iter().all(|t| t.deleted));
+        assert_ne!(deleted_tables[0].table_name, deleted_tables[1].table_name);
+        assert_eq!(2, db.table_map.len());
+
+        // and do the same with a database named foo:
+        let mut db_ids = vec![];
+        for _ in 0..2 {
+            let db_id = catalog.db_or_create("foo").unwrap().id;
+            let delete_db = create::catalog_batch_op(
+                db_id,
+                "foo",
+                deletion_time,
+                [create::delete_database_op(db_id, "foo", deletion_time)],
+            );
+            catalog
+                .apply_catalog_batch(delete_db.as_catalog().unwrap())
+                .unwrap();
+            db_ids.push(db_id);
+        }
+        let deleted_dbs = db_ids
+            .iter()
+            .map(|id| catalog.db_schema_by_id(id).unwrap())
+            .collect::<Vec<_>>();
+        assert!(deleted_dbs.iter().all(|db| db.deleted));
+        assert_ne!(deleted_dbs[0].name, deleted_dbs[1].name);
+        assert_eq!(2, catalog.list_db_schema_with_deleted().len());
+        assert!(catalog.db_names().is_empty());
+    }
+
     /// See: https://github.com/influxdata/influxdb/issues/25524
     #[test]
     fn apply_catalog_batch_fails_for_add_fields_on_nonexist_table() {
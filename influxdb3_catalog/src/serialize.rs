@@ -7,12 +7,13 @@ use influxdb3_id::ColumnId;
 use influxdb3_id::DbId;
 use influxdb3_id::SerdeVecMap;
 use influxdb3_id::TableId;
-use influxdb3_wal::{LastCacheDefinition, LastCacheValueColumnsDef};
+use influxdb3_wal::{LastCacheDefinition, LastCacheValueColumnsDef, MetaCacheDefinition};
 use schema::InfluxColumnType;
 use schema::InfluxFieldType;
 use schema::TIME_DATA_TIMEZONE;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
 
 impl Serialize for DatabaseSchema {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -38,6 +39,12 @@ struct DatabaseSnapshot {
     id: DbId,
     name: Arc<str>,
     tables: SerdeVecMap<TableId, TableSnapshot>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retention_period_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    deleted: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    schema_locked: bool,
 }
 
 impl From<&DatabaseSchema> for DatabaseSnapshot {
@@ -50,6 +57,9 @@ impl From<&DatabaseSchema> for DatabaseSnapshot {
                 .iter()
                 .map(|(table_id, table_def)| (*table_id, table_def.as_ref().into()))
                 .collect(),
+            retention_period_seconds: db.retention_period.map(|p| p.as_secs()),
+            deleted: db.deleted,
+            schema_locked: db.schema_locked,
         }
     }
 }
@@ -70,6 +80,9 @@ impl From<DatabaseSnapshot> for DatabaseSchema {
             name: snap.name,
             tables,
             table_map,
+            retention_period: snap.retention_period_seconds.map(Duration::from_secs),
+            deleted: snap.deleted,
+            schema_locked: snap.schema_locked,
         }
     }
 }
@@ -108,6 +121,12 @@ struct TableSnapshot {
     cols: SerdeVecMap<ColumnId, ColumnDefinitionSnapshot>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     last_caches: Vec<LastCacheSnapshot>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    meta_caches: Vec<MetaCacheSnapshot>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    deleted: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    schema_locked: bool,
 }
 
 /// Representation of Arrow's `DataType` for table snapshots.
@@ -252,6 +271,9 @@ impl From<&TableDefinition> for TableSnapshot {
                 })
                 .collect(),
             last_caches: def.last_caches.values().map(Into::into).collect(),
+            meta_caches: def.meta_caches.values().map(Into::into).collect(),
+            deleted: def.deleted,
+            schema_locked: def.schema_locked,
         }
     }
 }
@@ -336,6 +358,13 @@ impl From<TableSnapshot> for TableDefinition {
                 .into_iter()
                 .map(|lc_snap| (Arc::clone(&lc_snap.name), lc_snap.into()))
                 .collect(),
+            meta_caches: snap
+                .meta_caches
+                .into_iter()
+                .map(|mc_snap| (Arc::clone(&mc_snap.name), mc_snap.into()))
+                .collect(),
+            deleted: snap.deleted,
+            schema_locked: snap.schema_locked,
             ..table_def
         }
     }
@@ -420,3 +449,39 @@ impl From<LastCacheSnapshot> for LastCacheDefinition {
         }
     }
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MetaCacheSnapshot {
+    table_id: TableId,
+    table: Arc<str>,
+    name: Arc<str>,
+    cols: Vec<ColumnId>,
+    max_cardinality: usize,
+    max_age_seconds: u64,
+}
+
+impl From<&MetaCacheDefinition> for MetaCacheSnapshot {
+    fn from(mcd: &MetaCacheDefinition) -> Self {
+        Self {
+            table_id: mcd.table_id,
+            table: Arc::clone(&mcd.table_name),
+            name: Arc::clone(&mcd.cache_name),
+            cols: mcd.column_ids.clone(),
+            max_cardinality: mcd.max_cardinality,
+            max_age_seconds: mcd.max_age_seconds,
+        }
+    }
+}
+
+impl From<MetaCacheSnapshot> for MetaCacheDefinition {
+    fn from(snap: MetaCacheSnapshot) -> Self {
+        Self {
+            table_id: snap.table_id,
+            table_name: snap.table,
+            cache_name: snap.name,
+            column_ids: snap.cols,
+            max_cardinality: snap.max_cardinality,
+            max_age_seconds: snap.max_age_seconds,
+        }
+    }
+}
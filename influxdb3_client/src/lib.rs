@@ -38,9 +38,21 @@ pub enum Error {
     #[error("failed to parse plaintext response: {0}")]
     Text(#[source] reqwest::Error),
 
+    #[error("failed to parse the plan returned by explain=analyze: {0}")]
+    ExplainJson(#[source] serde_json::Error),
+
     #[error("server responded with error [{code}]: {message}")]
     ApiError { code: StatusCode, message: String },
 
+    #[error("failed to compress the request body: {0}")]
+    Compress(#[source] std::io::Error),
+
+    #[error("a streamed request body cannot be compressed by the client")]
+    CompressStreamedBody,
+
+    #[error("failed to decompress the response body: {0}")]
+    Decompress(#[source] std::io::Error),
+
     #[error("failed to send {method} {url} request: {source}")]
     RequestSend {
         method: Method,
@@ -73,6 +85,8 @@ pub struct Client {
     auth_token: Option<Secret<String>>,
     /// A [`reqwest::Client`] for handling HTTP requests
     http_client: reqwest::Client,
+    /// Whether to request that query responses are compressed by the server
+    compressed_responses: bool,
 }
 
 impl Client {
@@ -82,6 +96,7 @@ impl Client {
             base_url: base_url.into_url().map_err(Error::BaseUrl)?,
             auth_token: None,
             http_client: reqwest::Client::new(),
+            compressed_responses: true,
         })
     }
 
@@ -104,6 +119,16 @@ impl Client {
         self
     }
 
+    /// Set whether query responses are requested compressed from the server, which they are
+    /// by default
+    ///
+    /// Compressed responses are decompressed by the client, so this only affects the amount of
+    /// data sent over the network.
+    pub fn with_compressed_responses(mut self, enabled: bool) -> Self {
+        self.compressed_responses = enabled;
+        self
+    }
+
     /// Compose a request to the `/api/v3/write_lp` API
     ///
     /// # Example
@@ -130,6 +155,8 @@ impl Client {
             db: db.into(),
             precision: None,
             accept_partial: None,
+            stream: None,
+            content_encoding: None,
             body: NoBody,
         }
     }
@@ -162,6 +189,7 @@ impl Client {
             query: query.into(),
             format: None,
             params: None,
+            explain: None,
         }
     }
 
@@ -193,6 +221,76 @@ impl Client {
             query: query.into(),
             format: None,
             params: None,
+            explain: None,
+        }
+    }
+
+    /// Compose a request to the `GET /api/v3/export` API
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let mut resp = client
+    ///     .api_v3_export("db_name")
+    ///     .table("cpu")
+    ///     .start("2024-01-01T00:00:00Z")
+    ///     .send()
+    ///     .await?;
+    /// while let Some(lp) = resp.chunk().await? {
+    ///     print!("{}", String::from_utf8_lossy(&lp));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn api_v3_export(&self, db: impl Into<String>) -> ExportRequestBuilder<'_> {
+        ExportRequestBuilder {
+            client: self,
+            db: db.into(),
+            tables: vec![],
+            start: None,
+            end: None,
+            gzip: false,
+        }
+    }
+
+    /// Compose a request to the `POST /api/v3/import` API
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::{Client, FieldType, ImportFormat};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let summary = client
+    ///     .api_v3_import("db_name", "cpu")
+    ///     .format(ImportFormat::Csv)
+    ///     .tag("host")
+    ///     .field("usage", Some(FieldType::Float))
+    ///     .body("host,usage,time\na,0.5,1000\n")
+    ///     .send()
+    ///     .await?;
+    /// println!("imported {} rows", summary.row_count);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn api_v3_import(
+        &self,
+        db: impl Into<String>,
+        table: impl Into<String>,
+    ) -> ImportRequestBuilder<'_, NoBody> {
+        ImportRequestBuilder {
+            client: self,
+            db: db.into(),
+            table: table.into(),
+            format: None,
+            tags: vec![],
+            fields: vec![],
+            time_column: None,
+            precision: None,
+            body: NoBody,
         }
     }
 
@@ -259,443 +357,1473 @@ impl Client {
         }
     }
 
-    /// Send a `/ping` request to the target `influxdb3` server to check its
-    /// status and gather `version` and `revision` information
-    pub async fn ping(&self) -> Result<PingResponse> {
-        let url = self.base_url.join("/ping")?;
-        let mut req = self.http_client.get(url);
-        if let Some(t) = &self.auth_token {
-            req = req.bearer_auth(t.expose_secret());
+    /// Compose a request to the `POST /api/v3/configure/meta_cache` API
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let resp = client
+    ///     .api_v3_configure_meta_cache_create("db_name", "table_name", ["col1", "col2"])
+    ///     .name("cache_name")
+    ///     .max_cardinality(1_000)
+    ///     .max_age(3_600)
+    ///     .send()
+    ///     .await
+    ///     .expect("send create meta cache request");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn api_v3_configure_meta_cache_create(
+        &self,
+        db: impl Into<String>,
+        table: impl Into<String>,
+        columns: impl IntoIterator<Item: Into<String>>,
+    ) -> CreateMetaCacheRequestBuilder<'_> {
+        CreateMetaCacheRequestBuilder::new(self, db, table, columns)
+    }
+
+    /// Make a request to the `DELETE /api/v3/configure/meta_cache` API
+    pub async fn api_v3_configure_meta_cache_delete(
+        &self,
+        db: impl Into<String> + Send,
+        table: impl Into<String> + Send,
+        name: impl Into<String> + Send,
+    ) -> Result<()> {
+        let url = self.base_url.join("/api/v3/configure/meta_cache")?;
+        #[derive(Serialize)]
+        struct Req {
+            db: String,
+            table: String,
+            name: String,
         }
-        let resp = req
-            .send()
-            .await
-            .map_err(|src| Error::request_send(Method::GET, "/ping", src))?;
-        if resp.status().is_success() {
-            resp.json().await.map_err(Error::Json)
-        } else {
-            Err(Error::ApiError {
-                code: resp.status(),
+        let mut req = self.http_client.delete(url).json(&Req {
+            db: db.into(),
+            table: table.into(),
+            name: name.into(),
+        });
+        if let Some(token) = &self.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req.send().await.map_err(|src| {
+            Error::request_send(Method::DELETE, "/api/v3/configure/meta_cache", src)
+        })?;
+        let status = resp.status();
+        match status {
+            StatusCode::OK => Ok(()),
+            code => Err(Error::ApiError {
+                code,
                 message: resp.text().await.map_err(Error::Text)?,
-            })
+            }),
         }
     }
-}
-
-/// The response of the `/ping` API on `influxdb3`
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PingResponse {
-    version: String,
-    revision: String,
-}
-
-impl PingResponse {
-    /// Get the `version` from the response
-    pub fn version(&self) -> &str {
-        &self.version
-    }
-
-    /// Get the `revision` from the response
-    pub fn revision(&self) -> &str {
-        &self.revision
-    }
-}
-
-/// The URL parameters of the request to the `/api/v3/write_lp` API
-// TODO - this should re-use a type defined in the server code, or a separate crate,
-//        central to both.
-#[derive(Debug, Serialize)]
-struct WriteParams<'a> {
-    db: &'a str,
-    precision: Option<Precision>,
-    accept_partial: Option<bool>,
-}
 
-impl<'a, B> From<&'a WriteRequestBuilder<'a, B>> for WriteParams<'a> {
-    fn from(builder: &'a WriteRequestBuilder<'a, B>) -> Self {
-        Self {
-            db: &builder.db,
-            precision: builder.precision,
-            accept_partial: builder.accept_partial,
+    /// Make a request to the `POST /api/v3/configure/schema_lock` API
+    ///
+    /// Locks, or unlocks, the schema of the given table, or of the whole database if no table is
+    /// given. Writes that would add new tables or columns to a locked schema are rejected.
+    pub async fn api_v3_configure_schema_lock(
+        &self,
+        db: impl Into<String> + Send,
+        table: Option<&str>,
+        locked: bool,
+    ) -> Result<()> {
+        let url = self.base_url.join("/api/v3/configure/schema_lock")?;
+        #[derive(Serialize)]
+        struct Req {
+            db: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            table: Option<String>,
+            locked: bool,
+        }
+        let mut req = self.http_client.post(url).json(&Req {
+            db: db.into(),
+            table: table.map(ToOwned::to_owned),
+            locked,
+        });
+        if let Some(token) = &self.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req.send().await.map_err(|src| {
+            Error::request_send(Method::POST, "/api/v3/configure/schema_lock", src)
+        })?;
+        let status = resp.status();
+        match status {
+            StatusCode::OK => Ok(()),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
         }
     }
-}
-
-/// Time series precision
-// TODO - this should re-use a type defined in the server code, or a separate crate,
-//        central to both.
-#[derive(Debug, Copy, Clone, Serialize)]
-#[serde(rename_all = "lowercase")]
-pub enum Precision {
-    Second,
-    Millisecond,
-    Microsecond,
-    Nanosecond,
-}
-
-/// Builder type for composing a request to `/api/v3/write_lp`
-///
-/// Produced by [`Client::api_v3_write_lp`]
-#[derive(Debug)]
-pub struct WriteRequestBuilder<'c, B> {
-    client: &'c Client,
-    db: String,
-    precision: Option<Precision>,
-    accept_partial: Option<bool>,
-    body: B,
-}
-
-impl<'c, B> WriteRequestBuilder<'c, B> {
-    /// Set the precision
-    pub fn precision(mut self, set_to: Precision) -> Self {
-        self.precision = Some(set_to);
-        self
-    }
-
-    /// Set the `accept_partial` parameter
-    pub fn accept_partial(mut self, set_to: bool) -> Self {
-        self.accept_partial = Some(set_to);
-        self
-    }
-}
 
-impl<'c> WriteRequestBuilder<'c, NoBody> {
-    /// Set the body of the request to the `/api/v3/write_lp` API
+    /// Make a request to the `POST /api/v3/configure/database` API
     ///
-    /// This essentially wraps `reqwest`'s [`body`][reqwest::RequestBuilder::body]
-    /// method, and puts the responsibility on the caller for now.
-    pub fn body<T: Into<Body>>(self, body: T) -> WriteRequestBuilder<'c, Body> {
-        WriteRequestBuilder {
-            client: self.client,
-            db: self.db,
-            precision: self.precision,
-            accept_partial: self.accept_partial,
-            body: body.into(),
+    /// The retention period is given in seconds, or `None` to retain data indefinitely.
+    pub async fn api_v3_configure_db_create(
+        &self,
+        db: impl Into<String> + Send,
+        retention_period: Option<u64>,
+    ) -> Result<()> {
+        let url = self.base_url.join("/api/v3/configure/database")?;
+        #[derive(Serialize)]
+        struct Req {
+            db: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            retention_period: Option<u64>,
         }
-    }
-}
-
-impl<'c> WriteRequestBuilder<'c, Body> {
-    /// Send the request to the server
-    pub async fn send(self) -> Result<()> {
-        let url = self.client.base_url.join("/api/v3/write_lp")?;
-        let params = WriteParams::from(&self);
-        let mut req = self.client.http_client.post(url).query(&params);
-        if let Some(token) = &self.client.auth_token {
+        let mut req = self.http_client.post(url).json(&Req {
+            db: db.into(),
+            retention_period,
+        });
+        if let Some(token) = &self.auth_token {
             req = req.bearer_auth(token.expose_secret());
         }
         let resp = req
-            .body(self.body)
             .send()
             .await
-            .map_err(|src| Error::request_send(Method::POST, "/api/v3/write_lp", src))?;
+            .map_err(|src| Error::request_send(Method::POST, "/api/v3/configure/database", src))?;
         let status = resp.status();
-        let content = resp.bytes().await.map_err(Error::Bytes)?;
         match status {
-            // TODO - handle the OK response content, return to caller, etc.
             StatusCode::OK => Ok(()),
             code => Err(Error::ApiError {
                 code,
-                message: String::from_utf8(content.to_vec())?,
+                message: resp.text().await.map_err(Error::Text)?,
             }),
         }
     }
-}
-
-#[doc(hidden)]
-/// Typestate type for [`WriteRequestBuilder`]
-#[derive(Debug, Copy, Clone)]
-pub struct NoBody;
 
-/// Used to compose a request to the `/api/v3/query_sql` API
-///
-/// Produced by [`Client::api_v3_query_sql`] method.
-#[derive(Debug)]
-pub struct QueryRequestBuilder<'c> {
-    client: &'c Client,
-    kind: QueryKind,
-    db: String,
-    query: String,
-    format: Option<Format>,
-    params: Option<HashMap<String, StatementParam>>,
-}
-
-// TODO - for now the send method just returns the bytes from the response.
-//   It may be nicer to have the format parameter dictate how we return from
-//   send, e.g., using types more specific to the format selected.
-impl<'c> QueryRequestBuilder<'c> {
-    /// Specify the format, `json`, `csv`, `pretty`, or `parquet`
-    pub fn format(mut self, format: Format) -> Self {
-        self.format = Some(format);
-        self
-    }
-
-    /// Set a query parameter value with the given `name`
+    /// Compose a request to the `POST /api/v3/configure/table` API
     ///
     /// # Example
     /// ```no_run
-    /// # use influxdb3_client::Client;
+    /// # use influxdb3_client::{Client, FieldType};
     /// # #[tokio::main]
     /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     /// let client = Client::new("http://localhost:8181")?;
-    /// let response_bytes = client
-    ///     .api_v3_query_sql("db_name", "SELECT * FROM foo WHERE bar = $bar AND baz > $baz")
-    ///     .with_param("bar", "bop")
-    ///     .with_param("baz", 0.5)
+    /// client
+    ///     .api_v3_configure_table_create("db_name", "cpu")
+    ///     .tag("region")
+    ///     .tag("host")
+    ///     .field("usage", FieldType::Float)
     ///     .send()
-    ///     .await
-    ///     .expect("send query_sql request");
+    ///     .await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn with_param<S: Into<String>, P: Into<StatementParam>>(
-        mut self,
-        name: S,
-        param: P,
-    ) -> Self {
-        self.params
-            .get_or_insert_with(Default::default)
-            .insert(name.into(), param.into());
-        self
+    pub fn api_v3_configure_table_create(
+        &self,
+        db: impl Into<String>,
+        table: impl Into<String>,
+    ) -> CreateTableRequestBuilder<'_> {
+        CreateTableRequestBuilder::new(self, db, table)
     }
 
-    /// Set a query parameters from the given collection of pairs
-    ///
-    /// # Example
-    /// ```no_run
-    /// # use influxdb3_client::Client;
-    /// # #[tokio::main]
-    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    /// use serde_json::json;
-    /// use std::collections::HashMap;
-    ///
-    /// let client = Client::new("http://localhost:8181")?;
-    /// let response_bytes = client
-    ///     .api_v3_query_sql("db_name", "SELECT * FROM foo WHERE bar = $bar AND foo > $fooz")
-    ///     .with_params_from([
-    ///         ("bar", json!(false)),
-    ///         ("foo", json!(10)),
-    ///     ])?
-    ///     .send()
-    ///     .await?;
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn with_params_from<S, P, C>(mut self, params: C) -> Result<Self>
-    where
-        S: Into<String>,
-        P: TryInto<StatementParam, Error = iox_query_params::Error>,
-        C: IntoIterator<Item = (S, P)>,
-    {
-        for (name, param) in params.into_iter() {
-            let name = name.into();
-            let param = param
-                .try_into()
-                .map_err(|source| Error::ConvertQueryParam {
-                    name: name.clone(),
-                    source,
-                })?;
+    /// Make a request to the `DELETE /api/v3/configure/database` API
+    pub async fn api_v3_configure_db_delete(&self, db: impl Into<String> + Send) -> Result<()> {
+        let url = self.base_url.join("/api/v3/configure/database")?;
+        #[derive(Serialize)]
+        struct Req {
+            db: String,
+        }
+        let mut req = self.http_client.delete(url).json(&Req { db: db.into() });
+        if let Some(token) = &self.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req.send().await.map_err(|src| {
+            Error::request_send(Method::DELETE, "/api/v3/configure/database", src)
+        })?;
+        let status = resp.status();
+        match status {
+            StatusCode::OK => Ok(()),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
+        }
+    }
 
-            self.params
-                .get_or_insert_with(Default::default)
-                .insert(name, param);
+    /// Make a request to the `DELETE /api/v3/configure/table` API
+    pub async fn api_v3_configure_table_delete(
+        &self,
+        db: impl Into<String> + Send,
+        table: impl Into<String> + Send,
+    ) -> Result<()> {
+        let url = self.base_url.join("/api/v3/configure/table")?;
+        #[derive(Serialize)]
+        struct Req {
+            db: String,
+            table: String,
+        }
+        let mut req = self.http_client.delete(url).json(&Req {
+            db: db.into(),
+            table: table.into(),
+        });
+        if let Some(token) = &self.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|src| Error::request_send(Method::DELETE, "/api/v3/configure/table", src))?;
+        let status = resp.status();
+        match status {
+            StatusCode::OK => Ok(()),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
         }
-        Ok(self)
     }
 
-    /// Try to set a query parameter value with the given `name`
+    /// Make a request to the `POST /api/v3/configure/database/retention_period` API
+    ///
+    /// The retention period is given in seconds, or `None` to retain data indefinitely.
+    pub async fn api_v3_configure_database_retention_period(
+        &self,
+        db: impl Into<String> + Send,
+        retention_period: Option<u64>,
+    ) -> Result<()> {
+        let url = self
+            .base_url
+            .join("/api/v3/configure/database/retention_period")?;
+        #[derive(Serialize)]
+        struct Req {
+            db: String,
+            retention_period: Option<u64>,
+        }
+        let mut req = self.http_client.post(url).json(&Req {
+            db: db.into(),
+            retention_period,
+        });
+        if let Some(token) = &self.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req.send().await.map_err(|src| {
+            Error::request_send(
+                Method::POST,
+                "/api/v3/configure/database/retention_period",
+                src,
+            )
+        })?;
+        let status = resp.status();
+        match status {
+            StatusCode::OK => Ok(()),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
+        }
+    }
+
+    /// Compose a request to the `POST /api/v3/configure/token` API
     ///
     /// # Example
     /// ```no_run
-    /// # use influxdb3_client::Client;
+    /// # use influxdb3_client::{Client, TokenAction};
     /// # #[tokio::main]
     /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    /// use serde_json::json;
-    ///
-    /// let client = Client::new("http://localhost:8181")?;
-    /// let response_bytes = client
-    ///     .api_v3_query_sql("db_name", "SELECT * FROM foo WHERE bar = $bar AND baz > $baz")
-    ///     .with_try_param("bar", json!("baz"))?
+    /// let client = Client::new("http://localhost:8181")?.with_auth_token("admin-token");
+    /// let resp = client
+    ///     .api_v3_configure_token_create("ingest")
+    ///     .permission("db_name", [TokenAction::Write])
+    ///     .description("telegraf agents")
     ///     .send()
     ///     .await?;
+    /// println!("{}", resp.token);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn with_try_param<S, P>(mut self, name: S, param: P) -> Result<Self>
-    where
-        S: Into<String>,
-        P: TryInto<StatementParam, Error = iox_query_params::Error>,
-    {
-        let name = name.into();
-        let param = param
-            .try_into()
-            .map_err(|source| Error::ConvertQueryParam {
-                name: name.clone(),
-                source,
-            })?;
-        self.params
-            .get_or_insert_with(Default::default)
-            .insert(name, param);
-        Ok(self)
+    pub fn api_v3_configure_token_create(
+        &self,
+        name: impl Into<String>,
+    ) -> CreateTokenRequestBuilder<'_> {
+        CreateTokenRequestBuilder::new(self, name)
+    }
+
+    /// Make a request to the `GET /api/v3/configure/token` API
+    pub async fn api_v3_configure_token_list(&self) -> Result<Vec<TokenInfo>> {
+        let url = self.base_url.join("/api/v3/configure/token")?;
+        let mut req = self.http_client.get(url);
+        if let Some(token) = &self.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|src| Error::request_send(Method::GET, "/api/v3/configure/token", src))?;
+        let status = resp.status();
+        match status {
+            StatusCode::OK => resp.json().await.map_err(Error::Json),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
+        }
+    }
+
+    /// Make a request to the `DELETE /api/v3/configure/token` API
+    pub async fn api_v3_configure_token_delete(
+        &self,
+        name: impl Into<String> + Send,
+    ) -> Result<()> {
+        let url = self.base_url.join("/api/v3/configure/token")?;
+        #[derive(Serialize)]
+        struct Req {
+            name: String,
+        }
+        let mut req = self
+            .http_client
+            .delete(url)
+            .json(&Req { name: name.into() });
+        if let Some(token) = &self.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|src| Error::request_send(Method::DELETE, "/api/v3/configure/token", src))?;
+        let status = resp.status();
+        match status {
+            StatusCode::OK => Ok(()),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
+        }
+    }
+
+    /// Make a request to the `DELETE /api/v3/query/{id}` API, to kill the running query with the
+    /// given ID, as shown in `system.queries`
+    pub async fn api_v3_query_kill(&self, id: impl AsRef<str> + Send) -> Result<()> {
+        let path = format!("/api/v3/query/{}", id.as_ref());
+        let url = self.base_url.join(&path)?;
+        let mut req = self.http_client.delete(url);
+        if let Some(token) = &self.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|src| Error::request_send(Method::DELETE, path, src))?;
+        let status = resp.status();
+        match status {
+            StatusCode::OK => Ok(()),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
+        }
+    }
+
+    /// Send a `/ping` request to the target `influxdb3` server to check its
+    /// status and gather `version` and `revision` information
+    pub async fn ping(&self) -> Result<PingResponse> {
+        let url = self.base_url.join("/ping")?;
+        let mut req = self.http_client.get(url);
+        if let Some(t) = &self.auth_token {
+            req = req.bearer_auth(t.expose_secret());
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|src| Error::request_send(Method::GET, "/ping", src))?;
+        if resp.status().is_success() {
+            resp.json().await.map_err(Error::Json)
+        } else {
+            Err(Error::ApiError {
+                code: resp.status(),
+                message: resp.text().await.map_err(Error::Text)?,
+            })
+        }
+    }
+}
+
+/// The response of the `/ping` API on `influxdb3`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PingResponse {
+    version: String,
+    revision: String,
+}
+
+impl PingResponse {
+    /// Get the `version` from the response
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Get the `revision` from the response
+    pub fn revision(&self) -> &str {
+        &self.revision
+    }
+}
+
+/// The URL parameters of the request to the `/api/v3/write_lp` API
+// TODO - this should re-use a type defined in the server code, or a separate crate,
+//        central to both.
+#[derive(Debug, Serialize)]
+struct WriteParams<'a> {
+    db: &'a str,
+    precision: Option<Precision>,
+    accept_partial: Option<bool>,
+    stream: Option<bool>,
+}
+
+impl<'a, B> From<&'a WriteRequestBuilder<'a, B>> for WriteParams<'a> {
+    fn from(builder: &'a WriteRequestBuilder<'a, B>) -> Self {
+        Self {
+            db: &builder.db,
+            precision: builder.precision,
+            accept_partial: builder.accept_partial,
+            stream: builder.stream,
+        }
+    }
+}
+
+/// Time series precision
+// TODO - this should re-use a type defined in the server code, or a separate crate,
+//        central to both.
+#[derive(Debug, Copy, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    Second,
+    Millisecond,
+    Microsecond,
+    Nanosecond,
+}
+
+/// Builder type for composing a request to `/api/v3/write_lp`
+///
+/// Produced by [`Client::api_v3_write_lp`]
+#[derive(Debug)]
+pub struct WriteRequestBuilder<'c, B> {
+    client: &'c Client,
+    db: String,
+    precision: Option<Precision>,
+    accept_partial: Option<bool>,
+    stream: Option<bool>,
+    content_encoding: Option<ContentEncoding>,
+    body: B,
+}
+
+impl<'c, B> WriteRequestBuilder<'c, B> {
+    /// Set the precision
+    pub fn precision(mut self, set_to: Precision) -> Self {
+        self.precision = Some(set_to);
+        self
+    }
+
+    /// Set the `accept_partial` parameter
+    pub fn accept_partial(mut self, set_to: bool) -> Self {
+        self.accept_partial = Some(set_to);
+        self
+    }
+
+    /// Set the `stream` parameter
+    ///
+    /// Streamed writes are written by the server in slices as the body arrives, so they can be
+    /// larger than the server's maximum request size. Slices are written separately, so a
+    /// streamed write that fails part way through is not rolled back.
+    pub fn stream(mut self, set_to: bool) -> Self {
+        self.stream = Some(set_to);
+        self
+    }
+
+    /// Compress the body with the given encoding before sending it
+    ///
+    /// Bodies created from a stream, e.g., with [`Body::wrap_stream`], can not be compressed by
+    /// the client.
+    pub fn content_encoding(mut self, set_to: ContentEncoding) -> Self {
+        self.content_encoding = Some(set_to);
+        self
+    }
+}
+
+impl<'c> WriteRequestBuilder<'c, NoBody> {
+    /// Set the body of the request to the `/api/v3/write_lp` API
+    ///
+    /// This essentially wraps `reqwest`'s [`body`][reqwest::RequestBuilder::body]
+    /// method, and puts the responsibility on the caller for now.
+    pub fn body<T: Into<Body>>(self, body: T) -> WriteRequestBuilder<'c, Body> {
+        WriteRequestBuilder {
+            client: self.client,
+            db: self.db,
+            precision: self.precision,
+            accept_partial: self.accept_partial,
+            stream: self.stream,
+            content_encoding: self.content_encoding,
+            body: body.into(),
+        }
+    }
+}
+
+impl<'c> WriteRequestBuilder<'c, Body> {
+    /// Send the request to the server
+    pub async fn send(self) -> Result<()> {
+        let url = self.client.base_url.join("/api/v3/write_lp")?;
+        let params = WriteParams::from(&self);
+        let mut req = self.client.http_client.post(url).query(&params);
+        if let Some(token) = &self.client.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let body = match self.content_encoding {
+            Some(encoding) => {
+                let body = self.body.as_bytes().ok_or(Error::CompressStreamedBody)?;
+                req = req.header(reqwest::header::CONTENT_ENCODING, encoding.as_str());
+                encoding.compress(body).map_err(Error::Compress)?.into()
+            }
+            None => self.body,
+        };
+        let resp = req
+            .body(body)
+            .send()
+            .await
+            .map_err(|src| Error::request_send(Method::POST, "/api/v3/write_lp", src))?;
+        let status = resp.status();
+        let content = resp.bytes().await.map_err(Error::Bytes)?;
+        match status {
+            // TODO - handle the OK response content, return to caller, etc.
+            StatusCode::OK => Ok(()),
+            code => Err(Error::ApiError {
+                code,
+                message: String::from_utf8(content.to_vec())?,
+            }),
+        }
+    }
+}
+
+/// The encodings that the client can compress a request body with
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Gzip,
+    Zstd,
+    /// The snappy block format, which the server decodes in full rather than as it streams in,
+    /// so bodies compressed with it are limited to the server's maximum request size
+    Snappy,
+}
+
+impl ContentEncoding {
+    /// The value of the `Content-Encoding` header for this encoding
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Snappy => "snappy",
+        }
+    }
+
+    fn compress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip => {
+                use std::io::Write;
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Self::Zstd => zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+            Self::Snappy => snap::raw::Encoder::new()
+                .compress_vec(data)
+                .map_err(Into::into),
+        }
+    }
+}
+
+#[doc(hidden)]
+/// Typestate type for [`WriteRequestBuilder`]
+#[derive(Debug, Copy, Clone)]
+pub struct NoBody;
+
+/// Used to compose a request to the `/api/v3/query_sql` API
+///
+/// Produced by [`Client::api_v3_query_sql`] method.
+#[derive(Debug)]
+pub struct QueryRequestBuilder<'c> {
+    client: &'c Client,
+    kind: QueryKind,
+    db: String,
+    query: String,
+    format: Option<Format>,
+    params: Option<HashMap<String, StatementParam>>,
+    explain: Option<Explain>,
+}
+
+// TODO - for now the send method just returns the bytes from the response.
+//   It may be nicer to have the format parameter dictate how we return from
+//   send, e.g., using types more specific to the format selected.
+impl<'c> QueryRequestBuilder<'c> {
+    /// Specify the format, `json`, `csv`, `pretty`, or `parquet`
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Set a query parameter value with the given `name`
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let response_bytes = client
+    ///     .api_v3_query_sql("db_name", "SELECT * FROM foo WHERE bar = $bar AND baz > $baz")
+    ///     .with_param("bar", "bop")
+    ///     .with_param("baz", 0.5)
+    ///     .send()
+    ///     .await
+    ///     .expect("send query_sql request");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_param<S: Into<String>, P: Into<StatementParam>>(
+        mut self,
+        name: S,
+        param: P,
+    ) -> Self {
+        self.params
+            .get_or_insert_with(Default::default)
+            .insert(name.into(), param.into());
+        self
+    }
+
+    /// Set a query parameters from the given collection of pairs
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// use serde_json::json;
+    /// use std::collections::HashMap;
+    ///
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let response_bytes = client
+    ///     .api_v3_query_sql("db_name", "SELECT * FROM foo WHERE bar = $bar AND foo > $fooz")
+    ///     .with_params_from([
+    ///         ("bar", json!(false)),
+    ///         ("foo", json!(10)),
+    ///     ])?
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_params_from<S, P, C>(mut self, params: C) -> Result<Self>
+    where
+        S: Into<String>,
+        P: TryInto<StatementParam, Error = iox_query_params::Error>,
+        C: IntoIterator<Item = (S, P)>,
+    {
+        for (name, param) in params.into_iter() {
+            let name = name.into();
+            let param = param
+                .try_into()
+                .map_err(|source| Error::ConvertQueryParam {
+                    name: name.clone(),
+                    source,
+                })?;
+
+            self.params
+                .get_or_insert_with(Default::default)
+                .insert(name, param);
+        }
+        Ok(self)
+    }
+
+    /// Try to set a query parameter value with the given `name`
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// use serde_json::json;
+    ///
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let response_bytes = client
+    ///     .api_v3_query_sql("db_name", "SELECT * FROM foo WHERE bar = $bar AND baz > $baz")
+    ///     .with_try_param("bar", json!("baz"))?
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_try_param<S, P>(mut self, name: S, param: P) -> Result<Self>
+    where
+        S: Into<String>,
+        P: TryInto<StatementParam, Error = iox_query_params::Error>,
+    {
+        let name = name.into();
+        let param = param
+            .try_into()
+            .map_err(|source| Error::ConvertQueryParam {
+                name: name.clone(),
+                source,
+            })?;
+        self.params
+            .get_or_insert_with(Default::default)
+            .insert(name, param);
+        Ok(self)
+    }
+
+    /// Send the request with `explain=analyze`, which runs the query to completion, and
+    /// returns the plan that was executed, with the metrics from its execution, instead of the
+    /// results of the query
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let explain = client
+    ///     .api_v3_query_sql("db_name", "SELECT * FROM foo")
+    ///     .explain_analyze()
+    ///     .await?;
+    /// println!("{} rows from {} Parquet files", explain.rows, explain.parquet_chunks);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn explain_analyze(mut self) -> Result<ExplainAnalyze> {
+        self.explain = Some(Explain::Analyze);
+        let content = self.send().await?;
+        serde_json::from_slice(&content).map_err(Error::ExplainJson)
+    }
+
+    /// Send the request to `/api/v3/query_sql` or `/api/v3/query_influxql`
+    pub async fn send(self) -> Result<Bytes> {
+        let url = match self.kind {
+            QueryKind::Sql => self.client.base_url.join("/api/v3/query_sql")?,
+            QueryKind::InfluxQl => self.client.base_url.join("/api/v3/query_influxql")?,
+        };
+        let params = QueryParams::from(&self);
+        let mut req = self.client.http_client.post(url).json(&params);
+        if let Some(token) = &self.client.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        if self.client.compressed_responses {
+            req = req.header(reqwest::header::ACCEPT_ENCODING, "zstd, gzip");
+        }
+        let resp = req.send().await.map_err(|src| {
+            Error::request_send(Method::POST, format!("/api/v3/query_{}", self.kind), src)
+        })?;
+        let status = resp.status();
+        let encoding = resp
+            .headers()
+            .get(reqwest::header::CONTENT_ENCODING)
+            .cloned();
+        let content = resp.bytes().await.map_err(Error::Bytes)?;
+        let content = decompress(encoding.as_ref(), content).map_err(Error::Decompress)?;
+
+        match status {
+            StatusCode::OK => Ok(content),
+            code => Err(Error::ApiError {
+                code,
+                message: String::from_utf8(content.to_vec()).map_err(Error::InvalidUtf8)?,
+            }),
+        }
+    }
+}
+
+/// Decompress a response body according to its `Content-Encoding`
+fn decompress(
+    encoding: Option<&reqwest::header::HeaderValue>,
+    content: Bytes,
+) -> std::io::Result<Bytes> {
+    use std::io::Read;
+    match encoding.map(|e| e.as_bytes()) {
+        None | Some(b"identity") => Ok(content),
+        Some(b"gzip") => {
+            let mut decoded = Vec::new();
+            flate2::read::GzDecoder::new(&content[..]).read_to_end(&mut decoded)?;
+            Ok(decoded.into())
+        }
+        Some(b"zstd") => zstd::decode_all(&content[..]).map(Into::into),
+        Some(other) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "unsupported content-encoding: {}",
+                String::from_utf8_lossy(other)
+            ),
+        )),
+    }
+}
+
+/// Query parameters for the `/api/v3/query_sql` API
+#[derive(Debug, Serialize)]
+pub struct QueryParams<'a> {
+    db: &'a str,
+    #[serde(rename = "q")]
+    query: &'a str,
+    format: Option<Format>,
+    params: Option<&'a HashMap<String, StatementParam>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explain: Option<Explain>,
+}
+
+impl<'a> From<&'a QueryRequestBuilder<'a>> for QueryParams<'a> {
+    fn from(builder: &'a QueryRequestBuilder<'a>) -> Self {
+        Self {
+            db: &builder.db,
+            query: &builder.query,
+            format: builder.format,
+            params: builder.params.as_ref(),
+            explain: builder.explain,
+        }
+    }
+}
+
+/// The `explain` parameter of the query APIs
+#[derive(Debug, Serialize, Copy, Clone)]
+#[serde(rename_all = "snake_case")]
+enum Explain {
+    Analyze,
+}
+
+/// The plan of a query made with `explain=analyze`, along with the metrics from its execution
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct ExplainAnalyze {
+    /// The number of rows the query returned
+    pub rows: usize,
+    /// How long the query took to run, in nanoseconds
+    pub elapsed_ns: u64,
+    /// The number of chunks of data in the write buffer that were read by the query
+    pub buffer_chunks: usize,
+    /// The number of Parquet files that were read by the query
+    pub parquet_chunks: usize,
+    /// The physical plan that was executed
+    pub plan: PlanNode,
+}
+
+/// An operator in the physical plan of a query, along with the metrics from its execution,
+/// which are only present for the operators that record them
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct PlanNode {
+    /// The name of the operator, e.g., `ProjectionExec`
+    pub operator: String,
+    /// The operator as it is shown in the output of `EXPLAIN`
+    pub details: String,
+    pub output_rows: Option<usize>,
+    /// The CPU time spent in the operator, in nanoseconds
+    pub elapsed_compute_ns: Option<usize>,
+    pub bytes_scanned: Option<usize>,
+    /// The number of chunks of data in the write buffer scanned by the operator
+    pub buffer_chunks: Option<usize>,
+    /// The number of Parquet files scanned by the operator
+    pub parquet_chunks: Option<usize>,
+    #[serde(default)]
+    pub children: Vec<PlanNode>,
+}
+
+/// The type of query, SQL or InfluxQL
+#[derive(Debug, Copy, Clone)]
+pub enum QueryKind {
+    Sql,
+    InfluxQl,
+}
+
+impl Display for QueryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryKind::Sql => write!(f, "sql"),
+            QueryKind::InfluxQl => write!(f, "influxql"),
+        }
+    }
+}
+
+/// Output format to request from the server in the `/api/v3/query_sql` API
+#[derive(Debug, Serialize, Copy, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    Json,
+    JsonLines,
+    Csv,
+    Parquet,
+    Pretty,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateLastCacheRequestBuilder<'c> {
+    #[serde(skip_serializing)]
+    client: &'c Client,
+    db: String,
+    table: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_columns: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value_columns: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<u64>,
+}
+
+impl<'c> CreateLastCacheRequestBuilder<'c> {
+    /// Create a new [`CreateLastCacheRequestBuilder`]
+    fn new(client: &'c Client, db: impl Into<String>, table: impl Into<String>) -> Self {
+        Self {
+            client,
+            db: db.into(),
+            table: table.into(),
+            name: None,
+            key_columns: None,
+            value_columns: None,
+            count: None,
+            ttl: None,
+        }
+    }
+
+    /// Specify a cache name
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Speciffy the key columns for the cache
+    pub fn key_columns(mut self, column_names: impl IntoIterator<Item: Into<String>>) -> Self {
+        self.key_columns = Some(column_names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Specify the value columns for the cache
+    pub fn value_columns(mut self, column_names: impl IntoIterator<Item: Into<String>>) -> Self {
+        self.value_columns = Some(column_names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Specify the size, or number of new entries a cache will hold before evicting old ones
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Specify the time-to-live (TTL) in seconds for entries in the cache
+    pub fn ttl(mut self, ttl: u64) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Send the request to `POST /api/v3/configure/last_cache`
+    pub async fn send(self) -> Result<Option<LastCacheCreatedResponse>> {
+        let url = self.client.base_url.join("/api/v3/configure/last_cache")?;
+        let mut req = self.client.http_client.post(url).json(&self);
+        if let Some(token) = &self.client.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req.send().await.map_err(|src| {
+            Error::request_send(Method::POST, "/api/v3/configure/last_cache", src)
+        })?;
+        let status = resp.status();
+        match status {
+            StatusCode::CREATED => {
+                let content = resp
+                    .json::<LastCacheCreatedResponse>()
+                    .await
+                    .map_err(Error::Json)?;
+                Ok(Some(content))
+            }
+            StatusCode::NO_CONTENT => Ok(None),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LastCacheCreatedResponse {
+    /// The table name the cache is associated with
+    pub table: String,
+    /// Given name of the cache
+    pub name: String,
+    /// Columns intended to be used as predicates in the cache
+    pub key_columns: Vec<u32>,
+    /// Columns that store values in the cache
+    pub value_columns: LastCacheValueColumnsDef,
+    /// The number of last values to hold in the cache
+    pub count: usize,
+    /// The time-to-live (TTL) in seconds for entries in the cache
+    pub ttl: u64,
+}
+
+/// A last cache will either store values for an explicit set of columns, or will accept all
+/// non-key columns
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LastCacheValueColumnsDef {
+    /// Explicit list of column names
+    Explicit { columns: Vec<u32> },
+    /// Stores all non-key columns
+    AllNonKeyColumns,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateMetaCacheRequestBuilder<'c> {
+    #[serde(skip_serializing)]
+    client: &'c Client,
+    db: String,
+    table: String,
+    columns: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_cardinality: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_age: Option<u64>,
+}
+
+impl<'c> CreateMetaCacheRequestBuilder<'c> {
+    /// Create a new [`CreateMetaCacheRequestBuilder`]
+    fn new(
+        client: &'c Client,
+        db: impl Into<String>,
+        table: impl Into<String>,
+        columns: impl IntoIterator<Item: Into<String>>,
+    ) -> Self {
+        Self {
+            client,
+            db: db.into(),
+            table: table.into(),
+            columns: columns.into_iter().map(Into::into).collect(),
+            name: None,
+            max_cardinality: None,
+            max_age: None,
+        }
+    }
+
+    /// Specify a cache name
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Specify the maximum number of distinct value combinations the cache will hold
+    pub fn max_cardinality(mut self, max_cardinality: usize) -> Self {
+        self.max_cardinality = Some(max_cardinality);
+        self
+    }
+
+    /// Specify the maximum age in seconds for entries in the cache
+    pub fn max_age(mut self, max_age: u64) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Send the request to `POST /api/v3/configure/meta_cache`
+    pub async fn send(self) -> Result<Option<MetaCacheCreatedResponse>> {
+        let url = self.client.base_url.join("/api/v3/configure/meta_cache")?;
+        let mut req = self.client.http_client.post(url).json(&self);
+        if let Some(token) = &self.client.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req.send().await.map_err(|src| {
+            Error::request_send(Method::POST, "/api/v3/configure/meta_cache", src)
+        })?;
+        let status = resp.status();
+        match status {
+            StatusCode::CREATED => {
+                let content = resp
+                    .json::<MetaCacheCreatedResponse>()
+                    .await
+                    .map_err(Error::Json)?;
+                Ok(Some(content))
+            }
+            StatusCode::NO_CONTENT => Ok(None),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetaCacheCreatedResponse {
+    /// The table name the cache is associated with
+    pub table_name: String,
+    /// Given name of the cache
+    pub cache_name: String,
+    /// Columns whose distinct values are stored in the cache
+    pub column_ids: Vec<u32>,
+    /// The maximum number of distinct value combinations the cache will hold
+    pub max_cardinality: usize,
+    /// The maximum age in seconds for entries in the cache
+    pub max_age_seconds: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateTokenRequestBuilder<'c> {
+    #[serde(skip_serializing)]
+    client: &'c Client,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    permissions: Vec<TokenPermission>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_in: Option<u64>,
+}
+
+impl<'c> CreateTokenRequestBuilder<'c> {
+    /// Create a new [`CreateTokenRequestBuilder`]
+    fn new(client: &'c Client, name: impl Into<String>) -> Self {
+        Self {
+            client,
+            name: name.into(),
+            description: None,
+            permissions: vec![],
+            expires_in: None,
+        }
+    }
+
+    /// Describe what the token is used for
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Grant the token the given actions on a database, or on all databases if the database
+    /// is `*`
+    pub fn permission(
+        mut self,
+        database: impl Into<String>,
+        actions: impl IntoIterator<Item = TokenAction>,
+    ) -> Self {
+        self.permissions.push(TokenPermission {
+            database: database.into(),
+            actions: actions.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Specify the number of seconds after which the token expires
+    pub fn expires_in(mut self, expires_in: u64) -> Self {
+        self.expires_in = Some(expires_in);
+        self
+    }
+
+    /// Send the request to `POST /api/v3/configure/token`
+    pub async fn send(self) -> Result<TokenCreatedResponse> {
+        let url = self.client.base_url.join("/api/v3/configure/token")?;
+        let mut req = self.client.http_client.post(url).json(&self);
+        if let Some(token) = &self.client.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|src| Error::request_send(Method::POST, "/api/v3/configure/token", src))?;
+        let status = resp.status();
+        match status {
+            StatusCode::CREATED => resp.json().await.map_err(Error::Json),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
+        }
+    }
+}
+
+/// Builder type for composing a request to `GET /api/v3/export`
+///
+/// Produced by [`Client::api_v3_export`]
+#[derive(Debug)]
+pub struct ExportRequestBuilder<'c> {
+    client: &'c Client,
+    db: String,
+    tables: Vec<String>,
+    start: Option<String>,
+    end: Option<String>,
+    gzip: bool,
+}
+
+impl<'c> ExportRequestBuilder<'c> {
+    /// Export the given table, can be called more than once to export several tables. All
+    /// tables are exported if none are given.
+    pub fn table(mut self, table: impl Into<String>) -> Self {
+        self.tables.push(table.into());
+        self
     }
 
-    /// Send the request to `/api/v3/query_sql` or `/api/v3/query_influxql`
-    pub async fn send(self) -> Result<Bytes> {
-        let url = match self.kind {
-            QueryKind::Sql => self.client.base_url.join("/api/v3/query_sql")?,
-            QueryKind::InfluxQl => self.client.base_url.join("/api/v3/query_influxql")?,
+    /// Only export rows at or after the given time, either an RFC3339 timestamp or
+    /// nanoseconds since the epoch
+    pub fn start(mut self, start: impl Into<String>) -> Self {
+        self.start = Some(start.into());
+        self
+    }
+
+    /// Only export rows before the given time, either an RFC3339 timestamp or nanoseconds
+    /// since the epoch
+    pub fn end(mut self, end: impl Into<String>) -> Self {
+        self.end = Some(end.into());
+        self
+    }
+
+    /// Request the line protocol gzip-compressed. The response bytes are left compressed.
+    pub fn gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+
+    /// Send the request to `GET /api/v3/export`
+    pub async fn send(self) -> Result<ExportResponse> {
+        let url = self.client.base_url.join("/api/v3/export")?;
+        #[derive(Serialize)]
+        struct Params<'a> {
+            db: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            table: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            start: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            end: Option<&'a str>,
+        }
+        let params = Params {
+            db: &self.db,
+            table: (!self.tables.is_empty()).then(|| self.tables.join(",")),
+            start: self.start.as_deref(),
+            end: self.end.as_deref(),
         };
-        let params = QueryParams::from(&self);
-        let mut req = self.client.http_client.post(url).json(&params);
+        let mut req = self.client.http_client.get(url).query(&params);
+        if self.gzip {
+            req = req.header(reqwest::header::ACCEPT_ENCODING, "gzip");
+        }
         if let Some(token) = &self.client.auth_token {
             req = req.bearer_auth(token.expose_secret());
         }
-        let resp = req.send().await.map_err(|src| {
-            Error::request_send(Method::POST, format!("/api/v3/query_{}", self.kind), src)
-        })?;
+        let resp = req
+            .send()
+            .await
+            .map_err(|src| Error::request_send(Method::GET, "/api/v3/export", src))?;
         let status = resp.status();
-        let content = resp.bytes().await.map_err(Error::Bytes)?;
-
         match status {
-            StatusCode::OK => Ok(content),
+            StatusCode::OK => Ok(ExportResponse { resp }),
             code => Err(Error::ApiError {
                 code,
-                message: String::from_utf8(content.to_vec()).map_err(Error::InvalidUtf8)?,
+                message: resp.text().await.map_err(Error::Text)?,
             }),
         }
     }
 }
 
-/// Query parameters for the `/api/v3/query_sql` API
-#[derive(Debug, Serialize)]
-pub struct QueryParams<'a> {
-    db: &'a str,
-    #[serde(rename = "q")]
-    query: &'a str,
-    format: Option<Format>,
-    params: Option<&'a HashMap<String, StatementParam>>,
+/// The streamed response of the `GET /api/v3/export` API
+#[derive(Debug)]
+pub struct ExportResponse {
+    resp: reqwest::Response,
 }
 
-impl<'a> From<&'a QueryRequestBuilder<'a>> for QueryParams<'a> {
-    fn from(builder: &'a QueryRequestBuilder<'a>) -> Self {
-        Self {
-            db: &builder.db,
-            query: &builder.query,
-            format: builder.format,
-            params: builder.params.as_ref(),
-        }
+impl ExportResponse {
+    /// The next chunk of line protocol bytes, or `None` once the export is complete
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        self.resp.chunk().await.map_err(Error::Bytes)
     }
 }
 
-/// The type of query, SQL or InfluxQL
-#[derive(Debug, Copy, Clone)]
-pub enum QueryKind {
-    Sql,
-    InfluxQl,
+/// The format of a file imported with the `POST /api/v3/import` API
+#[derive(Debug, Copy, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    Parquet,
+    Csv,
 }
 
-impl Display for QueryKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            QueryKind::Sql => write!(f, "sql"),
-            QueryKind::InfluxQl => write!(f, "influxql"),
+/// Builder type for composing a request to `POST /api/v3/import`
+///
+/// Produced by [`Client::api_v3_import`]
+#[derive(Debug)]
+pub struct ImportRequestBuilder<'c, B> {
+    client: &'c Client,
+    db: String,
+    table: String,
+    format: Option<ImportFormat>,
+    tags: Vec<String>,
+    fields: Vec<(String, Option<FieldType>)>,
+    time_column: Option<String>,
+    precision: Option<Precision>,
+    body: B,
+}
+
+impl<'c, B> ImportRequestBuilder<'c, B> {
+    /// Set the format of the imported file. If not set, the server requires a `Content-Type`
+    /// of `application/vnd.apache.parquet` or `text/csv`, so this should usually be set.
+    pub fn format(mut self, format: ImportFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Import the given column of the file as a tag
+    pub fn tag(mut self, name: impl Into<String>) -> Self {
+        self.tags.push(name.into());
+        self
+    }
+
+    /// Import the given column of the file as a field, of the given type, or of the type it has
+    /// in the file or catalog if `None`. Required for CSV files, where only the tags and fields
+    /// given are imported.
+    pub fn field(mut self, name: impl Into<String>, field_type: Option<FieldType>) -> Self {
+        self.fields.push((name.into(), field_type));
+        self
+    }
+
+    /// Set the column of the file holding the time of each row, `time` by default
+    pub fn time_column(mut self, name: impl Into<String>) -> Self {
+        self.time_column = Some(name.into());
+        self
+    }
+
+    /// Set the precision of integer times in the file, nanoseconds by default
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+}
+
+impl<'c> ImportRequestBuilder<'c, NoBody> {
+    /// Set the contents of the imported file
+    pub fn body<T: Into<Body>>(self, body: T) -> ImportRequestBuilder<'c, Body> {
+        ImportRequestBuilder {
+            client: self.client,
+            db: self.db,
+            table: self.table,
+            format: self.format,
+            tags: self.tags,
+            fields: self.fields,
+            time_column: self.time_column,
+            precision: self.precision,
+            body: body.into(),
         }
     }
 }
 
-/// Output format to request from the server in the `/api/v3/query_sql` API
-#[derive(Debug, Serialize, Copy, Clone)]
-#[serde(rename_all = "snake_case")]
-pub enum Format {
-    Json,
-    Csv,
-    Parquet,
-    Pretty,
+impl<'c> ImportRequestBuilder<'c, Body> {
+    /// Send the request to `POST /api/v3/import`
+    pub async fn send(self) -> Result<ImportSummary> {
+        let url = self.client.base_url.join("/api/v3/import")?;
+        #[derive(Serialize)]
+        struct Params<'a> {
+            db: &'a str,
+            table: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            format: Option<ImportFormat>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            tags: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            fields: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            time: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            precision: Option<Precision>,
+        }
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, field_type)| match field_type {
+                Some(FieldType::String) => format!("{name}:string"),
+                Some(FieldType::Integer) => format!("{name}:integer"),
+                Some(FieldType::UInteger) => format!("{name}:uinteger"),
+                Some(FieldType::Float) => format!("{name}:float"),
+                Some(FieldType::Boolean) => format!("{name}:boolean"),
+                None => name.clone(),
+            })
+            .collect::<Vec<_>>();
+        let params = Params {
+            db: &self.db,
+            table: &self.table,
+            format: self.format,
+            tags: (!self.tags.is_empty()).then(|| self.tags.join(",")),
+            fields: (!fields.is_empty()).then(|| fields.join(",")),
+            time: self.time_column.as_deref(),
+            precision: self.precision,
+        };
+        let mut req = self.client.http_client.post(url).query(&params);
+        if let Some(token) = &self.client.auth_token {
+            req = req.bearer_auth(token.expose_secret());
+        }
+        let resp = req
+            .body(self.body)
+            .send()
+            .await
+            .map_err(|src| Error::request_send(Method::POST, "/api/v3/import", src))?;
+        let status = resp.status();
+        match status {
+            StatusCode::OK => resp.json().await.map_err(Error::Json),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
+        }
+    }
 }
 
+/// The rows imported by the `POST /api/v3/import` API
+#[derive(Debug, Deserialize, Eq, PartialEq, Clone, Copy)]
+pub struct ImportSummary {
+    /// The number of rows imported
+    pub row_count: usize,
+    /// The number of rows skipped, because they had no time, no field values, were missing a
+    /// tag of the series key, or were older than the database's retention period
+    pub skipped_row_count: usize,
+}
+
+/// Builder type for composing a request to `POST /api/v3/configure/table`
+///
+/// Produced by [`Client::api_v3_configure_table_create`]
 #[derive(Debug, Serialize)]
-pub struct CreateLastCacheRequestBuilder<'c> {
+pub struct CreateTableRequestBuilder<'c> {
     #[serde(skip_serializing)]
     client: &'c Client,
     db: String,
     table: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    key_columns: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    value_columns: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    count: Option<usize>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    ttl: Option<u64>,
+    tags: Vec<String>,
+    fields: Vec<TableField>,
 }
 
-impl<'c> CreateLastCacheRequestBuilder<'c> {
-    /// Create a new [`CreateLastCacheRequestBuilder`]
+impl<'c> CreateTableRequestBuilder<'c> {
+    /// Create a new [`CreateTableRequestBuilder`]
     fn new(client: &'c Client, db: impl Into<String>, table: impl Into<String>) -> Self {
         Self {
             client,
             db: db.into(),
             table: table.into(),
-            name: None,
-            key_columns: None,
-            value_columns: None,
-            count: None,
-            ttl: None,
+            tags: vec![],
+            fields: vec![],
         }
     }
 
-    /// Specify a cache name
-    pub fn name(mut self, name: impl Into<String>) -> Self {
-        self.name = Some(name.into());
-        self
-    }
-
-    /// Speciffy the key columns for the cache
-    pub fn key_columns(mut self, column_names: impl IntoIterator<Item: Into<String>>) -> Self {
-        self.key_columns = Some(column_names.into_iter().map(Into::into).collect());
-        self
-    }
-
-    /// Specify the value columns for the cache
-    pub fn value_columns(mut self, column_names: impl IntoIterator<Item: Into<String>>) -> Self {
-        self.value_columns = Some(column_names.into_iter().map(Into::into).collect());
-        self
-    }
-
-    /// Specify the size, or number of new entries a cache will hold before evicting old ones
-    pub fn count(mut self, count: usize) -> Self {
-        self.count = Some(count);
+    /// Add a tag column to the table
+    pub fn tag(mut self, name: impl Into<String>) -> Self {
+        self.tags.push(name.into());
         self
     }
 
-    /// Specify the time-to-live (TTL) in seconds for entries in the cache
-    pub fn ttl(mut self, ttl: u64) -> Self {
-        self.ttl = Some(ttl);
+    /// Add a field column of the given type to the table
+    pub fn field(mut self, name: impl Into<String>, field_type: FieldType) -> Self {
+        self.fields.push(TableField {
+            name: name.into(),
+            field_type,
+        });
         self
     }
 
-    /// Send the request to `POST /api/v3/configure/last_cache`
-    pub async fn send(self) -> Result<Option<LastCacheCreatedResponse>> {
-        let url = self.client.base_url.join("/api/v3/configure/last_cache")?;
+    /// Send the request to `POST /api/v3/configure/table`
+    pub async fn send(self) -> Result<()> {
+        let url = self.client.base_url.join("/api/v3/configure/table")?;
         let mut req = self.client.http_client.post(url).json(&self);
         if let Some(token) = &self.client.auth_token {
             req = req.bearer_auth(token.expose_secret());
         }
-        let resp = req.send().await.map_err(|src| {
-            Error::request_send(Method::POST, "/api/v3/configure/last_cache", src)
-        })?;
+        let resp = req
+            .send()
+            .await
+            .map_err(|src| Error::request_send(Method::POST, "/api/v3/configure/table", src))?;
         let status = resp.status();
         match status {
-            StatusCode::CREATED => {
-                let content = resp
-                    .json::<LastCacheCreatedResponse>()
-                    .await
-                    .map_err(Error::Json)?;
-                Ok(Some(content))
-            }
-            StatusCode::NO_CONTENT => Ok(None),
+            StatusCode::OK => Ok(()),
             code => Err(Error::ApiError {
                 code,
                 message: resp.text().await.map_err(Error::Text)?,
@@ -704,31 +1832,65 @@ impl<'c> CreateLastCacheRequestBuilder<'c> {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LastCacheCreatedResponse {
-    /// The table name the cache is associated with
-    pub table: String,
-    /// Given name of the cache
+/// A field column declared when creating a table
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct TableField {
     pub name: String,
-    /// Columns intended to be used as predicates in the cache
-    pub key_columns: Vec<u32>,
-    /// Columns that store values in the cache
-    pub value_columns: LastCacheValueColumnsDef,
-    /// The number of last values to hold in the cache
-    pub count: usize,
-    /// The time-to-live (TTL) in seconds for entries in the cache
-    pub ttl: u64,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
 }
 
-/// A last cache will either store values for an explicit set of columns, or will accept all
-/// non-key columns
+/// The data type of a field column
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    String,
+    Integer,
+    #[serde(rename = "uinteger")]
+    UInteger,
+    Float,
+    Boolean,
+}
+
+/// The actions granted by a token on a database, or on all databases
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
-#[serde(tag = "type", rename_all = "snake_case")]
-pub enum LastCacheValueColumnsDef {
-    /// Explicit list of column names
-    Explicit { columns: Vec<u32> },
-    /// Stores all non-key columns
-    AllNonKeyColumns,
+pub struct TokenPermission {
+    /// The database name, or `*` for all databases
+    pub database: String,
+    pub actions: Vec<TokenAction>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenAction {
+    /// Query data
+    Read,
+    /// Write data
+    Write,
+    /// Manage configuration, such as caches and tokens, along with read and write access
+    Admin,
+}
+
+/// A named token, as returned by the `/api/v3/configure/token` APIs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenInfo {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub permissions: Vec<TokenPermission>,
+    /// The RFC3339 time the token was created
+    pub created_at: String,
+    /// The RFC3339 time the token expires, if it ever does
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenCreatedResponse {
+    /// The token, which the server only returns when it is created
+    pub token: String,
+    #[serde(flatten)]
+    pub info: TokenInfo,
 }
 
 #[cfg(test)]
@@ -736,7 +1898,10 @@ mod tests {
     use mockito::{Matcher, Server};
     use serde_json::json;
 
-    use crate::{Client, Format, Precision};
+    use crate::{
+        Client, ContentEncoding, FieldType, Format, ImportFormat, ImportSummary, Precision,
+        TokenAction, TokenPermission,
+    };
 
     #[tokio::test]
     async fn api_v3_write_lp() {
@@ -755,18 +1920,47 @@ mod tests {
                 Matcher::UrlEncoded("db".into(), db.into()),
                 Matcher::UrlEncoded("accept_partial".into(), "true".into()),
             ]))
-            .match_body(body)
+            .match_body(body)
+            .create_async()
+            .await;
+
+        let client = Client::new(mock_server.url())
+            .expect("create client")
+            .with_auth_token(token);
+
+        client
+            .api_v3_write_lp(db)
+            .precision(Precision::Millisecond)
+            .accept_partial(true)
+            .body(body)
+            .send()
+            .await
+            .expect("send write_lp request");
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn api_v3_write_lp_compressed() {
+        let body = "cpu,host=s1 usage=0.5\ncpu,host=s2 usage=0.7\n";
+
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/write_lp")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("db".into(), "stats".into()),
+                Matcher::UrlEncoded("stream".into(), "true".into()),
+            ]))
+            .match_header("Content-Encoding", "zstd")
+            .match_body(zstd::encode_all(body.as_bytes(), zstd::DEFAULT_COMPRESSION_LEVEL).unwrap())
             .create_async()
             .await;
 
-        let client = Client::new(mock_server.url())
-            .expect("create client")
-            .with_auth_token(token);
-
+        let client = Client::new(mock_server.url()).expect("create client");
         client
-            .api_v3_write_lp(db)
-            .precision(Precision::Millisecond)
-            .accept_partial(true)
+            .api_v3_write_lp("stats")
+            .stream(true)
+            .content_encoding(ContentEncoding::Zstd)
             .body(body)
             .send()
             .await
@@ -816,6 +2010,65 @@ mod tests {
         mock.assert_async().await;
     }
 
+    #[tokio::test]
+    async fn api_v3_query_sql_explain_analyze() {
+        let db = "stats";
+        let query = "SELECT * FROM foo";
+        let body = serde_json::json!({
+            "rows": 1,
+            "elapsed_ns": 1000,
+            "buffer_chunks": 1,
+            "parquet_chunks": 0,
+            "plan": {
+                "operator": "ProjectionExec",
+                "details": "ProjectionExec: expr=[host@0 as host]",
+                "output_rows": 1,
+                "elapsed_compute_ns": 100,
+                "children": [{
+                    "operator": "RecordBatchesExec",
+                    "details": "RecordBatchesExec: chunks=1",
+                    "output_rows": 1,
+                    "buffer_chunks": 1,
+                    "children": [],
+                }],
+            },
+        });
+
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/query_sql")
+            .match_body(Matcher::Json(serde_json::json!({
+                "db": db,
+                "q": query,
+                "format": null,
+                "params": null,
+                "explain": "analyze",
+            })))
+            .with_status(200)
+            .with_body(body.to_string())
+            .create_async()
+            .await;
+
+        let client = Client::new(mock_server.url()).expect("create client");
+
+        let explain = client
+            .api_v3_query_sql(db, query)
+            .explain_analyze()
+            .await
+            .expect("send explain request to server");
+
+        assert_eq!(explain.rows, 1);
+        assert_eq!(explain.buffer_chunks, 1);
+        assert_eq!(explain.plan.operator, "ProjectionExec");
+        assert_eq!(explain.plan.bytes_scanned, None);
+        let scan = &explain.plan.children[0];
+        assert_eq!(scan.operator, "RecordBatchesExec");
+        assert_eq!(scan.buffer_chunks, Some(1));
+        assert!(scan.children.is_empty());
+
+        mock.assert_async().await;
+    }
+
     #[tokio::test]
     async fn api_v3_query_sql_params() {
         let db = "stats";
@@ -853,6 +2106,45 @@ mod tests {
         r.expect("sent request successfully");
     }
 
+    #[tokio::test]
+    async fn api_v3_query_sql_compressed() {
+        let body = r#"[{"host": "foo", "time": "1990-07-23T06:00:00:000", "val": 1}]"#;
+
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/query_sql")
+            .match_header("Accept-Encoding", "zstd, gzip")
+            .with_header("Content-Encoding", "zstd")
+            .with_body(zstd::encode_all(body.as_bytes(), 0).unwrap())
+            .create_async()
+            .await;
+
+        let client = Client::new(mock_server.url()).expect("create client");
+        let r = client
+            .api_v3_query_sql("stats", "SELECT * FROM foo")
+            .send()
+            .await
+            .expect("send request to server");
+        assert_eq!(&r, body);
+        mock.assert_async().await;
+
+        // compression can be turned off:
+        let mock = mock_server
+            .mock("POST", "/api/v3/query_sql")
+            .match_header("Accept-Encoding", Matcher::Missing)
+            .with_body(body)
+            .create_async()
+            .await;
+        let client = client.with_compressed_responses(false);
+        let r = client
+            .api_v3_query_sql("stats", "SELECT * FROM foo")
+            .send()
+            .await
+            .expect("send request to server");
+        assert_eq!(&r, body);
+        mock.assert_async().await;
+    }
+
     #[tokio::test]
     async fn api_v3_query_influxql() {
         let db = "stats";
@@ -1071,4 +2363,322 @@ mod tests {
             .unwrap();
         mock.assert_async().await;
     }
+
+    #[tokio::test]
+    async fn api_v3_configure_meta_cache_create_201() {
+        let db = "db";
+        let table = "table";
+        let name = "cache_name";
+        let columns = ["col1", "col2"];
+        let max_cardinality = 1_000;
+        let max_age = 3_600;
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/configure/meta_cache")
+            .match_body(Matcher::Json(serde_json::json!({
+                "db": db,
+                "table": table,
+                "name": name,
+                "columns": columns,
+                "max_cardinality": max_cardinality,
+                "max_age": max_age,
+            })))
+            .with_status(201)
+            .with_body(
+                r#"{
+                    "table_id": 0,
+                    "table_name": "table",
+                    "cache_name": "cache_name",
+                    "column_ids": [0, 1],
+                    "max_cardinality": 1000,
+                    "max_age_seconds": 3600
+                }"#,
+            )
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url()).unwrap();
+        client
+            .api_v3_configure_meta_cache_create(db, table, columns)
+            .name(name)
+            .max_cardinality(max_cardinality)
+            .max_age(max_age)
+            .send()
+            .await
+            .expect("creates meta cache and parses response");
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn api_v3_configure_meta_cache_create_204() {
+        let db = "db";
+        let table = "table";
+        let columns = ["col1"];
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/configure/meta_cache")
+            .match_body(Matcher::Json(serde_json::json!({
+                "db": db,
+                "table": table,
+                "columns": columns,
+            })))
+            .with_status(204)
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url()).unwrap();
+        let resp = client
+            .api_v3_configure_meta_cache_create(db, table, columns)
+            .send()
+            .await
+            .unwrap();
+        mock.assert_async().await;
+        assert!(resp.is_none());
+    }
+
+    #[tokio::test]
+    async fn api_v3_configure_meta_cache_delete() {
+        let db = "db";
+        let table = "table";
+        let name = "cache_name";
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("DELETE", "/api/v3/configure/meta_cache")
+            .match_body(Matcher::Json(serde_json::json!({
+                "db": db,
+                "table": table,
+                "name": name,
+            })))
+            .with_status(200)
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url()).unwrap();
+        client
+            .api_v3_configure_meta_cache_delete(db, table, name)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn api_v3_configure_schema_lock() {
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/configure/schema_lock")
+            .match_body(Matcher::Json(serde_json::json!({
+                "db": "db",
+                "table": "cpu",
+                "locked": true,
+            })))
+            .with_status(200)
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url()).unwrap();
+        client
+            .api_v3_configure_schema_lock("db", Some("cpu"), true)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn api_v3_configure_table_create() {
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/configure/table")
+            .match_body(Matcher::Json(serde_json::json!({
+                "db": "db",
+                "table": "cpu",
+                "tags": ["region", "host"],
+                "fields": [
+                    {"name": "usage", "type": "float"},
+                    {"name": "count", "type": "uinteger"},
+                ],
+            })))
+            .with_status(200)
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url()).unwrap();
+        client
+            .api_v3_configure_table_create("db", "cpu")
+            .tag("region")
+            .tag("host")
+            .field("usage", FieldType::Float)
+            .field("count", FieldType::UInteger)
+            .send()
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn api_v3_configure_table_delete() {
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("DELETE", "/api/v3/configure/table")
+            .match_body(Matcher::Json(serde_json::json!({
+                "db": "db",
+                "table": "table",
+            })))
+            .with_status(200)
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url()).unwrap();
+        client
+            .api_v3_configure_table_delete("db", "table")
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn api_v3_configure_token_create() {
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/configure/token")
+            .match_header("Authorization", "Bearer admin-token")
+            .match_body(Matcher::Json(serde_json::json!({
+                "name": "ingest",
+                "description": "telegraf",
+                "permissions": [{"database": "db", "actions": ["write"]}],
+                "expires_in": 3600,
+            })))
+            .with_status(201)
+            .with_body(
+                r#"{
+                    "token": "apiv3_abc",
+                    "name": "ingest",
+                    "description": "telegraf",
+                    "permissions": [{"database": "db", "actions": ["write"]}],
+                    "created_at": "1970-01-01T00:00:00+00:00",
+                    "expires_at": "1970-01-01T01:00:00+00:00"
+                }"#,
+            )
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url())
+            .unwrap()
+            .with_auth_token("admin-token");
+        let resp = client
+            .api_v3_configure_token_create("ingest")
+            .description("telegraf")
+            .permission("db", [TokenAction::Write])
+            .expires_in(3600)
+            .send()
+            .await
+            .expect("creates token and parses response");
+        mock.assert_async().await;
+        assert_eq!("apiv3_abc", resp.token);
+        assert_eq!("ingest", resp.info.name);
+        assert_eq!(
+            vec![TokenPermission {
+                database: "db".into(),
+                actions: vec![TokenAction::Write],
+            }],
+            resp.info.permissions
+        );
+    }
+
+    #[tokio::test]
+    async fn api_v3_configure_token_delete() {
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("DELETE", "/api/v3/configure/token")
+            .match_body(Matcher::Json(serde_json::json!({ "name": "ingest" })))
+            .with_status(200)
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url()).unwrap();
+        client
+            .api_v3_configure_token_delete("ingest")
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn api_v3_query_kill() {
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("DELETE", "/api/v3/query/b8e5b0a4")
+            .with_status(200)
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url()).unwrap();
+        client.api_v3_query_kill("b8e5b0a4").await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn api_v3_export() {
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("GET", "/api/v3/export")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("db".into(), "db".into()),
+                Matcher::UrlEncoded("table".into(), "cpu,mem".into()),
+                Matcher::UrlEncoded("start".into(), "1000".into()),
+            ]))
+            .match_header("Accept-Encoding", "gzip")
+            .with_status(200)
+            .with_body("cpu,host=a usage=0.5 1000\n")
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url()).unwrap();
+        let mut resp = client
+            .api_v3_export("db")
+            .table("cpu")
+            .table("mem")
+            .start("1000")
+            .gzip(true)
+            .send()
+            .await
+            .unwrap();
+        let mut lp = vec![];
+        while let Some(chunk) = resp.chunk().await.unwrap() {
+            lp.extend_from_slice(&chunk);
+        }
+        mock.assert_async().await;
+        assert_eq!(lp, b"cpu,host=a usage=0.5 1000\n");
+    }
+
+    #[tokio::test]
+    async fn api_v3_import() {
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/import")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("db".into(), "db".into()),
+                Matcher::UrlEncoded("table".into(), "cpu".into()),
+                Matcher::UrlEncoded("format".into(), "csv".into()),
+                Matcher::UrlEncoded("tags".into(), "host".into()),
+                Matcher::UrlEncoded("fields".into(), "usage:float,count".into()),
+                Matcher::UrlEncoded("time".into(), "ts".into()),
+                Matcher::UrlEncoded("precision".into(), "second".into()),
+            ]))
+            .match_body("host,usage,count,ts\na,0.5,1,1\n")
+            .with_status(200)
+            .with_body(r#"{"row_count":1,"skipped_row_count":0}"#)
+            .create_async()
+            .await;
+        let client = Client::new(mock_server.url()).unwrap();
+        let summary = client
+            .api_v3_import("db", "cpu")
+            .format(ImportFormat::Csv)
+            .tag("host")
+            .field("usage", Some(FieldType::Float))
+            .field("count", None)
+            .time_column("ts")
+            .precision(Precision::Second)
+            .body("host,usage,count,ts\na,0.5,1,1\n")
+            .send()
+            .await
+            .unwrap();
+        mock.assert_async().await;
+        assert_eq!(
+            summary,
+            ImportSummary {
+                row_count: 1,
+                skipped_row_count: 0
+            }
+        );
+    }
 }
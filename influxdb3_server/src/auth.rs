@@ -1,6 +1,14 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
-use authz::{Authorizer, Error, Permission};
+use authz::{Action, Authorizer, Error, Permission, Resource, Target};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as B64;
+use base64::Engine as _;
+use influxdb3_catalog::catalog::{Catalog, TokenAction, TokenPermission};
+use iox_time::TimeProvider;
 use observability_deps::tracing::{debug, warn};
+use rand::rngs::OsRng;
+use rand::RngCore;
 use sha2::{Digest, Sha512};
 
 /// An [`Authorizer`] implementation that will grant access to all
@@ -38,6 +46,135 @@ impl Authorizer for AllOrNothingAuthorizer {
     }
 }
 
+/// An [`Authorizer`] implementation that checks the requested permissions against those of the
+/// named tokens stored in the [`Catalog`]
+///
+/// An optional admin token can also be provided, which is granted every permission. Tokens are
+/// looked up in the catalog on every request, so that newly created or revoked tokens take effect
+/// immediately. While there is no admin token and the catalog holds no tokens, all requests are
+/// allowed.
+#[derive(Debug)]
+pub struct CatalogAuthorizer {
+    catalog: Arc<Catalog>,
+    admin_token: Option<Vec<u8>>,
+    time_provider: Arc<dyn TimeProvider>,
+}
+
+impl CatalogAuthorizer {
+    /// Create a new [`CatalogAuthorizer`], where `admin_token` is the SHA-512 hash of the admin
+    /// token, if there is one
+    pub fn new(
+        catalog: Arc<Catalog>,
+        admin_token: Option<Vec<u8>>,
+        time_provider: Arc<dyn TimeProvider>,
+    ) -> Self {
+        Self {
+            catalog,
+            admin_token,
+            time_provider,
+        }
+    }
+}
+
+#[async_trait]
+impl Authorizer for CatalogAuthorizer {
+    async fn permissions(
+        &self,
+        token: Option<Vec<u8>>,
+        perms: &[Permission],
+    ) -> Result<Vec<Permission>, Error> {
+        debug!(?perms, "requesting permissions");
+        // authorization is only enforced once there is some token to check against:
+        if self.admin_token.is_none() && self.catalog.list_tokens().is_empty() {
+            return Ok(perms.to_vec());
+        }
+        let provided = token.as_deref().ok_or(Error::NoToken)?;
+        let hash = Sha512::digest(provided);
+        if self.admin_token.as_deref() == Some(&hash[..]) {
+            return Ok(perms.to_vec());
+        }
+        let Some(token) = self.catalog.token_by_hash(&hex::encode(hash)) else {
+            warn!("invalid token provided");
+            return Err(Error::InvalidToken);
+        };
+        if token.is_expired(self.time_provider.now().timestamp_nanos()) {
+            warn!(token_name = %token.name, "expired token provided");
+            return Err(Error::InvalidToken);
+        }
+
+        let granted = perms
+            .iter()
+            .filter(|perm| {
+                let Permission::ResourceAction(resource, action) = perm;
+                database_name(resource).is_some_and(|db| token.allows(db, token_action(action)))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if granted.is_empty() && !perms.is_empty() {
+            debug!(token_name = %token.name, ?perms, "token does not grant requested permissions");
+            return Err(Error::Forbidden);
+        }
+        Ok(granted)
+    }
+
+    async fn probe(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// The name of the database that a permission is requested on
+///
+/// Requests that act on all databases use [`TokenPermission::ALL_DATABASES`] as the name.
+fn database_name(resource: &Resource) -> Option<&str> {
+    match resource {
+        Resource::Database(Target::ResourceName(name)) => Some(name.as_str()),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+fn token_action(action: &Action) -> TokenAction {
+    if matches!(action, Action::Read | Action::ReadSchema) {
+        TokenAction::Read
+    } else if matches!(action, Action::Write) {
+        TokenAction::Write
+    } else {
+        TokenAction::Admin
+    }
+}
+
+/// Build the [`Permission`] to perform an action on the database with the given name, or on
+/// all databases if no name is given
+pub(crate) fn database_permission(database: Option<&str>, action: Action) -> Permission {
+    Permission::ResourceAction(
+        Resource::Database(Target::ResourceName(
+            database
+                .unwrap_or(TokenPermission::ALL_DATABASES)
+                .to_string(),
+        )),
+        action,
+    )
+}
+
+/// Generate a new random token, which is only ever shown to the user once
+pub fn generate_token() -> String {
+    let mut key = [0u8; 64];
+    OsRng.fill_bytes(&mut key);
+    format!("apiv3_{}", B64.encode(key))
+}
+
+/// The hex encoded SHA-512 hash of a token, which is what gets stored in the catalog
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha512::digest(token))
+}
+
+/// The name of the named token in the [`Catalog`] that was provided as `token`, if it is one
+pub(crate) fn token_name(catalog: &Catalog, token: &[u8]) -> Option<Arc<str>> {
+    catalog
+        .token_by_hash(&hex::encode(Sha512::digest(token)))
+        .map(|definition| Arc::clone(&definition.name))
+}
+
 /// The defult [`Authorizer`] implementation that will authorize all requests
 #[derive(Debug)]
 pub struct DefaultAuthorizer;
@@ -56,3 +193,176 @@ impl Authorizer for DefaultAuthorizer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use authz::{Action, Authorizer, Error};
+    use influxdb3_catalog::catalog::{Catalog, TokenAction, TokenDefinition, TokenPermission};
+    use iox_time::{MockProvider, Time};
+    use sha2::{Digest, Sha512};
+
+    use super::{database_permission, CatalogAuthorizer};
+
+    fn hash(token: &str) -> Vec<u8> {
+        Sha512::digest(token).to_vec()
+    }
+
+    #[tokio::test]
+    async fn catalog_authorizer_checks_token_permissions() {
+        let catalog = Arc::new(Catalog::new("host".into(), "instance".into()));
+        catalog
+            .add_token(TokenDefinition {
+                name: "ingest".into(),
+                hash: hex::encode(hash("ingest-token")).into(),
+                permissions: vec![TokenPermission {
+                    database: "prod".into(),
+                    actions: vec![TokenAction::Write],
+                }],
+                description: None,
+                created_at_ns: 0,
+                expires_at_ns: None,
+            })
+            .unwrap();
+        catalog
+            .add_token(TokenDefinition {
+                name: "expiring".into(),
+                hash: hex::encode(hash("expiring-token")).into(),
+                permissions: vec![TokenPermission {
+                    database: "prod".into(),
+                    actions: vec![TokenAction::Read],
+                }],
+                description: None,
+                created_at_ns: 0,
+                expires_at_ns: Some(1_000),
+            })
+            .unwrap();
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let authz = CatalogAuthorizer::new(
+            Arc::clone(&catalog),
+            Some(hash("admin-token")),
+            Arc::clone(&time_provider) as _,
+        );
+        let token = |t: &str| Some(t.as_bytes().to_vec());
+
+        // the admin token is granted everything:
+        let perms = [
+            database_permission(Some("prod"), Action::Read),
+            database_permission(None, Action::Delete),
+        ];
+        assert_eq!(
+            2,
+            authz
+                .permissions(token("admin-token"), &perms)
+                .await
+                .unwrap()
+                .len()
+        );
+
+        // a named token is only granted the actions on the databases it was created with:
+        let write = [database_permission(Some("prod"), Action::Write)];
+        assert_eq!(
+            1,
+            authz
+                .permissions(token("ingest-token"), &write)
+                .await
+                .unwrap()
+                .len()
+        );
+        for forbidden in [
+            database_permission(Some("prod"), Action::Read),
+            database_permission(Some("prod"), Action::Delete),
+            database_permission(Some("other"), Action::Write),
+            database_permission(None, Action::Write),
+        ] {
+            assert!(matches!(
+                authz.permissions(token("ingest-token"), &[forbidden]).await,
+                Err(Error::Forbidden)
+            ));
+        }
+
+        // a valid token is accepted when no specific permissions are requested:
+        assert!(authz
+            .permissions(token("ingest-token"), &[])
+            .await
+            .unwrap()
+            .is_empty());
+
+        // unknown or missing tokens are rejected:
+        assert!(matches!(
+            authz.permissions(token("nope"), &[]).await,
+            Err(Error::InvalidToken)
+        ));
+        assert!(matches!(
+            authz.permissions(None, &[]).await,
+            Err(Error::NoToken)
+        ));
+
+        // tokens are rejected once they have expired:
+        let read = [database_permission(Some("prod"), Action::Read)];
+        assert!(authz
+            .permissions(token("expiring-token"), &read)
+            .await
+            .is_ok());
+        time_provider.set(Time::from_timestamp_nanos(1_000));
+        assert!(matches!(
+            authz.permissions(token("expiring-token"), &read).await,
+            Err(Error::InvalidToken)
+        ));
+
+        // and when they are revoked:
+        catalog.remove_token("ingest").unwrap();
+        assert!(matches!(
+            authz.permissions(token("ingest-token"), &write).await,
+            Err(Error::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn catalog_authorizer_is_open_until_a_token_exists() {
+        let catalog = Arc::new(Catalog::new("host".into(), "instance".into()));
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let authz = CatalogAuthorizer::new(Arc::clone(&catalog), None, time_provider);
+        let perms = [database_permission(None, Action::Create)];
+
+        // with no tokens, every request is allowed, with or without a token:
+        assert_eq!(1, authz.permissions(None, &perms).await.unwrap().len());
+        assert_eq!(
+            1,
+            authz
+                .permissions(Some(b"anything".to_vec()), &perms)
+                .await
+                .unwrap()
+                .len()
+        );
+
+        // once a token is created, requests must be authorized, including those that manage
+        // tokens:
+        catalog
+            .add_token(TokenDefinition {
+                name: "admin".into(),
+                hash: hex::encode(hash("admin-token")).into(),
+                permissions: vec![TokenPermission {
+                    database: TokenPermission::ALL_DATABASES.into(),
+                    actions: vec![TokenAction::Admin],
+                }],
+                description: None,
+                created_at_ns: 0,
+                expires_at_ns: None,
+            })
+            .unwrap();
+        assert!(matches!(
+            authz.permissions(None, &perms).await,
+            Err(Error::NoToken)
+        ));
+        assert_eq!(
+            1,
+            authz
+                .permissions(Some(b"admin-token".to_vec()), &perms)
+                .await
+                .unwrap()
+                .len()
+        );
+    }
+}
@@ -11,6 +11,7 @@ pub struct ServerBuilder<W, Q, P, T, L> {
     common_state: CommonServerState,
     time_provider: T,
     max_request_size: usize,
+    max_import_size: usize,
     write_buffer: W,
     query_executor: Q,
     persister: P,
@@ -24,6 +25,7 @@ impl ServerBuilder<NoWriteBuf, NoQueryExec, NoPersister, NoTimeProvider, NoListe
             common_state,
             time_provider: NoTimeProvider,
             max_request_size: usize::MAX,
+            max_import_size: usize::MAX,
             write_buffer: NoWriteBuf,
             query_executor: NoQueryExec,
             persister: NoPersister,
@@ -39,6 +41,11 @@ impl<W, Q, P, T, L> ServerBuilder<W, Q, P, T, L> {
         self
     }
 
+    pub fn max_import_size(mut self, max_import_size: usize) -> Self {
+        self.max_import_size = max_import_size;
+        self
+    }
+
     pub fn authorizer(mut self, a: Arc<dyn Authorizer>) -> Self {
         self.authorizer = a;
         self
@@ -72,6 +79,7 @@ impl<Q, P, T, L> ServerBuilder<NoWriteBuf, Q, P, T, L> {
             common_state: self.common_state,
             time_provider: self.time_provider,
             max_request_size: self.max_request_size,
+            max_import_size: self.max_import_size,
             write_buffer: WithWriteBuf(wb),
             query_executor: self.query_executor,
             persister: self.persister,
@@ -87,6 +95,7 @@ impl<W, P, T, L> ServerBuilder<W, NoQueryExec, P, T, L> {
             common_state: self.common_state,
             time_provider: self.time_provider,
             max_request_size: self.max_request_size,
+            max_import_size: self.max_import_size,
             write_buffer: self.write_buffer,
             query_executor: WithQueryExec(qe),
             persister: self.persister,
@@ -102,6 +111,7 @@ impl<W, Q, T, L> ServerBuilder<W, Q, NoPersister, T, L> {
             common_state: self.common_state,
             time_provider: self.time_provider,
             max_request_size: self.max_request_size,
+            max_import_size: self.max_import_size,
             write_buffer: self.write_buffer,
             query_executor: self.query_executor,
             persister: WithPersister(p),
@@ -117,6 +127,7 @@ impl<W, Q, P, L> ServerBuilder<W, Q, P, NoTimeProvider, L> {
             common_state: self.common_state,
             time_provider: WithTimeProvider(tp),
             max_request_size: self.max_request_size,
+            max_import_size: self.max_import_size,
             write_buffer: self.write_buffer,
             query_executor: self.query_executor,
             persister: self.persister,
@@ -132,6 +143,7 @@ impl<W, Q, P, T> ServerBuilder<W, Q, P, T, NoListener> {
             common_state: self.common_state,
             time_provider: self.time_provider,
             max_request_size: self.max_request_size,
+            max_import_size: self.max_import_size,
             write_buffer: self.write_buffer,
             query_executor: self.query_executor,
             persister: self.persister,
@@ -153,6 +165,7 @@ impl<Q, T>
             Arc::clone(&self.write_buffer.0),
             Arc::clone(&self.query_executor.0),
             self.max_request_size,
+            self.max_import_size,
             Arc::clone(&authorizer),
         ));
         Server {
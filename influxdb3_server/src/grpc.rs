@@ -1,14 +1,206 @@
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
 
 use arrow_flight::flight_service_server::{
     FlightService as Flight, FlightServiceServer as FlightServer,
 };
 use authz::Authorizer;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use hyper::body::HttpBody;
+use hyper::header::AUTHORIZATION;
+use hyper::{Body, HeaderMap, Request, Response};
+use influxdb3_catalog::catalog::Catalog;
 use iox_query::QueryDatabase;
+use tokio::time::{Instant, Sleep};
+use tokio_util::sync::WaitForCancellationFutureOwned;
+use tonic::body::BoxBody;
+use tonic::{Code, Status};
+use tower::Service;
+
+use crate::auth::token_name;
+use crate::query_executor::{self, CurrentQuery, QueryLimits, RunningQueryGuard, CURRENT_QUERY};
+
+/// The path of the Flight `DoGet` method, through which queries are run
+const DO_GET_PATH: &str = "/arrow.flight.protocol.FlightService/DoGet";
 
 pub(crate) fn make_flight_server<Q: QueryDatabase>(
     server: Arc<Q>,
     authz: Option<Arc<dyn Authorizer>>,
-) -> FlightServer<impl Flight> {
-    service_grpc_flight::make_server(server, authz)
+    limits: QueryLimits,
+    catalog: Arc<Catalog>,
+) -> QueryLimitService<FlightServer<impl Flight>> {
+    QueryLimitService {
+        inner: service_grpc_flight::make_server(server, authz),
+        limits,
+        catalog,
+    }
+}
+
+/// Applies the server's [`QueryLimits`] to queries made through the Flight service, and allows
+/// them to be killed
+///
+/// The Flight service queues for a permit to run each query, so queries are rejected here,
+/// before they reach it, when the limit has been reached and queries are being rejected. The
+/// query timeout is enforced, and killed queries are stopped, by cutting off the response,
+/// which drops, and so cancels, the query's execution.
+///
+/// The name of the token a query was made with is also passed on to the query executor here,
+/// for the slow query log, as the Flight service does not expose it.
+#[derive(Debug, Clone)]
+pub(crate) struct QueryLimitService<S> {
+    inner: S,
+    limits: QueryLimits,
+    catalog: Arc<Catalog>,
+}
+
+impl<S> Service<Request<Body>> for QueryLimitService<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        if req.uri().path() != DO_GET_PATH {
+            return self.inner.call(req).boxed();
+        }
+        if let Err(e) = self.limits.check_rejected() {
+            let response = status_from_query_error(Code::ResourceExhausted, e).to_http();
+            return futures::future::ready(Ok(response)).boxed();
+        }
+
+        let deadline = self
+            .limits
+            .timeout()
+            .map(|timeout| (Instant::now() + timeout, timeout));
+        // the query is recorded in the query log by the Flight service, within this scope:
+        let current_query = CurrentQuery::with_token_name(
+            request_token(&req).and_then(|token| token_name(&self.catalog, token)),
+        );
+        let response = CURRENT_QUERY.scope(current_query.clone(), self.inner.call(req));
+        async move {
+            let (deadline_at, timeout) = deadline.unzip();
+            let response = tokio::select! {
+                response = response => response?,
+                _ = tokio::time::sleep_until(deadline_at.unwrap_or_else(Instant::now)),
+                    if deadline.is_some() =>
+                {
+                    return Ok(timeout_status(timeout.unwrap_or_default()).to_http());
+                }
+                _ = current_query.cancel.cancelled() => return Ok(cancelled_status().to_http()),
+            };
+            let running_query = current_query.take_guard();
+            Ok(response.map(|body| {
+                tonic::body::boxed(QueryBody {
+                    inner: Some(body),
+                    deadline: deadline.map(|(deadline, timeout)| {
+                        (Box::pin(tokio::time::sleep_until(deadline)), timeout)
+                    }),
+                    cancelled: Box::pin(current_query.cancel.cancelled_owned()),
+                    _running_query: running_query,
+                    end_status: None,
+                })
+            }))
+        }
+        .boxed()
+    }
+}
+
+/// The bearer token provided in the `authorization` metadata of a Flight request
+fn request_token(req: &Request<Body>) -> Option<&[u8]> {
+    req.headers()
+        .get(AUTHORIZATION)?
+        .as_bytes()
+        .strip_prefix(b"Bearer ")
+}
+
+/// Convert a query error into a gRPC [`Status`], with the time the client should wait before
+/// retrying in its `retry-after` metadata, if it should be retried
+fn status_from_query_error(code: Code, e: query_executor::Error) -> Status {
+    let mut status = Status::new(code, e.to_string());
+    if let Some(retry_after) = e.retry_after() {
+        if let Ok(value) = retry_after.as_secs().to_string().parse() {
+            status.metadata_mut().insert("retry-after", value);
+        }
+    }
+    status
+}
+
+fn timeout_status(timeout: Duration) -> Status {
+    status_from_query_error(
+        Code::DeadlineExceeded,
+        query_executor::Error::QueryTimeout { timeout },
+    )
+}
+
+fn cancelled_status() -> Status {
+    status_from_query_error(Code::Cancelled, query_executor::Error::QueryCancelled)
+}
+
+/// The body of a query's response, which is cut off, ending with an error status, once the
+/// query's deadline has passed, or it has been killed
+struct QueryBody {
+    /// The body of the response, which is dropped once it has been cut off
+    inner: Option<BoxBody>,
+    deadline: Option<(Pin<Box<Sleep>>, Duration)>,
+    cancelled: Pin<Box<WaitForCancellationFutureOwned>>,
+    /// Keeps the query registered as running, so that it can be killed, until the response ends
+    _running_query: Option<RunningQueryGuard>,
+    /// The status the response ends with, once it has been cut off
+    end_status: Option<Status>,
+}
+
+impl HttpBody for QueryBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.get_mut();
+        if this.inner.is_some() {
+            if this.cancelled.poll_unpin(cx).is_ready() {
+                this.end_status = Some(cancelled_status());
+            } else if let Some((deadline, timeout)) = &mut this.deadline {
+                if deadline.poll_unpin(cx).is_ready() {
+                    this.end_status = Some(timeout_status(*timeout));
+                }
+            }
+            if this.end_status.is_some() {
+                this.inner = None;
+            }
+        }
+        match this.inner.as_mut() {
+            Some(inner) => Pin::new(inner).poll_data(cx),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.get_mut();
+        match (this.inner.as_mut(), this.end_status.take()) {
+            (Some(inner), _) => Pin::new(inner).poll_trailers(cx),
+            (None, Some(status)) => Poll::Ready(status.to_header_map().map(Some)),
+            (None, None) => Poll::Ready(Ok(None)),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.as_ref().is_some_and(HttpBody::is_end_stream)
+    }
 }
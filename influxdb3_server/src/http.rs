@@ -1,28 +1,37 @@
 //! HTTP API service implementations for `server`
 
-use crate::{query_executor, QueryKind};
+use crate::auth::{database_permission, generate_token, hash_token, token_name};
+use crate::query_executor::{self, CurrentQuery, ExplainAnalyze, CURRENT_QUERY};
+use crate::QueryKind;
 use crate::{CommonServerState, QueryExecutor};
 use arrow::record_batch::RecordBatch;
 use arrow::util::pretty;
+use arrow_schema::SchemaRef;
 use authz::http::AuthorizationHeaderExtension;
-use authz::Authorizer;
+use authz::{Action, Authorizer};
 use bytes::{Bytes, BytesMut};
+use compression::{compress_response, ResponseEncoding};
 use data_types::NamespaceName;
 use datafusion::error::DataFusionError;
 use datafusion::execution::memory_pool::UnboundedMemoryPool;
 use datafusion::execution::RecordBatchStream;
+use datafusion::physical_plan::EmptyRecordBatchStream;
 use datafusion::physical_plan::SendableRecordBatchStream;
-use futures::{StreamExt, TryStreamExt};
+use futures::stream::Fuse;
+use futures::{ready, Stream, StreamExt};
 use hyper::header::ACCEPT;
 use hyper::header::AUTHORIZATION;
 use hyper::header::CONTENT_ENCODING;
 use hyper::header::CONTENT_TYPE;
+use hyper::header::RETRY_AFTER;
 use hyper::http::HeaderValue;
 use hyper::HeaderMap;
 use hyper::{Body, Method, Request, Response, StatusCode};
+use influxdb3_cache::meta_cache::{self, CreateMetaCacheArgs, MaxAge, MaxCardinality};
 use influxdb3_catalog::catalog::Error as CatalogError;
+use influxdb3_catalog::catalog::{TokenDefinition, TokenPermission};
 use influxdb3_process::{INFLUXDB3_GIT_HASH_SHORT, INFLUXDB3_VERSION};
-use influxdb3_wal::LastCacheDefinition;
+use influxdb3_wal::{LastCacheDefinition, MetaCacheDefinition};
 use influxdb3_write::last_cache;
 use influxdb3_write::persister::TrackedMemoryArrowWriter;
 use influxdb3_write::write_buffer::Error as WriteBufferError;
@@ -34,8 +43,9 @@ use iox_http::write::v1::V1_NAMESPACE_RP_SEPARATOR;
 use iox_http::write::{WriteParseError, WriteRequestUnifier};
 use iox_query_influxql_rewrite as rewrite;
 use iox_query_params::StatementParams;
-use iox_time::TimeProvider;
+use iox_time::{Time, TimeProvider};
 use observability_deps::tracing::{debug, error, info};
+use schema::InfluxFieldType;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
@@ -45,10 +55,15 @@ use std::pin::Pin;
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Duration;
 use thiserror::Error;
 use unicode_segmentation::UnicodeSegmentation;
 
+mod compression;
+mod export;
+mod import;
+mod streaming;
 mod v1;
 
 #[derive(Debug, Error)]
@@ -89,6 +104,18 @@ pub enum Error {
     #[error("error decoding gzip stream: {0}")]
     InvalidGzip(std::io::Error),
 
+    /// Decoding a zstd-compressed stream of data failed.
+    #[error("error decoding zstd stream: {0}")]
+    InvalidZstd(std::io::Error),
+
+    /// Decoding snappy-compressed data failed.
+    #[error("error decoding snappy data: {0}")]
+    InvalidSnappy(snap::Error),
+
+    /// Setting up the compression of a response body failed.
+    #[error("error compressing response: {0}")]
+    CompressResponse(std::io::Error),
+
     #[error("invalid mime type ({0})")]
     InvalidMimeType(String),
 
@@ -185,6 +212,21 @@ pub enum Error {
     #[error("must provide only one InfluxQl statement per query")]
     InfluxqlSingleStatement,
 
+    #[error("must provide at least one InfluxQL statement")]
+    InfluxqlNoStatements,
+
+    #[error("invalid KILL QUERY statement, expected a statement of the form: KILL QUERY <id>")]
+    InvalidKillQuery,
+
+    #[error("no running query found with id '{0}'")]
+    QueryNotFound(String),
+
+    #[error("explain=analyze is only supported for queries that select data")]
+    ExplainNotSupported,
+
+    #[error("token expiry of {0} seconds is too far in the future")]
+    InvalidTokenExpiry(u64),
+
     #[error("must specify a 'db' parameter, or provide the database in the InfluxQL query")]
     InfluxqlNoDatabase,
 
@@ -198,6 +240,12 @@ pub enum Error {
 
     #[error("v1 query API error: {0}")]
     V1Query(#[from] v1::QueryError),
+
+    #[error("export API error: {0}")]
+    Export(#[from] export::ExportError),
+
+    #[error("import API error: {0}")]
+    Import(#[from] import::ImportError),
 }
 
 #[derive(Debug, Error)]
@@ -239,6 +287,37 @@ impl Error {
                     .body(body)
                     .unwrap()
             }
+            Self::WriteBuffer(WriteBufferError::CatalogUpdateError(
+                ref err @ CatalogError::TokenAlreadyExists(_),
+            )) => Response::builder()
+                .status(StatusCode::CONFLICT)
+                .body(Body::from(err.to_string()))
+                .unwrap(),
+            Self::WriteBuffer(WriteBufferError::CatalogUpdateError(
+                ref err @ CatalogError::TokenNotFound(_),
+            )) => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::from(err.to_string()))
+                .unwrap(),
+            Self::WriteBuffer(
+                ref err @ (WriteBufferError::DbDoesNotExist | WriteBufferError::TableDoesNotExist),
+            ) => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::from(err.to_string()))
+                .unwrap(),
+            Self::WriteBuffer(
+                ref err @ (WriteBufferError::DbAlreadyExists(_)
+                | WriteBufferError::TableAlreadyExists { .. }),
+            ) => Response::builder()
+                .status(StatusCode::CONFLICT)
+                .body(Body::from(err.to_string()))
+                .unwrap(),
+            Self::WriteBuffer(ref err @ WriteBufferError::InvalidTableDefinition(_)) => {
+                Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(Body::from(err.to_string()))
+                    .unwrap()
+            }
             Self::WriteBuffer(WriteBufferError::ParseError(err)) => {
                 let err = ErrorMessage {
                     error: "parsing failed for write_lp endpoint".into(),
@@ -280,6 +359,17 @@ impl Error {
                     .body(Body::from(self.to_string()))
                     .unwrap(),
             },
+            Self::WriteBuffer(WriteBufferError::MetaCacheError(ref mc_err)) => match mc_err {
+                meta_cache::ProviderError::InvalidConfiguration(_)
+                | meta_cache::ProviderError::CacheAlreadyExists(_) => Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(Body::from(mc_err.to_string()))
+                    .unwrap(),
+                meta_cache::ProviderError::CacheDoesNotExist => Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::from(self.to_string()))
+                    .unwrap(),
+            },
             Self::DbName(e) => {
                 let err: ErrorMessage<()> = ErrorMessage {
                     error: e.to_string(),
@@ -320,7 +410,10 @@ impl Error {
                 .status(StatusCode::BAD_REQUEST)
                 .body(Body::from(self.to_string()))
                 .unwrap(),
-            Self::InvalidContentEncoding(_) => Response::builder()
+            Self::InvalidContentEncoding(_)
+            | Self::InvalidGzip(_)
+            | Self::InvalidZstd(_)
+            | Self::InvalidSnappy(_) => Response::builder()
                 .status(StatusCode::BAD_REQUEST)
                 .body(Body::from(self.to_string()))
                 .unwrap(),
@@ -332,6 +425,67 @@ impl Error {
                 .status(StatusCode::BAD_REQUEST)
                 .body(Body::from(self.to_string()))
                 .unwrap(),
+            Self::Unauthenticated => Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(Body::from(self.to_string()))
+                .unwrap(),
+            Self::Forbidden => Response::builder()
+                .status(StatusCode::FORBIDDEN)
+                .body(Body::from(self.to_string()))
+                .unwrap(),
+            Self::Export(
+                export::ExportError::MissingDatabase | export::ExportError::InvalidTime { .. },
+            ) => Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(self.to_string()))
+                .unwrap(),
+            Self::Export(export::ExportError::TableNotFound(_)) => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::from(self.to_string()))
+                .unwrap(),
+            Self::Import(import::ImportError::SizeExceeded(_)) => Response::builder()
+                .status(StatusCode::PAYLOAD_TOO_LARGE)
+                .body(Body::from(self.to_string()))
+                .unwrap(),
+            // a temporary file that cannot be written is an internal error:
+            Self::Import(ref err) if !matches!(err, import::ImportError::TempFile(_)) => {
+                Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(Body::from(self.to_string()))
+                    .unwrap()
+            }
+            Self::WriteBuffer(WriteBufferError::ImportError(_)) => Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(self.to_string()))
+                .unwrap(),
+            Self::InvalidKillQuery
+            | Self::ExplainNotSupported
+            | Self::InfluxqlNoStatements
+            | Self::InvalidTokenExpiry(_) => Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(self.to_string()))
+                .unwrap(),
+            Self::QueryNotFound(_) => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::from(self.to_string()))
+                .unwrap(),
+            Self::Query(
+                ref err @ (query_executor::Error::QueryLimitReached { .. }
+                | query_executor::Error::QueryQueueTimeout { .. }),
+            ) => {
+                let status = match err {
+                    query_executor::Error::QueryLimitReached { .. } => {
+                        StatusCode::TOO_MANY_REQUESTS
+                    }
+                    _ => StatusCode::SERVICE_UNAVAILABLE,
+                };
+                let retry_after = err.retry_after().unwrap_or_default().as_secs();
+                Response::builder()
+                    .status(status)
+                    .header(RETRY_AFTER, retry_after)
+                    .body(Body::from(self.to_string()))
+                    .unwrap()
+            }
             _ => {
                 let body = Body::from(self.to_string());
                 Response::builder()
@@ -345,13 +499,17 @@ impl Error {
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// The path of the API to kill a query, which is followed by the query's ID
+const KILL_QUERY_PATH_PREFIX: &str = "/api/v3/query/";
+
 #[derive(Debug)]
 pub(crate) struct HttpApi<Q, T> {
     common_state: CommonServerState,
-    write_buffer: Arc<dyn WriteBuffer>,
+    pub(crate) write_buffer: Arc<dyn WriteBuffer>,
     time_provider: Arc<T>,
     pub(crate) query_executor: Arc<Q>,
     max_request_bytes: usize,
+    max_import_bytes: usize,
     authorizer: Arc<dyn Authorizer>,
     legacy_write_param_unifier: SingleTenantRequestUnifier,
 }
@@ -363,6 +521,7 @@ impl<Q, T> HttpApi<Q, T> {
         write_buffer: Arc<dyn WriteBuffer>,
         query_executor: Arc<Q>,
         max_request_bytes: usize,
+        max_import_bytes: usize,
         authorizer: Arc<dyn Authorizer>,
     ) -> Self {
         let legacy_write_param_unifier = SingleTenantRequestUnifier::new(Arc::clone(&authorizer));
@@ -372,6 +531,7 @@ impl<Q, T> HttpApi<Q, T> {
             write_buffer,
             query_executor,
             max_request_bytes,
+            max_import_bytes,
             authorizer,
             legacy_write_param_unifier,
         }
@@ -404,15 +564,40 @@ where
         use_v3: bool,
     ) -> Result<Response<Body>> {
         validate_db_name(&params.db, accept_rp)?;
+        self.authorize_database_action(request_token(&req), Some(&params.db), Action::Write)
+            .await?;
         info!("write_lp to {}", params.db);
 
-        let body = self.read_body(req).await?;
-        let body = std::str::from_utf8(&body).map_err(Error::NonUtf8Body)?;
-
-        let database = NamespaceName::new(params.db)?;
+        let database = NamespaceName::new(params.db.clone())?;
 
         let default_time = self.time_provider.now();
 
+        let result = if params.stream {
+            self.write_lp_streaming(database, &params, req, default_time, use_v3)
+                .await?
+        } else {
+            let body = self.read_body(req).await?;
+            let body = std::str::from_utf8(&body).map_err(Error::NonUtf8Body)?;
+            self.write_lp_to_buffer(database, body, default_time, &params, use_v3)
+                .await?
+        };
+
+        if result.invalid_lines.is_empty() {
+            Ok(Response::new(Body::empty()))
+        } else {
+            Err(Error::PartialLpWrite(result))
+        }
+    }
+
+    /// Validate and buffer the line protocol of a write, or of a slice of a streamed write
+    async fn write_lp_to_buffer(
+        &self,
+        database: NamespaceName<'static>,
+        body: &str,
+        default_time: Time,
+        params: &WriteParams,
+        use_v3: bool,
+    ) -> Result<BufferedWriteRequest> {
         let result = if use_v3 {
             self.write_buffer
                 .write_lp_v3(
@@ -441,54 +626,133 @@ where
             .telemetry_store
             .add_write_metrics(num_lines, payload_size);
 
-        if result.invalid_lines.is_empty() {
-            Ok(Response::new(Body::empty()))
-        } else {
-            Err(Error::PartialLpWrite(result))
-        }
+        Ok(result)
     }
 
     async fn query_sql(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let encoding = ResponseEncoding::from_accept_encoding(req.headers());
         let QueryRequest {
             database,
             query_str,
             format,
             params,
+            explain,
         } = self.extract_query_request::<String>(req).await?;
 
-        info!(%database, %query_str, ?format, "handling query_sql");
+        info!(%database, %query_str, ?format, ?explain, "handling query_sql");
+        let current_query = self.current_query(token.as_deref());
+        self.authorize_database_action(token, Some(&database), Action::Read)
+            .await?;
 
-        let stream = self
-            .query_executor
-            .query(&database, &query_str, params, QueryKind::Sql, None, None)
+        if let Some(Explain::Analyze) = explain {
+            let explain = CURRENT_QUERY
+                .scope(
+                    current_query,
+                    self.query_executor.explain_analyze(
+                        &database,
+                        &query_str,
+                        params,
+                        QueryKind::Sql,
+                        None,
+                        None,
+                    ),
+                )
+                .await?;
+            return explain_analyze_response(&explain, encoding);
+        }
+
+        let stream = CURRENT_QUERY
+            .scope(
+                current_query,
+                self.query_executor.query(
+                    &database,
+                    &query_str,
+                    params,
+                    QueryKind::Sql,
+                    None,
+                    None,
+                ),
+            )
             .await?;
 
-        Response::builder()
+        let response = Response::builder()
             .status(StatusCode::OK)
             .header(CONTENT_TYPE, format.as_content_type())
-            .body(record_batch_stream_to_body(stream, format).await?)
-            .map_err(Into::into)
+            .body(record_batch_stream_to_body(stream, format)?)?;
+        compress_response(response, encoding).map_err(Error::CompressResponse)
     }
 
     async fn query_influxql(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let encoding = ResponseEncoding::from_accept_encoding(req.headers());
         let QueryRequest {
             database,
             query_str,
             format,
             params,
+            explain,
         } = self.extract_query_request::<Option<String>>(req).await?;
 
-        info!(?database, %query_str, ?format, "handling query_influxql");
+        info!(?database, %query_str, ?format, ?explain, "handling query_influxql");
+
+        if let Some(Explain::Analyze) = explain {
+            let explain = self
+                .explain_influxql(token, database, &query_str, params)
+                .await?;
+            return explain_analyze_response(&explain, encoding);
+        }
 
         let stream = self
-            .query_influxql_inner(database, &query_str, params)
+            .query_influxql_inner(token, database, &query_str, params)
             .await?;
 
-        Response::builder()
+        let response = Response::builder()
             .status(StatusCode::OK)
             .header(CONTENT_TYPE, format.as_content_type())
-            .body(record_batch_stream_to_body(stream, format).await?)
-            .map_err(Into::into)
+            .body(record_batch_stream_to_body(stream, format)?)?;
+        compress_response(response, encoding).map_err(Error::CompressResponse)
+    }
+
+    async fn kill_query(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let id = req
+            .uri()
+            .path()
+            .trim_start_matches(KILL_QUERY_PATH_PREFIX)
+            .to_string();
+        self.kill_query_by_id(request_token(&req), &id).await?;
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    /// Kill the running query with the given ID in the query log, which requires permission to
+    /// delete from the database the query is running against
+    ///
+    /// The token is authenticated before the query is looked up, and a query that the token is
+    /// not allowed to kill is reported as not found, so that the IDs of queries running against
+    /// other databases are not revealed.
+    async fn kill_query_by_id(&self, token: Option<Vec<u8>>, id: &str) -> Result<()> {
+        self.authorizer
+            .permissions(token.clone(), &[])
+            .await
+            .map_err(|_| Error::Unauthenticated)?;
+        let not_found = || Error::QueryNotFound(id.to_string());
+        let query = self
+            .query_executor
+            .running_query(id)
+            .ok_or_else(not_found)?;
+        self.authorize_database_action(token, Some(query.database()), Action::Delete)
+            .await
+            .map_err(|e| match e {
+                Error::Forbidden => not_found(),
+                e => e,
+            })?;
+        info!(%id, database = query.database(), "killing query");
+        query.cancel();
+        Ok(())
     }
 
     fn health(&self) -> Result<Response<Body>> {
@@ -523,16 +787,7 @@ where
     /// Parse the request's body into raw bytes, applying the configured size
     /// limits and decoding any content encoding.
     async fn read_body(&self, req: hyper::Request<Body>) -> Result<Bytes> {
-        let encoding = req
-            .headers()
-            .get(&CONTENT_ENCODING)
-            .map(|v| v.to_str().map_err(Error::NonUtf8ContentEncodingHeader))
-            .transpose()?;
-        let ungzip = match encoding {
-            None | Some("identity") => false,
-            Some("gzip") => true,
-            Some(v) => return Err(Error::InvalidContentEncoding(v.to_string())),
-        };
+        let encoding = ContentEncoding::from_headers(req.headers())?;
 
         let mut payload = req.into_body();
 
@@ -547,26 +802,48 @@ where
         }
         let body = body.freeze();
 
-        // If the body is not compressed, return early.
-        if !ungzip {
-            return Ok(body);
-        }
-
-        // Unzip the gzip-encoded content
         use std::io::Read;
-        let decoder = flate2::read::GzDecoder::new(&body[..]);
-
-        // Read at most max_request_bytes bytes to prevent a decompression bomb
-        // based DoS.
-        //
-        // In order to detect if the entire stream ahs been read, or truncated,
-        // read an extra byte beyond the limit and check the resulting data
-        // length - see the max_request_size_truncation test.
-        let mut decoder = decoder.take(self.max_request_bytes as u64 + 1);
-        let mut decoded_data = Vec::new();
-        decoder
-            .read_to_end(&mut decoded_data)
-            .map_err(Error::InvalidGzip)?;
+        let decoded_data = match encoding {
+            // If the body is not compressed, return early.
+            ContentEncoding::Identity => return Ok(body),
+            ContentEncoding::Gzip => {
+                // Read at most max_request_bytes bytes to prevent a decompression bomb
+                // based DoS.
+                //
+                // In order to detect if the entire stream ahs been read, or truncated,
+                // read an extra byte beyond the limit and check the resulting data
+                // length - see the max_request_size_truncation test.
+                let mut decoder =
+                    flate2::read::GzDecoder::new(&body[..]).take(self.max_request_bytes as u64 + 1);
+                let mut decoded_data = Vec::new();
+                decoder
+                    .read_to_end(&mut decoded_data)
+                    .map_err(Error::InvalidGzip)?;
+                decoded_data
+            }
+            ContentEncoding::Zstd => {
+                // zstd is limited in the same way as gzip, above
+                let mut decoder = zstd::stream::read::Decoder::new(&body[..])
+                    .map_err(Error::InvalidZstd)?
+                    .take(self.max_request_bytes as u64 + 1);
+                let mut decoded_data = Vec::new();
+                decoder
+                    .read_to_end(&mut decoded_data)
+                    .map_err(Error::InvalidZstd)?;
+                decoded_data
+            }
+            ContentEncoding::Snappy => {
+                // the snappy block format records the decoded length up front, so it can be
+                // checked before decoding anything:
+                let len = snap::raw::decompress_len(&body).map_err(Error::InvalidSnappy)?;
+                if len > self.max_request_bytes {
+                    return Err(Error::RequestSizeExceeded(self.max_request_bytes));
+                }
+                snap::raw::Decoder::new()
+                    .decompress_vec(&body)
+                    .map_err(Error::InvalidSnappy)?
+            }
+        };
 
         // If the length is max_size+1, the body is at least max_size+1 bytes in
         // length, and possibly longer, but truncated.
@@ -595,8 +872,12 @@ where
                 .transpose()?
         };
 
-        // Currently we pass an empty permissions list, but in future we may be able to derive
-        // the permissions based on the incoming request
+        // Keep the token so that handlers can authorize the actions a request performs on
+        // specific databases, once those are known:
+        req.extensions_mut().insert(RequestToken(auth.clone()));
+
+        // An empty permissions list is passed here to check that the token is valid, the
+        // permissions needed by each request are checked by the request handlers
         let permissions = self.authorizer.permissions(auth, &[]).await?;
 
         // Extend the request with the permissions, which may be useful in future
@@ -605,6 +886,31 @@ where
         Ok(())
     }
 
+    /// Check that the `token` grants the `action` on the given database, or on all databases
+    /// if `None` is given
+    async fn authorize_database_action(
+        &self,
+        token: Option<Vec<u8>>,
+        database: Option<&str>,
+        action: Action,
+    ) -> Result<()> {
+        self.authorizer
+            .permissions(token, &[database_permission(database, action)])
+            .await
+            .map_err(|e| match e {
+                authz::Error::Forbidden => Error::Forbidden,
+                _ => Error::Unauthenticated,
+            })?;
+        Ok(())
+    }
+
+    /// The [`CurrentQuery`] for a query made with `token`, which carries the name of the token,
+    /// if it is a named token, to the query executor, e.g., for the slow query log
+    fn current_query(&self, token: Option<&[u8]>) -> CurrentQuery {
+        let token_name = token.and_then(|token| token_name(&self.write_buffer.catalog(), token));
+        CurrentQuery::with_token_name(token_name)
+    }
+
     async fn extract_query_request<D: DeserializeOwned>(
         &self,
         req: Request<Body>,
@@ -621,6 +927,7 @@ where
                     query_str: r.query_str,
                     format: r.format,
                     params: r.params.map(|s| serde_json::from_str(&s)).transpose()?,
+                    explain: r.explain,
                 }
             }
             Method::POST => {
@@ -635,69 +942,132 @@ where
             query_str: request.query_str,
             format: request.format.unwrap_or(header_format),
             params: request.params,
+            explain: request.explain,
         })
     }
 
     /// Inner function for performing InfluxQL queries
     ///
-    /// This is used by both the `/api/v3/query_influxql` and `/api/v1/query`
-    /// APIs.
+    /// This is used by the `/api/v3/query_influxql` API, which only accepts a single statement.
     async fn query_influxql_inner(
         &self,
+        token: Option<Vec<u8>>,
         database: Option<String>,
         query_str: &str,
         params: Option<StatementParams>,
     ) -> Result<SendableRecordBatchStream> {
-        let mut statements = rewrite::parse_statements(query_str)?;
+        let mut statements = self
+            .authorize_influxql_statements(token, database, query_str)
+            .await?;
 
         if statements.len() != 1 {
             return Err(Error::InfluxqlSingleStatement);
         }
-        let statement = statements.pop().unwrap();
 
-        let database = match (database, statement.resolve_dbrp()) {
-            (None, None) => None,
-            (None, Some(db)) | (Some(db), None) => Some(db),
-            (Some(p), Some(q)) => {
-                if p == q {
-                    Some(p)
-                } else {
-                    return Err(Error::InfluxqlDatabaseMismatch {
-                        param_db: p,
-                        query_db: q,
-                    });
-                }
-            }
-        };
+        statements
+            .pop()
+            .unwrap()
+            .run(self.query_executor.as_ref(), params)
+            .await
+            .map_err(Into::into)
+    }
 
-        if statement.statement().is_show_databases() {
-            self.query_executor.show_databases()
-        } else if statement.statement().is_show_retention_policies() {
-            self.query_executor
-                .show_retention_policies(database.as_deref(), None)
-                .await
-        } else {
-            let Some(database) = database else {
-                return Err(Error::InfluxqlNoDatabase);
-            };
+    /// Parse the statements in an InfluxQL query, resolving the database each one targets,
+    /// and authorizing the request to read from it, so that they can then be run
+    ///
+    /// This is used by both the `/api/v3/query_influxql` and `/api/v1/query` APIs. A
+    /// `KILL QUERY` statement, which must be the only statement in the query, is run here.
+    async fn authorize_influxql_statements(
+        &self,
+        token: Option<Vec<u8>>,
+        database: Option<String>,
+        query_str: &str,
+    ) -> Result<Vec<InfluxqlStatement>> {
+        if let Some(id) = parse_kill_query(query_str)? {
+            self.kill_query_by_id(token, id).await?;
+            return Ok(vec![InfluxqlStatement::Done]);
+        }
 
-            self.query_executor
-                .query(
-                    &database,
+        let mut authorized = vec![];
+        for statement in rewrite::parse_statements(query_str)? {
+            let database = resolve_influxql_database(database.clone(), statement.resolve_dbrp())?;
+
+            // statements that do not target a single database require access to all databases:
+            let target_db = if statement.statement().is_show_databases() {
+                None
+            } else {
+                database.as_deref()
+            };
+            let current_query = self.current_query(token.as_deref());
+            self.authorize_database_action(token.clone(), target_db, Action::Read)
+                .await?;
+
+            authorized.push(if statement.statement().is_show_databases() {
+                InfluxqlStatement::ShowDatabases
+            } else if statement.statement().is_show_retention_policies() {
+                InfluxqlStatement::ShowRetentionPolicies { database }
+            } else {
+                let Some(database) = database else {
+                    return Err(Error::InfluxqlNoDatabase);
+                };
+                InfluxqlStatement::Query {
+                    database,
                     // TODO - implement an interface that takes the statement directly,
                     // so we don't need to double down on the parsing
+                    query: statement.to_statement().to_string(),
+                    current_query,
+                }
+            });
+        }
+        Ok(authorized)
+    }
+
+    /// Run an InfluxQL query made with `explain=analyze`, which must select data from a
+    /// single database
+    async fn explain_influxql(
+        &self,
+        token: Option<Vec<u8>>,
+        database: Option<String>,
+        query_str: &str,
+        params: Option<StatementParams>,
+    ) -> Result<ExplainAnalyze> {
+        if parse_kill_query(query_str)?.is_some() {
+            return Err(Error::ExplainNotSupported);
+        }
+        let mut statements = rewrite::parse_statements(query_str)?;
+        if statements.len() != 1 {
+            return Err(Error::InfluxqlSingleStatement);
+        }
+        let statement = statements.pop().unwrap();
+        if statement.statement().is_show_databases()
+            || statement.statement().is_show_retention_policies()
+        {
+            return Err(Error::ExplainNotSupported);
+        }
+        let database = resolve_influxql_database(database, statement.resolve_dbrp())?
+            .ok_or(Error::InfluxqlNoDatabase)?;
+
+        let current_query = self.current_query(token.as_deref());
+        self.authorize_database_action(token, Some(&database), Action::Read)
+            .await?;
+        CURRENT_QUERY
+            .scope(
+                current_query,
+                self.query_executor.explain_analyze(
+                    &database,
                     &statement.to_statement().to_string(),
                     params,
                     QueryKind::InfluxQl,
                     None,
                     None,
-                )
-                .await
-        }
-        .map_err(Into::into)
+                ),
+            )
+            .await
+            .map_err(Into::into)
     }
 
     async fn configure_last_cache_create(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
         let LastCacheCreateRequest {
             db,
             table,
@@ -707,6 +1077,8 @@ where
             count,
             ttl,
         } = self.read_body_json(req).await?;
+        self.authorize_database_action(token, Some(&db), Action::Create)
+            .await?;
 
         let (db_id, db_schema) = self
             .write_buffer
@@ -775,11 +1147,14 @@ where
     /// This will first attempt to parse the parameters from the URI query string, if a query string
     /// is provided, but if not, will attempt to parse them from the request body as JSON.
     async fn configure_last_cache_delete(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
         let LastCacheDeleteRequest { db, table, name } = if let Some(query) = req.uri().query() {
             serde_urlencoded::from_str(query)?
         } else {
             self.read_body_json(req).await?
         };
+        self.authorize_database_action(token, Some(&db), Action::Delete)
+            .await?;
 
         let (db_id, db_schema) = self
             .write_buffer
@@ -799,41 +1174,395 @@ where
             .unwrap())
     }
 
-    async fn read_body_json<ReqBody: DeserializeOwned>(
-        &self,
-        req: hyper::Request<Body>,
-    ) -> Result<ReqBody> {
-        if !json_content_type(req.headers()) {
-            return Err(Error::InvalidContentType {
-                expected: mime::APPLICATION_JSON,
-            });
+    async fn configure_meta_cache_create(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let MetaCacheCreateRequest {
+            db,
+            table,
+            name,
+            columns,
+            max_cardinality,
+            max_age,
+        } = self.read_body_json(req).await?;
+        self.authorize_database_action(token, Some(&db), Action::Create)
+            .await?;
+
+        let (db_id, db_schema) = self
+            .write_buffer
+            .catalog()
+            .db_schema_and_id(&db)
+            .ok_or_else(|| WriteBufferError::DbDoesNotExist)?;
+        let table_def = db_schema
+            .table_definition(table.as_str())
+            .ok_or_else(|| WriteBufferError::TableDoesNotExist)?;
+        let column_ids = columns
+            .into_iter()
+            .map(|name| {
+                table_def
+                    .column_name_to_id(name.as_str())
+                    .ok_or_else(|| WriteBufferError::ColumnDoesNotExist(name))
+            })
+            .collect::<Result<Vec<_>, WriteBufferError>>()?;
+        let max_cardinality = max_cardinality
+            .map(MaxCardinality::try_from)
+            .transpose()
+            .map_err(|e| {
+                WriteBufferError::MetaCacheError(meta_cache::ProviderError::InvalidConfiguration(e))
+            })?
+            .unwrap_or_default();
+        let max_age = max_age
+            .map(Duration::from_secs)
+            .map(MaxAge::from)
+            .unwrap_or_default();
+
+        match self
+            .write_buffer
+            .create_meta_cache(
+                db_id,
+                name,
+                CreateMetaCacheArgs {
+                    time_provider: Arc::clone(&self.time_provider) as _,
+                    table_def,
+                    max_cardinality,
+                    max_age,
+                    column_ids,
+                },
+            )
+            .await?
+        {
+            Some(def) => Response::builder()
+                .status(StatusCode::CREATED)
+                .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_string(&MetaCacheCreatedResponse(def)).unwrap(),
+                ))
+                .map_err(Into::into),
+            None => Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .map_err(Into::into),
         }
-        let bytes = self.read_body(req).await?;
-        serde_json::from_slice(&bytes).map_err(Into::into)
     }
-}
 
-/// Check that the content type is application/json
-fn json_content_type(headers: &HeaderMap) -> bool {
-    let content_type = if let Some(content_type) = headers.get(CONTENT_TYPE) {
-        content_type
-    } else {
-        return false;
-    };
+    /// Delete a metadata cache with the given [`MetaCacheDeleteRequest`] parameters
+    ///
+    /// This will first attempt to parse the parameters from the URI query string, if a query string
+    /// is provided, but if not, will attempt to parse them from the request body as JSON.
+    async fn configure_meta_cache_delete(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let MetaCacheDeleteRequest { db, table, name } = if let Some(query) = req.uri().query() {
+            serde_urlencoded::from_str(query)?
+        } else {
+            self.read_body_json(req).await?
+        };
+        self.authorize_database_action(token, Some(&db), Action::Delete)
+            .await?;
 
-    let content_type = if let Ok(content_type) = content_type.to_str() {
-        content_type
-    } else {
-        return false;
-    };
+        let (db_id, db_schema) = self
+            .write_buffer
+            .catalog()
+            .db_schema_and_id(&db)
+            .ok_or_else(|| WriteBufferError::DbDoesNotExist)?;
+        let table_id = db_schema
+            .table_name_to_id(table)
+            .ok_or_else(|| WriteBufferError::TableDoesNotExist)?;
+        self.write_buffer
+            .delete_meta_cache(&db_id, &table_id, &name)
+            .await?;
 
-    let mime = if let Ok(mime) = content_type.parse::<mime::Mime>() {
-        mime
-    } else {
-        return false;
-    };
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::empty())
+            .unwrap())
+    }
 
-    let is_json_content_type = mime.type_() == "application"
+    /// Set or clear the retention period of a database with the given
+    /// [`RetentionPeriodRequest`] parameters
+    async fn configure_database_retention_period(
+        &self,
+        req: Request<Body>,
+    ) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let RetentionPeriodRequest {
+            db,
+            retention_period,
+        } = self.read_body_json(req).await?;
+        validate_db_name(&db, false)?;
+        self.authorize_database_action(token, Some(&db), Action::Create)
+            .await?;
+        let database = NamespaceName::new(db)?;
+
+        self.write_buffer
+            .set_retention_period(database, retention_period.map(Duration::from_secs))
+            .await?;
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    /// Lock, or unlock, the schema of a database or table with the given [`SchemaLockRequest`]
+    /// parameters
+    async fn configure_schema_lock(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let SchemaLockRequest { db, table, locked } = self.read_body_json(req).await?;
+        self.authorize_database_action(token, Some(&db), Action::Create)
+            .await?;
+
+        self.write_buffer
+            .set_schema_locked(&db, table.as_deref(), locked)
+            .await?;
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    /// Create a new, empty database with the given [`DatabaseCreateRequest`] parameters
+    async fn configure_database_create(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let DatabaseCreateRequest {
+            db,
+            retention_period,
+        } = self.read_body_json(req).await?;
+        validate_db_name(&db, false)?;
+        self.authorize_database_action(token, Some(&db), Action::Create)
+            .await?;
+        let database = NamespaceName::new(db)?;
+
+        self.write_buffer
+            .create_database(database, retention_period.map(Duration::from_secs))
+            .await?;
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    /// Create a new table with the schema declared in the given [`TableCreateRequest`]
+    async fn configure_table_create(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let TableCreateRequest {
+            db,
+            table,
+            tags,
+            fields,
+        } = self.read_body_json(req).await?;
+        self.authorize_database_action(token, Some(&db), Action::Create)
+            .await?;
+
+        self.write_buffer
+            .create_table(
+                &db,
+                &table,
+                tags,
+                fields
+                    .into_iter()
+                    .map(|field| (field.name, field.field_type.into()))
+                    .collect(),
+            )
+            .await?;
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    /// Delete a database with the given [`DatabaseDeleteRequest`] parameters
+    ///
+    /// This will first attempt to parse the parameters from the URI query string, if a query string
+    /// is provided, but if not, will attempt to parse them from the request body as JSON.
+    async fn configure_database_delete(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let DatabaseDeleteRequest { db } = if let Some(query) = req.uri().query() {
+            serde_urlencoded::from_str(query)?
+        } else {
+            self.read_body_json(req).await?
+        };
+        self.authorize_database_action(token, Some(&db), Action::Delete)
+            .await?;
+
+        self.write_buffer.delete_database(&db).await?;
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    /// Delete a table with the given [`TableDeleteRequest`] parameters
+    ///
+    /// This will first attempt to parse the parameters from the URI query string, if a query string
+    /// is provided, but if not, will attempt to parse them from the request body as JSON.
+    async fn configure_table_delete(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let TableDeleteRequest { db, table } = if let Some(query) = req.uri().query() {
+            serde_urlencoded::from_str(query)?
+        } else {
+            self.read_body_json(req).await?
+        };
+        self.authorize_database_action(token, Some(&db), Action::Delete)
+            .await?;
+
+        self.write_buffer.delete_table(&db, &table).await?;
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    /// Create a named token with the given [`TokenCreateRequest`] parameters
+    ///
+    /// The token is generated by the server and is only returned in this response; the catalog
+    /// only stores its hash.
+    async fn configure_token_create(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        self.authorize_database_action(token, None, Action::Create)
+            .await?;
+        let TokenCreateRequest {
+            name,
+            description,
+            permissions,
+            expires_in,
+        } = self.read_body_json(req).await?;
+
+        let created_at_ns = self.time_provider.now().timestamp_nanos();
+        let expires_at_ns = expires_in
+            .map(|secs| {
+                i64::try_from(Duration::from_secs(secs).as_nanos())
+                    .ok()
+                    .and_then(|ns| created_at_ns.checked_add(ns))
+                    .ok_or(Error::InvalidTokenExpiry(secs))
+            })
+            .transpose()?;
+        let token = generate_token();
+        let definition = TokenDefinition {
+            name: name.into(),
+            hash: hash_token(&token).into(),
+            permissions,
+            description,
+            created_at_ns,
+            expires_at_ns,
+        };
+        let info = TokenInfo::from(&definition);
+        self.write_buffer.create_token(definition).await?;
+
+        Response::builder()
+            .status(StatusCode::CREATED)
+            .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                serde_json::to_string(&TokenCreatedResponse { token, info }).unwrap(),
+            ))
+            .map_err(Into::into)
+    }
+
+    /// List the named tokens in the catalog, without their hashes
+    async fn configure_token_list(&self, req: Request<Body>) -> Result<Response<Body>> {
+        // listing tokens requires the same admin access as creating them:
+        self.authorize_database_action(request_token(&req), None, Action::Create)
+            .await?;
+        let tokens = self
+            .write_buffer
+            .catalog()
+            .list_tokens()
+            .iter()
+            .map(|t| TokenInfo::from(t.as_ref()))
+            .collect::<Vec<_>>();
+
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(serde_json::to_string(&tokens).unwrap()))
+            .map_err(Into::into)
+    }
+
+    /// Revoke a named token with the given [`TokenDeleteRequest`] parameters
+    ///
+    /// This will first attempt to parse the parameters from the URI query string, if a query string
+    /// is provided, but if not, will attempt to parse them from the request body as JSON.
+    async fn configure_token_delete(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        self.authorize_database_action(token, None, Action::Delete)
+            .await?;
+        let TokenDeleteRequest { name } = if let Some(query) = req.uri().query() {
+            serde_urlencoded::from_str(query)?
+        } else {
+            self.read_body_json(req).await?
+        };
+        self.write_buffer.revoke_token(&name).await?;
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    async fn read_body_json<ReqBody: DeserializeOwned>(
+        &self,
+        req: hyper::Request<Body>,
+    ) -> Result<ReqBody> {
+        if !json_content_type(req.headers()) {
+            return Err(Error::InvalidContentType {
+                expected: mime::APPLICATION_JSON,
+            });
+        }
+        let bytes = self.read_body(req).await?;
+        serde_json::from_slice(&bytes).map_err(Into::into)
+    }
+}
+
+/// The encodings accepted for request bodies, given by the `Content-Encoding` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentEncoding {
+    Identity,
+    Gzip,
+    Zstd,
+    /// The snappy block format, as opposed to the framed format
+    Snappy,
+}
+
+impl ContentEncoding {
+    fn from_headers(headers: &HeaderMap) -> Result<Self> {
+        let encoding = headers
+            .get(&CONTENT_ENCODING)
+            .map(|v| v.to_str().map_err(Error::NonUtf8ContentEncodingHeader))
+            .transpose()?;
+        match encoding {
+            None | Some("identity") => Ok(Self::Identity),
+            Some("gzip") => Ok(Self::Gzip),
+            Some("zstd") => Ok(Self::Zstd),
+            Some("snappy") => Ok(Self::Snappy),
+            Some(v) => Err(Error::InvalidContentEncoding(v.to_string())),
+        }
+    }
+}
+
+/// Check that the content type is application/json
+fn json_content_type(headers: &HeaderMap) -> bool {
+    let content_type = if let Some(content_type) = headers.get(CONTENT_TYPE) {
+        content_type
+    } else {
+        return false;
+    };
+
+    let content_type = if let Ok(content_type) = content_type.to_str() {
+        content_type
+    } else {
+        return false;
+    };
+
+    let mime = if let Ok(mime) = content_type.parse::<mime::Mime>() {
+        mime
+    } else {
+        return false;
+    };
+
+    let is_json_content_type = mime.type_() == "application"
         && (mime.subtype() == "json" || mime.suffix().map_or(false, |name| name == "json"));
 
     is_json_content_type
@@ -862,6 +1591,42 @@ fn extract_v1_auth_token(req: &mut Request<Body>) -> Option<Vec<u8>> {
         .map(String::into_bytes)
 }
 
+/// The token that was provided with a request, if any
+#[derive(Clone)]
+struct RequestToken(Option<Vec<u8>>);
+
+impl Debug for RequestToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never log the token itself:
+        f.debug_tuple("RequestToken").field(&"<redacted>").finish()
+    }
+}
+
+/// Get the token that was provided with the request, see [`HttpApi::authorize_request`]
+/// Parse a `KILL QUERY <id>` statement, returning the ID of the query to kill, or `None` if the
+/// query is not a `KILL QUERY` statement
+///
+/// The InfluxQL parser does not support `KILL QUERY`, so it is parsed here. The ID may be quoted,
+/// and unlike InfluxDB 1.x, is the ID of the query in `system.queries`.
+fn parse_kill_query(query_str: &str) -> Result<Option<&str>> {
+    let mut words = query_str.trim().trim_end_matches(';').split_whitespace();
+    match (words.next(), words.next()) {
+        (Some(kill), Some(query))
+            if kill.eq_ignore_ascii_case("kill") && query.eq_ignore_ascii_case("query") => {}
+        _ => return Ok(None),
+    }
+    match (words.next(), words.next()) {
+        (Some(id), None) => Ok(Some(id.trim_matches(|c| c == '\'' || c == '"'))),
+        _ => Err(Error::InvalidKillQuery),
+    }
+}
+
+fn request_token(req: &Request<Body>) -> Option<Vec<u8>> {
+    req.extensions()
+        .get::<RequestToken>()
+        .and_then(|t| t.0.clone())
+}
+
 fn validate_auth_header(header: HeaderValue) -> Result<Vec<u8>, AuthorizationError> {
     // Split the header value into two parts
     let mut header = header.to_str()?.split(' ');
@@ -971,6 +1736,109 @@ pub(crate) struct QueryRequest<D, F, P> {
     pub(crate) query_str: String,
     pub(crate) format: F,
     pub(crate) params: Option<P>,
+    #[serde(default)]
+    pub(crate) explain: Option<Explain>,
+}
+
+/// The `explain` parameter of the query APIs, which returns how a query was executed instead of
+/// its results
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Explain {
+    /// Run the query to completion, and return the plan that was executed, with the metrics
+    /// from its execution, as JSON
+    Analyze,
+}
+
+/// Resolve the database an InfluxQL query targets, from the `db` parameter and the database
+/// given in the statement itself, which must match if both are given
+fn resolve_influxql_database(
+    param_db: Option<String>,
+    query_db: Option<String>,
+) -> Result<Option<String>> {
+    match (param_db, query_db) {
+        (None, None) => Ok(None),
+        (None, Some(db)) | (Some(db), None) => Ok(Some(db)),
+        (Some(p), Some(q)) => {
+            if p == q {
+                Ok(Some(p))
+            } else {
+                Err(Error::InfluxqlDatabaseMismatch {
+                    param_db: p,
+                    query_db: q,
+                })
+            }
+        }
+    }
+}
+
+/// An InfluxQL statement that the request has been authorized to run, see
+/// [`HttpApi::authorize_influxql_statements`]
+#[derive(Debug)]
+enum InfluxqlStatement {
+    /// A statement that was run when it was authorized, i.e., `KILL QUERY`, and has no results
+    Done,
+    ShowDatabases,
+    ShowRetentionPolicies {
+        database: Option<String>,
+    },
+    Query {
+        database: String,
+        query: String,
+        current_query: CurrentQuery,
+    },
+}
+
+impl InfluxqlStatement {
+    /// Run the statement, producing the stream of its results
+    async fn run<Q: QueryExecutor>(
+        self,
+        query_executor: &Q,
+        params: Option<StatementParams>,
+    ) -> Result<SendableRecordBatchStream, Q::Error> {
+        match self {
+            Self::Done => Ok(Box::pin(EmptyRecordBatchStream::new(Arc::new(
+                arrow_schema::Schema::empty(),
+            )))),
+            Self::ShowDatabases => query_executor.show_databases(),
+            Self::ShowRetentionPolicies { database } => {
+                query_executor
+                    .show_retention_policies(database.as_deref(), None)
+                    .await
+            }
+            Self::Query {
+                database,
+                query,
+                current_query,
+            } => {
+                CURRENT_QUERY
+                    .scope(
+                        current_query,
+                        query_executor.query(
+                            &database,
+                            &query,
+                            params,
+                            QueryKind::InfluxQl,
+                            None,
+                            None,
+                        ),
+                    )
+                    .await
+            }
+        }
+    }
+}
+
+/// Produce the response to a query made with `explain=analyze`, which is always JSON
+fn explain_analyze_response(
+    explain: &ExplainAnalyze,
+    encoding: ResponseEncoding,
+) -> Result<Response<Body>> {
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(explain)?))?;
+    compress_response(response, encoding).map_err(Error::CompressResponse)
 }
 
 #[derive(Debug, Deserialize)]
@@ -980,6 +1848,8 @@ pub(crate) enum QueryFormat {
     Csv,
     Pretty,
     Json,
+    #[serde(alias = "jsonl")]
+    JsonLines,
 }
 
 impl QueryFormat {
@@ -989,6 +1859,7 @@ impl QueryFormat {
             Self::Csv => "text/csv",
             Self::Pretty => "text/plain; charset=utf-8",
             Self::Json => "application/json",
+            Self::JsonLines => "application/jsonl",
         }
     }
 
@@ -1002,6 +1873,7 @@ impl QueryFormat {
             Some(b"application/vnd.apache.parquet") => Ok(Self::Parquet),
             Some(b"text/csv") => Ok(Self::Csv),
             Some(b"text/plain") => Ok(Self::Pretty),
+            Some(b"application/jsonl") => Ok(Self::JsonLines),
             Some(b"application/json" | b"*/*") | None => Ok(Self::Json),
             Some(mime_type) => match String::from_utf8(mime_type.to_vec()) {
                 Ok(s) => Err(Error::InvalidMimeType(s)),
@@ -1011,58 +1883,182 @@ impl QueryFormat {
     }
 }
 
-async fn record_batch_stream_to_body(
+fn record_batch_stream_to_body(
     stream: Pin<Box<dyn RecordBatchStream + Send>>,
     format: QueryFormat,
 ) -> Result<Body, Error> {
-    fn to_json(batches: Vec<RecordBatch>) -> Result<Bytes> {
-        let mut writer = arrow_json::ArrayWriter::new(Vec::new());
-        for batch in batches {
-            writer.write(&batch)?;
-        }
+    let encoder = RecordBatchEncoder::try_new(format, stream.schema())?;
+    Ok(Body::wrap_stream(RecordBatchBodyStream {
+        input: stream.fuse(),
+        encoder,
+        done: false,
+    }))
+}
 
-        writer.finish()?;
+/// Incrementally encodes [`RecordBatch`]es into the bytes of a query response
+///
+/// Each call to [`RecordBatchEncoder::encode`] produces the bytes for a single batch,
+/// so that results can be sent to the client as they are produced, rather than being
+/// buffered in memory until the query completes. The exception is the `pretty` format,
+/// which needs to see every row to determine the column widths of the output table.
+enum RecordBatchEncoder {
+    Csv {
+        header_written: bool,
+    },
+    Json {
+        started: bool,
+    },
+    JsonLines,
+    Parquet {
+        writer: Option<TrackedMemoryArrowWriter<Vec<u8>>>,
+    },
+    Pretty {
+        batches: Vec<RecordBatch>,
+    },
+}
 
-        Ok(Bytes::from(writer.into_inner()))
+impl RecordBatchEncoder {
+    fn try_new(format: QueryFormat, schema: SchemaRef) -> Result<Self> {
+        Ok(match format {
+            QueryFormat::Csv => Self::Csv {
+                header_written: false,
+            },
+            QueryFormat::Json => Self::Json { started: false },
+            QueryFormat::JsonLines => Self::JsonLines,
+            QueryFormat::Parquet => {
+                let mem_pool = Arc::new(UnboundedMemoryPool::default());
+                Self::Parquet {
+                    writer: Some(TrackedMemoryArrowWriter::try_new(
+                        Vec::new(),
+                        schema,
+                        mem_pool,
+                    )?),
+                }
+            }
+            QueryFormat::Pretty => Self::Pretty {
+                batches: Vec::new(),
+            },
+        })
     }
 
-    fn to_csv(batches: Vec<RecordBatch>) -> Result<Bytes> {
-        let mut writer = arrow_csv::writer::Writer::new(Vec::new());
-        for batch in batches {
-            writer.write(&batch)?;
+    /// Encode a single [`RecordBatch`], returning any bytes that are ready to be sent
+    fn encode(&mut self, batch: RecordBatch) -> Result<Option<Bytes>> {
+        match self {
+            Self::Csv { header_written } => {
+                if batch.num_rows() == 0 {
+                    return Ok(None);
+                }
+                let mut writer = arrow_csv::WriterBuilder::new()
+                    .with_header(!*header_written)
+                    .build(Vec::new());
+                writer.write(&batch)?;
+                *header_written = true;
+                Ok(Some(Bytes::from(writer.into_inner())))
+            }
+            Self::Json { started } => {
+                if batch.num_rows() == 0 {
+                    return Ok(None);
+                }
+                let mut writer = arrow_json::ArrayWriter::new(Vec::new());
+                writer.write(&batch)?;
+                writer.finish()?;
+                let mut bytes = writer.into_inner();
+                // The array writer wraps the rows of the batch in '[' and ']', the opening
+                // bracket is replaced by a ',' for every batch after the first, and the
+                // closing bracket is dropped, to be written once the stream is finished:
+                bytes.pop();
+                if *started {
+                    bytes[0] = b',';
+                }
+                *started = true;
+                Ok(Some(Bytes::from(bytes)))
+            }
+            Self::JsonLines => {
+                let mut writer = arrow_json::LineDelimitedWriter::new(Vec::new());
+                writer.write(&batch)?;
+                writer.finish()?;
+                let bytes = writer.into_inner();
+                Ok((!bytes.is_empty()).then(|| Bytes::from(bytes)))
+            }
+            Self::Parquet { writer } => {
+                let writer = writer
+                    .as_mut()
+                    .expect("parquet writer should not be used after it was closed");
+                writer.write(batch)?;
+                // The writer flushes a row group into the underlying buffer once it has
+                // accumulated enough rows, so take whatever has been flushed so far:
+                let bytes = std::mem::take(writer.inner_mut());
+                Ok((!bytes.is_empty()).then(|| Bytes::from(bytes)))
+            }
+            Self::Pretty { batches } => {
+                batches.push(batch);
+                Ok(None)
+            }
         }
-
-        Ok(Bytes::from(writer.into_inner()))
-    }
-
-    fn to_pretty(batches: Vec<RecordBatch>) -> Result<Bytes> {
-        Ok(Bytes::from(format!(
-            "{}",
-            pretty::pretty_format_batches(&batches)?
-        )))
     }
 
-    fn to_parquet(batches: Vec<RecordBatch>) -> Result<Bytes> {
-        let mut bytes = Vec::new();
-        let mem_pool = Arc::new(UnboundedMemoryPool::default());
-        let mut writer =
-            TrackedMemoryArrowWriter::try_new(&mut bytes, batches[0].schema(), mem_pool)?;
-        for batch in batches {
-            writer.write(batch)?;
+    /// Finish encoding, returning any remaining bytes that need to be sent
+    fn finish(&mut self) -> Result<Option<Bytes>> {
+        match self {
+            Self::Csv { .. } | Self::JsonLines => Ok(None),
+            Self::Json { started } => Ok(Some(Bytes::from_static(if *started {
+                b"]"
+            } else {
+                b"[]"
+            }))),
+            Self::Parquet { writer } => {
+                let Some(writer) = writer.take() else {
+                    return Ok(None);
+                };
+                Ok(Some(Bytes::from(writer.into_inner()?)))
+            }
+            Self::Pretty { batches } => Ok(Some(Bytes::from(format!(
+                "{}",
+                pretty::pretty_format_batches(batches)?
+            )))),
         }
-        writer.close()?;
-        Ok(Bytes::from(bytes))
     }
+}
 
-    let batches = stream.try_collect::<Vec<RecordBatch>>().await?;
+/// A [`Stream`] of response body [`Bytes`] that encodes [`RecordBatch`]es from the
+/// input stream as they arrive
+///
+/// The input stream is wrapped in [`Fuse`] so that it is safe to poll after completion.
+struct RecordBatchBodyStream {
+    input: Fuse<Pin<Box<dyn RecordBatchStream + Send>>>,
+    encoder: RecordBatchEncoder,
+    done: bool,
+}
+
+impl Stream for RecordBatchBodyStream {
+    type Item = Result<Bytes>;
 
-    match format {
-        QueryFormat::Pretty => to_pretty(batches),
-        QueryFormat::Parquet => to_parquet(batches),
-        QueryFormat::Csv => to_csv(batches),
-        QueryFormat::Json => to_json(batches),
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+            let result = match ready!(self.input.poll_next_unpin(cx)) {
+                Some(Ok(batch)) => self.encoder.encode(batch),
+                Some(Err(e)) => Err(e.into()),
+                None => {
+                    self.done = true;
+                    self.encoder.finish()
+                }
+            };
+            match result {
+                Ok(Some(bytes)) => return Poll::Ready(Some(Ok(bytes))),
+                // nothing to send for this batch, so poll the input again:
+                Ok(None) => continue,
+                Err(e) => {
+                    // the response status has already been sent, so the best we can do is
+                    // end the stream with an error, which will terminate the connection:
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
     }
-    .map(Body::from)
 }
 
 // This is a hack around the fact that bool default is false not true
@@ -1076,6 +2072,10 @@ pub(crate) struct WriteParams {
     pub(crate) accept_partial: bool,
     #[serde(default)]
     pub(crate) precision: Precision,
+    /// Write the body in slices as it streams in, rather than reading it into memory in full,
+    /// which allows writes larger than `max_request_bytes`, but is not atomic
+    #[serde(default)]
+    pub(crate) stream: bool,
 }
 
 impl From<iox_http::write::WriteParams> for WriteParams {
@@ -1085,6 +2085,7 @@ impl From<iox_http::write::WriteParams> for WriteParams {
             // legacy behaviour was to not accept partial:
             accept_partial: false,
             precision: legacy.precision.into(),
+            stream: false,
         }
     }
 }
@@ -1112,6 +2113,163 @@ struct LastCacheDeleteRequest {
     name: String,
 }
 
+/// Request definition for the `POST /api/v3/configure/meta_cache` API
+#[derive(Debug, Deserialize)]
+struct MetaCacheCreateRequest {
+    db: String,
+    table: String,
+    name: Option<String>,
+    columns: Vec<String>,
+    max_cardinality: Option<usize>,
+    max_age: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct MetaCacheCreatedResponse(MetaCacheDefinition);
+
+/// Request definition for the `DELETE /api/v3/configure/meta_cache` API
+#[derive(Debug, Deserialize)]
+struct MetaCacheDeleteRequest {
+    db: String,
+    table: String,
+    name: String,
+}
+
+/// Request definition for the `POST /api/v3/configure/database/retention_period` API
+#[derive(Debug, Deserialize)]
+struct RetentionPeriodRequest {
+    db: String,
+    /// The retention period in seconds, or `None` to retain data indefinitely
+    retention_period: Option<u64>,
+}
+
+/// Request definition for the `POST /api/v3/configure/schema_lock` API
+#[derive(Debug, Deserialize)]
+struct SchemaLockRequest {
+    db: String,
+    /// The table to lock, or `None` to lock the schema of the whole database
+    table: Option<String>,
+    locked: bool,
+}
+
+/// Request definition for the `POST /api/v3/configure/database` API
+#[derive(Debug, Deserialize)]
+struct DatabaseCreateRequest {
+    db: String,
+    /// The retention period in seconds, or `None` to retain data indefinitely
+    #[serde(default)]
+    retention_period: Option<u64>,
+}
+
+/// Request definition for the `POST /api/v3/configure/table` API
+#[derive(Debug, Deserialize)]
+struct TableCreateRequest {
+    db: String,
+    table: String,
+    /// The tag columns of the table
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    fields: Vec<TableCreateField>,
+}
+
+/// A field column declared in a [`TableCreateRequest`]
+#[derive(Debug, Deserialize)]
+struct TableCreateField {
+    name: String,
+    #[serde(rename = "type")]
+    field_type: FieldType,
+}
+
+/// The data types that can be declared for a field column
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FieldType {
+    String,
+    Integer,
+    #[serde(rename = "uinteger")]
+    UInteger,
+    Float,
+    #[serde(alias = "bool")]
+    Boolean,
+}
+
+impl From<FieldType> for InfluxFieldType {
+    fn from(field_type: FieldType) -> Self {
+        match field_type {
+            FieldType::String => Self::String,
+            FieldType::Integer => Self::Integer,
+            FieldType::UInteger => Self::UInteger,
+            FieldType::Float => Self::Float,
+            FieldType::Boolean => Self::Boolean,
+        }
+    }
+}
+
+/// Request definition for the `DELETE /api/v3/configure/database` API
+#[derive(Debug, Deserialize)]
+struct DatabaseDeleteRequest {
+    db: String,
+}
+
+/// Request definition for the `DELETE /api/v3/configure/table` API
+#[derive(Debug, Deserialize)]
+struct TableDeleteRequest {
+    db: String,
+    table: String,
+}
+
+/// Request definition for the `POST /api/v3/configure/token` API
+#[derive(Debug, Deserialize)]
+struct TokenCreateRequest {
+    name: String,
+    description: Option<String>,
+    permissions: Vec<TokenPermission>,
+    /// The number of seconds after which the token expires, or `None` if it never expires
+    expires_in: Option<u64>,
+}
+
+/// The description of a named token returned by the `/api/v3/configure/token` APIs, which
+/// never includes the token or its hash
+#[derive(Debug, Serialize)]
+struct TokenInfo {
+    name: Arc<str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    permissions: Vec<TokenPermission>,
+    created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<String>,
+}
+
+impl From<&TokenDefinition> for TokenInfo {
+    fn from(def: &TokenDefinition) -> Self {
+        Self {
+            name: Arc::clone(&def.name),
+            description: def.description.clone(),
+            permissions: def.permissions.clone(),
+            created_at: Time::from_timestamp_nanos(def.created_at_ns).to_rfc3339(),
+            expires_at: def
+                .expires_at_ns
+                .map(|ns| Time::from_timestamp_nanos(ns).to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TokenCreatedResponse {
+    /// The token, which is only ever returned once, when it is created
+    token: String,
+    #[serde(flatten)]
+    info: TokenInfo,
+}
+
+/// Request definition for the `DELETE /api/v3/configure/token` API
+#[derive(Debug, Deserialize)]
+struct TokenDeleteRequest {
+    name: String,
+}
+
 pub(crate) async fn route_request<Q: QueryExecutor, T: TimeProvider>(
     http_server: Arc<HttpApi<Q, T>>,
     mut req: Request<Body>,
@@ -1180,7 +2338,12 @@ where
         (Method::GET | Method::POST, "/api/v3/query_influxql") => {
             http_server.query_influxql(req).await
         }
+        (Method::DELETE, path) if path.starts_with(KILL_QUERY_PATH_PREFIX) => {
+            http_server.kill_query(req).await
+        }
         (Method::GET, "/query") => http_server.v1_query(req).await,
+        (Method::GET, "/api/v3/export") => http_server.export(req).await,
+        (Method::POST, "/api/v3/import") => http_server.import(req).await,
         (Method::GET, "/health" | "/api/v1/health") => http_server.health(),
         (Method::GET | Method::POST, "/ping") => http_server.ping(),
         (Method::GET, "/metrics") => http_server.handle_metrics(),
@@ -1190,6 +2353,33 @@ where
         (Method::DELETE, "/api/v3/configure/last_cache") => {
             http_server.configure_last_cache_delete(req).await
         }
+        (Method::POST, "/api/v3/configure/meta_cache") => {
+            http_server.configure_meta_cache_create(req).await
+        }
+        (Method::DELETE, "/api/v3/configure/meta_cache") => {
+            http_server.configure_meta_cache_delete(req).await
+        }
+        (Method::POST, "/api/v3/configure/database/retention_period") => {
+            http_server.configure_database_retention_period(req).await
+        }
+        (Method::POST, "/api/v3/configure/schema_lock") => {
+            http_server.configure_schema_lock(req).await
+        }
+        (Method::POST, "/api/v3/configure/database") => {
+            http_server.configure_database_create(req).await
+        }
+        (Method::DELETE, "/api/v3/configure/database") => {
+            http_server.configure_database_delete(req).await
+        }
+        (Method::POST, "/api/v3/configure/table") => http_server.configure_table_create(req).await,
+        (Method::DELETE, "/api/v3/configure/table") => {
+            http_server.configure_table_delete(req).await
+        }
+        (Method::POST, "/api/v3/configure/token") => http_server.configure_token_create(req).await,
+        (Method::GET, "/api/v3/configure/token") => http_server.configure_token_list(req).await,
+        (Method::DELETE, "/api/v3/configure/token") => {
+            http_server.configure_token_delete(req).await
+        }
         _ => {
             let body = Body::from("not found");
             Ok(Response::builder()
@@ -1229,7 +2419,9 @@ fn legacy_write_error_to_response(e: WriteParseError) -> Response<Body> {
 
 #[cfg(test)]
 mod tests {
+    use super::parse_kill_query;
     use super::validate_db_name;
+    use super::Error;
     use super::ValidateDbNameError;
 
     macro_rules! assert_validate_db_name {
@@ -1258,4 +2450,24 @@ mod tests {
         assert_validate_db_name!("_foo", false, Err(ValidateDbNameError::InvalidStartChar));
         assert_validate_db_name!("", false, Err(ValidateDbNameError::Empty));
     }
+
+    #[test]
+    fn test_parse_kill_query() {
+        assert_eq!(parse_kill_query("KILL QUERY abc").unwrap(), Some("abc"));
+        assert_eq!(
+            parse_kill_query("  kill query 'abc';").unwrap(),
+            Some("abc")
+        );
+        assert_eq!(parse_kill_query("Kill Query \"abc\"").unwrap(), Some("abc"));
+        assert_eq!(parse_kill_query("SELECT * FROM cpu").unwrap(), None);
+        assert_eq!(parse_kill_query("KILL").unwrap(), None);
+        assert!(matches!(
+            parse_kill_query("KILL QUERY"),
+            Err(Error::InvalidKillQuery)
+        ));
+        assert!(matches!(
+            parse_kill_query("KILL QUERY abc ON \"host\""),
+            Err(Error::InvalidKillQuery)
+        ));
+    }
 }
@@ -0,0 +1,299 @@
+//! Compression of HTTP response bodies, using an encoding negotiated with the client's
+//! `Accept-Encoding` header
+
+use std::{
+    io::{self, Write},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use flate2::{write::GzEncoder, Compression};
+use futures::{ready, Stream};
+use hyper::{
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, VARY},
+    http::HeaderValue,
+    Body, HeaderMap, Response,
+};
+
+/// The quality level used for brotli, which trades some compression for speed, since responses
+/// are compressed as they are streamed
+const BROTLI_QUALITY: u32 = 5;
+/// The base 2 logarithm of the brotli window size
+const BROTLI_LG_WINDOW_SIZE: u32 = 22;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// The encodings that response bodies can be compressed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ResponseEncoding {
+    Gzip,
+    Zstd,
+    Brotli,
+}
+
+impl ResponseEncoding {
+    /// Encodings in the order they are preferred when a client accepts several equally
+    const PREFERENCE: [Self; 3] = [Self::Zstd, Self::Brotli, Self::Gzip];
+
+    /// Choose the encoding for a response from the request's `Accept-Encoding` header, or
+    /// `None` if the response should not be compressed
+    ///
+    /// The encoding with the highest quality value is chosen, with ties broken by the server's
+    /// preference. Encodings with a quality value of zero are never chosen.
+    pub(super) fn from_accept_encoding(headers: &HeaderMap) -> Option<Self> {
+        let mut qualities: [Option<f32>; 3] = [None; 3];
+        let mut wildcard = None;
+        for (name, quality) in headers
+            .get_all(ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(parse_accepted_encoding)
+        {
+            match name.as_str() {
+                "*" => wildcard = Some(quality),
+                name => {
+                    if let Some(i) = Self::PREFERENCE.iter().position(|e| e.as_str() == name) {
+                        qualities[i] = Some(quality);
+                    }
+                }
+            }
+        }
+
+        let mut chosen: Option<(Self, f32)> = None;
+        for (encoding, quality) in Self::PREFERENCE.into_iter().zip(qualities) {
+            let Some(quality) = quality.or(wildcard) else {
+                continue;
+            };
+            if quality > 0.0 && chosen.map_or(true, |(_, q)| quality > q) {
+                chosen = Some((encoding, quality));
+            }
+        }
+        chosen.map(|(encoding, _)| encoding)
+    }
+
+    /// The value of the `Content-Encoding` header for this encoding
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Brotli => "br",
+        }
+    }
+}
+
+/// Parse an encoding and its quality value, which defaults to 1, from an `Accept-Encoding`
+/// header value, e.g., `gzip;q=0.5`
+fn parse_accepted_encoding(value: &str) -> Option<(String, f32)> {
+    let mut parts = value.split(';').map(str::trim);
+    let name = parts.next().filter(|n| !n.is_empty())?.to_ascii_lowercase();
+    let quality = parts
+        .find_map(|p| p.strip_prefix("q="))
+        .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+    Some((name, quality))
+}
+
+/// Compress the body of a response with the given encoding, if any
+///
+/// The body is compressed as it is streamed, with the output flushed after each chunk of the
+/// original body, so that results are sent to the client as they are produced.
+pub(super) fn compress_response(
+    response: Response<Body>,
+    encoding: Option<ResponseEncoding>,
+) -> io::Result<Response<Body>> {
+    let Some(encoding) = encoding else {
+        return Ok(response);
+    };
+    let (mut parts, body) = response.into_parts();
+    parts.headers.insert(
+        CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    parts
+        .headers
+        .append(VARY, HeaderValue::from_static("accept-encoding"));
+    parts.headers.remove(CONTENT_LENGTH);
+    let body = Body::wrap_stream(CompressedBodyStream {
+        input: body,
+        encoder: Some(BodyEncoder::try_new(encoding)?),
+    });
+    Ok(Response::from_parts(parts, body))
+}
+
+enum BodyEncoder {
+    Gzip(GzEncoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+}
+
+impl BodyEncoder {
+    fn try_new(encoding: ResponseEncoding) -> io::Result<Self> {
+        Ok(match encoding {
+            ResponseEncoding::Gzip => Self::Gzip(GzEncoder::new(vec![], Compression::default())),
+            ResponseEncoding::Zstd => Self::Zstd(zstd::stream::write::Encoder::new(
+                vec![],
+                zstd::DEFAULT_COMPRESSION_LEVEL,
+            )?),
+            ResponseEncoding::Brotli => Self::Brotli(Box::new(brotli::CompressorWriter::new(
+                vec![],
+                BROTLI_BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_LG_WINDOW_SIZE,
+            ))),
+        })
+    }
+
+    /// Compress a chunk of the body, returning the compressed bytes that are ready to be sent
+    fn encode(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+        let output = match self {
+            Self::Gzip(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+            Self::Zstd(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+            Self::Brotli(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+        };
+        Ok(Bytes::from(std::mem::take(output)))
+    }
+
+    /// Finish compressing, returning the remaining bytes that need to be sent
+    fn finish(self) -> io::Result<Bytes> {
+        match self {
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Zstd(encoder) => encoder.finish(),
+            Self::Brotli(encoder) => Ok(encoder.into_inner()),
+        }
+        .map(Bytes::from)
+    }
+}
+
+struct CompressedBodyStream {
+    input: Body,
+    /// The encoder, which is taken once the input is exhausted, or fails
+    encoder: Option<BodyEncoder>,
+}
+
+impl Stream for CompressedBodyStream {
+    type Item = Result<Bytes, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let Some(encoder) = this.encoder.as_mut() else {
+                return Poll::Ready(None);
+            };
+            match ready!(Pin::new(&mut this.input).poll_next(cx)) {
+                Some(Ok(chunk)) => match encoder.encode(&chunk) {
+                    // an empty chunk of the body produces no output:
+                    Ok(bytes) if bytes.is_empty() => continue,
+                    Ok(bytes) => return Poll::Ready(Some(Ok(bytes))),
+                    Err(e) => {
+                        this.encoder = None;
+                        return Poll::Ready(Some(Err(e.into())));
+                    }
+                },
+                Some(Err(e)) => {
+                    this.encoder = None;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+                None => {
+                    let encoder = this.encoder.take().expect("encoder was checked above");
+                    return Poll::Ready(Some(encoder.finish().map_err(Into::into)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn headers(accept_encoding: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for v in accept_encoding {
+            headers.append(ACCEPT_ENCODING, HeaderValue::from_str(v).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn choose_encoding() {
+        use ResponseEncoding::*;
+        let cases: [(&[&str], Option<ResponseEncoding>); 9] = [
+            (&[], None),
+            (&["identity"], None),
+            (&["gzip"], Some(Gzip)),
+            (&["gzip, deflate, br"], Some(Brotli)),
+            (&["gzip", "zstd"], Some(Zstd)),
+            (&["zstd;q=0.5, gzip"], Some(Gzip)),
+            (&["br;q=0, gzip;q=0"], None),
+            (&["*"], Some(Zstd)),
+            (&["*, zstd;q=0, br;q=0"], Some(Gzip)),
+        ];
+        for (accept_encoding, expected) in cases {
+            assert_eq!(
+                ResponseEncoding::from_accept_encoding(&headers(accept_encoding)),
+                expected,
+                "accept-encoding: {accept_encoding:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn compressed_body_is_streamed() {
+        let decoders: [(ResponseEncoding, fn(&[u8]) -> Vec<u8>); 3] = [
+            (ResponseEncoding::Gzip, |b| {
+                let mut out = vec![];
+                flate2::read::GzDecoder::new(b)
+                    .read_to_end(&mut out)
+                    .unwrap();
+                out
+            }),
+            (ResponseEncoding::Zstd, |b| zstd::decode_all(b).unwrap()),
+            (ResponseEncoding::Brotli, |b| {
+                let mut out = vec![];
+                brotli::Decompressor::new(b, 4096)
+                    .read_to_end(&mut out)
+                    .unwrap();
+                out
+            }),
+        ];
+        for (encoding, decode) in decoders {
+            let response = Response::builder()
+                .header(CONTENT_LENGTH, 12)
+                .body(Body::wrap_stream(futures::stream::iter(
+                    ["a,b\n", "1,2\n", "3,4\n"].map(Ok::<_, io::Error>),
+                )))
+                .unwrap();
+            let response = compress_response(response, Some(encoding)).unwrap();
+            assert_eq!(response.headers()[CONTENT_ENCODING], encoding.as_str());
+            assert_eq!(response.headers()[VARY], "accept-encoding");
+            assert!(!response.headers().contains_key(CONTENT_LENGTH));
+
+            let mut body = response.into_body();
+            let mut compressed = vec![];
+            let mut chunk_count = 0;
+            while let Some(chunk) = body.next().await {
+                compressed.extend_from_slice(&chunk.unwrap());
+                chunk_count += 1;
+            }
+            // a chunk for each input chunk, and the end of the compressed stream:
+            assert_eq!(chunk_count, 4, "encoding: {encoding:?}");
+            assert_eq!(decode(&compressed), b"a,b\n1,2\n3,4\n");
+        }
+    }
+}
@@ -0,0 +1,469 @@
+//! The `/api/v3/export` API, which streams the buffered and persisted data of a database's
+//! tables back out as line protocol
+
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray},
+    compute::cast,
+    datatypes::{DataType, Float64Type, Int64Type, TimeUnit, TimestampNanosecondType, UInt64Type},
+    record_batch::RecordBatch,
+};
+use authz::Action;
+use chrono::DateTime;
+use datafusion::{
+    prelude::{col, lit, Expr},
+    scalar::ScalarValue,
+};
+use futures::{future, StreamExt, TryStreamExt};
+use hyper::{header::CONTENT_TYPE, Body, Request, Response, StatusCode};
+use influxdb3_catalog::catalog::TableDefinition;
+use influxdb3_write::line_protocol::{FieldValue, LineBuilder};
+use influxdb3_write::write_buffer::Error as WriteBufferError;
+use iox_query::QueryDatabase;
+use iox_time::TimeProvider;
+use observability_deps::tracing::info;
+use schema::{InfluxColumnType, InfluxFieldType, TIME_COLUMN_NAME};
+use serde::Deserialize;
+
+use crate::QueryExecutor;
+
+use super::{
+    compression::{compress_response, ResponseEncoding},
+    request_token, Error, HttpApi, Result,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("missing query parameter 'db'")]
+    MissingDatabase,
+
+    #[error("table not found: {0}")]
+    TableNotFound(String),
+
+    #[error("invalid '{param}' parameter ({value}), expected an RFC3339 timestamp or nanoseconds")]
+    InvalidTime { param: &'static str, value: String },
+
+    #[error("column {column} of table {table} does not have the type in the catalog")]
+    UnexpectedColumnType { table: Arc<str>, column: String },
+}
+
+impl<Q, T> HttpApi<Q, T>
+where
+    Q: QueryExecutor,
+    T: TimeProvider,
+    Error: From<<Q as QueryExecutor>::Error>,
+{
+    /// Implements the `GET /api/v3/export` API
+    ///
+    /// Streams the rows of the tables in the database given by the [`ExportParams`] as line
+    /// protocol, reading the chunks of each table, both buffered and persisted, one at a time.
+    /// The response is compressed with an encoding that the client accepts.
+    pub(super) async fn export(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let encoding = ResponseEncoding::from_accept_encoding(req.headers());
+        let query = req.uri().query().ok_or(ExportError::MissingDatabase)?;
+        let params: ExportParams = serde_urlencoded::from_str(query)?;
+        info!(?params, ?encoding, "handling export");
+        self.authorize_database_action(token, Some(&params.db), Action::Read)
+            .await?;
+
+        let db_schema = self
+            .write_buffer
+            .catalog()
+            .db_schema(&params.db)
+            .ok_or(WriteBufferError::DbDoesNotExist)?;
+        let tables = match &params.table {
+            Some(names) => names
+                .split(',')
+                .map(|name| {
+                    db_schema
+                        .table_definition(name)
+                        .ok_or_else(|| ExportError::TableNotFound(name.to_string()))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => db_schema.tables().collect(),
+        };
+        let time_range = TimeRange {
+            start: params
+                .start
+                .as_deref()
+                .map(|v| parse_time("start", v))
+                .transpose()?,
+            end: params
+                .end
+                .as_deref()
+                .map(|v| parse_time("end", v))
+                .transpose()?,
+        };
+
+        let namespace = self
+            .query_executor
+            .namespace(&params.db, None, false)
+            .await?
+            .ok_or(WriteBufferError::DbDoesNotExist)?;
+        let ctx = Arc::new(namespace.new_query_context(None, None));
+        // The filters only prune the persisted files that are read, the rows of the chunks
+        // are filtered by time as they are written out:
+        let filters = time_range.filters();
+        let mut chunks = vec![];
+        for table_def in tables {
+            for chunk in self.write_buffer.get_table_chunks(
+                &params.db,
+                &table_def.table_name,
+                &filters,
+                None,
+                &ctx.inner().state(),
+            )? {
+                chunks.push((Arc::clone(&table_def), chunk));
+            }
+        }
+
+        let lines = futures::stream::iter(chunks)
+            .then(move |(table_def, chunk)| {
+                let ctx = Arc::clone(&ctx);
+                async move {
+                    let batches = chunk
+                        .data()
+                        .read_to_batches(chunk.schema(), ctx.inner())
+                        .await;
+                    let mut lp = String::new();
+                    for batch in &batches {
+                        write_batch_as_line_protocol(&mut lp, &table_def, batch, time_range)?;
+                    }
+                    Ok::<_, ExportError>(lp)
+                }
+            })
+            .try_filter(|lp| future::ready(!lp.is_empty()));
+
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(Body::wrap_stream(lines))?;
+        compress_response(response, encoding).map_err(Error::CompressResponse)
+    }
+}
+
+/// Query parameters for the `GET /api/v3/export` API
+#[derive(Debug, Deserialize)]
+struct ExportParams {
+    db: String,
+    /// A comma-separated list of the tables to export, all tables are exported if not given
+    table: Option<String>,
+    /// The inclusive lower bound on the time of the rows to export
+    start: Option<String>,
+    /// The exclusive upper bound on the time of the rows to export
+    end: Option<String>,
+}
+
+/// Parse a time parameter given either as an RFC3339 timestamp or in nanoseconds since the
+/// epoch
+fn parse_time(param: &'static str, value: &str) -> Result<i64, ExportError> {
+    value
+        .parse::<i64>()
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .and_then(|t| t.timestamp_nanos_opt())
+        })
+        .ok_or_else(|| ExportError::InvalidTime {
+            param,
+            value: value.to_string(),
+        })
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct TimeRange {
+    start: Option<i64>,
+    end: Option<i64>,
+}
+
+impl TimeRange {
+    fn contains(&self, t: i64) -> bool {
+        self.start.is_none_or(|start| t >= start) && self.end.is_none_or(|end| t < end)
+    }
+
+    fn filters(&self) -> Vec<Expr> {
+        let time = |t| lit(ScalarValue::TimestampNanosecond(Some(t), None));
+        self.start
+            .map(|start| col(TIME_COLUMN_NAME).gt_eq(time(start)))
+            .into_iter()
+            .chain(self.end.map(|end| col(TIME_COLUMN_NAME).lt(time(end))))
+            .collect()
+    }
+}
+
+/// The columns of a [`RecordBatch`], cast to the arrays for their type in the catalog
+enum ColumnValues {
+    Tag(ArrayRef),
+    String(ArrayRef),
+    Integer(ArrayRef),
+    UInteger(ArrayRef),
+    Float(ArrayRef),
+    Boolean(ArrayRef),
+}
+
+impl ColumnValues {
+    fn array(&self) -> &ArrayRef {
+        match self {
+            Self::Tag(a)
+            | Self::String(a)
+            | Self::Integer(a)
+            | Self::UInteger(a)
+            | Self::Float(a)
+            | Self::Boolean(a) => a,
+        }
+    }
+}
+
+/// Writes the rows of the batch that are within the time range as line protocol, one line per
+/// row, using the types of the table's columns in the catalog. Rows for tables with a series
+/// key are written in the v3 line protocol format.
+///
+/// Null tags and fields are left out of a row, and rows without any fields are skipped, as
+/// they cannot be represented in line protocol.
+fn write_batch_as_line_protocol(
+    lp: &mut String,
+    table_def: &TableDefinition,
+    batch: &RecordBatch,
+    time_range: TimeRange,
+) -> Result<(), ExportError> {
+    let unexpected_type = |column: &str| ExportError::UnexpectedColumnType {
+        table: Arc::clone(&table_def.table_name),
+        column: column.to_string(),
+    };
+    let schema = batch.schema();
+
+    let mut time = None;
+    let mut columns = vec![];
+    for (field, array) in schema.fields().iter().zip(batch.columns()) {
+        let name = field.name().as_str();
+        let values = match table_def.field_type_by_name(name) {
+            Some(InfluxColumnType::Timestamp) => {
+                time = Some(
+                    cast(array, &DataType::Timestamp(TimeUnit::Nanosecond, None))
+                        .map_err(|_| unexpected_type(name))?,
+                );
+                continue;
+            }
+            Some(InfluxColumnType::Tag) => ColumnValues::Tag(cast(array, &DataType::Utf8)),
+            Some(InfluxColumnType::Field(InfluxFieldType::String)) => {
+                ColumnValues::String(cast(array, &DataType::Utf8))
+            }
+            Some(InfluxColumnType::Field(InfluxFieldType::Integer)) => {
+                ColumnValues::Integer(cast(array, &DataType::Int64))
+            }
+            Some(InfluxColumnType::Field(InfluxFieldType::UInteger)) => {
+                ColumnValues::UInteger(cast(array, &DataType::UInt64))
+            }
+            Some(InfluxColumnType::Field(InfluxFieldType::Float)) => {
+                ColumnValues::Float(cast(array, &DataType::Float64))
+            }
+            Some(InfluxColumnType::Field(InfluxFieldType::Boolean)) => {
+                ColumnValues::Boolean(cast(array, &DataType::Boolean))
+            }
+            None => return Err(unexpected_type(name)),
+        }
+        .map_err(|_| unexpected_type(name))?;
+        columns.push((name, values));
+    }
+    let time = time.ok_or_else(|| unexpected_type(TIME_COLUMN_NAME))?;
+    let time = time.as_primitive::<TimestampNanosecondType>();
+
+    // the tags of v3 tables are written in the order of the series key:
+    if let Some(series_key) = &table_def.series_key {
+        let position = |name: &str| {
+            series_key
+                .iter()
+                .position(|id| table_def.column_id_to_name(id).as_deref() == Some(name))
+        };
+        columns.sort_by_key(|(name, _)| position(name));
+    }
+
+    let mut line = LineBuilder::new(table_def);
+    for row in 0..batch.num_rows() {
+        let t = time.value(row);
+        if time.is_null(row) || !time_range.contains(t) {
+            continue;
+        }
+
+        for (name, values) in &columns {
+            if values.array().is_null(row) {
+                continue;
+            }
+            match values {
+                ColumnValues::Tag(a) => line.tag(name, a.as_string::<i32>().value(row)),
+                ColumnValues::String(a) => {
+                    line.field(name, FieldValue::String(a.as_string::<i32>().value(row)))
+                }
+                ColumnValues::Integer(a) => line.field(
+                    name,
+                    FieldValue::Integer(a.as_primitive::<Int64Type>().value(row)),
+                ),
+                ColumnValues::UInteger(a) => line.field(
+                    name,
+                    FieldValue::UInteger(a.as_primitive::<UInt64Type>().value(row)),
+                ),
+                ColumnValues::Float(a) => line.field(
+                    name,
+                    FieldValue::Float(a.as_primitive::<Float64Type>().value(row)),
+                ),
+                ColumnValues::Boolean(a) => {
+                    line.field(name, FieldValue::Boolean(a.as_boolean().value(row)))
+                }
+            }
+        }
+        line.finish_line(lp, t);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{
+        BooleanArray, DictionaryArray, Float64Array, Int64Array, StringArray,
+        TimestampNanosecondArray, UInt64Array,
+    };
+    use arrow::datatypes::Int32Type;
+    use influxdb3_id::{ColumnId, TableId};
+
+    use super::*;
+
+    fn table_def(series_key: Option<Vec<ColumnId>>) -> TableDefinition {
+        TableDefinition::new(
+            TableId::from(0),
+            "cpu load".into(),
+            vec![
+                (ColumnId::from(0), "host".into(), InfluxColumnType::Tag),
+                (ColumnId::from(1), "region".into(), InfluxColumnType::Tag),
+                (
+                    ColumnId::from(2),
+                    "count".into(),
+                    InfluxColumnType::Field(InfluxFieldType::Integer),
+                ),
+                (
+                    ColumnId::from(3),
+                    "total".into(),
+                    InfluxColumnType::Field(InfluxFieldType::UInteger),
+                ),
+                (
+                    ColumnId::from(4),
+                    "usage".into(),
+                    InfluxColumnType::Field(InfluxFieldType::Float),
+                ),
+                (
+                    ColumnId::from(5),
+                    "label".into(),
+                    InfluxColumnType::Field(InfluxFieldType::String),
+                ),
+                (
+                    ColumnId::from(6),
+                    "up".into(),
+                    InfluxColumnType::Field(InfluxFieldType::Boolean),
+                ),
+                (
+                    ColumnId::from(7),
+                    "time".into(),
+                    InfluxColumnType::Timestamp,
+                ),
+            ],
+            series_key,
+        )
+        .unwrap()
+    }
+
+    fn batch() -> RecordBatch {
+        let tag = |values: Vec<Option<&str>>| -> ArrayRef {
+            Arc::new(values.into_iter().collect::<DictionaryArray<Int32Type>>())
+        };
+        RecordBatch::try_from_iter([
+            (
+                "count",
+                Arc::new(Int64Array::from(vec![Some(1), None, None])) as ArrayRef,
+            ),
+            ("host", tag(vec![Some("a,b"), Some("c"), Some("d")])),
+            (
+                "label",
+                Arc::new(StringArray::from(vec![None, Some("say \"hi\""), None])),
+            ),
+            ("region", tag(vec![Some("us west"), None, Some("eu")])),
+            (
+                "time",
+                Arc::new(TimestampNanosecondArray::from(vec![1, 2, 3])),
+            ),
+            (
+                "total",
+                Arc::new(UInt64Array::from(vec![Some(2), None, None])),
+            ),
+            (
+                "up",
+                Arc::new(BooleanArray::from(vec![None, Some(true), None])),
+            ),
+            (
+                "usage",
+                Arc::new(Float64Array::from(vec![Some(0.5), Some(1.0), None])),
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn batch_exported_as_line_protocol() {
+        let mut lp = String::new();
+        write_batch_as_line_protocol(&mut lp, &table_def(None), &batch(), TimeRange::default())
+            .unwrap();
+        // the third row has no fields, so is skipped:
+        assert_eq!(
+            lp,
+            "cpu\\ load,host=a\\,b,region=us\\ west count=1i,total=2u,usage=0.5 1\n\
+            cpu\\ load,host=c label=\"say \\\"hi\\\"\",up=true,usage=1 2\n"
+        );
+
+        let mut lp = String::new();
+        write_batch_as_line_protocol(
+            &mut lp,
+            &table_def(None),
+            &batch(),
+            TimeRange {
+                start: Some(2),
+                end: Some(3),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            lp,
+            "cpu\\ load,host=c label=\"say \\\"hi\\\"\",up=true,usage=1 2\n"
+        );
+    }
+
+    #[test]
+    fn v3_batch_exported_in_series_key_order() {
+        let mut lp = String::new();
+        write_batch_as_line_protocol(
+            &mut lp,
+            &table_def(Some(vec![ColumnId::from(1), ColumnId::from(0)])),
+            &batch(),
+            TimeRange {
+                start: None,
+                end: Some(2),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            lp,
+            "cpu\\ load,region/us\\ west/host/a\\,b count=1i,total=2u,usage=0.5 1\n"
+        );
+    }
+
+    #[test]
+    fn parse_time_params() {
+        assert_eq!(parse_time("start", "1000").unwrap(), 1000);
+        assert_eq!(
+            parse_time("start", "1970-01-01T00:00:01Z").unwrap(),
+            1_000_000_000
+        );
+        assert!(parse_time("start", "yesterday").is_err());
+    }
+}
@@ -0,0 +1,775 @@
+//! The `/api/v3/import` API, which bulk loads Parquet or CSV files into a table without
+//! converting them to line protocol
+
+use std::{
+    io::{self, Write},
+    sync::Arc,
+};
+
+use arrow::{
+    array::{ArrayRef, Int64Array},
+    compute::{cast_with_options, kernels::numeric::mul, CastOptions},
+    datatypes::{DataType, Field, Schema, TimeUnit},
+    error::ArrowError,
+    record_batch::RecordBatch,
+};
+use authz::Action;
+use bytes::Bytes;
+use data_types::NamespaceName;
+use futures::{StreamExt, TryStreamExt};
+use hyper::{header::CONTENT_TYPE, Body, HeaderMap, Request, Response, StatusCode};
+use influxdb3_write::{write_buffer::import::ImportSummary, Precision, WriteBuffer};
+use iox_time::{Time, TimeProvider};
+use observability_deps::tracing::info;
+use parquet::{arrow::ParquetRecordBatchStreamBuilder, errors::ParquetError};
+use schema::{InfluxColumnType, TIME_COLUMN_NAME};
+use serde::Deserialize;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+use crate::QueryExecutor;
+
+use super::{request_token, validate_db_name, ContentEncoding, Error, FieldType, HttpApi, Result};
+
+/// The number of rows in each batch read from an imported file
+const IMPORT_BATCH_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error(
+        "the format of the import must be given with the 'format' parameter, or a content-type \
+        of 'application/vnd.apache.parquet' or 'text/csv'"
+    )]
+    MissingFormat,
+
+    #[error("invalid field '{0}', expected a field name, optionally followed by ':' and a type")]
+    InvalidField(String),
+
+    #[error("'auto' precision is not supported for imports")]
+    AutoPrecision,
+
+    #[error("a column mapping with the 'fields' parameter is required for CSV imports")]
+    MissingCsvFields,
+
+    #[error("column {0} was not found in the imported file")]
+    ColumnNotFound(String),
+
+    #[error("error reading CSV: {0}")]
+    Csv(#[source] ArrowError),
+
+    #[error("error reading CSV header: {0}")]
+    CsvHeader(#[from] csv::Error),
+
+    #[error("the CSV header is larger than the maximum of {0} bytes")]
+    CsvHeaderTooLarge(usize),
+
+    #[error("error reading Parquet: {0}")]
+    Parquet(#[from] ParquetError),
+
+    #[error("error building the imported batch: {0}")]
+    Batch(#[source] ArrowError),
+
+    #[error("column {column} cannot be imported: {source}")]
+    Cast { column: String, source: ArrowError },
+
+    #[error("error decompressing the import: {0}")]
+    Decompress(#[source] io::Error),
+
+    #[error(
+        "imports cannot be compressed with snappy, as the snappy block format cannot be \
+        decompressed as it is received, use gzip or zstd instead"
+    )]
+    SnappyEncoding,
+
+    #[error("error writing the Parquet file to a temporary file: {0}")]
+    TempFile(#[source] io::Error),
+
+    #[error("the import is larger than the maximum of {0} bytes, compressed or decompressed")]
+    SizeExceeded(usize),
+}
+
+impl<Q, T> HttpApi<Q, T>
+where
+    Q: QueryExecutor,
+    T: TimeProvider,
+    Error: From<<Q as QueryExecutor>::Error>,
+{
+    /// Implements the `POST /api/v3/import` API
+    ///
+    /// Maps the columns of the Parquet or CSV file in the request body to the tags, fields and
+    /// time of a table, using the [`ImportParams`], and imports the rows directly into the
+    /// write buffer.
+    ///
+    /// The body is not limited to the maximum request size, but to the maximum import size, both
+    /// as received and once decompressed: CSV files are read as they are received, and Parquet
+    /// files, which are read from their footer at the end of the file, are written to a temporary
+    /// file. Each batch read from the file is written to the WAL on its own, so an import that
+    /// fails part way through keeps the rows written before the failure.
+    pub(super) async fn import(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let token = request_token(&req);
+        let query = req.uri().query().unwrap_or_default();
+        let params: ImportParams = serde_urlencoded::from_str(query)?;
+        let format = match params.format {
+            Some(format) => format,
+            None => ImportFormat::try_from_headers(req.headers())?,
+        };
+        let mapping = ColumnMapping::try_from(&params)?;
+        info!(db = %params.db, table = %params.table, ?format, "handling import");
+        validate_db_name(&params.db, false)?;
+        self.authorize_database_action(token, Some(&params.db), Action::Write)
+            .await?;
+        let database = NamespaceName::new(params.db)?;
+
+        let mut decoder = BodyDecoder::new(
+            ContentEncoding::from_headers(req.headers())?,
+            self.max_import_bytes,
+        )?;
+        let mut body = req.into_body();
+        let mut writer = ImportWriter {
+            write_buffer: self.write_buffer.as_ref(),
+            database,
+            table: &params.table,
+            ingest_time: self.time_provider.now(),
+            summary: ImportSummary::default(),
+        };
+
+        match format {
+            ImportFormat::Csv => {
+                let mut reader = CsvReader::new(&mapping, self.max_request_bytes)?;
+                while let Some(chunk) = body.next().await {
+                    let chunk = decoder.decode(chunk.map_err(Error::ClientHangup)?)?;
+                    for batch in reader.read(&chunk)? {
+                        writer.write(batch).await?;
+                    }
+                }
+                let mut batches = reader.read(&decoder.finish()?)?;
+                batches.extend(reader.finish()?);
+                for batch in batches {
+                    writer.write(batch).await?;
+                }
+            }
+            ImportFormat::Parquet => {
+                let mut file =
+                    tokio::fs::File::from_std(tempfile::tempfile().map_err(ImportError::TempFile)?);
+                while let Some(chunk) = body.next().await {
+                    let chunk = decoder.decode(chunk.map_err(Error::ClientHangup)?)?;
+                    file.write_all(&chunk)
+                        .await
+                        .map_err(ImportError::TempFile)?;
+                }
+                file.write_all(&decoder.finish()?)
+                    .await
+                    .map_err(ImportError::TempFile)?;
+                file.rewind().await.map_err(ImportError::TempFile)?;
+
+                let mut batches = ParquetRecordBatchStreamBuilder::new(file)
+                    .await
+                    .map_err(ImportError::from)?
+                    .with_batch_size(IMPORT_BATCH_SIZE)
+                    .build()
+                    .map_err(ImportError::from)?;
+                while let Some(batch) = batches.try_next().await.map_err(ImportError::from)? {
+                    writer.write(mapping.map_batch(&batch, false)?).await?;
+                }
+            }
+        }
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_vec(&writer.summary)?))?)
+    }
+}
+
+/// Writes the batches read from an imported file to the write buffer, one at a time
+struct ImportWriter<'a> {
+    write_buffer: &'a dyn WriteBuffer,
+    database: NamespaceName<'static>,
+    table: &'a str,
+    ingest_time: Time,
+    summary: ImportSummary,
+}
+
+impl ImportWriter<'_> {
+    async fn write(&mut self, batch: RecordBatch) -> Result<()> {
+        let summary = self
+            .write_buffer
+            .import_record_batch(self.database.clone(), self.table, batch, self.ingest_time)
+            .await?;
+        self.summary.row_count += summary.row_count;
+        self.summary.skipped_row_count += summary.skipped_row_count;
+        Ok(())
+    }
+}
+
+/// Decompresses the chunks of a request body as they are received, failing once either the
+/// received or the decompressed size of the body exceeds `max_bytes`
+struct BodyDecoder {
+    decompressor: Decompressor,
+    max_bytes: usize,
+    received_bytes: usize,
+}
+
+enum Decompressor {
+    Identity,
+    Gzip(flate2::write::GzDecoder<DecodedBuf>),
+    Zstd(zstd::stream::write::Decoder<'static, DecodedBuf>),
+}
+
+impl BodyDecoder {
+    fn new(encoding: ContentEncoding, max_bytes: usize) -> Result<Self, ImportError> {
+        let decompressor = match encoding {
+            ContentEncoding::Identity => Decompressor::Identity,
+            ContentEncoding::Gzip => {
+                Decompressor::Gzip(flate2::write::GzDecoder::new(DecodedBuf::new(max_bytes)))
+            }
+            ContentEncoding::Zstd => zstd::stream::write::Decoder::new(DecodedBuf::new(max_bytes))
+                .map(Decompressor::Zstd)
+                .map_err(ImportError::Decompress)?,
+            ContentEncoding::Snappy => return Err(ImportError::SnappyEncoding),
+        };
+        Ok(Self {
+            decompressor,
+            max_bytes,
+            received_bytes: 0,
+        })
+    }
+
+    /// Decompress a chunk of the body, returning the data decompressed from it so far
+    fn decode(&mut self, chunk: Bytes) -> Result<Bytes, ImportError> {
+        self.received_bytes += chunk.len();
+        if self.received_bytes > self.max_bytes {
+            return Err(ImportError::SizeExceeded(self.max_bytes));
+        }
+        match &mut self.decompressor {
+            Decompressor::Identity => Ok(chunk),
+            Decompressor::Gzip(decoder) => {
+                let result = decoder.write_all(&chunk);
+                let output = decoder.get_mut();
+                output.check(result)?;
+                Ok(output.take())
+            }
+            Decompressor::Zstd(decoder) => {
+                let result = decoder.write_all(&chunk);
+                let output = decoder.get_mut();
+                output.check(result)?;
+                Ok(output.take())
+            }
+        }
+    }
+
+    /// Finish decompressing the body, returning the rest of the decompressed data
+    fn finish(self) -> Result<Bytes, ImportError> {
+        match self.decompressor {
+            Decompressor::Identity => Ok(Bytes::new()),
+            Decompressor::Gzip(mut decoder) => {
+                let result = decoder.try_finish();
+                let output = decoder.get_mut();
+                output.check(result)?;
+                Ok(output.take())
+            }
+            Decompressor::Zstd(mut decoder) => {
+                let result = decoder.flush();
+                let output = decoder.get_mut();
+                output.check(result)?;
+                Ok(output.take())
+            }
+        }
+    }
+}
+
+/// The output of a [`Decompressor`], which fails writes once more than `max_bytes` have been
+/// decompressed in total, so that a small body cannot decompress to an unbounded amount of data
+struct DecodedBuf {
+    data: Vec<u8>,
+    max_bytes: usize,
+    total_bytes: usize,
+}
+
+impl DecodedBuf {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            data: vec![],
+            max_bytes,
+            total_bytes: 0,
+        }
+    }
+
+    /// Convert the `result` of writing to the decompressor to an import error
+    fn check(&self, result: io::Result<()>) -> Result<(), ImportError> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(_) if self.total_bytes > self.max_bytes => {
+                Err(ImportError::SizeExceeded(self.max_bytes))
+            }
+            Err(e) => Err(ImportError::Decompress(e)),
+        }
+    }
+
+    /// Take the data decompressed since the last call
+    fn take(&mut self) -> Bytes {
+        std::mem::take(&mut self.data).into()
+    }
+}
+
+impl Write for DecodedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.total_bytes += buf.len();
+        if self.total_bytes > self.max_bytes {
+            return Err(io::Error::other("the decompressed import is too large"));
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Query parameters for the `POST /api/v3/import` API
+#[derive(Debug, Deserialize)]
+struct ImportParams {
+    db: String,
+    table: String,
+    format: Option<ImportFormat>,
+    /// A comma-separated list of the columns to import as tags
+    tags: Option<String>,
+    /// A comma-separated list of the columns to import as fields, each optionally followed by
+    /// `:` and the type of the field, e.g., `usage:float,count:integer`
+    fields: Option<String>,
+    /// The column holding the time of each row, `time` by default
+    time: Option<String>,
+    /// The precision of integer times, nanoseconds by default
+    precision: Option<Precision>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ImportFormat {
+    Parquet,
+    Csv,
+}
+
+impl ImportFormat {
+    fn try_from_headers(headers: &HeaderMap) -> Result<Self, ImportError> {
+        match headers.get(CONTENT_TYPE).map(|v| v.as_bytes()) {
+            Some(b"application/vnd.apache.parquet") => Ok(Self::Parquet),
+            Some(b"text/csv") => Ok(Self::Csv),
+            _ => Err(ImportError::MissingFormat),
+        }
+    }
+}
+
+/// How the columns of an imported file map to the columns of a table
+///
+/// Tags are imported as dictionary-encoded strings, and fields are cast to the arrays of their
+/// type, if one is given. Columns without a mapping keep the data type they have in the file, and
+/// the write buffer converts them to the types of the table in the catalog.
+#[derive(Debug)]
+struct ColumnMapping {
+    tags: Vec<String>,
+    fields: Vec<(String, Option<FieldType>)>,
+    time: String,
+    time_multiplier: i64,
+}
+
+impl TryFrom<&ImportParams> for ColumnMapping {
+    type Error = ImportError;
+
+    fn try_from(params: &ImportParams) -> Result<Self, Self::Error> {
+        let list = |s: &Option<String>| -> Vec<String> {
+            s.as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect()
+        };
+        let fields = list(&params.fields)
+            .into_iter()
+            .map(|field| match field.split_once(':') {
+                None => Ok((field, None)),
+                Some((name, field_type)) => serde_json::from_value(field_type.into())
+                    .map(|t| (name.to_string(), Some(t)))
+                    .map_err(|_| ImportError::InvalidField(field.clone())),
+            })
+            .collect::<Result<_, _>>()?;
+        let time_multiplier = match params.precision.unwrap_or(Precision::Nanosecond) {
+            Precision::Auto => return Err(ImportError::AutoPrecision),
+            Precision::Second => 1_000_000_000,
+            Precision::Millisecond => 1_000_000,
+            Precision::Microsecond => 1_000,
+            Precision::Nanosecond => 1,
+        };
+        Ok(Self {
+            tags: list(&params.tags),
+            fields,
+            time: params
+                .time
+                .clone()
+                .unwrap_or_else(|| TIME_COLUMN_NAME.to_string()),
+            time_multiplier,
+        })
+    }
+}
+
+impl ColumnMapping {
+    /// Map a column of the imported file, returning `None` if it should not be imported
+    fn map_column(
+        &self,
+        name: &str,
+        array: &ArrayRef,
+        only_mapped: bool,
+    ) -> Result<Option<(String, ArrayRef)>, ImportError> {
+        let cast = |data_type: &DataType| {
+            // values that cannot be cast are an error, rather than being imported as nulls:
+            let options = CastOptions {
+                safe: false,
+                ..Default::default()
+            };
+            cast_with_options(array, data_type, &options).map_err(|source| ImportError::Cast {
+                column: name.to_string(),
+                source,
+            })
+        };
+
+        if name == self.time {
+            return Ok(Some((
+                TIME_COLUMN_NAME.to_string(),
+                self.map_time(name, array)?,
+            )));
+        }
+        if self.tags.iter().any(|t| t == name) {
+            let tag_type =
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8));
+            return Ok(Some((name.to_string(), cast(&tag_type)?)));
+        }
+        match self.fields.iter().find(|(f, _)| f == name) {
+            Some((_, Some(field_type))) => {
+                let data_type = InfluxColumnType::Field((*field_type).into()).into();
+                Ok(Some((name.to_string(), cast(&data_type)?)))
+            }
+            Some((_, None)) => Ok(Some((name.to_string(), Arc::clone(array)))),
+            None if only_mapped => Ok(None),
+            None => Ok(Some((name.to_string(), Arc::clone(array)))),
+        }
+    }
+
+    /// Convert the time column to nanosecond timestamps. Integers are in the precision of the
+    /// mapping, and strings are either integers or RFC3339 timestamps.
+    fn map_time(&self, name: &str, array: &ArrayRef) -> Result<ArrayRef, ImportError> {
+        let options = CastOptions {
+            safe: false,
+            ..Default::default()
+        };
+        let cast_error = |source| ImportError::Cast {
+            column: name.to_string(),
+            source,
+        };
+        let nanos = DataType::Timestamp(TimeUnit::Nanosecond, None);
+        let integers = match array.data_type() {
+            DataType::Timestamp(_, _) => {
+                return cast_with_options(array, &nanos, &options).map_err(cast_error)
+            }
+            data_type if data_type.is_integer() => {
+                cast_with_options(array, &DataType::Int64, &options).map_err(cast_error)?
+            }
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+                match cast_with_options(array, &DataType::Int64, &options) {
+                    Ok(integers) => integers,
+                    Err(_) => {
+                        return cast_with_options(array, &nanos, &options).map_err(cast_error)
+                    }
+                }
+            }
+            _ => return cast_with_options(array, &nanos, &options).map_err(cast_error),
+        };
+        let integers =
+            mul(&integers, &Int64Array::new_scalar(self.time_multiplier)).map_err(cast_error)?;
+        cast_with_options(&integers, &nanos, &options).map_err(cast_error)
+    }
+
+    /// Map the columns of a batch read from the imported file
+    fn map_batch(
+        &self,
+        batch: &RecordBatch,
+        only_mapped: bool,
+    ) -> Result<RecordBatch, ImportError> {
+        let schema = batch.schema();
+        for name in self
+            .tags
+            .iter()
+            .chain(self.fields.iter().map(|(f, _)| f))
+            .chain([&self.time])
+        {
+            if schema.column_with_name(name).is_none() {
+                return Err(ImportError::ColumnNotFound(name.clone()));
+            }
+        }
+        let mut columns = vec![];
+        for (field, array) in schema.fields().iter().zip(batch.columns()) {
+            if let Some(column) = self.map_column(field.name(), array, only_mapped)? {
+                columns.push(column);
+            }
+        }
+        RecordBatch::try_from_iter(columns).map_err(ImportError::Batch)
+    }
+}
+
+/// Reads the batches of a CSV file with a header as the file is received, only importing the
+/// columns in the mapping
+struct CsvReader<'a> {
+    mapping: &'a ColumnMapping,
+    max_header_bytes: usize,
+    /// The start of the file, held until the end of its header has been received
+    header: Vec<u8>,
+    decoder: Option<arrow_csv::reader::Decoder>,
+}
+
+impl<'a> CsvReader<'a> {
+    fn new(mapping: &'a ColumnMapping, max_header_bytes: usize) -> Result<Self, ImportError> {
+        if mapping.fields.is_empty() {
+            return Err(ImportError::MissingCsvFields);
+        }
+        Ok(Self {
+            mapping,
+            max_header_bytes,
+            header: vec![],
+            decoder: None,
+        })
+    }
+
+    /// Read the next chunk of the file, returning the batches that it completes
+    fn read(&mut self, chunk: &[u8]) -> Result<Vec<RecordBatch>, ImportError> {
+        if self.decoder.is_some() {
+            return self.decode(chunk);
+        }
+        self.header.extend_from_slice(chunk);
+        if !self.header.contains(&b'\n') {
+            if self.header.len() > self.max_header_bytes {
+                return Err(ImportError::CsvHeaderTooLarge(self.max_header_bytes));
+            }
+            return Ok(vec![]);
+        }
+        self.start()
+    }
+
+    /// Read the end of the file, returning its last batch
+    fn finish(mut self) -> Result<Vec<RecordBatch>, ImportError> {
+        let mut batches = if self.decoder.is_none() {
+            // the file ended without a line ending after its header:
+            self.start()?
+        } else {
+            vec![]
+        };
+        // decoding nothing ends the last row, if the file does not end with a line ending:
+        if let Some(decoder) = self.decoder.as_mut() {
+            decoder.decode(&[]).map_err(ImportError::Csv)?;
+        }
+        batches.extend(self.flush()?);
+        Ok(batches)
+    }
+
+    /// Create the decoder from the header at the start of the file, and decode the rows received
+    /// with the header
+    fn start(&mut self) -> Result<Vec<RecordBatch>, ImportError> {
+        let start = std::mem::take(&mut self.header);
+        // every column is read as a string, and then cast to the type in the mapping:
+        let header = csv::Reader::from_reader(&start[..]).headers()?.clone();
+        let schema = Schema::new(
+            header
+                .iter()
+                .map(|name| Field::new(name, DataType::Utf8, true))
+                .collect::<Vec<_>>(),
+        );
+        self.decoder = Some(
+            arrow_csv::ReaderBuilder::new(Arc::new(schema))
+                .with_header(true)
+                .with_batch_size(IMPORT_BATCH_SIZE)
+                .build_decoder(),
+        );
+        self.decode(&start)
+    }
+
+    /// Decode rows, returning a batch each time a full batch of rows has been decoded
+    fn decode(&mut self, mut rows: &[u8]) -> Result<Vec<RecordBatch>, ImportError> {
+        let mut batches = vec![];
+        // an empty slice would end the last row, so is only decoded at the end of the file:
+        while !rows.is_empty() {
+            let Some(decoder) = self.decoder.as_mut() else {
+                break;
+            };
+            let decoded = decoder.decode(rows).map_err(ImportError::Csv)?;
+            rows = &rows[decoded..];
+            if decoder.capacity() == 0 {
+                batches.extend(self.flush()?);
+            }
+        }
+        Ok(batches)
+    }
+
+    fn flush(&mut self) -> Result<Option<RecordBatch>, ImportError> {
+        let Some(decoder) = self.decoder.as_mut() else {
+            return Ok(None);
+        };
+        match decoder.flush().map_err(ImportError::Csv)? {
+            Some(batch) => self.mapping.map_batch(&batch, true).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{AsArray, StringArray};
+    use arrow::datatypes::TimestampNanosecondType;
+    use arrow::util::pretty::pretty_format_batches;
+
+    use super::*;
+
+    fn params(query: &str) -> ImportParams {
+        serde_urlencoded::from_str(query).unwrap()
+    }
+
+    #[test]
+    fn column_mapping_from_params() {
+        let mapping = ColumnMapping::try_from(&params(
+            "db=foo&table=cpu&tags=host,region&fields=usage:float,%20count&time=ts&precision=millisecond",
+        ))
+        .unwrap();
+        assert_eq!(mapping.tags, ["host", "region"]);
+        assert_eq!(mapping.fields.len(), 2);
+        assert!(matches!(mapping.fields[0], (ref n, Some(FieldType::Float)) if n == "usage"));
+        assert!(matches!(mapping.fields[1], (ref n, None) if n == "count"));
+        assert_eq!(mapping.time, "ts");
+        assert_eq!(mapping.time_multiplier, 1_000_000);
+
+        assert!(matches!(
+            ColumnMapping::try_from(&params("db=foo&table=cpu&fields=usage:number")),
+            Err(ImportError::InvalidField(_))
+        ));
+        assert!(matches!(
+            ColumnMapping::try_from(&params("db=foo&table=cpu&precision=auto")),
+            Err(ImportError::AutoPrecision)
+        ));
+    }
+
+    #[test]
+    fn time_from_integers_and_rfc3339() {
+        let mapping =
+            ColumnMapping::try_from(&params("db=foo&table=cpu&precision=second")).unwrap();
+        let times = |values: Vec<Option<&str>>| {
+            let array: ArrayRef = Arc::new(StringArray::from(values));
+            mapping
+                .map_time("time", &array)
+                .map(|a| a.as_primitive::<TimestampNanosecondType>().clone())
+        };
+
+        let integers = times(vec![Some("1"), None, Some("2")]).unwrap();
+        assert_eq!(
+            integers.iter().collect::<Vec<_>>(),
+            [Some(1_000_000_000), None, Some(2_000_000_000)]
+        );
+        let rfc3339 = times(vec![Some("1970-01-01T00:00:01.5Z")]).unwrap();
+        assert_eq!(rfc3339.value(0), 1_500_000_000);
+        assert!(matches!(
+            times(vec![Some("yesterday")]),
+            Err(ImportError::Cast { .. })
+        ));
+    }
+
+    #[test]
+    fn csv_is_read_as_it_is_received() {
+        let mapping = ColumnMapping::try_from(&params(
+            "db=foo&table=cpu&tags=host&fields=usage:float&precision=second",
+        ))
+        .unwrap();
+        let csv = b"host,usage,ignored,time\na,0.5,x,1\nb,\"1.5\",y,2\nc,2.5,z,3";
+
+        // the rows are the same however the file is split into chunks, including within the
+        // header and within quoted values:
+        for chunk_size in [1, 2, 7, csv.len()] {
+            let mut reader = CsvReader::new(&mapping, 1024).unwrap();
+            let mut batches = vec![];
+            for chunk in csv.chunks(chunk_size) {
+                batches.extend(reader.read(chunk).unwrap());
+            }
+            batches.extend(reader.finish().unwrap());
+            assert_eq!(
+                "+------+-------+---------------------+\n\
+                | host | usage | time                |\n\
+                +------+-------+---------------------+\n\
+                | a    | 0.5   | 1970-01-01T00:00:01 |\n\
+                | b    | 1.5   | 1970-01-01T00:00:02 |\n\
+                | c    | 2.5   | 1970-01-01T00:00:03 |\n\
+                +------+-------+---------------------+",
+                pretty_format_batches(&batches).unwrap().to_string(),
+                "chunk size {chunk_size}"
+            );
+        }
+
+        let mut reader = CsvReader::new(&mapping, 8).unwrap();
+        assert!(matches!(
+            reader.read(b"host,usage,time"),
+            Err(ImportError::CsvHeaderTooLarge(8))
+        ));
+    }
+
+    #[test]
+    fn gzip_body_is_decompressed_as_it_is_received() {
+        use flate2::{write::GzEncoder, Compression};
+
+        let data = b"host,usage,time\na,0.5,1\n".repeat(100);
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut decoder = BodyDecoder::new(ContentEncoding::Gzip, data.len()).unwrap();
+        let mut decompressed = vec![];
+        for chunk in compressed.chunks(16) {
+            decompressed.extend_from_slice(&decoder.decode(Bytes::copy_from_slice(chunk)).unwrap());
+        }
+        decompressed.extend_from_slice(&decoder.finish().unwrap());
+        assert_eq!(data, decompressed);
+
+        assert!(matches!(
+            BodyDecoder::new(ContentEncoding::Snappy, data.len()),
+            Err(ImportError::SnappyEncoding)
+        ));
+    }
+
+    #[test]
+    fn body_is_limited_as_received_and_decompressed() {
+        use flate2::{write::GzEncoder, Compression};
+
+        let data = vec![b'a'; 64 * 1024];
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert!(compressed.len() < 1024);
+
+        // the body is small as it is received, but too large once decompressed:
+        let mut decoder = BodyDecoder::new(ContentEncoding::Gzip, 1024).unwrap();
+        let result = decoder
+            .decode(Bytes::from(compressed.clone()))
+            .and_then(|_| decoder.finish());
+        assert!(matches!(result, Err(ImportError::SizeExceeded(1024))));
+
+        let mut decoder = BodyDecoder::new(ContentEncoding::Gzip, data.len()).unwrap();
+        let mut decompressed = decoder
+            .decode(Bytes::from(compressed.clone()))
+            .unwrap()
+            .to_vec();
+        decompressed.extend_from_slice(&decoder.finish().unwrap());
+        assert_eq!(data, decompressed);
+
+        // the size of an uncompressed body is limited as it is received:
+        let mut decoder = BodyDecoder::new(ContentEncoding::Identity, 1024).unwrap();
+        decoder.decode(Bytes::from(vec![b'a'; 1024])).unwrap();
+        assert!(matches!(
+            decoder.decode(Bytes::from_static(b"a")),
+            Err(ImportError::SizeExceeded(1024))
+        ));
+    }
+}
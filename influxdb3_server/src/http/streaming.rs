@@ -0,0 +1,315 @@
+//! Incremental ingestion of line protocol writes
+//!
+//! Rather than reading the whole request body into memory before writing it, a streamed write
+//! decodes the body as it arrives, and writes it to the buffer in slices of complete lines, each
+//! of which is at most the server's `max_request_bytes`. This allows bulk writes of any size,
+//! with memory use bounded by the size of a slice.
+
+use std::io::Write;
+
+use bytes::{Buf, Bytes, BytesMut};
+use data_types::NamespaceName;
+use futures::StreamExt;
+use hyper::{Body, Request};
+use influxdb3_write::{write_buffer::Error as WriteBufferError, BufferedWriteRequest};
+use iox_time::{Time, TimeProvider};
+
+use crate::QueryExecutor;
+
+use super::{ContentEncoding, Error, HttpApi, Result, WriteParams};
+
+impl<Q, T> HttpApi<Q, T>
+where
+    Q: QueryExecutor,
+    T: TimeProvider,
+    Error: From<<Q as QueryExecutor>::Error>,
+{
+    /// Write the line protocol in the body of the request in slices as it streams in
+    ///
+    /// Each slice is validated and buffered as a separate write, so slices written before one
+    /// that fails are not rolled back. The line numbers of invalid lines are relative to the
+    /// whole body, as they are for a write that is not streamed.
+    pub(super) async fn write_lp_streaming(
+        &self,
+        database: NamespaceName<'static>,
+        params: &WriteParams,
+        req: Request<Body>,
+        default_time: Time,
+        use_v3: bool,
+    ) -> Result<BufferedWriteRequest> {
+        let encoding = ContentEncoding::from_headers(req.headers())?;
+        let mut slicer = LineSlicer::new(encoding, self.max_request_bytes)?;
+        let mut payload = req.into_body();
+
+        let mut result = BufferedWriteRequest {
+            db_name: database.clone(),
+            invalid_lines: vec![],
+            line_count: 0,
+            field_count: 0,
+            index_count: 0,
+        };
+        let mut finished = false;
+        while !finished {
+            match payload.next().await {
+                Some(chunk) => slicer.push(chunk.map_err(Error::ClientHangup)?)?,
+                None => {
+                    slicer.finish()?;
+                    finished = true;
+                }
+            }
+            while let Some(slice) = slicer.next_slice()? {
+                let lp = std::str::from_utf8(&slice).map_err(Error::NonUtf8Body)?;
+                let lines_before_slice = result.line_count + result.invalid_lines.len();
+                let slice_result = match self
+                    .write_lp_to_buffer(database.clone(), lp, default_time, params, use_v3)
+                    .await
+                {
+                    Ok(slice_result) => slice_result,
+                    Err(Error::WriteBuffer(WriteBufferError::ParseError(mut e))) => {
+                        e.line_number += lines_before_slice;
+                        return Err(Error::WriteBuffer(WriteBufferError::ParseError(e)));
+                    }
+                    Err(e) => return Err(e),
+                };
+                result.line_count += slice_result.line_count;
+                result.field_count += slice_result.field_count;
+                result.index_count += slice_result.index_count;
+                result
+                    .invalid_lines
+                    .extend(slice_result.invalid_lines.into_iter().map(|mut e| {
+                        e.line_number += lines_before_slice;
+                        e
+                    }));
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Decodes a request body that is pushed to it in chunks, and splits the decoded line protocol
+/// into slices of complete lines
+///
+/// Compressed input is fed to the decoder a step at a time, as slices are taken, so the decoded
+/// data held in memory stays bounded, even for a highly compressed body.
+struct LineSlicer {
+    decoder: StreamDecoder,
+    /// Input that has been pushed, but not yet fed to the decoder
+    pending: Bytes,
+    /// Decoded line protocol that has not yet been taken in a slice
+    decoded: BytesMut,
+    max_slice_bytes: usize,
+    finished: bool,
+}
+
+enum StreamDecoder {
+    Identity,
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+    /// The snappy block format cannot be decoded incrementally, so a snappy body is buffered
+    /// in full, up to the size of a slice, and decoded once it is complete.
+    Snappy(BytesMut),
+}
+
+impl LineSlicer {
+    fn new(encoding: ContentEncoding, max_slice_bytes: usize) -> Result<Self> {
+        let decoder = match encoding {
+            ContentEncoding::Identity => StreamDecoder::Identity,
+            ContentEncoding::Gzip => StreamDecoder::Gzip(flate2::write::GzDecoder::new(vec![])),
+            ContentEncoding::Zstd => StreamDecoder::Zstd(
+                zstd::stream::write::Decoder::new(vec![]).map_err(Error::InvalidZstd)?,
+            ),
+            ContentEncoding::Snappy => StreamDecoder::Snappy(BytesMut::new()),
+        };
+        Ok(Self {
+            decoder,
+            pending: Bytes::new(),
+            decoded: BytesMut::new(),
+            max_slice_bytes,
+            finished: false,
+        })
+    }
+
+    /// Push the next chunk of the body, which must only be done once all slices have been taken
+    /// from the previous chunk
+    fn push(&mut self, chunk: Bytes) -> Result<()> {
+        debug_assert!(self.pending.is_empty());
+        if let StreamDecoder::Snappy(compressed) = &mut self.decoder {
+            if compressed.len() + chunk.len() > self.max_slice_bytes {
+                return Err(Error::RequestSizeExceeded(self.max_slice_bytes));
+            }
+            compressed.extend_from_slice(&chunk);
+        } else {
+            self.pending = chunk;
+        }
+        Ok(())
+    }
+
+    /// Mark the end of the body, after which the remaining decoded data can be taken, even if
+    /// it does not end with a newline
+    fn finish(&mut self) -> Result<()> {
+        debug_assert!(self.pending.is_empty());
+        self.finished = true;
+        match &mut self.decoder {
+            StreamDecoder::Identity => (),
+            StreamDecoder::Gzip(decoder) => decoder.try_finish().map_err(Error::InvalidGzip)?,
+            StreamDecoder::Zstd(decoder) => decoder.flush().map_err(Error::InvalidZstd)?,
+            StreamDecoder::Snappy(compressed) => {
+                let len =
+                    snap::raw::decompress_len(&compressed[..]).map_err(Error::InvalidSnappy)?;
+                if len > self.max_slice_bytes {
+                    return Err(Error::RequestSizeExceeded(self.max_slice_bytes));
+                }
+                let decoded = snap::raw::Decoder::new()
+                    .decompress_vec(&compressed[..])
+                    .map_err(Error::InvalidSnappy)?;
+                self.decoded.extend_from_slice(&decoded);
+            }
+        }
+        self.take_decoder_output();
+        Ok(())
+    }
+
+    /// Take the next slice of complete lines, if enough of the body has been decoded to fill
+    /// one, or the rest of the body once it is finished
+    fn next_slice(&mut self) -> Result<Option<Bytes>> {
+        loop {
+            if self.decoded.len() >= self.max_slice_bytes {
+                // the slice ends with the last complete line that fits within its maximum size:
+                let slice_end = self.decoded[..self.max_slice_bytes]
+                    .iter()
+                    .rposition(|b| *b == b'\n')
+                    .ok_or(Error::RequestSizeExceeded(self.max_slice_bytes))?;
+                return Ok(Some(self.decoded.split_to(slice_end + 1).freeze()));
+            }
+            if self.pending.is_empty() {
+                break;
+            }
+            self.decode_step()?;
+        }
+        if self.finished && !self.decoded.is_empty() {
+            return Ok(Some(self.decoded.split().freeze()));
+        }
+        Ok(None)
+    }
+
+    /// Feed pending input to the decoder with a single write, which bounds the amount of
+    /// data decoded at once by the size of the decoder's output buffer
+    fn decode_step(&mut self) -> Result<()> {
+        let consumed = match &mut self.decoder {
+            StreamDecoder::Identity => {
+                self.decoded.extend_from_slice(&self.pending);
+                self.pending.len()
+            }
+            StreamDecoder::Gzip(decoder) => {
+                decoder.write(&self.pending).map_err(Error::InvalidGzip)?
+            }
+            StreamDecoder::Zstd(decoder) => {
+                decoder.write(&self.pending).map_err(Error::InvalidZstd)?
+            }
+            StreamDecoder::Snappy(_) => unreachable!("snappy bodies are decoded when finished"),
+        };
+        if consumed == 0 {
+            // the decoder has reached the end of the compressed stream, but there is more input:
+            let e = std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "unexpected data after the end of the compressed stream",
+            );
+            return Err(match self.decoder {
+                StreamDecoder::Zstd(_) => Error::InvalidZstd(e),
+                _ => Error::InvalidGzip(e),
+            });
+        }
+        self.pending.advance(consumed);
+        self.take_decoder_output();
+        Ok(())
+    }
+
+    /// Move the output of a decoder into the decoded line protocol
+    fn take_decoder_output(&mut self) {
+        let output = match &mut self.decoder {
+            StreamDecoder::Gzip(decoder) => decoder.get_mut(),
+            StreamDecoder::Zstd(decoder) => decoder.get_mut(),
+            StreamDecoder::Identity | StreamDecoder::Snappy(_) => return,
+        };
+        self.decoded.extend_from_slice(output);
+        output.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slices(mut slicer: LineSlicer, body: &[u8], chunk_size: usize) -> Result<Vec<String>> {
+        let mut slices = vec![];
+        let mut take_slices = |slicer: &mut LineSlicer| -> Result<()> {
+            while let Some(slice) = slicer.next_slice()? {
+                slices.push(String::from_utf8(slice.to_vec()).unwrap());
+            }
+            Ok(())
+        };
+        for chunk in body.chunks(chunk_size) {
+            slicer.push(Bytes::copy_from_slice(chunk))?;
+            take_slices(&mut slicer)?;
+        }
+        slicer.finish()?;
+        take_slices(&mut slicer)?;
+        Ok(slices)
+    }
+
+    const LP: &str = "cpu,host=a usage=0.5 1\n\
+        cpu,host=b usage=0.6 2\n\
+        cpu,host=c usage=0.7 3";
+
+    #[test]
+    fn slices_end_with_complete_lines() {
+        let expected = [
+            "cpu,host=a usage=0.5 1\ncpu,host=b usage=0.6 2\n",
+            "cpu,host=c usage=0.7 3",
+        ];
+
+        let identity = LineSlicer::new(ContentEncoding::Identity, 50).unwrap();
+        assert_eq!(slices(identity, LP.as_bytes(), 7).unwrap(), expected);
+
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gzip.write_all(LP.as_bytes()).unwrap();
+        let gzip_body = gzip.finish().unwrap();
+        let slicer = LineSlicer::new(ContentEncoding::Gzip, 50).unwrap();
+        assert_eq!(slices(slicer, &gzip_body, 7).unwrap(), expected);
+
+        let zstd_body = zstd::encode_all(LP.as_bytes(), 0).unwrap();
+        let slicer = LineSlicer::new(ContentEncoding::Zstd, 50).unwrap();
+        assert_eq!(slices(slicer, &zstd_body, 7).unwrap(), expected);
+
+        let snappy_body = snap::raw::Encoder::new()
+            .compress_vec(LP.as_bytes())
+            .unwrap();
+        // snappy bodies must fit within a single slice:
+        let slicer = LineSlicer::new(ContentEncoding::Snappy, 50).unwrap();
+        assert!(matches!(
+            slices(slicer, &snappy_body, 7),
+            Err(Error::RequestSizeExceeded(50))
+        ));
+        let slicer = LineSlicer::new(ContentEncoding::Snappy, 100).unwrap();
+        assert_eq!(slices(slicer, &snappy_body, 7).unwrap(), [LP]);
+    }
+
+    #[test]
+    fn line_longer_than_slice() {
+        let slicer = LineSlicer::new(ContentEncoding::Identity, 10).unwrap();
+        assert!(matches!(
+            slices(slicer, LP.as_bytes(), 64),
+            Err(Error::RequestSizeExceeded(10))
+        ));
+    }
+
+    #[test]
+    fn invalid_compressed_body() {
+        let slicer = LineSlicer::new(ContentEncoding::Gzip, 50).unwrap();
+        assert!(matches!(
+            slices(slicer, LP.as_bytes(), 64),
+            Err(Error::InvalidGzip(_))
+        ));
+    }
+}
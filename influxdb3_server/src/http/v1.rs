@@ -20,7 +20,7 @@ use arrow::{
 use bytes::Bytes;
 use chrono::{format::SecondsFormat, DateTime};
 use datafusion::physical_plan::SendableRecordBatchStream;
-use futures::{ready, stream::Fuse, Stream, StreamExt};
+use futures::{ready, stream::Fuse, Stream, StreamExt, TryStreamExt};
 use hyper::http::HeaderValue;
 use hyper::{header::ACCEPT, header::CONTENT_TYPE, Body, Request, Response, StatusCode};
 use iox_time::TimeProvider;
@@ -31,7 +31,10 @@ use serde_json::Value;
 
 use crate::QueryExecutor;
 
-use super::{Error, HttpApi, Result};
+use super::{
+    compression::{compress_response, ResponseEncoding},
+    request_token, Error, HttpApi, InfluxqlStatement, Result,
+};
 
 const DEFAULT_CHUNK_SIZE: usize = 10_000;
 
@@ -48,9 +51,15 @@ where
     /// response stream will be chunked into chunks of size `chunk_size`, if provided,
     /// or 10,000. For InfluxQL queries that select from multiple measurements, chunks
     /// will be split on the `chunk_size`, or series, whichever comes first.
+    ///
+    /// The query may contain multiple `;`-separated statements, which are run one after
+    /// another, each producing the results with its own `statement_id`. As in InfluxDB 1.x,
+    /// if a statement fails, the error is returned as its result, and the statements after it
+    /// are not run. Only errors parsing or authorizing the query fail the request.
     pub(super) async fn v1_query(&self, req: Request<Body>) -> Result<Response<Body>> {
         let params = QueryParams::from_request(&req)?;
         info!(?params, "handle v1 query API");
+        let encoding = ResponseEncoding::from_accept_encoding(req.headers());
         let QueryParams {
             chunk_size,
             chunked,
@@ -65,18 +74,145 @@ where
 
         let chunk_size = chunked.then(|| chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE));
 
-        // TODO - Currently not supporting parameterized queries, see
-        //        https://github.com/influxdata/influxdb/issues/24805
-        let stream = self.query_influxql_inner(database, &query, None).await?;
-        let stream =
-            QueryResponseStream::new(0, stream, chunk_size, format, epoch).map_err(QueryError)?;
-        let body = Body::wrap_stream(stream);
+        // all statements are authorized before any of them are run:
+        let statements: VecDeque<_> = self
+            .authorize_influxql_statements(request_token(&req), database, &query)
+            .await?
+            .into_iter()
+            .enumerate()
+            .collect();
+        if statements.is_empty() {
+            return Err(Error::InfluxqlNoStatements);
+        }
 
-        Ok(Response::builder()
+        let runner = StatementRunner {
+            query_executor: Arc::clone(&self.query_executor),
+            current: None,
+            remaining: statements,
+            chunk_size,
+            format,
+            epoch,
+        };
+        let responses = futures::stream::unfold(runner, |mut runner| async move {
+            let response = runner.next_response().await?;
+            Some((response, runner))
+        });
+
+        let body = if chunk_size.is_some() {
+            Body::wrap_stream(responses)
+        } else {
+            // when not chunked, the results of all statements are returned in one response:
+            Body::wrap_stream(futures::stream::once(responses.try_fold(
+                QueryResponse {
+                    results: vec![],
+                    format,
+                },
+                |mut all, response| async move {
+                    all.results.extend(response.results);
+                    Ok(all)
+                },
+            )))
+        };
+
+        let response = Response::builder()
             .status(StatusCode::OK)
             .header(CONTENT_TYPE, format.as_content_type())
             .body(body)
-            .unwrap())
+            .unwrap();
+        compress_response(response, encoding).map_err(Error::CompressResponse)
+    }
+}
+
+/// Runs the statements of a v1 query one after another, producing the [`QueryResponse`]s
+/// for each of them
+struct StatementRunner<Q> {
+    query_executor: Arc<Q>,
+    /// The results of the statement being run, and whether they have produced a response yet
+    current: Option<(QueryResponseStream, bool)>,
+    /// The statements yet to be run, with their `statement_id`s
+    remaining: VecDeque<(usize, InfluxqlStatement)>,
+    chunk_size: Option<usize>,
+    format: QueryFormat,
+    epoch: Option<Precision>,
+}
+
+impl<Q> StatementRunner<Q>
+where
+    Q: QueryExecutor,
+    Error: From<<Q as QueryExecutor>::Error>,
+{
+    /// Produce the next response, running the next statement once the current one has
+    /// finished, or `None` once all statements have been run
+    async fn next_response(&mut self) -> Option<Result<QueryResponse, anyhow::Error>> {
+        loop {
+            if let Some((stream, responded)) = &mut self.current {
+                match stream.next().await {
+                    Some(Ok(response)) => {
+                        *responded = true;
+                        return Some(Ok(response));
+                    }
+                    Some(Err(e)) => {
+                        self.stop();
+                        return Some(Err(e));
+                    }
+                    None => {
+                        let statement_id = stream.statement_id;
+                        let responded = *responded;
+                        self.current = None;
+                        if !responded {
+                            // a statement that produces no results still has a result, with no
+                            // series, as in InfluxDB 1.x:
+                            return Some(Ok(self.response(StatementResponse {
+                                statement_id,
+                                series: vec![],
+                                error: None,
+                            })));
+                        }
+                    }
+                }
+            }
+
+            let (statement_id, statement) = self.remaining.pop_front()?;
+            // TODO - Currently not supporting parameterized queries, see
+            //        https://github.com/influxdata/influxdb/issues/24805
+            let stream = match statement.run(self.query_executor.as_ref(), None).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    self.stop();
+                    return Some(Ok(self.response(StatementResponse {
+                        statement_id,
+                        series: vec![],
+                        error: Some(Error::from(e).to_string()),
+                    })));
+                }
+            };
+            match QueryResponseStream::new(
+                statement_id,
+                stream,
+                self.chunk_size,
+                self.format,
+                self.epoch,
+            ) {
+                Ok(stream) => self.current = Some((stream, false)),
+                Err(e) => {
+                    self.stop();
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+
+    /// Stop running statements, once one has failed
+    fn stop(&mut self) {
+        self.current = None;
+        self.remaining.clear();
+    }
+
+    fn response(&self, statement: StatementResponse) -> QueryResponse {
+        QueryResponse {
+            results: vec![statement],
+            format: self.format,
+        }
     }
 }
 
@@ -97,7 +233,6 @@ struct QueryParams {
     #[serde(rename = "db")]
     database: Option<String>,
     /// Map timestamps to UNIX epoch time, with the given precision
-    #[allow(dead_code)]
     epoch: Option<Precision>,
     /// Format the JSON outputted in pretty format
     #[serde(default)]
@@ -183,7 +318,7 @@ impl QueryFormat {
 /// UNIX epoch precision
 #[derive(Debug, Deserialize, Clone, Copy)]
 enum Precision {
-    #[serde(rename = "ns")]
+    #[serde(rename = "ns", alias = "n")]
     Nanoseconds,
     #[serde(rename = "u", alias = "µ")]
     Microseconds,
@@ -236,30 +371,48 @@ impl From<QueryResponse> for Bytes {
 
         /// Convert a [`QueryResponse`] to a CSV byte vector.
         ///
-        /// This function serializes the `QueryResponse` to CSV format. It dynamically
-        /// extracts column names from the first series and writes the header and data
-        /// rows to the CSV writer.
+        /// This function serializes the `QueryResponse` to CSV format. For each statement,
+        /// it dynamically extracts column names from the first series and writes the header
+        /// and data rows to the CSV writer. As in InfluxDB 1.x, the results of each statement
+        /// are separated by an empty line, statements with no results are skipped, and failed
+        /// statements are written as an `error` column.
         fn to_csv(s: QueryResponse) -> Vec<u8> {
             let mut wtr = csv::WriterBuilder::new()
                 .quote_style(csv::QuoteStyle::Never)
+                .flexible(true)
                 .from_writer(vec![]);
-            // Extract column names dynamically from the first series
-            let mut headers = vec!["name", "tags"];
-            if let Some(first_statement) = s.results.first() {
-                if let Some(first_series) = first_statement.series.first() {
+            let mut first = true;
+            for statement in s.results {
+                if statement.series.is_empty() && statement.error.is_none() {
+                    continue;
+                }
+                if !first {
+                    wtr.flush().expect("flush csv writer");
+                    wtr.get_mut().push(b'\n');
+                }
+                first = false;
+
+                if let Some(error) = statement.error {
+                    wtr.write_record(["error"])
+                        .expect("Failed to write CSV header");
+                    wtr.write_record([error])
+                        .expect("Failed to write CSV record");
+                    continue;
+                }
+
+                // Extract column names dynamically from the first series
+                let mut headers = vec!["name", "tags"];
+                if let Some(first_series) = statement.series.first() {
                     headers.extend(first_series.columns.iter().map(|s| s.as_str()));
                 }
-            }
-            // Write the header
-            wtr.write_record(&headers)
-                .expect("Failed to write CSV header");
-
-            // Iterate through the hierarchical structure of QueryResponse to write data
-            // to the CSV writer. The loop processes each statement, series, and row to
-            // build and write CSV records. Each record is initialized with the series name
-            // and an empty tag field, followed by the string representations of the row's values.
-            // Finally, the record is written to the CSV writer
-            for statement in s.results {
+                // Write the header
+                wtr.write_record(&headers)
+                    .expect("Failed to write CSV header");
+
+                // Iterate through the series and rows of the statement to write data to the
+                // CSV writer. Each record is initialized with the series name and an empty tag
+                // field, followed by the string representations of the row's values. Finally,
+                // the record is written to the CSV writer
                 for series in statement.series {
                     for row in series.values {
                         let mut record = vec![series.name.clone(), "".to_string()];
@@ -296,11 +449,17 @@ impl From<QueryResponse> for Bytes {
     }
 }
 
-/// The response to an individual InfluxQL query
+/// The response to an individual InfluxQL statement
+///
+/// As in InfluxDB 1.x, `series` is left out for statements that produce no results, and
+/// `error` is only given for statements that failed.
 #[derive(Debug, Serialize)]
 struct StatementResponse {
     statement_id: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     series: Vec<Series>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
 }
 
 /// The records produced for a single time series (measurement)
@@ -545,6 +704,7 @@ impl QueryResponseStream {
             results: vec![StatementResponse {
                 statement_id: self.statement_id,
                 series,
+                error: None,
             }],
             format: self.format,
         }
@@ -567,6 +727,7 @@ impl QueryResponseStream {
             results: vec![StatementResponse {
                 statement_id: self.statement_id,
                 series,
+                error: None,
             }],
             format: self.format,
         })
@@ -22,6 +22,7 @@ mod system_tables;
 use crate::grpc::make_flight_server;
 use crate::http::route_request;
 use crate::http::HttpApi;
+use crate::query_executor::{ExplainAnalyze, QueryLimits, RunningQuery};
 use async_trait::async_trait;
 use authz::Authorizer;
 use datafusion::execution::SendableRecordBatchStream;
@@ -141,6 +142,18 @@ pub trait QueryExecutor: QueryDatabase + Debug + Send + Sync + 'static {
         external_span_ctx: Option<RequestLogContext>,
     ) -> Result<SendableRecordBatchStream, Self::Error>;
 
+    /// Run a query to completion, discarding its results, and produce the plan that was
+    /// executed, with the metrics from its execution, as for `EXPLAIN ANALYZE`
+    async fn explain_analyze(
+        &self,
+        database: &str,
+        q: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+        span_ctx: Option<SpanContext>,
+        external_span_ctx: Option<RequestLogContext>,
+    ) -> Result<ExplainAnalyze, Self::Error>;
+
     fn show_databases(&self) -> Result<SendableRecordBatchStream, Self::Error>;
 
     async fn show_retention_policies(
@@ -148,6 +161,12 @@ pub trait QueryExecutor: QueryDatabase + Debug + Send + Sync + 'static {
         database: Option<&str>,
         span_ctx: Option<SpanContext>,
     ) -> Result<SendableRecordBatchStream, Self::Error>;
+
+    /// The limits on concurrent queries and their run time, which also apply to Flight queries
+    fn query_limits(&self) -> QueryLimits;
+
+    /// Get the running query with the given ID in the query log, so that it can be killed
+    fn running_query(&self, id: &str) -> Option<RunningQuery>;
 }
 
 #[derive(Debug)]
@@ -181,6 +200,8 @@ where
     let grpc_service = trace_layer.clone().layer(make_flight_server(
         Arc::clone(&server.http.query_executor),
         Some(server.authorizer()),
+        server.http.query_executor.query_limits(),
+        server.http.write_buffer.catalog(),
     ));
 
     let rest_service = hyper::service::make_service_fn(|_| {
@@ -234,6 +255,7 @@ mod tests {
     use crate::serve;
     use datafusion::parquet::data_type::AsBytes;
     use hyper::{body, Body, Client, Request, Response, StatusCode};
+    use influxdb3_cache::meta_cache::MetaCacheProvider;
     use influxdb3_catalog::catalog::Catalog;
     use influxdb3_id::{DbId, TableId};
     use influxdb3_telemetry::store::TelemetryStore;
@@ -780,11 +802,18 @@ mod tests {
             influxdb3_write::write_buffer::WriteBufferImpl::new(
                 Arc::clone(&persister),
                 Arc::clone(&catalog),
-                LastCacheProvider::new_from_catalog(catalog as _).unwrap(),
+                LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap(),
+                MetaCacheProvider::new_from_catalog(
+                    Arc::<MockProvider>::clone(&time_provider),
+                    catalog,
+                )
+                .unwrap(),
                 Arc::<MockProvider>::clone(&time_provider),
                 Arc::clone(&exec),
                 WalConfig::test_config(),
+                None,
                 Some(parquet_cache),
+                Arc::clone(&metrics),
             )
             .await
             .unwrap(),
@@ -809,8 +838,12 @@ mod tests {
             metrics: Arc::clone(&metrics),
             datafusion_config: Default::default(),
             concurrent_query_limit: 10,
+            query_limit_behavior: Default::default(),
+            query_timeout: None,
             query_log_size: 10,
+            slow_query_log: None,
             telemetry_store: Arc::clone(&sample_telem_store),
+            time_provider: Arc::<MockProvider>::clone(&time_provider),
         });
 
         // bind to port 0 will assign a random available port:
@@ -12,12 +12,15 @@ use datafusion::common::arrow::array::StringArray;
 use datafusion::common::arrow::datatypes::{DataType, Field, Schema as DatafusionSchema};
 use datafusion::datasource::{TableProvider, TableType};
 use datafusion::error::DataFusionError;
-use datafusion::execution::SendableRecordBatchStream;
+use datafusion::execution::{RecordBatchStream, SendableRecordBatchStream};
 use datafusion::logical_expr::TableProviderFilterPushDown;
 use datafusion::physical_plan::ExecutionPlan;
-use datafusion::prelude::Expr;
+use datafusion::prelude::{col, lit, Expr};
+use datafusion::scalar::ScalarValue;
 use datafusion_util::config::DEFAULT_SCHEMA;
 use datafusion_util::MemoryStream;
+use futures::{ready, FutureExt, Stream, StreamExt};
+use influxdb3_cache::meta_cache::MetaCacheFunction;
 use influxdb3_catalog::catalog::{Catalog, DatabaseSchema};
 use influxdb3_telemetry::store::TelemetryStore;
 use influxdb3_write::last_cache::LastCacheFunction;
@@ -27,19 +30,27 @@ use iox_query::frontend::sql::SqlQueryPlanner;
 use iox_query::provider::ProviderBuilder;
 use iox_query::query_log::QueryLog;
 use iox_query::query_log::QueryText;
+use iox_query::query_log::StatePermit;
 use iox_query::query_log::StateReceived;
 use iox_query::query_log::{QueryCompletedToken, QueryLogEntries};
 use iox_query::QueryDatabase;
 use iox_query::{QueryChunk, QueryNamespace};
 use iox_query_influxql::frontend::planner::InfluxQLQueryPlanner;
 use iox_query_params::StatementParams;
+use iox_time::TimeProvider;
 use metric::Registry;
 use observability_deps::tracing::{debug, info};
-use schema::Schema;
+use schema::{Schema, TIME_COLUMN_NAME};
 use std::any::Any;
 use std::collections::HashMap;
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
+use std::pin::Pin;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{Instant, Sleep};
+use tokio_util::sync::WaitForCancellationFutureOwned;
 use trace::ctx::SpanContext;
 use trace::span::{Span, SpanExt, SpanRecorder};
 use trace_http::ctx::RequestLogContext;
@@ -47,15 +58,28 @@ use tracker::{
     AsyncSemaphoreMetrics, InstrumentedAsyncOwnedSemaphorePermit, InstrumentedAsyncSemaphore,
 };
 
+mod explain;
+mod running_queries;
+mod slow_query_log;
+
+pub use explain::{ExplainAnalyze, PlanNode};
+use running_queries::RunningQueries;
+pub use running_queries::RunningQuery;
+pub(crate) use running_queries::{CurrentQuery, RunningQueryGuard, CURRENT_QUERY};
+use slow_query_log::SlowQueryLog;
+pub use slow_query_log::{SlowQueryLogConfig, SlowQuerySink, SLOW_QUERIES_TABLE_NAME};
+
 #[derive(Debug)]
 pub struct QueryExecutorImpl {
     catalog: Arc<Catalog>,
     write_buffer: Arc<dyn WriteBuffer>,
     exec: Arc<Executor>,
     datafusion_config: Arc<HashMap<String, String>>,
-    query_execution_semaphore: Arc<InstrumentedAsyncSemaphore>,
+    query_limits: QueryLimits,
     query_log: Arc<QueryLog>,
+    running_queries: Arc<RunningQueries>,
     telemetry_store: Arc<TelemetryStore>,
+    time_provider: Arc<dyn TimeProvider>,
 }
 
 /// Arguments for [`QueryExecutorImpl::new`]
@@ -67,8 +91,12 @@ pub struct CreateQueryExecutorArgs {
     pub metrics: Arc<Registry>,
     pub datafusion_config: Arc<HashMap<String, String>>,
     pub concurrent_query_limit: usize,
+    pub query_limit_behavior: QueryLimitBehavior,
+    pub query_timeout: Option<Duration>,
     pub query_log_size: usize,
+    pub slow_query_log: Option<SlowQueryLogConfig>,
     pub telemetry_store: Arc<TelemetryStore>,
+    pub time_provider: Arc<dyn TimeProvider>,
 }
 
 impl QueryExecutorImpl {
@@ -80,37 +108,48 @@ impl QueryExecutorImpl {
             metrics,
             datafusion_config,
             concurrent_query_limit,
+            query_limit_behavior,
+            query_timeout,
             query_log_size,
+            slow_query_log,
             telemetry_store,
+            time_provider,
         }: CreateQueryExecutorArgs,
     ) -> Self {
         let semaphore_metrics = Arc::new(AsyncSemaphoreMetrics::new(
             &metrics,
             &[("semaphore", "query_execution")],
         ));
-        let query_execution_semaphore =
-            Arc::new(semaphore_metrics.new_semaphore(concurrent_query_limit));
-        let query_log = Arc::new(QueryLog::new(
-            query_log_size,
-            Arc::new(iox_time::SystemProvider::new()),
-        ));
+        let query_limits = QueryLimits {
+            semaphore: Arc::new(semaphore_metrics.new_semaphore(concurrent_query_limit)),
+            limit: concurrent_query_limit,
+            behavior: query_limit_behavior,
+            timeout: query_timeout,
+        };
+        let query_log = Arc::new(QueryLog::new(query_log_size, Arc::clone(&time_provider)));
+        let slow_query_log = slow_query_log.map(|config| {
+            SlowQueryLog::new(
+                config,
+                Arc::clone(&write_buffer),
+                Arc::clone(&time_provider),
+            )
+        });
         Self {
             catalog,
             write_buffer,
             exec,
             datafusion_config,
-            query_execution_semaphore,
+            query_limits,
             query_log,
+            running_queries: Arc::new(RunningQueries::new(slow_query_log)),
             telemetry_store,
+            time_provider,
         }
     }
-}
-
-#[async_trait]
-impl QueryExecutor for QueryExecutorImpl {
-    type Error = Error;
 
-    async fn query(
+    /// Plan a query, and start executing it, once it has a permit to run, producing the plan
+    /// being executed, and the stream of its results
+    async fn execute_query(
         &self,
         database: &str,
         query: &str,
@@ -118,8 +157,8 @@ impl QueryExecutor for QueryExecutorImpl {
         kind: QueryKind,
         span_ctx: Option<SpanContext>,
         external_span_ctx: Option<RequestLogContext>,
-    ) -> Result<SendableRecordBatchStream, Self::Error> {
-        info!(%database, %query, ?params, ?kind, "QueryExecutorImpl as QueryExecutor::query");
+    ) -> Result<(Arc<dyn ExecutionPlan>, QueryStream), Error> {
+        let deadline = self.query_limits.deadline();
         let db = self
             .namespace(database, span_ctx.child_span("get database"), false)
             .await
@@ -130,6 +169,7 @@ impl QueryExecutor for QueryExecutorImpl {
                 db_name: database.to_string(),
             })?;
 
+        let permit_span = span_ctx.child_span("query rate limit semaphore");
         // TODO - configure query here?
         let ctx = db.new_query_context(span_ctx, Default::default());
 
@@ -146,12 +186,16 @@ impl QueryExecutor for QueryExecutorImpl {
                 "influxql",
             ),
         };
-        let token = db.record_query(
-            external_span_ctx.as_ref().map(RequestLogContext::ctx),
-            query_type,
-            Box::new(query.to_string()),
-            params,
-        );
+        let current_query = CurrentQuery::inherit();
+        let token = CURRENT_QUERY.sync_scope(current_query.clone(), || {
+            db.record_query(
+                external_span_ctx.as_ref().map(RequestLogContext::ctx),
+                query_type,
+                Box::new(query.to_string()),
+                params,
+            )
+        });
+        let mut running_query = current_query.take_guard();
         let plan = match plan.map_err(Error::QueryPlanning) {
             Ok(plan) => plan,
             Err(e) => {
@@ -161,23 +205,100 @@ impl QueryExecutor for QueryExecutorImpl {
         };
         let token = token.planned(&ctx, Arc::clone(&plan));
 
-        // TODO: Enforce concurrency limit here
+        let permit = tokio::select! {
+            permit = self.query_limits.acquire(deadline, permit_span) => permit,
+            _ = current_query.cancel.cancelled() => {
+                // the token is dropped without completing it, which marks the query as cancelled
+                drop(token);
+                return Err(Error::QueryCancelled);
+            }
+        };
+        let permit = match permit {
+            Ok(permit) => permit,
+            Err(e) => {
+                token.fail();
+                return Err(e);
+            }
+        };
         let token = token.permit();
 
         debug!("execute stream of query results");
         self.telemetry_store.update_num_queries();
 
+        if let Some(running_query) = &mut running_query {
+            running_query.executing(Arc::clone(&plan));
+        }
         match ctx.execute_stream(Arc::clone(&plan)).await {
-            Ok(query_results) => {
-                token.success();
-                Ok(query_results)
-            }
+            Ok(query_results) => Ok((
+                plan,
+                QueryStream {
+                    schema: query_results.schema(),
+                    inner: Some(query_results),
+                    token: Some(token),
+                    permit: Some(permit),
+                    deadline: deadline.map(|(deadline, timeout)| {
+                        (Box::pin(tokio::time::sleep_until(deadline)), timeout)
+                    }),
+                    cancelled: Box::pin(current_query.cancel.cancelled_owned()),
+                    running_query,
+                },
+            )),
             Err(err) => {
                 token.fail();
                 Err(Error::ExecuteStream(err))
             }
         }
     }
+}
+
+#[async_trait]
+impl QueryExecutor for QueryExecutorImpl {
+    type Error = Error;
+
+    async fn query(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+        span_ctx: Option<SpanContext>,
+        external_span_ctx: Option<RequestLogContext>,
+    ) -> Result<SendableRecordBatchStream, Self::Error> {
+        info!(%database, %query, ?params, ?kind, "QueryExecutorImpl as QueryExecutor::query");
+        let (_, stream) = self
+            .execute_query(database, query, params, kind, span_ctx, external_span_ctx)
+            .await?;
+        Ok(Box::pin(stream))
+    }
+
+    async fn explain_analyze(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+        span_ctx: Option<SpanContext>,
+        external_span_ctx: Option<RequestLogContext>,
+    ) -> Result<ExplainAnalyze, Self::Error> {
+        info!(%database, %query, ?params, ?kind, "QueryExecutorImpl as QueryExecutor::explain_analyze");
+        let start = Instant::now();
+        let (plan, mut stream) = self
+            .execute_query(database, query, params, kind, span_ctx, external_span_ctx)
+            .await?;
+        let mut rows = 0;
+        while let Some(batch) = stream.next().await {
+            rows += batch.map_err(Error::ExecuteStream)?.num_rows();
+        }
+        Ok(ExplainAnalyze::new(plan.as_ref(), rows, start.elapsed()))
+    }
+
+    fn query_limits(&self) -> QueryLimits {
+        self.query_limits.clone()
+    }
+
+    fn running_query(&self, id: &str) -> Option<RunningQuery> {
+        self.running_queries.get(id)
+    }
 
     fn show_databases(&self) -> Result<SendableRecordBatchStream, Self::Error> {
         let mut databases = self.catalog.db_names();
@@ -204,18 +325,18 @@ impl QueryExecutor for QueryExecutorImpl {
         // sort them to ensure consistent order:
         databases.sort_unstable();
 
+        let _span_recorder = SpanRecorder::new(span_ctx.child_span("get databases"));
         let mut rows = Vec::with_capacity(databases.len());
         for database in databases {
-            let db = self
-                .namespace(&database, span_ctx.child_span("get database"), false)
-                .await
-                .map_err(|_| Error::DatabaseNotFound {
-                    db_name: database.to_string(),
-                })?
-                .ok_or_else(|| Error::DatabaseNotFound {
-                    db_name: database.to_string(),
-                })?;
-            let duration = db.retention_time_ns();
+            let db_schema =
+                self.catalog
+                    .db_schema(&database)
+                    .ok_or_else(|| Error::DatabaseNotFound {
+                        db_name: database.to_string(),
+                    })?;
+            let duration = db_schema
+                .retention_period
+                .map(|period| i64::try_from(period.as_nanos()).unwrap_or(i64::MAX));
             let (db_name, rp_name) = split_database_name(&database);
             rows.push(RetentionPolicyRow {
                 database: db_name,
@@ -304,6 +425,210 @@ pub enum Error {
     DatabasesToRecordBatch(#[source] ArrowError),
     #[error("unable to compose record batches from retention policies: {0}")]
     RetentionPoliciesToRecordBatch(#[source] ArrowError),
+    #[error("the limit of {limit} concurrent queries has been reached")]
+    QueryLimitReached { limit: usize },
+    #[error(
+        "timed out after {timeout:?} waiting for one of {limit} concurrent queries to complete"
+    )]
+    QueryQueueTimeout { limit: usize, timeout: Duration },
+    #[error("query exceeded its timeout of {timeout:?}")]
+    QueryTimeout { timeout: Duration },
+    #[error("query was cancelled")]
+    QueryCancelled,
+}
+
+impl Error {
+    /// How long a client should wait before retrying a query that failed with this error, if
+    /// it should be retried at all
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::QueryLimitReached { .. } | Self::QueryQueueTimeout { .. } => {
+                Some(QUERY_RETRY_AFTER)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// How long clients are asked to wait before retrying a query that could not run because the
+/// concurrent query limit had been reached
+const QUERY_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// How queries that arrive once the concurrent query limit has been reached are handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueryLimitBehavior {
+    /// Wait for a running query to complete, for up to the query timeout, if one is set
+    #[default]
+    Queue,
+    /// Fail the query immediately
+    Reject,
+}
+
+impl FromStr for QueryLimitBehavior {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "queue" => Ok(Self::Queue),
+            "reject" => Ok(Self::Reject),
+            _ => Err(format!(
+                "invalid query limit behavior '{s}', expected one of queue or reject"
+            )),
+        }
+    }
+}
+
+impl Display for QueryLimitBehavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Queue => write!(f, "queue"),
+            Self::Reject => write!(f, "reject"),
+        }
+    }
+}
+
+/// The limits on the queries run by the server, which are shared by the HTTP and Flight APIs
+#[derive(Debug, Clone)]
+pub struct QueryLimits {
+    semaphore: Arc<InstrumentedAsyncSemaphore>,
+    limit: usize,
+    behavior: QueryLimitBehavior,
+    timeout: Option<Duration>,
+}
+
+impl QueryLimits {
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Produce the error for a query that was rejected because the limit has been reached, if
+    /// queries are being rejected and the limit has been reached
+    ///
+    /// This is only a check: the query must still acquire a permit before it runs, which it
+    /// may have to wait for if other queries take the remaining permits in the meantime.
+    pub fn check_rejected(&self) -> Result<(), Error> {
+        if self.behavior == QueryLimitBehavior::Reject && self.semaphore.available_permits() == 0 {
+            return Err(Error::QueryLimitReached { limit: self.limit });
+        }
+        Ok(())
+    }
+
+    /// The deadline of a query received now, along with the timeout it was derived from
+    fn deadline(&self) -> Option<(Instant, Duration)> {
+        self.timeout
+            .map(|timeout| (Instant::now() + timeout, timeout))
+    }
+
+    /// Acquire a permit to run a query, which must be held until its results are complete
+    async fn acquire(
+        &self,
+        deadline: Option<(Instant, Duration)>,
+        span: Option<Span>,
+    ) -> Result<InstrumentedAsyncOwnedSemaphorePermit, Error> {
+        let acquire = Arc::clone(&self.semaphore).acquire_owned(span);
+        let permit = match (self.behavior, deadline) {
+            (QueryLimitBehavior::Reject, _) => acquire
+                .now_or_never()
+                .ok_or(Error::QueryLimitReached { limit: self.limit })?,
+            (QueryLimitBehavior::Queue, Some((deadline, timeout))) => {
+                tokio::time::timeout_at(deadline, acquire)
+                    .await
+                    .map_err(|_| Error::QueryQueueTimeout {
+                        limit: self.limit,
+                        timeout,
+                    })?
+            }
+            (QueryLimitBehavior::Queue, None) => acquire.await,
+        };
+        Ok(permit.expect("Semaphore should not be closed by anyone"))
+    }
+}
+
+/// The results of a query, which hold its permit to run until they are complete, and record
+/// the outcome of the query in the query log
+///
+/// If the query runs past its deadline, the underlying stream is dropped, cancelling its
+/// execution, and the query is marked as failed. If the query is killed, the stream is dropped
+/// in the same way, and the query is marked as cancelled.
+struct QueryStream {
+    schema: SchemaRef,
+    inner: Option<SendableRecordBatchStream>,
+    token: Option<QueryCompletedToken<StatePermit>>,
+    permit: Option<InstrumentedAsyncOwnedSemaphorePermit>,
+    deadline: Option<(Pin<Box<Sleep>>, Duration)>,
+    cancelled: Pin<Box<WaitForCancellationFutureOwned>>,
+    running_query: Option<RunningQueryGuard>,
+}
+
+/// How a query ended
+#[derive(Debug, Clone, Copy)]
+enum QueryOutcome {
+    Success,
+    Failure,
+    Cancelled,
+}
+
+impl QueryStream {
+    /// Release the resources held by the query, now that it has ended
+    fn complete(&mut self, outcome: QueryOutcome) {
+        self.inner = None;
+        self.permit = None;
+        self.deadline = None;
+        if let Some(token) = self.token.take() {
+            match outcome {
+                QueryOutcome::Success => token.success(),
+                QueryOutcome::Failure => token.fail(),
+                // the token is dropped without completing it, which marks the query as cancelled
+                QueryOutcome::Cancelled => drop(token),
+            }
+        }
+        // this is released once the outcome of the query is in the query log, which the slow
+        // query log reads from:
+        self.running_query = None;
+    }
+}
+
+impl Stream for QueryStream {
+    type Item = Result<RecordBatch, DataFusionError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.inner.is_some() && this.cancelled.poll_unpin(cx).is_ready() {
+            this.complete(QueryOutcome::Cancelled);
+            return Poll::Ready(Some(Err(DataFusionError::External(Box::new(
+                Error::QueryCancelled,
+            )))));
+        }
+        if let Some((sleep, timeout)) = &mut this.deadline {
+            if sleep.poll_unpin(cx).is_ready() {
+                let timeout = *timeout;
+                this.complete(QueryOutcome::Failure);
+                return Poll::Ready(Some(Err(DataFusionError::External(Box::new(
+                    Error::QueryTimeout { timeout },
+                )))));
+            }
+        }
+        let Some(inner) = this.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+        let next = ready!(inner.poll_next_unpin(cx));
+        match &next {
+            Some(Ok(batch)) => {
+                if let Some(running_query) = &mut this.running_query {
+                    running_query.returned_rows(batch.num_rows());
+                }
+            }
+            Some(Err(_)) => this.complete(QueryOutcome::Failure),
+            None => this.complete(QueryOutcome::Success),
+        }
+        Poll::Ready(next)
+    }
+}
+
+impl RecordBatchStream for QueryStream {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
 }
 
 // This implementation is for the Flight service
@@ -329,11 +654,15 @@ impl QueryDatabase for QueryExecutorImpl {
             Arc::clone(&self.exec),
             Arc::clone(&self.datafusion_config),
             Arc::clone(&self.query_log),
+            Arc::clone(&self.running_queries),
+            Arc::clone(&self.time_provider),
         ))))
     }
 
     async fn acquire_semaphore(&self, span: Option<Span>) -> InstrumentedAsyncOwnedSemaphorePermit {
-        Arc::clone(&self.query_execution_semaphore)
+        // Flight queries always queue for a permit here; rejecting them, and enforcing their
+        // timeout, is done by the Flight service, see `crate::grpc::QueryLimitService`.
+        Arc::clone(&self.query_limits.semaphore)
             .acquire_owned(span)
             .await
             .expect("Semaphore should not be closed by anyone")
@@ -351,7 +680,9 @@ pub struct Database {
     exec: Arc<Executor>,
     datafusion_config: Arc<HashMap<String, String>>,
     query_log: Arc<QueryLog>,
+    running_queries: Arc<RunningQueries>,
     system_schema_provider: Arc<SystemSchemaProvider>,
+    time_provider: Arc<dyn TimeProvider>,
 }
 
 impl Database {
@@ -361,6 +692,8 @@ impl Database {
         exec: Arc<Executor>,
         datafusion_config: Arc<HashMap<String, String>>,
         query_log: Arc<QueryLog>,
+        running_queries: Arc<RunningQueries>,
+        time_provider: Arc<dyn TimeProvider>,
     ) -> Self {
         let system_schema_provider = Arc::new(SystemSchemaProvider::new(
             Arc::clone(&db_schema),
@@ -373,7 +706,9 @@ impl Database {
             exec,
             datafusion_config,
             query_log,
+            running_queries,
             system_schema_provider,
+            time_provider,
         }
     }
 
@@ -384,7 +719,9 @@ impl Database {
             exec: Arc::clone(&db.exec),
             datafusion_config: Arc::clone(&db.datafusion_config),
             query_log: Arc::clone(&db.query_log),
+            running_queries: Arc::clone(&db.running_queries),
             system_schema_provider: Arc::clone(&db.system_schema_provider),
+            time_provider: Arc::clone(&db.time_provider),
         }
     }
 
@@ -398,6 +735,7 @@ impl Database {
                     table_name,
                     schema: schema.clone(),
                     write_buffer: Arc::clone(&self.write_buffer),
+                    time_provider: Arc::clone(&self.time_provider),
                 })
             })
     }
@@ -406,7 +744,8 @@ impl Database {
 #[async_trait]
 impl QueryNamespace for Database {
     fn retention_time_ns(&self) -> Option<i64> {
-        None
+        self.db_schema
+            .retention_cutoff_ns(self.time_provider.now().timestamp_nanos())
     }
 
     fn record_query(
@@ -418,13 +757,20 @@ impl QueryNamespace for Database {
     ) -> QueryCompletedToken<StateReceived> {
         let trace_id = span_ctx.map(|ctx| ctx.trace_id);
         let namespace_name: Arc<str> = Arc::from("influxdb3 edge");
-        self.query_log.push(
-            NamespaceId::new(0),
-            namespace_name,
-            query_type,
-            query_text,
-            query_params,
-            trace_id,
+        self.running_queries.push(
+            &self.query_log,
+            Arc::clone(&self.db_schema.name),
+            query_params.clone(),
+            || {
+                self.query_log.push(
+                    NamespaceId::new(0),
+                    namespace_name,
+                    query_type,
+                    query_text,
+                    query_params,
+                    trace_id,
+                )
+            },
         )
     }
 
@@ -451,6 +797,13 @@ impl QueryNamespace for Database {
                 self.write_buffer.last_cache_provider(),
             )),
         );
+        ctx.inner().register_udtf(
+            META_CACHE_UDTF_NAME,
+            Arc::new(MetaCacheFunction::new(
+                self.db_schema.id,
+                self.write_buffer.meta_cache_provider(),
+            )),
+        );
         ctx
     }
 
@@ -465,6 +818,7 @@ impl QueryNamespace for Database {
 }
 
 const LAST_CACHE_UDTF_NAME: &str = "last_cache";
+const META_CACHE_UDTF_NAME: &str = "meta_cache";
 
 impl CatalogProvider for Database {
     fn as_any(&self) -> &dyn Any {
@@ -518,6 +872,7 @@ pub struct QueryTable {
     table_name: Arc<str>,
     schema: Schema,
     write_buffer: Arc<dyn WriteBuffer>,
+    time_provider: Arc<dyn TimeProvider>,
 }
 
 impl QueryTable {
@@ -566,7 +921,18 @@ impl TableProvider for QueryTable {
         filters: &[Expr],
         limit: Option<usize>,
     ) -> datafusion::common::Result<Arc<dyn ExecutionPlan>> {
-        let filters = filters.to_vec();
+        let mut filters = filters.to_vec();
+        // rows that are older than the database retention period may not have been removed yet,
+        // so they are filtered out here:
+        if let Some(cutoff_ns) = self
+            .db_schema
+            .retention_cutoff_ns(self.time_provider.now().timestamp_nanos())
+        {
+            filters.push(
+                col(TIME_COLUMN_NAME)
+                    .gt_eq(lit(ScalarValue::TimestampNanosecond(Some(cutoff_ns), None))),
+            );
+        }
         debug!(
             ?projection,
             ?filters,
@@ -595,10 +961,11 @@ mod tests {
     use arrow::array::RecordBatch;
     use data_types::NamespaceName;
     use datafusion::{assert_batches_sorted_eq, error::DataFusionError};
-    use futures::TryStreamExt;
+    use futures::{StreamExt, TryStreamExt};
+    use influxdb3_cache::meta_cache::MetaCacheProvider;
     use influxdb3_catalog::catalog::Catalog;
     use influxdb3_telemetry::store::TelemetryStore;
-    use influxdb3_wal::{Gen1Duration, WalConfig};
+    use influxdb3_wal::{Gen1Duration, WalCompression, WalConfig};
     use influxdb3_write::{
         last_cache::LastCacheProvider,
         parquet_cache::test_cached_obj_store_and_oracle,
@@ -607,6 +974,8 @@ mod tests {
         WriteBuffer,
     };
     use iox_query::exec::{DedicatedExecutor, Executor, ExecutorConfig};
+    use iox_query::query_log::QueryPhase;
+    use iox_query::QueryDatabase;
     use iox_time::{MockProvider, Time};
     use metric::Registry;
     use object_store::{local::LocalFileSystem, ObjectStore};
@@ -616,7 +985,10 @@ mod tests {
         query_executor::QueryExecutorImpl, system_tables::table_name_predicate_error, QueryExecutor,
     };
 
-    use super::CreateQueryExecutorArgs;
+    use super::{
+        CreateQueryExecutorArgs, CurrentQuery, Error, QueryLimitBehavior, SlowQueryLogConfig,
+        SlowQuerySink, CURRENT_QUERY,
+    };
 
     fn make_exec(object_store: Arc<dyn ObjectStore>) -> Arc<Executor> {
         let metrics = Arc::new(metric::Registry::default());
@@ -641,6 +1013,29 @@ mod tests {
     }
 
     async fn setup() -> (Arc<dyn WriteBuffer>, QueryExecutorImpl, Arc<MockProvider>) {
+        setup_with_query_limits(10, QueryLimitBehavior::Queue, None).await
+    }
+
+    async fn setup_with_query_limits(
+        concurrent_query_limit: usize,
+        query_limit_behavior: QueryLimitBehavior,
+        query_timeout: Option<Duration>,
+    ) -> (Arc<dyn WriteBuffer>, QueryExecutorImpl, Arc<MockProvider>) {
+        setup_with_args(
+            concurrent_query_limit,
+            query_limit_behavior,
+            query_timeout,
+            None,
+        )
+        .await
+    }
+
+    async fn setup_with_args(
+        concurrent_query_limit: usize,
+        query_limit_behavior: QueryLimitBehavior,
+        query_timeout: Option<Duration>,
+        slow_query_log: Option<SlowQueryLogConfig>,
+    ) -> (Arc<dyn WriteBuffer>, QueryExecutorImpl, Arc<MockProvider>) {
         // Set up QueryExecutor
         let object_store: Arc<dyn ObjectStore> =
             Arc::new(LocalFileSystem::new_with_prefix(test_helpers::tmp_dir().unwrap()).unwrap());
@@ -652,11 +1047,17 @@ mod tests {
         let host_id = Arc::from("sample-host-id");
         let instance_id = Arc::from("instance-id");
         let catalog = Arc::new(Catalog::new(host_id, instance_id));
+        let metrics = Arc::new(Registry::new());
         let write_buffer_impl = Arc::new(
             WriteBufferImpl::new(
                 Arc::clone(&persister),
                 Arc::clone(&catalog),
-                LastCacheProvider::new_from_catalog(catalog as _).unwrap(),
+                LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap(),
+                MetaCacheProvider::new_from_catalog(
+                    Arc::<MockProvider>::clone(&time_provider),
+                    catalog,
+                )
+                .unwrap(),
                 Arc::<MockProvider>::clone(&time_provider),
                 Arc::clone(&exec),
                 WalConfig {
@@ -664,8 +1065,11 @@ mod tests {
                     max_write_buffer_size: 100,
                     flush_interval: Duration::from_millis(10),
                     snapshot_size: 1,
+                    compression: WalCompression::default(),
                 },
+                None,
                 Some(parquet_cache),
+                Arc::clone(&metrics),
             )
             .await
             .unwrap(),
@@ -674,7 +1078,6 @@ mod tests {
         let persisted_files: Arc<PersistedFiles> = Arc::clone(&write_buffer_impl.persisted_files());
         let telemetry_store = TelemetryStore::new_without_background_runners(persisted_files);
         let write_buffer: Arc<dyn WriteBuffer> = write_buffer_impl;
-        let metrics = Arc::new(Registry::new());
         let datafusion_config = Arc::new(Default::default());
         let query_executor = QueryExecutorImpl::new(CreateQueryExecutorArgs {
             catalog: write_buffer.catalog(),
@@ -682,9 +1085,13 @@ mod tests {
             exec,
             metrics,
             datafusion_config,
-            concurrent_query_limit: 10,
+            concurrent_query_limit,
+            query_limit_behavior,
+            query_timeout,
             query_log_size: 10,
+            slow_query_log,
             telemetry_store,
+            time_provider: Arc::<MockProvider>::clone(&time_provider),
         });
 
         (write_buffer, query_executor, time_provider)
@@ -792,4 +1199,279 @@ mod tests {
         let error: DataFusionError = stream.try_collect::<Vec<RecordBatch>>().await.unwrap_err();
         assert_eq!(error.message(), table_name_predicate_error().message());
     }
+
+    async fn write_cpu(write_buffer: &Arc<dyn WriteBuffer>, db_name: &str) {
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name.to_string()).unwrap(),
+                "cpu,host=a usage=0.1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn query_limit_reject() {
+        let (write_buffer, query_executor, _) =
+            setup_with_query_limits(1, QueryLimitBehavior::Reject, None).await;
+        write_cpu(&write_buffer, "test_db").await;
+        let query = "SELECT * FROM cpu";
+
+        // the permit to run a query is held until its results are complete:
+        let running = query_executor
+            .query("test_db", query, None, crate::QueryKind::Sql, None, None)
+            .await
+            .unwrap();
+        let err = query_executor
+            .query("test_db", query, None, crate::QueryKind::Sql, None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::QueryLimitReached { limit: 1 }));
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(1)));
+        assert!(query_executor.query_limits().check_rejected().is_err());
+
+        running.try_collect::<Vec<RecordBatch>>().await.unwrap();
+        query_executor
+            .query("test_db", query, None, crate::QueryKind::Sql, None, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn query_limit_queue_timeout() {
+        let (write_buffer, query_executor, _) = setup_with_query_limits(
+            1,
+            QueryLimitBehavior::Queue,
+            Some(Duration::from_millis(100)),
+        )
+        .await;
+        write_cpu(&write_buffer, "test_db").await;
+        let query = "SELECT * FROM cpu";
+
+        let running = query_executor
+            .query("test_db", query, None, crate::QueryKind::Sql, None, None)
+            .await
+            .unwrap();
+        // queries are not rejected when they can be queued:
+        assert!(query_executor.query_limits().check_rejected().is_ok());
+        let err = query_executor
+            .query("test_db", query, None, crate::QueryKind::Sql, None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::QueryQueueTimeout { limit: 1, .. }));
+
+        // a queued query runs once the running query completes:
+        let queued = tokio::spawn(async move {
+            query_executor
+                .query("test_db", query, None, crate::QueryKind::Sql, None, None)
+                .await
+                .map(|_| ())
+        });
+        drop(running);
+        queued.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn query_timeout_fails_query() {
+        let (write_buffer, query_executor, _) =
+            setup_with_query_limits(1, QueryLimitBehavior::Queue, Some(Duration::ZERO)).await;
+        write_cpu(&write_buffer, "test_db").await;
+
+        let query = "SELECT * FROM cpu";
+
+        let mut stream = query_executor
+            .query("test_db", query, None, crate::QueryKind::Sql, None, None)
+            .await
+            .unwrap();
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(
+            err.to_string().contains("query exceeded its timeout"),
+            "unexpected error: {err}"
+        );
+        assert!(stream.next().await.is_none());
+
+        let entries = query_executor.query_log().entries;
+        assert!(!entries.iter().last().unwrap().state().success);
+        // the permit of the timed out query was released, so another query can acquire it:
+        query_executor
+            .query("test_db", query, None, crate::QueryKind::Sql, None, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn kill_running_query() {
+        let (write_buffer, query_executor, _) = setup().await;
+        write_cpu(&write_buffer, "test_db").await;
+
+        let mut stream = query_executor
+            .query(
+                "test_db",
+                "SELECT * FROM cpu",
+                None,
+                crate::QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let id = query_executor
+            .query_log()
+            .entries
+            .iter()
+            .last()
+            .unwrap()
+            .state()
+            .id
+            .to_string();
+        let query = query_executor.running_query(&id).unwrap();
+        assert_eq!(query.database(), "test_db");
+
+        query.cancel();
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(
+            err.to_string().contains("query was cancelled"),
+            "unexpected error: {err}"
+        );
+        assert!(stream.next().await.is_none());
+        // the query is no longer running, and shows as cancelled in the query log:
+        assert!(query_executor.running_query(&id).is_none());
+        let entries = query_executor.query_log().entries;
+        let state = entries.iter().last().unwrap().state();
+        assert_eq!(state.phase, QueryPhase::Cancel);
+    }
+
+    /// Set up a query executor with a slow query log that logs every query, since the mock time
+    /// provider does not advance, so every query takes no time
+    async fn setup_with_slow_query_log(
+        sink: SlowQuerySink,
+    ) -> (Arc<dyn WriteBuffer>, QueryExecutorImpl, Arc<MockProvider>) {
+        setup_with_args(
+            10,
+            QueryLimitBehavior::Queue,
+            None,
+            Some(SlowQueryLogConfig {
+                duration_threshold: Duration::ZERO,
+                memory_threshold: None,
+                sink,
+            }),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn slow_query_log_to_files() {
+        let dir = test_helpers::tmp_dir().unwrap();
+        let (write_buffer, query_executor, _) = setup_with_slow_query_log(SlowQuerySink::Files {
+            dir: dir.path().to_path_buf(),
+            max_file_size: 1024 * 1024,
+            max_files: 2,
+        })
+        .await;
+        write_cpu(&write_buffer, "test_db").await;
+
+        let current_query = CurrentQuery::with_token_name(Some(Arc::from("dashboards")));
+        let stream = CURRENT_QUERY
+            .scope(
+                current_query,
+                query_executor.query(
+                    "test_db",
+                    "SELECT * FROM cpu",
+                    None,
+                    crate::QueryKind::Sql,
+                    None,
+                    None,
+                ),
+            )
+            .await
+            .unwrap();
+        stream.try_collect::<Vec<RecordBatch>>().await.unwrap();
+
+        // slow queries are written in the background:
+        let path = dir.path().join("slow_queries.jsonl");
+        let contents = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match tokio::fs::read_to_string(&path).await {
+                    Ok(contents) if contents.ends_with('\n') => break contents,
+                    _ => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        })
+        .await
+        .expect("slow query should be written to the log file");
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 1);
+        let entry: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(entry["database"], "test_db");
+        assert_eq!(entry["token_name"], "dashboards");
+        assert_eq!(entry["query_type"], "sql");
+        assert_eq!(entry["query_text"], "SELECT * FROM cpu");
+        assert_eq!(entry["success"], true);
+        assert_eq!(entry["rows"], 1);
+        assert!(entry["plan"].as_str().is_some_and(|plan| !plan.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn slow_query_log_to_database() {
+        let (write_buffer, query_executor, _) =
+            setup_with_slow_query_log(SlowQuerySink::Database("_internal".to_string())).await;
+        write_cpu(&write_buffer, "test_db").await;
+
+        let stream = query_executor
+            .query(
+                "test_db",
+                "SELECT * FROM cpu",
+                None,
+                crate::QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        stream.try_collect::<Vec<RecordBatch>>().await.unwrap();
+
+        // slow queries are written in the background, and the queries made to check for them
+        // are logged too, so only those against the `test_db` are selected:
+        let batches = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let result = async {
+                    query_executor
+                        .query(
+                            "_internal",
+                            "SELECT database, query_type, query_text, rows, success \
+                            FROM slow_queries WHERE database = 'test_db'",
+                            None,
+                            crate::QueryKind::Sql,
+                            None,
+                            None,
+                        )
+                        .await
+                        .ok()?
+                        .try_collect::<Vec<RecordBatch>>()
+                        .await
+                        .ok()
+                }
+                .await;
+                match result {
+                    Some(batches) if batches.iter().any(|b| b.num_rows() > 0) => break batches,
+                    _ => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        })
+        .await
+        .expect("slow query should be written to the database");
+        assert_batches_sorted_eq!(
+            [
+                "+----------+------------+-------------------+------+---------+",
+                "| database | query_type | query_text        | rows | success |",
+                "+----------+------------+-------------------+------+---------+",
+                "| test_db  | sql        | SELECT * FROM cpu | 1    | true    |",
+                "+----------+------------+-------------------+------+---------+",
+            ],
+            &batches
+        );
+    }
 }
@@ -0,0 +1,112 @@
+//! The structured output of `EXPLAIN ANALYZE`, as returned by the `explain=analyze` parameter
+//! of the query APIs
+
+use std::time::Duration;
+
+use datafusion::datasource::physical_plan::ParquetExec;
+use datafusion::physical_plan::display::DisplayableExecutionPlan;
+use datafusion::physical_plan::ExecutionPlan;
+use iox_query::provider::RecordBatchesExec;
+use serde::Serialize;
+
+/// A query that was run to completion, with the metrics from its execution
+#[derive(Debug, Serialize)]
+pub struct ExplainAnalyze {
+    /// The number of rows the query returned
+    pub rows: usize,
+    /// How long it took to run the query, from when it was received until its results were
+    /// complete
+    pub elapsed_ns: u64,
+    /// The number of chunks of data in the write buffer that were read by the query
+    pub buffer_chunks: usize,
+    /// The number of Parquet files that were read by the query
+    pub parquet_chunks: usize,
+    /// The physical plan that was executed
+    pub plan: PlanNode,
+}
+
+impl ExplainAnalyze {
+    pub(crate) fn new(plan: &dyn ExecutionPlan, rows: usize, elapsed: Duration) -> Self {
+        let plan = PlanNode::new(plan);
+        let (buffer_chunks, parquet_chunks) = plan.total_chunks();
+        Self {
+            rows,
+            elapsed_ns: u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX),
+            buffer_chunks,
+            parquet_chunks,
+            plan,
+        }
+    }
+}
+
+/// An operator in an executed physical plan, along with the metrics from its execution
+///
+/// Metrics that an operator does not record are left out.
+#[derive(Debug, Serialize)]
+pub struct PlanNode {
+    /// The name of the operator, e.g., `ProjectionExec`
+    pub operator: String,
+    /// The operator as it is shown in the output of `EXPLAIN`
+    pub details: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_rows: Option<usize>,
+    /// The CPU time spent in the operator, summed across its partitions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_compute_ns: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_scanned: Option<usize>,
+    /// The number of chunks of data in the write buffer scanned by the operator
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buffer_chunks: Option<usize>,
+    /// The number of Parquet files scanned by the operator
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parquet_chunks: Option<usize>,
+    pub children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    fn new(plan: &dyn ExecutionPlan) -> Self {
+        let metrics = plan.metrics().map(|m| m.aggregate_by_name());
+        let details = DisplayableExecutionPlan::new(plan)
+            .one_line()
+            .to_string()
+            .trim_end()
+            .to_string();
+        let buffer_chunks = plan
+            .as_any()
+            .downcast_ref::<RecordBatchesExec>()
+            .map(|exec| exec.chunks().count());
+        let parquet_chunks = plan
+            .as_any()
+            .downcast_ref::<ParquetExec>()
+            .map(|exec| exec.base_config().file_groups.iter().map(Vec::len).sum());
+        Self {
+            operator: plan.name().to_string(),
+            details,
+            output_rows: metrics.as_ref().and_then(|m| m.output_rows()),
+            elapsed_compute_ns: metrics.as_ref().and_then(|m| m.elapsed_compute()),
+            bytes_scanned: metrics
+                .as_ref()
+                .and_then(|m| m.sum_by_name("bytes_scanned"))
+                .map(|v| v.as_usize()),
+            buffer_chunks,
+            parquet_chunks,
+            children: plan
+                .children()
+                .into_iter()
+                .map(|child| Self::new(child.as_ref()))
+                .collect(),
+        }
+    }
+
+    /// The number of buffer and Parquet chunks scanned by this operator and its children
+    fn total_chunks(&self) -> (usize, usize) {
+        self.children.iter().map(Self::total_chunks).fold(
+            (
+                self.buffer_chunks.unwrap_or_default(),
+                self.parquet_chunks.unwrap_or_default(),
+            ),
+            |(buffer, parquet), (b, p)| (buffer + b, parquet + p),
+        )
+    }
+}
@@ -0,0 +1,281 @@
+//! Tracking of the queries that are running, so that they can be cancelled by their ID in the
+//! query log, which is the `id` shown in `system.queries`, and so that they can be written to
+//! the [`SlowQueryLog`] once they end
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use datafusion::physical_plan::display::DisplayableExecutionPlan;
+use datafusion::physical_plan::ExecutionPlan;
+use iox_query::query_log::{
+    QueryCompletedToken, QueryLog, QueryLogEntry, QueryPhase, StateReceived,
+};
+use iox_query_params::StatementParams;
+use parking_lot::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use super::slow_query_log::{SlowQuery, SlowQueryLog};
+
+tokio::task_local! {
+    /// The query being recorded by the current task
+    ///
+    /// The query log is written to through [`QueryNamespace::record_query`], which is also
+    /// called by the Flight service, so this is how the cancellation token of a query is passed
+    /// in, and how its [`RunningQueryGuard`] is passed back out.
+    ///
+    /// [`QueryNamespace::record_query`]: iox_query::QueryNamespace::record_query
+    pub(crate) static CURRENT_QUERY: CurrentQuery;
+}
+
+/// The state of the query being recorded by the current task, see [`CURRENT_QUERY`]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CurrentQuery {
+    pub(crate) cancel: CancellationToken,
+    /// The name of the token the query was made with, if it is a token stored in the catalog
+    token_name: Option<Arc<str>>,
+    guard: Arc<Mutex<Option<RunningQueryGuard>>>,
+}
+
+impl CurrentQuery {
+    /// A query made with the token with the given name
+    pub(crate) fn with_token_name(token_name: Option<Arc<str>>) -> Self {
+        Self {
+            token_name,
+            ..Default::default()
+        }
+    }
+
+    /// A query made by the current task, which was made with the same token as the enclosing
+    /// [`CURRENT_QUERY`], if there is one
+    pub(crate) fn inherit() -> Self {
+        Self::with_token_name(
+            CURRENT_QUERY
+                .try_with(|current| current.token_name.clone())
+                .ok()
+                .flatten(),
+        )
+    }
+
+    /// Take the guard of the query, once it has been recorded, which keeps the query registered
+    /// as running until it is dropped
+    pub(crate) fn take_guard(&self) -> Option<RunningQueryGuard> {
+        self.guard.lock().take()
+    }
+}
+
+/// The queries that are running on the server, by their ID in the query log
+#[derive(Debug, Default)]
+pub struct RunningQueries {
+    queries: Mutex<HashMap<Arc<str>, RunningQuery>>,
+    slow_query_log: Option<SlowQueryLog>,
+}
+
+/// A query that is running, and can be cancelled
+#[derive(Debug, Clone)]
+pub struct RunningQuery {
+    database: Arc<str>,
+    cancel: CancellationToken,
+}
+
+impl RunningQuery {
+    /// The name of the database the query is running against
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+
+    /// Cancel the query, which stops its execution, releasing the memory it holds, and marks it
+    /// as cancelled in the query log
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+}
+
+impl RunningQueries {
+    /// Create a new [`RunningQueries`], which writes the queries that were slow to the
+    /// `slow_query_log`, if there is one
+    pub(crate) fn new(slow_query_log: Option<SlowQueryLog>) -> Self {
+        Self {
+            queries: Default::default(),
+            slow_query_log,
+        }
+    }
+
+    /// Push a query to the query log with `push`, and register it as running, with the
+    /// cancellation token of the [`CURRENT_QUERY`], if there is one
+    ///
+    /// The ID of the query is read back from the newest entry in the log, so the lock is held
+    /// while the entry is pushed, so that no other query is pushed in between. A query is not
+    /// registered if the query log has no room for any entries, or if there is no current query.
+    pub(crate) fn push(
+        self: &Arc<Self>,
+        query_log: &QueryLog,
+        database: Arc<str>,
+        params: StatementParams,
+        push: impl FnOnce() -> QueryCompletedToken<StateReceived>,
+    ) -> QueryCompletedToken<StateReceived> {
+        let mut queries = self.queries.lock();
+        let token = push();
+        let Ok(current) = CURRENT_QUERY.try_with(Clone::clone) else {
+            return token;
+        };
+        if let Some(entry) = query_log.entries().entries.into_iter().last() {
+            let id: Arc<str> = entry.state().id.to_string().into();
+            queries.insert(
+                Arc::clone(&id),
+                RunningQuery {
+                    database: Arc::clone(&database),
+                    cancel: current.cancel.clone(),
+                },
+            );
+            *current.guard.lock() = Some(RunningQueryGuard {
+                queries: Arc::clone(self),
+                id,
+                entry,
+                database,
+                params,
+                token_name: current.token_name.clone(),
+                started: Instant::now(),
+                execution: None,
+            });
+        }
+        token
+    }
+
+    /// Get the running query with the given ID
+    pub(crate) fn get(&self, id: &str) -> Option<RunningQuery> {
+        self.queries.lock().get(id).cloned()
+    }
+}
+
+/// Keeps a query registered as running until it is dropped, at which point the query is
+/// written to the [`SlowQueryLog`], if it was slow
+///
+/// The guard must be dropped after the query's [`QueryCompletedToken`], so that the outcome of
+/// the query has been recorded in its query log entry.
+#[derive(Debug)]
+pub(crate) struct RunningQueryGuard {
+    queries: Arc<RunningQueries>,
+    id: Arc<str>,
+    entry: Arc<QueryLogEntry>,
+    database: Arc<str>,
+    params: StatementParams,
+    token_name: Option<Arc<str>>,
+    started: Instant,
+    /// The plan being executed, and the number of rows it has returned, which are only known
+    /// for queries run through [`QueryExecutor::query`]
+    ///
+    /// [`QueryExecutor::query`]: crate::QueryExecutor::query
+    execution: Option<(Arc<dyn ExecutionPlan>, u64)>,
+}
+
+impl RunningQueryGuard {
+    /// The ID of the query in the query log
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Record that the query is executing `plan`
+    pub(crate) fn executing(&mut self, plan: Arc<dyn ExecutionPlan>) {
+        self.execution = Some((plan, 0));
+    }
+
+    /// Record that the query has returned another `rows` rows
+    pub(crate) fn returned_rows(&mut self, rows: usize) {
+        if let Some((_, returned)) = &mut self.execution {
+            *returned += rows as u64;
+        }
+    }
+
+    fn slow_query(&self, slow_query_log: &SlowQueryLog) -> Option<SlowQuery> {
+        let state = self.entry.state();
+        let duration = state
+            .end2end_duration
+            .unwrap_or_else(|| self.started.elapsed());
+        if !slow_query_log.is_slow(duration, state.max_memory) {
+            return None;
+        }
+        let nanos = |d: std::time::Duration| i64::try_from(d.as_nanos()).unwrap_or(i64::MAX);
+        Some(SlowQuery {
+            id: self.id.to_string(),
+            issue_time: state.issue_time.to_rfc3339(),
+            issue_time_ns: state.issue_time.timestamp_nanos(),
+            database: self.database.to_string(),
+            token_name: self.token_name.as_deref().map(ToString::to_string),
+            query_type: state.query_type.to_string(),
+            query_text: state.query_text.to_string(),
+            params: self.params.clone(),
+            success: state.success,
+            cancelled: state.phase == QueryPhase::Cancel,
+            plan_duration_ns: state.plan_duration.map(nanos),
+            permit_duration_ns: state.permit_duration.map(nanos),
+            execute_duration_ns: state.execute_duration.map(nanos),
+            end2end_duration_ns: nanos(duration),
+            max_memory_bytes: state.max_memory,
+            rows: self.execution.as_ref().map(|(_, rows)| *rows),
+            plan: self.execution.as_ref().map(|(plan, _)| {
+                DisplayableExecutionPlan::with_metrics(plan.as_ref())
+                    .indent(false)
+                    .to_string()
+            }),
+        })
+    }
+}
+
+impl Drop for RunningQueryGuard {
+    fn drop(&mut self) {
+        self.queries.queries.lock().remove(&self.id);
+        if let Some(slow_query_log) = &self.queries.slow_query_log {
+            if let Some(query) = self.slow_query(slow_query_log) {
+                slow_query_log.record(query);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use iox_query::query_log::QueryLog;
+    use iox_time::{MockProvider, Time};
+
+    use super::*;
+
+    #[test]
+    fn register_running_queries() {
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let query_log = QueryLog::new(10, time_provider);
+        let running = Arc::new(RunningQueries::default());
+        let push = || {
+            query_log.push(
+                data_types::NamespaceId::new(0),
+                Arc::from("db"),
+                "sql",
+                Box::new("SELECT 1".to_string()),
+                Default::default(),
+                None,
+            )
+        };
+
+        // queries recorded outside of a current query are not registered:
+        let _token = running.push(&query_log, Arc::from("db"), Default::default(), push);
+        assert!(running.queries.lock().is_empty());
+
+        let current = CurrentQuery::default();
+        let _token = CURRENT_QUERY.sync_scope(current.clone(), || {
+            running.push(&query_log, Arc::from("db"), Default::default(), push)
+        });
+        let guard = current.take_guard().unwrap();
+        let newest = query_log.entries().entries.into_iter().last().unwrap();
+        assert_eq!(guard.id(), newest.state().id.to_string());
+
+        let query = running.get(guard.id()).unwrap();
+        assert_eq!(query.database(), "db");
+        query.cancel();
+        assert!(current.cancel.is_cancelled());
+
+        drop(guard);
+        assert!(running.queries.lock().is_empty());
+    }
+}
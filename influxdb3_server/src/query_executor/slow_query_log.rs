@@ -0,0 +1,430 @@
+//! A durable log of the queries that exceed a duration or memory threshold
+//!
+//! Unlike the [`QueryLog`] behind `system.queries`, which only holds the most recent queries in
+//! memory, slow queries are written either to rotated JSON lines files in a local directory, or
+//! to the `slow_queries` table of a database on this server, where they can be queried with SQL.
+//!
+//! [`QueryLog`]: iox_query::query_log::QueryLog
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use data_types::{NamespaceName, NamespaceNameError};
+use influxdb3_write::{Precision, WriteBuffer};
+use iox_query_params::StatementParams;
+use iox_time::TimeProvider;
+use observability_deps::tracing::warn;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// The name of the table that slow queries are written to, when they are logged to a database
+pub const SLOW_QUERIES_TABLE_NAME: &str = "slow_queries";
+
+/// The name of the file that slow queries are written to, when they are logged to a directory;
+/// rotated files have their number inserted before the extension, e.g., `slow_queries.1.jsonl`
+const SLOW_QUERIES_FILE_STEM: &str = "slow_queries";
+
+/// How many slow queries can be waiting to be written before new ones are dropped
+const SLOW_QUERY_LOG_CAPACITY: usize = 1_000;
+
+/// Configuration of the slow query log
+#[derive(Debug, Clone)]
+pub struct SlowQueryLogConfig {
+    /// Queries that take at least this long, from when they are received until their results
+    /// are complete, are logged
+    pub duration_threshold: Duration,
+    /// Queries that use at least this many bytes of memory are logged, regardless of how long
+    /// they take
+    pub memory_threshold: Option<u64>,
+    /// Where slow queries are written
+    pub sink: SlowQuerySink,
+}
+
+/// Where slow queries are written
+#[derive(Debug, Clone)]
+pub enum SlowQuerySink {
+    /// Write slow queries as JSON lines to files in a local directory, rotating the file once it
+    /// reaches `max_file_size` bytes, and keeping at most `max_files` files
+    Files {
+        dir: PathBuf,
+        max_file_size: u64,
+        max_files: usize,
+    },
+    /// Write slow queries to the [`SLOW_QUERIES_TABLE_NAME`] table of the named database
+    Database(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    #[error("failed to write slow query log file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to serialize slow query: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("invalid slow query log database name: {0}")]
+    DatabaseName(#[from] NamespaceNameError),
+    #[error("failed to write slow query to database: {0}")]
+    WriteBuffer(#[from] influxdb3_write::write_buffer::Error),
+}
+
+/// A query that exceeded one of the thresholds of the [`SlowQueryLog`]
+#[derive(Debug, Serialize)]
+pub(crate) struct SlowQuery {
+    /// The ID of the query in the query log
+    pub(crate) id: String,
+    /// When the query was received, as an RFC 3339 timestamp
+    pub(crate) issue_time: String,
+    #[serde(skip)]
+    pub(crate) issue_time_ns: i64,
+    pub(crate) database: String,
+    /// The name of the token the query was made with, which is only known for tokens stored in
+    /// the catalog
+    pub(crate) token_name: Option<String>,
+    pub(crate) query_type: String,
+    pub(crate) query_text: String,
+    pub(crate) params: StatementParams,
+    pub(crate) success: bool,
+    pub(crate) cancelled: bool,
+    pub(crate) plan_duration_ns: Option<i64>,
+    pub(crate) permit_duration_ns: Option<i64>,
+    pub(crate) execute_duration_ns: Option<i64>,
+    pub(crate) end2end_duration_ns: i64,
+    pub(crate) max_memory_bytes: Option<i64>,
+    /// The number of rows returned, which is not known for queries made through Flight
+    pub(crate) rows: Option<u64>,
+    /// The physical plan of the query, with the metrics from its execution, which is not known
+    /// for queries made through Flight
+    pub(crate) plan: Option<String>,
+}
+
+impl SlowQuery {
+    /// Convert the query to a line of line protocol, in the [`SLOW_QUERIES_TABLE_NAME`] table
+    fn to_line_protocol(&self) -> String {
+        let mut line = String::from(SLOW_QUERIES_TABLE_NAME);
+        write_tag(&mut line, "database", &self.database);
+        write_tag(&mut line, "query_type", &self.query_type);
+        if let Some(token_name) = self.token_name.as_deref().filter(|n| !n.is_empty()) {
+            write_tag(&mut line, "token_name", token_name);
+        }
+
+        let params = serde_json::to_string(&self.params).unwrap_or_default();
+        let mut fields = vec![
+            string_field("id", &self.id),
+            string_field("query_text", &self.query_text),
+            string_field("params", &params),
+            format!("success={}", self.success),
+            format!("cancelled={}", self.cancelled),
+            format!("end2end_duration_ns={}i", self.end2end_duration_ns),
+        ];
+        let optional_fields = [
+            ("plan_duration_ns", self.plan_duration_ns),
+            ("permit_duration_ns", self.permit_duration_ns),
+            ("execute_duration_ns", self.execute_duration_ns),
+            ("max_memory_bytes", self.max_memory_bytes),
+            ("rows", self.rows.map(|r| r as i64)),
+        ];
+        for (name, value) in optional_fields {
+            if let Some(value) = value {
+                fields.push(format!("{name}={value}i"));
+            }
+        }
+        if let Some(plan) = &self.plan {
+            fields.push(string_field("plan", plan));
+        }
+
+        write!(line, " {} {}", fields.join(","), self.issue_time_ns).unwrap();
+        line
+    }
+}
+
+fn write_tag(line: &mut String, key: &str, value: &str) {
+    let value = value
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ");
+    write!(line, ",{key}={value}").unwrap();
+}
+
+fn string_field(key: &str, value: &str) -> String {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("{key}=\"{value}\"")
+}
+
+/// Logs the queries that exceed its thresholds
+///
+/// Slow queries are written in the background, so that logging them does not hold up the
+/// queries themselves; if they can not be written as fast as they are logged, the newest are
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct SlowQueryLog {
+    duration_threshold: Duration,
+    memory_threshold: Option<u64>,
+    tx: mpsc::Sender<SlowQuery>,
+}
+
+impl SlowQueryLog {
+    /// Create a new [`SlowQueryLog`], and start the background task that writes to it, which
+    /// requires a tokio runtime
+    pub(crate) fn new(
+        SlowQueryLogConfig {
+            duration_threshold,
+            memory_threshold,
+            sink,
+        }: SlowQueryLogConfig,
+        write_buffer: Arc<dyn WriteBuffer>,
+        time_provider: Arc<dyn TimeProvider>,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel(SLOW_QUERY_LOG_CAPACITY);
+        let writer = match sink {
+            SlowQuerySink::Files {
+                dir,
+                max_file_size,
+                max_files,
+            } => Writer::Files(FileWriter::new(dir, max_file_size, max_files)),
+            SlowQuerySink::Database(database) => Writer::Database {
+                database,
+                write_buffer,
+                time_provider,
+            },
+        };
+        tokio::spawn(writer.run(rx));
+        Self {
+            duration_threshold,
+            memory_threshold,
+            tx,
+        }
+    }
+
+    /// Whether a query that took `duration`, and used `max_memory` bytes of memory, is slow
+    pub(crate) fn is_slow(&self, duration: Duration, max_memory: Option<i64>) -> bool {
+        duration >= self.duration_threshold
+            || self
+                .memory_threshold
+                .zip(max_memory)
+                .is_some_and(|(threshold, memory)| memory >= 0 && memory as u64 >= threshold)
+    }
+
+    /// Log a slow query
+    pub(crate) fn record(&self, query: SlowQuery) {
+        if let Err(e) = self.tx.try_send(query) {
+            warn!(error = %e, "dropped query from the slow query log");
+        }
+    }
+}
+
+/// Writes slow queries to a [`SlowQuerySink`]
+enum Writer {
+    Files(FileWriter),
+    Database {
+        database: String,
+        write_buffer: Arc<dyn WriteBuffer>,
+        time_provider: Arc<dyn TimeProvider>,
+    },
+}
+
+impl Writer {
+    async fn run(mut self, mut rx: mpsc::Receiver<SlowQuery>) {
+        while let Some(query) = rx.recv().await {
+            if let Err(e) = self.write(&query).await {
+                warn!(error = %e, query_id = %query.id, "failed to write to the slow query log");
+            }
+        }
+    }
+
+    async fn write(&mut self, query: &SlowQuery) -> Result<(), Error> {
+        match self {
+            Self::Files(writer) => {
+                let mut line = serde_json::to_vec(query)?;
+                line.push(b'\n');
+                writer.write(&line).await?;
+            }
+            Self::Database {
+                database,
+                write_buffer,
+                time_provider,
+            } => {
+                write_buffer
+                    .write_lp(
+                        NamespaceName::new(database.clone())?,
+                        &query.to_line_protocol(),
+                        time_provider.now(),
+                        false,
+                        Precision::Nanosecond,
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Appends to the slow query log file in a directory, rotating it once it is full
+#[derive(Debug)]
+struct FileWriter {
+    dir: PathBuf,
+    max_file_size: u64,
+    max_files: usize,
+    /// The open log file, and its size
+    file: Option<(tokio::fs::File, u64)>,
+}
+
+impl FileWriter {
+    fn new(dir: PathBuf, max_file_size: u64, max_files: usize) -> Self {
+        Self {
+            dir,
+            max_file_size,
+            max_files: max_files.max(1),
+            file: None,
+        }
+    }
+
+    /// The path of the log file with the given number, where the file being written to is `0`
+    fn path(&self, n: usize) -> PathBuf {
+        if n == 0 {
+            self.dir.join(format!("{SLOW_QUERIES_FILE_STEM}.jsonl"))
+        } else {
+            self.dir.join(format!("{SLOW_QUERIES_FILE_STEM}.{n}.jsonl"))
+        }
+    }
+
+    async fn write(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self
+            .file
+            .as_ref()
+            .is_some_and(|(_, size)| *size > 0 && size + line.len() as u64 > self.max_file_size)
+        {
+            self.rotate().await?;
+        }
+        let (file, size) = match &mut self.file {
+            Some(file) => file,
+            None => {
+                tokio::fs::create_dir_all(&self.dir).await?;
+                let file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self.path(0))
+                    .await?;
+                let size = file.metadata().await?.len();
+                self.file.insert((file, size))
+            }
+        };
+        file.write_all(line).await?;
+        file.flush().await?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Close the log file, and shift it and the rotated files along by one, removing the oldest
+    /// file if there would be more than `max_files`
+    async fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        let last = self.max_files - 1;
+        ignore_not_found(tokio::fs::remove_file(self.path(last)).await)?;
+        for n in (0..last).rev() {
+            ignore_not_found(tokio::fs::rename(self.path(n), self.path(n + 1)).await)?;
+        }
+        Ok(())
+    }
+}
+
+fn ignore_not_found(result: std::io::Result<()>) -> std::io::Result<()> {
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use influxdb_line_protocol::{parse_lines, FieldValue};
+
+    use super::*;
+
+    fn slow_query() -> SlowQuery {
+        SlowQuery {
+            id: "1".to_string(),
+            issue_time: "1970-01-01T00:00:00+00:00".to_string(),
+            issue_time_ns: 0,
+            database: "my db".to_string(),
+            token_name: Some("dashboards".to_string()),
+            query_type: "sql".to_string(),
+            query_text: "SELECT \"usage\"\nFROM cpu".to_string(),
+            params: Default::default(),
+            success: true,
+            cancelled: false,
+            plan_duration_ns: Some(10),
+            permit_duration_ns: None,
+            execute_duration_ns: Some(20),
+            end2end_duration_ns: 30,
+            max_memory_bytes: Some(1024),
+            rows: Some(2),
+            plan: Some("ProjectionExec: expr=[usage@0 as usage]".to_string()),
+        }
+    }
+
+    #[test]
+    fn slow_query_line_protocol() {
+        let lp = slow_query().to_line_protocol();
+        let lines = parse_lines(&lp).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert_eq!(line.series.measurement.as_str(), SLOW_QUERIES_TABLE_NAME);
+        let tags = line.series.tag_set.as_ref().unwrap();
+        assert_eq!(tags[0].1.as_str(), "my db");
+        assert_eq!(tags[2].1.as_str(), "dashboards");
+        let field = |name: &str| {
+            line.field_set
+                .iter()
+                .find(|(k, _)| k.as_str() == name)
+                .map(|(_, v)| v.clone())
+                .unwrap()
+        };
+        assert_eq!(
+            field("query_text"),
+            FieldValue::String("SELECT \"usage\"\nFROM cpu".into())
+        );
+        assert_eq!(field("rows"), FieldValue::I64(2));
+        assert_eq!(field("success"), FieldValue::Boolean(true));
+        assert!(line
+            .field_set
+            .iter()
+            .all(|(k, _)| k.as_str() != "permit_duration_ns"));
+    }
+
+    #[tokio::test]
+    async fn rotate_slow_query_log_files() {
+        let dir = test_helpers::tmp_dir().unwrap();
+        let mut writer = FileWriter::new(dir.path().to_path_buf(), 10, 3);
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            writer.write(line.as_bytes()).await.unwrap();
+        }
+
+        // each line fills a file, and only the newest three files are kept:
+        let read = |n| std::fs::read_to_string(writer.path(n)).unwrap();
+        assert_eq!(read(0), "fourth\n");
+        assert_eq!(read(1), "third\n");
+        assert_eq!(read(2), "second\n");
+        assert!(!writer.path(3).exists());
+
+        // lines are appended to a file until it is full:
+        let mut writer = FileWriter::new(dir.path().to_path_buf(), 100, 3);
+        writer.write(b"fifth\n").await.unwrap();
+        assert_eq!(read(0), "fourth\nfifth\n");
+    }
+
+    #[test]
+    fn slow_query_thresholds() {
+        let (tx, _rx) = mpsc::channel(1);
+        let log = SlowQueryLog {
+            duration_threshold: Duration::from_secs(10),
+            memory_threshold: Some(1024),
+            tx,
+        };
+        assert!(!log.is_slow(Duration::from_secs(1), None));
+        assert!(!log.is_slow(Duration::from_secs(1), Some(512)));
+        assert!(log.is_slow(Duration::from_secs(10), None));
+        assert!(log.is_slow(Duration::from_secs(1), Some(1024)));
+    }
+}
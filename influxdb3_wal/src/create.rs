@@ -133,3 +133,85 @@ pub fn delete_last_cache_op(
         name: cache_name.into(),
     })
 }
+
+pub fn create_meta_cache_op(
+    table_id: TableId,
+    table_name: impl Into<Arc<str>>,
+    cache_name: impl Into<Arc<str>>,
+    column_ids: impl IntoIterator<Item = ColumnId>,
+) -> CatalogOp {
+    CatalogOp::CreateMetaCache(MetaCacheDefinition {
+        table_id,
+        table_name: table_name.into(),
+        cache_name: cache_name.into(),
+        column_ids: column_ids.into_iter().collect(),
+        max_cardinality: 100_000,
+        max_age_seconds: 86_400,
+    })
+}
+
+pub fn delete_meta_cache_op(
+    table_id: TableId,
+    table_name: impl Into<Arc<str>>,
+    cache_name: impl Into<Arc<str>>,
+) -> CatalogOp {
+    CatalogOp::DeleteMetaCache(MetaCacheDelete {
+        table_name: table_name.into(),
+        table_id,
+        cache_name: cache_name.into(),
+    })
+}
+
+pub fn set_retention_period_op(
+    database_id: DbId,
+    database_name: impl Into<Arc<str>>,
+    retention_period_seconds: Option<u64>,
+) -> CatalogOp {
+    CatalogOp::SetRetentionPeriod(RetentionPeriodDefinition {
+        database_id,
+        database_name: database_name.into(),
+        retention_period_seconds,
+    })
+}
+
+pub fn set_schema_locked_op(
+    database_id: DbId,
+    database_name: impl Into<Arc<str>>,
+    table: Option<(TableId, &str)>,
+    locked: bool,
+) -> CatalogOp {
+    CatalogOp::SetSchemaLocked(SchemaLockDefinition {
+        database_id,
+        database_name: database_name.into(),
+        table: table.map(|(table_id, table_name)| (table_id, table_name.into())),
+        locked,
+    })
+}
+
+pub fn delete_database_op(
+    database_id: DbId,
+    database_name: impl Into<Arc<str>>,
+    deletion_time: i64,
+) -> CatalogOp {
+    CatalogOp::DeleteDatabase(DeleteDatabaseDefinition {
+        database_id,
+        database_name: database_name.into(),
+        deletion_time,
+    })
+}
+
+pub fn delete_table_op(
+    database_id: DbId,
+    database_name: impl Into<Arc<str>>,
+    table_id: TableId,
+    table_name: impl Into<Arc<str>>,
+    deletion_time: i64,
+) -> CatalogOp {
+    CatalogOp::DeleteTable(DeleteTableDefinition {
+        database_id,
+        database_name: database_name.into(),
+        table_id,
+        table_name: table_name.into(),
+        deletion_time,
+    })
+}
@@ -4,6 +4,7 @@
 //! index files in object storage.
 
 pub mod create;
+pub mod local_disk;
 pub mod object_store;
 pub mod serialize;
 mod snapshot_tracker;
@@ -43,12 +44,21 @@ pub enum Error {
     #[error("object store error: {0}")]
     ObjectStoreError(#[from] ::object_store::Error),
 
+    #[error("local wal io error: {0}")]
+    Io(#[from] std::io::Error),
+
     #[error("wal is shutdown and not accepting writes")]
     Shutdown,
 
-    #[error("invalid gen1 duration {0}. Must be one of 1m, 5m, 10m")]
+    #[error(
+        "invalid gen1 duration {0}. Must be a whole number of seconds that evenly divides a day, \
+        e.g., 30s, 1m, 10m, 1h"
+    )]
     InvalidGen1Duration(String),
 
+    #[error("invalid wal compression {0}. Must be one of none, snappy, zstd")]
+    InvalidWalCompression(String),
+
     #[error("last cache size must be from 1 to 10")]
     InvalidLastCacheSize,
 
@@ -93,6 +103,14 @@ pub trait Wal: Debug + Send + Sync + 'static {
     /// Returns the last persisted wal file sequence number
     async fn last_snapshot_sequence_number(&self) -> SnapshotSequenceNumber;
 
+    /// Reserves the next snapshot sequence number, for a snapshot that is persisted outside of
+    /// the WAL's own snapshot process, e.g., to record parquet files that have been removed.
+    ///
+    /// This waits for any snapshot that is in progress to finish. The returned permit holds off
+    /// the next snapshot of the WAL, so it must be held until the reserved snapshot has been
+    /// persisted.
+    async fn reserve_snapshot(&self) -> (SnapshotSequenceNumber, OwnedSemaphorePermit);
+
     /// Stop all writes to the WAL and flush the buffer to a WAL file.
     async fn shutdown(&self);
 }
@@ -126,6 +144,8 @@ pub struct WalConfig {
     pub flush_interval: Duration,
     /// The number of wal files to snapshot at a time
     pub snapshot_size: usize,
+    /// The compression applied to wal files
+    pub compression: WalCompression,
 }
 
 impl WalConfig {
@@ -135,6 +155,7 @@ impl WalConfig {
             max_write_buffer_size: 1000,
             flush_interval: Duration::from_millis(10),
             snapshot_size: 100,
+            compression: WalCompression::default(),
         }
     }
 }
@@ -146,28 +167,68 @@ impl Default for WalConfig {
             max_write_buffer_size: 100_000,
             flush_interval: Duration::from_secs(1),
             snapshot_size: 600,
+            compression: WalCompression::default(),
+        }
+    }
+}
+
+/// The compression applied to the contents of WAL files
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum WalCompression {
+    None,
+    Snappy,
+    #[default]
+    Zstd,
+}
+
+impl FromStr for WalCompression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "snappy" => Ok(Self::Snappy),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(Error::InvalidWalCompression(s.to_string())),
         }
     }
 }
 
 /// The duration of data timestamps, grouped into files persisted into object storage.
+///
+/// Any whole number of seconds that evenly divides a day is a valid duration, so that chunks
+/// never straddle a day boundary.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Gen1Duration(Duration);
 
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
 impl Gen1Duration {
+    /// Create a duration, validating that it is a whole number of seconds that evenly divides
+    /// a day
+    pub fn new(duration: Duration) -> Result<Self> {
+        let seconds = duration.as_secs();
+        if duration.subsec_nanos() != 0 || seconds == 0 || SECONDS_PER_DAY % seconds != 0 {
+            return Err(Error::InvalidGen1Duration(
+                humantime::format_duration(duration).to_string(),
+            ));
+        }
+        Ok(Self(duration))
+    }
+
     pub fn duration_seconds(&self) -> i64 {
         self.0.as_secs() as i64
     }
 
     /// Returns the time of the chunk the given timestamp belongs to based on the duration
     pub fn chunk_time_for_timestamp(&self, t: Timestamp) -> i64 {
-        t.get() - (t.get() % self.0.as_nanos() as i64)
+        t.get() - t.get().rem_euclid(self.as_nanos())
     }
 
     /// Given a time, returns the start time of the gen1 chunk that contains the time.
     pub fn start_time(&self, timestamp_seconds: i64) -> Time {
         let duration_seconds = self.duration_seconds();
-        let rounded_seconds = (timestamp_seconds / duration_seconds) * duration_seconds;
+        let rounded_seconds = timestamp_seconds - timestamp_seconds.rem_euclid(duration_seconds);
         Time::from_timestamp(rounded_seconds, 0).unwrap()
     }
 
@@ -192,12 +253,9 @@ impl FromStr for Gen1Duration {
     type Err = Error;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        match s {
-            "1m" => Ok(Self(Duration::from_secs(60))),
-            "5m" => Ok(Self(Duration::from_secs(300))),
-            "10m" => Ok(Self(Duration::from_secs(600))),
-            _ => Err(Error::InvalidGen1Duration(s.to_string())),
-        }
+        let duration =
+            humantime::parse_duration(s).map_err(|_| Error::InvalidGen1Duration(s.to_string()))?;
+        Self::new(duration).map_err(|_| Error::InvalidGen1Duration(s.to_string()))
     }
 }
 
@@ -237,6 +295,33 @@ pub struct CatalogBatch {
     pub ops: Vec<CatalogOp>,
 }
 
+impl CatalogBatch {
+    /// The id used by batches that are not scoped to a database, see [`CatalogBatch::tokens`]
+    const NO_DATABASE_ID: u32 = u32::MAX;
+
+    /// Create a batch of operations on the named tokens in the catalog
+    ///
+    /// Tokens are not scoped to a database, so the database id and name of the batch are
+    /// placeholders that are ignored when the batch is applied.
+    pub fn tokens(time_ns: i64, ops: impl IntoIterator<Item = CatalogOp>) -> Self {
+        Self {
+            database_id: DbId::from(Self::NO_DATABASE_ID),
+            database_name: "".into(),
+            time_ns,
+            ops: ops.into_iter().collect(),
+        }
+    }
+
+    /// Check if this batch only operates on named tokens, see [`CatalogBatch::tokens`]
+    pub fn is_token_batch(&self) -> bool {
+        !self.ops.is_empty()
+            && self
+                .ops
+                .iter()
+                .all(|op| matches!(op, CatalogOp::CreateToken(_) | CatalogOp::DeleteToken(_)))
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum CatalogOp {
     CreateDatabase(DatabaseDefinition),
@@ -244,6 +329,114 @@ pub enum CatalogOp {
     AddFields(FieldAdditions),
     CreateLastCache(LastCacheDefinition),
     DeleteLastCache(LastCacheDelete),
+    CreateMetaCache(MetaCacheDefinition),
+    DeleteMetaCache(MetaCacheDelete),
+    SetRetentionPeriod(RetentionPeriodDefinition),
+    DeleteDatabase(DeleteDatabaseDefinition),
+    DeleteTable(DeleteTableDefinition),
+    SetSchemaLocked(SchemaLockDefinition),
+    CreateToken(TokenDefinition),
+    DeleteToken(DeleteTokenDefinition),
+}
+
+/// Locks, or unlocks, the schema of a database, or of a single table in a database
+///
+/// Writes cannot add new columns to a locked table. Locking a database also prevents writes
+/// from adding new tables to it, or new columns to any of its tables.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SchemaLockDefinition {
+    pub database_id: DbId,
+    pub database_name: Arc<str>,
+    /// The table whose schema is locked, or `None` to lock the schema of the whole database
+    pub table: Option<(TableId, Arc<str>)>,
+    pub locked: bool,
+}
+
+/// Soft deletes a database, so that its name can be reused by a new database
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeleteDatabaseDefinition {
+    pub database_id: DbId,
+    pub database_name: Arc<str>,
+    /// The time of the deletion, in nanoseconds since the epoch
+    pub deletion_time: i64,
+}
+
+/// Soft deletes a table, so that its name can be reused by a new table in the same database
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeleteTableDefinition {
+    pub database_id: DbId,
+    pub database_name: Arc<str>,
+    pub table_id: TableId,
+    pub table_name: Arc<str>,
+    /// The time of the deletion, in nanoseconds since the epoch
+    pub deletion_time: i64,
+}
+
+/// A named token, and the permissions that it grants
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct TokenDefinition {
+    pub name: Arc<str>,
+    /// The hex encoded SHA-512 hash of the token; the token itself is never stored
+    pub hash: Arc<str>,
+    pub permissions: Vec<TokenPermission>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The time the token was created, in nanoseconds since the epoch
+    #[serde(default)]
+    pub created_at_ns: i64,
+    /// The time after which the token is no longer valid, in nanoseconds since the epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at_ns: Option<i64>,
+}
+
+impl TokenDefinition {
+    /// Check if this token has expired as of `now_ns`
+    pub fn is_expired(&self, now_ns: i64) -> bool {
+        self.expires_at_ns.is_some_and(|exp| exp <= now_ns)
+    }
+
+    /// Check if this token grants the `action` on the database with the given name
+    ///
+    /// Passing [`TokenPermission::ALL_DATABASES`] as the database checks that the action is
+    /// granted on every database.
+    pub fn allows(&self, database: &str, action: TokenAction) -> bool {
+        self.permissions.iter().any(|p| {
+            (p.database.as_ref() == TokenPermission::ALL_DATABASES
+                || p.database.as_ref() == database)
+                && p.actions
+                    .iter()
+                    .any(|a| *a == action || *a == TokenAction::Admin)
+        })
+    }
+}
+
+/// The actions granted by a token on a database, or on all databases
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct TokenPermission {
+    pub database: Arc<str>,
+    pub actions: Vec<TokenAction>,
+}
+
+impl TokenPermission {
+    /// The database name used to grant actions on all databases
+    pub const ALL_DATABASES: &'static str = "*";
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenAction {
+    /// Query data
+    Read,
+    /// Write data
+    Write,
+    /// Manage configuration, such as caches, along with read and write access
+    Admin,
+}
+
+/// Revokes the named token
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeleteTokenDefinition {
+    pub name: Arc<str>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -252,6 +445,15 @@ pub struct DatabaseDefinition {
     pub database_name: Arc<str>,
 }
 
+/// Sets, or clears, the retention period of a database
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPeriodDefinition {
+    pub database_id: DbId,
+    pub database_name: Arc<str>,
+    /// The retention period in seconds, or `None` to retain data indefinitely
+    pub retention_period_seconds: Option<u64>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct TableDefinition {
     pub database_id: DbId,
@@ -475,6 +677,30 @@ pub struct LastCacheDelete {
     pub name: Arc<str>,
 }
 
+/// Defines a metadata cache in a given table and database
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct MetaCacheDefinition {
+    /// The id of the associated table
+    pub table_id: TableId,
+    /// The name of the associated table
+    pub table_name: Arc<str>,
+    /// The name of the cache, is unique within the associated table
+    pub cache_name: Arc<str>,
+    /// The ids of columns tracked by this metadata cache, in the defined order
+    pub column_ids: Vec<ColumnId>,
+    /// The maximum number of distinct value combinations the cache will hold
+    pub max_cardinality: usize,
+    /// The maximum age in seconds, similar to a time-to-live (TTL), for entries in the cache
+    pub max_age_seconds: u64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MetaCacheDelete {
+    pub table_name: Arc<str>,
+    pub table_id: TableId,
+    pub cache_name: Arc<str>,
+}
+
 #[serde_as]
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct WriteBatch {
@@ -772,3 +998,61 @@ pub fn background_wal_flush<W: Wal>(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gen1_duration_accepts_durations_that_divide_a_day() {
+        for (s, seconds) in [
+            ("1s", 1),
+            ("30s", 30),
+            ("1m", 60),
+            ("5m", 300),
+            ("10m", 600),
+            ("15m", 900),
+            ("1h", 3600),
+            ("6h", 21600),
+            ("1d", 86400),
+        ] {
+            assert_eq!(
+                Duration::from_secs(seconds),
+                Gen1Duration::from_str(s).unwrap().as_duration(),
+                "parsing {s}"
+            );
+        }
+
+        for s in ["0s", "7m", "500ms", "1m 10s", "2d", "ten minutes"] {
+            assert!(
+                matches!(
+                    Gen1Duration::from_str(s),
+                    Err(Error::InvalidGen1Duration(_))
+                ),
+                "parsing {s}"
+            );
+        }
+    }
+
+    #[test]
+    fn gen1_duration_chunk_time_for_timestamp() {
+        let one_hour = Gen1Duration::from_str("1h").unwrap();
+        let hour_ns = 3_600_000_000_000;
+        assert_eq!(0, one_hour.chunk_time_for_timestamp(Timestamp::new(0)));
+        assert_eq!(
+            hour_ns,
+            one_hour.chunk_time_for_timestamp(Timestamp::new(hour_ns + 59))
+        );
+        // timestamps before the epoch belong to the chunk that starts before them:
+        assert_eq!(
+            -hour_ns,
+            one_hour.chunk_time_for_timestamp(Timestamp::new(-1))
+        );
+
+        let thirty_seconds = Gen1Duration::from_str("30s").unwrap();
+        assert_eq!(
+            90_000_000_000,
+            thirty_seconds.chunk_time_for_timestamp(Timestamp::new(119_999_999_999))
+        );
+    }
+}
@@ -0,0 +1,1044 @@
+//! A [`Wal`] implementation that persists WAL files to a directory on local disk before
+//! acknowledging writes, and ships the sealed files to object store in the background.
+//!
+//! Every flush of the buffer is written to a new segment file named by its
+//! [`WalFileSequenceNumber`], so all writes buffered during a flush interval share a single
+//! fsync. Once a segment has been synced to disk it is never modified again and is uploaded to
+//! the same path in object store that [`WalObjectStore`](crate::object_store::WalObjectStore)
+//! would have written it to. Replay on startup reads the segments from the local directory,
+//! along with any segments in object store that are missing from it.
+
+use crate::object_store::{list_wal_files, wal_path, FlushBuffer, WalBuffer, WriteResult};
+use crate::serialize::{serialize_to_file_bytes, verify_file_type_and_deserialize};
+use crate::snapshot_tracker::{SnapshotInfo, SnapshotTracker, WalPeriod};
+use crate::{
+    background_wal_flush, SnapshotDetails, SnapshotSequenceNumber, Wal, WalCompression, WalConfig,
+    WalContents, WalFileNotifier, WalFileSequenceNumber, WalOp,
+};
+use bytes::Bytes;
+use data_types::Timestamp;
+use object_store::{ObjectStore, PutPayload};
+use observability_deps::tracing::{debug, error, info, warn};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot, Mutex, OwnedSemaphorePermit};
+use tokio::time::Instant;
+
+/// The extension of sealed segment files in the local WAL directory
+const SEGMENT_FILE_EXTENSION: &str = "wal";
+
+/// The extension of segment files that are still being written
+const TEMP_SEGMENT_FILE_EXTENSION: &str = "wal.tmp";
+
+#[derive(Debug)]
+pub struct WalLocalDisk {
+    dir: PathBuf,
+    object_store: Arc<dyn ObjectStore>,
+    host_identifier_prefix: String,
+    file_notifier: Arc<dyn WalFileNotifier>,
+    compression: WalCompression,
+    /// Buffered wal ops go in here along with the state to track when to snapshot
+    flush_buffer: Mutex<FlushBuffer>,
+    /// Requests for the background task that uploads sealed segments to object store
+    shipper: mpsc::UnboundedSender<ShipperRequest>,
+}
+
+/// The work done by the background shipper task. Uploads and deletes are handled by a single
+/// task, in order, so that a segment can't be uploaded after it has been removed by a snapshot.
+#[derive(Debug)]
+enum ShipperRequest {
+    Upload(WalFileSequenceNumber),
+    Remove {
+        wal_file_numbers: Vec<WalFileSequenceNumber>,
+        snapshot_permit: OwnedSemaphorePermit,
+    },
+    Sync(oneshot::Sender<()>),
+}
+
+/// How the shipper retries uploads of segments to object store that fail
+///
+/// Segments that still can't be uploaded after `max_attempts` are left on local disk, and are
+/// shipped again the next time the WAL is replayed.
+#[derive(Debug, Clone, Copy)]
+struct UploadRetryConfig {
+    max_attempts: u32,
+    /// How long a single upload may take before it is treated as failed
+    attempt_timeout: Duration,
+    /// The delay before the first retry, which doubles on each subsequent retry
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for UploadRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            attempt_timeout: Duration::from_secs(30),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Where a segment is read from when the WAL is replayed
+#[derive(Debug)]
+enum SegmentLocation {
+    Local(PathBuf),
+    ObjectStore(object_store::path::Path),
+}
+
+impl WalLocalDisk {
+    /// Creates a new WAL that writes to the given local directory, creating it if necessary.
+    /// This will replay the segments in the directory into the notifier and trigger any
+    /// snapshots that exist in them that haven't been cleaned up yet.
+    pub async fn new(
+        dir: impl Into<PathBuf> + Send,
+        object_store: Arc<dyn ObjectStore>,
+        host_identifier_prefix: impl Into<String> + Send,
+        file_notifier: Arc<dyn WalFileNotifier>,
+        config: WalConfig,
+        last_wal_sequence_number: Option<WalFileSequenceNumber>,
+        last_snapshot_sequence_number: Option<SnapshotSequenceNumber>,
+    ) -> Result<Arc<Self>, crate::Error> {
+        let flush_interval = config.flush_interval;
+        let wal = Self::new_without_replay(
+            dir,
+            object_store,
+            host_identifier_prefix,
+            file_notifier,
+            config,
+            last_wal_sequence_number,
+            last_snapshot_sequence_number,
+            UploadRetryConfig::default(),
+        )
+        .await?;
+
+        wal.replay().await?;
+        let wal = Arc::new(wal);
+        background_wal_flush(Arc::clone(&wal), flush_interval);
+
+        Ok(wal)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn new_without_replay(
+        dir: impl Into<PathBuf> + Send,
+        object_store: Arc<dyn ObjectStore>,
+        host_identifier_prefix: impl Into<String> + Send,
+        file_notifier: Arc<dyn WalFileNotifier>,
+        config: WalConfig,
+        last_wal_sequence_number: Option<WalFileSequenceNumber>,
+        last_snapshot_sequence_number: Option<SnapshotSequenceNumber>,
+        upload_retry: UploadRetryConfig,
+    ) -> Result<Self, crate::Error> {
+        let dir = dir.into();
+        let host_identifier_prefix = host_identifier_prefix.into();
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let (shipper, requests) = mpsc::unbounded_channel();
+        tokio::spawn(
+            SegmentShipper {
+                dir: dir.clone(),
+                object_store: Arc::clone(&object_store),
+                host_identifier_prefix: host_identifier_prefix.clone(),
+                upload_retry,
+                failed_uploads: BTreeMap::new(),
+            }
+            .run(requests),
+        );
+
+        let wal_file_sequence_number = last_wal_sequence_number.unwrap_or_default().next();
+        Ok(Self {
+            dir,
+            object_store,
+            host_identifier_prefix,
+            file_notifier,
+            compression: config.compression,
+            flush_buffer: Mutex::new(FlushBuffer::new(
+                WalBuffer::new(wal_file_sequence_number, config.max_write_buffer_size),
+                SnapshotTracker::new(
+                    config.snapshot_size,
+                    config.gen1_duration,
+                    last_snapshot_sequence_number,
+                ),
+            )),
+            shipper,
+        })
+    }
+
+    /// Loads the segments in order from the local directory and from object store, calling the
+    /// file notifier on each one and populating the snapshot tracker with the WAL periods.
+    ///
+    /// A segment that is in both places is only replayed once, from local disk. Every local
+    /// segment is queued for upload, in case the server stopped before it was shipped to object
+    /// store.
+    pub async fn replay(&self) -> crate::Result<()> {
+        for (wal_file_number, location) in self.load_existing_segments().await? {
+            let file_bytes = match location {
+                SegmentLocation::Local(path) => {
+                    self.ship(ShipperRequest::Upload(wal_file_number));
+                    Bytes::from(tokio::fs::read(&path).await?)
+                }
+                SegmentLocation::ObjectStore(path) => {
+                    self.object_store.get(&path).await?.bytes().await?
+                }
+            };
+            let wal_contents = verify_file_type_and_deserialize(file_bytes)?;
+
+            // add this to the snapshot tracker, so we know what to clear out later if the replay
+            // was a wal file that had a snapshot
+            self.flush_buffer
+                .lock()
+                .await
+                .replay_wal_period(WalPeriod::new(
+                    wal_contents.wal_file_number,
+                    Timestamp::new(wal_contents.min_timestamp_ns),
+                    Timestamp::new(wal_contents.max_timestamp_ns),
+                ));
+
+            match wal_contents.snapshot {
+                None => self.file_notifier.notify(wal_contents),
+                Some(snapshot_details) => {
+                    let snapshot_info = {
+                        let mut buffer = self.flush_buffer.lock().await;
+
+                        match buffer.snapshot_tracker.snapshot() {
+                            None => None,
+                            Some(info) => {
+                                let semaphore = Arc::clone(&buffer.snapshot_semaphore);
+                                let permit = semaphore.acquire_owned().await.unwrap();
+
+                                Some((info, permit))
+                            }
+                        }
+                    };
+
+                    let snapshot_done = self
+                        .file_notifier
+                        .notify_and_snapshot(wal_contents, snapshot_details)
+                        .await;
+                    let details = snapshot_done.await.unwrap();
+                    assert_eq!(snapshot_details, details);
+
+                    // if the info is there, we have wal files to delete
+                    if let Some((snapshot_info, snapshot_permit)) = snapshot_info {
+                        self.remove_snapshot_segments(snapshot_info, snapshot_permit);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stop accepting write operations, flush of buffered writes to a segment and return once
+    /// an upload of every sealed segment to object store has been attempted. Segments that could
+    /// not be uploaded remain on local disk, and are shipped again when the WAL is next replayed.
+    pub async fn shutdown(&self) {
+        // stop accepting writes
+        self.flush_buffer.lock().await.wal_buffer.is_shutdown = true;
+
+        // do the flush and wait for the snapshot if that's running
+        if let Some((snapshot_done, snapshot_info, snapshot_permit)) = self.flush_buffer().await {
+            let snapshot_details = snapshot_done.await.expect("snapshot should complete");
+            assert_eq!(snapshot_info.snapshot_details, snapshot_details);
+            self.remove_snapshot_segments(snapshot_info, snapshot_permit);
+        }
+
+        self.sync_shipper().await;
+    }
+
+    /// Returns once the shipper has handled every request sent to it before this call.
+    ///
+    /// Uploads that failed are retried in the background, so this does not wait for them.
+    pub async fn sync_shipper(&self) {
+        let (tx, rx) = oneshot::channel();
+        self.ship(ShipperRequest::Sync(tx));
+        let _ = rx.await;
+    }
+
+    async fn buffer_op_unconfirmed(&self, op: WalOp) -> crate::Result<(), crate::Error> {
+        self.flush_buffer
+            .lock()
+            .await
+            .wal_buffer
+            .buffer_op_unconfirmed(op)
+    }
+
+    async fn write_ops(&self, ops: Vec<WalOp>) -> crate::Result<(), crate::Error> {
+        let (tx, rx) = oneshot::channel();
+        self.flush_buffer
+            .lock()
+            .await
+            .wal_buffer
+            .buffer_ops_with_response(ops, tx)?;
+
+        match rx.await {
+            Ok(WriteResult::Success(())) => Ok(()),
+            Ok(WriteResult::Error(e)) => Err(crate::Error::WriteError(e)),
+            Err(_) => Err(crate::Error::WriteError(
+                "oneshot channel closed".to_string(),
+            )),
+        }
+    }
+
+    async fn flush_buffer(
+        &self,
+    ) -> Option<(
+        oneshot::Receiver<SnapshotDetails>,
+        SnapshotInfo,
+        OwnedSemaphorePermit,
+    )> {
+        let (wal_contents, responses, snapshot) = {
+            let mut flush_buffer = self.flush_buffer.lock().await;
+            if flush_buffer.wal_buffer.is_empty() {
+                return None;
+            }
+            flush_buffer
+                .flush_buffer_into_contents_and_responses()
+                .await
+        };
+        info!(
+            n_ops = %wal_contents.ops.len(),
+            min_timestamp_ns = %wal_contents.min_timestamp_ns,
+            max_timestamp_ns = %wal_contents.max_timestamp_ns,
+            wal_file_number = %wal_contents.wal_file_number,
+            snapshot_details = ?wal_contents.snapshot,
+            "flushing WAL buffer to local disk"
+        );
+
+        let data = serialize_to_file_bytes(&wal_contents, self.compression)
+            .expect("unable to serialize wal contents into bytes for file");
+
+        let mut retry_count = 0;
+
+        // every write buffered since the last flush is made durable by this one fsync
+        loop {
+            match self
+                .write_segment(wal_contents.wal_file_number, &data)
+                .await
+            {
+                Ok(_) => break,
+                Err(e) => {
+                    error!(%e, "error writing wal segment to local disk");
+                    retry_count += 1;
+                    if retry_count > 10 {
+                        // the disk isn't recovering, so fail these responses and any in the
+                        // new buffer
+                        for response in responses {
+                            let _ = response.send(WriteResult::Error(e.to_string()));
+                        }
+
+                        self.flush_buffer
+                            .lock()
+                            .await
+                            .flush_buffer_with_failure(WriteResult::Error(e.to_string()))
+                            .await;
+
+                        return None;
+                    }
+
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+
+        // the segment is sealed, so it can be shipped to object store
+        self.ship(ShipperRequest::Upload(wal_contents.wal_file_number));
+
+        // now that we've persisted this latest notify and start the snapshot, if set
+        let snapshot_response = match wal_contents.snapshot {
+            Some(snapshot_details) => {
+                info!(?snapshot_details, "snapshotting wal");
+                let snapshot_done = self
+                    .file_notifier
+                    .notify_and_snapshot(wal_contents, snapshot_details)
+                    .await;
+                let (snapshot_info, snapshot_permit) =
+                    snapshot.expect("snapshot should be set when snapshot details are set");
+                Some((snapshot_done, snapshot_info, snapshot_permit))
+            }
+            None => {
+                debug!(
+                    "notify sent to buffer for wal file {}",
+                    wal_contents.wal_file_number.as_u64()
+                );
+                self.file_notifier.notify(wal_contents);
+                None
+            }
+        };
+
+        // send all the responses back to clients
+        for response in responses {
+            let _ = response.send(WriteResult::Success(()));
+        }
+
+        snapshot_response
+    }
+
+    /// Writes the segment to a temporary file, syncs it and then renames it into place, so
+    /// that a crash part way through a write never leaves a partial segment to be replayed.
+    async fn write_segment(
+        &self,
+        wal_file_number: WalFileSequenceNumber,
+        data: &[u8],
+    ) -> std::io::Result<()> {
+        let temp_path =
+            segment_path(&self.dir, wal_file_number).with_extension(TEMP_SEGMENT_FILE_EXTENSION);
+        let mut file = tokio::fs::File::create(&temp_path).await?;
+        file.write_all(data).await?;
+        file.sync_all().await?;
+
+        tokio::fs::rename(&temp_path, segment_path(&self.dir, wal_file_number)).await?;
+        // sync the directory so the rename itself is durable
+        tokio::fs::File::open(&self.dir).await?.sync_all().await
+    }
+
+    /// The segments to replay, ordered by sequence number
+    async fn load_existing_segments(
+        &self,
+    ) -> crate::Result<BTreeMap<WalFileSequenceNumber, SegmentLocation>> {
+        let mut segments = BTreeMap::new();
+        // segments in object store may be missing locally, e.g., if the local directory was
+        // lost, or a snapshot removed the local segment before it was removed from object store
+        for meta in list_wal_files(self.object_store.as_ref(), &self.host_identifier_prefix).await?
+        {
+            segments.insert(
+                WalFileSequenceNumber::try_from(&meta.location)?,
+                SegmentLocation::ObjectStore(meta.location),
+            );
+        }
+        // local segments take precedence, as they may not have been shipped yet
+        for (wal_file_number, path) in self.load_existing_segment_paths().await? {
+            segments.insert(wal_file_number, SegmentLocation::Local(path));
+        }
+
+        Ok(segments)
+    }
+
+    async fn load_existing_segment_paths(
+        &self,
+    ) -> crate::Result<Vec<(WalFileSequenceNumber, PathBuf)>> {
+        let mut segments = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_FILE_EXTENSION) {
+                // leftovers from a write that didn't complete were never acknowledged
+                if path
+                    .to_string_lossy()
+                    .ends_with(TEMP_SEGMENT_FILE_EXTENSION)
+                {
+                    warn!(?path, "removing incomplete wal segment");
+                    tokio::fs::remove_file(&path).await?;
+                }
+                continue;
+            }
+
+            let Some(wal_file_number) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse().ok())
+            else {
+                // e.g., a copy of a segment, which was not written by the wal
+                warn!(
+                    ?path,
+                    "ignoring wal file that is not named after a sequence number"
+                );
+                continue;
+            };
+            segments.push((WalFileSequenceNumber::new(wal_file_number), path));
+        }
+        segments.sort();
+
+        Ok(segments)
+    }
+
+    fn remove_snapshot_segments(
+        &self,
+        snapshot_info: SnapshotInfo,
+        snapshot_permit: OwnedSemaphorePermit,
+    ) {
+        // the permit is released by the shipper once the segments are gone, so the next
+        // snapshot can be run when the time comes
+        self.ship(ShipperRequest::Remove {
+            wal_file_numbers: snapshot_info
+                .wal_periods
+                .into_iter()
+                .map(|period| period.wal_file_number)
+                .collect(),
+            snapshot_permit,
+        });
+    }
+
+    fn ship(&self, request: ShipperRequest) {
+        if self.shipper.send(request).is_err() {
+            error!("wal segment shipper is not running");
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Wal for WalLocalDisk {
+    async fn buffer_op_unconfirmed(&self, op: WalOp) -> crate::Result<(), crate::Error> {
+        self.buffer_op_unconfirmed(op).await
+    }
+
+    async fn write_ops(&self, ops: Vec<WalOp>) -> crate::Result<(), crate::Error> {
+        self.write_ops(ops).await
+    }
+
+    async fn flush_buffer(
+        &self,
+    ) -> Option<(
+        oneshot::Receiver<SnapshotDetails>,
+        SnapshotInfo,
+        OwnedSemaphorePermit,
+    )> {
+        self.flush_buffer().await
+    }
+
+    async fn cleanup_snapshot(
+        &self,
+        snapshot_info: SnapshotInfo,
+        snapshot_permit: OwnedSemaphorePermit,
+    ) {
+        self.remove_snapshot_segments(snapshot_info, snapshot_permit)
+    }
+
+    async fn last_wal_sequence_number(&self) -> WalFileSequenceNumber {
+        self.flush_buffer
+            .lock()
+            .await
+            .snapshot_tracker
+            .last_wal_sequence_number()
+    }
+
+    async fn last_snapshot_sequence_number(&self) -> SnapshotSequenceNumber {
+        self.flush_buffer
+            .lock()
+            .await
+            .snapshot_tracker
+            .last_snapshot_sequence_number()
+    }
+
+    async fn reserve_snapshot(&self) -> (SnapshotSequenceNumber, OwnedSemaphorePermit) {
+        self.flush_buffer.lock().await.reserve_snapshot().await
+    }
+
+    async fn shutdown(&self) {
+        self.shutdown().await
+    }
+}
+
+/// The path of the segment file for the given sequence number in the local WAL directory
+pub fn segment_path(dir: &Path, wal_file_number: WalFileSequenceNumber) -> PathBuf {
+    dir.join(format!(
+        "{:011}.{SEGMENT_FILE_EXTENSION}",
+        wal_file_number.as_u64()
+    ))
+}
+
+/// The background task that uploads sealed segments to object store and removes the segments
+/// covered by a snapshot, until the WAL is dropped
+#[derive(Debug)]
+struct SegmentShipper {
+    dir: PathBuf,
+    object_store: Arc<dyn ObjectStore>,
+    host_identifier_prefix: String,
+    upload_retry: UploadRetryConfig,
+    /// Uploads that failed, with the number of attempts made and when to retry them
+    failed_uploads: BTreeMap<WalFileSequenceNumber, (u32, Instant)>,
+}
+
+impl SegmentShipper {
+    /// Handles requests in order. Failed uploads are retried in between requests, rather than
+    /// in a loop, so that a segment that can't be uploaded doesn't hold up the removal of
+    /// segments after a snapshot, which holds the snapshot permit, or shutdown.
+    async fn run(mut self, mut requests: mpsc::UnboundedReceiver<ShipperRequest>) {
+        loop {
+            let next_retry = self.failed_uploads.values().map(|(_, at)| *at).min();
+            let request = tokio::select! {
+                request = requests.recv() => match request {
+                    Some(request) => request,
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(next_retry.unwrap_or_else(Instant::now)),
+                    if next_retry.is_some() =>
+                {
+                    self.retry_failed_uploads().await;
+                    continue;
+                }
+            };
+
+            match request {
+                ShipperRequest::Upload(wal_file_number) => {
+                    self.upload(wal_file_number, 0).await;
+                }
+                ShipperRequest::Remove {
+                    wal_file_numbers,
+                    snapshot_permit,
+                } => {
+                    for wal_file_number in wal_file_numbers {
+                        self.remove(wal_file_number).await;
+                    }
+                    drop(snapshot_permit);
+                }
+                ShipperRequest::Sync(done) => {
+                    let _ = done.send(());
+                }
+            }
+        }
+    }
+
+    async fn retry_failed_uploads(&mut self) {
+        let now = Instant::now();
+        let due = self
+            .failed_uploads
+            .iter()
+            .filter(|(_, (_, at))| *at <= now)
+            .map(|(wal_file_number, (attempts, _))| (*wal_file_number, *attempts))
+            .collect::<Vec<_>>();
+        for (wal_file_number, attempts) in due {
+            self.failed_uploads.remove(&wal_file_number);
+            self.upload(wal_file_number, attempts).await;
+        }
+    }
+
+    /// Makes one attempt to upload the segment, scheduling a retry if it fails
+    async fn upload(&mut self, wal_file_number: WalFileSequenceNumber, previous_attempts: u32) {
+        let data = match tokio::fs::read(segment_path(&self.dir, wal_file_number)).await {
+            Ok(data) => Bytes::from(data),
+            Err(e) => {
+                // the segment is only removed after a snapshot, so there is nothing left that
+                // needs it in object store
+                warn!(%e, %wal_file_number, "unable to read wal segment for upload");
+                return;
+            }
+        };
+        let path = wal_path(&self.host_identifier_prefix, wal_file_number);
+        let error = match tokio::time::timeout(
+            self.upload_retry.attempt_timeout,
+            self.object_store.put(&path, PutPayload::from_bytes(data)),
+        )
+        .await
+        {
+            Ok(Ok(_)) => return,
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => "upload timed out".to_string(),
+        };
+
+        // the segment is already durable on local disk, so it can be retried later
+        let attempts = previous_attempts + 1;
+        if attempts >= self.upload_retry.max_attempts {
+            error!(
+                %error,
+                %wal_file_number,
+                attempts,
+                "giving up uploading wal segment, it will be uploaded again on restart"
+            );
+            return;
+        }
+        let backoff = self
+            .upload_retry
+            .initial_backoff
+            .saturating_mul(2_u32.saturating_pow(attempts - 1))
+            .min(self.upload_retry.max_backoff);
+        error!(
+            %error,
+            %wal_file_number,
+            attempts,
+            ?backoff,
+            "error uploading wal segment to object store, retrying"
+        );
+        self.failed_uploads
+            .insert(wal_file_number, (attempts, Instant::now() + backoff));
+    }
+
+    async fn remove(&mut self, wal_file_number: WalFileSequenceNumber) {
+        // a removed segment no longer needs to be uploaded
+        self.failed_uploads.remove(&wal_file_number);
+
+        let local_path = segment_path(&self.dir, wal_file_number);
+        match tokio::fs::remove_file(&local_path).await {
+            Ok(_) => (),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => error!(%e, ?local_path, "error deleting wal segment"),
+        }
+
+        let path = wal_path(&self.host_identifier_prefix, wal_file_number);
+        loop {
+            match self.object_store.delete(&path).await {
+                Ok(_) | Err(object_store::Error::NotFound { .. }) => break,
+                Err(object_store::Error::Generic { store, source }) => {
+                    error!(%store, %source, "error deleting wal file");
+                    // hopefully just a temporary error, keep trying until we succeed
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+                Err(e) => {
+                    error!(%e, "error deleting wal file");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Gen1Duration, SnapshotSequenceNumber, TableChunks, WriteBatch};
+    use async_trait::async_trait;
+    use indexmap::IndexMap;
+    use influxdb3_id::{DbId, TableId};
+    use object_store::memory::InMemory;
+    use object_store::throttle::{ThrottleConfig, ThrottledStore};
+    use std::any::Any;
+    use tokio::sync::oneshot::Receiver;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn write_ship_replay_and_cleanup() {
+        let dir = test_helpers::tmp_dir().unwrap();
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let notifier: Arc<dyn WalFileNotifier> = Arc::new(TestNotifier::default());
+        let wal_config = WalConfig {
+            max_write_buffer_size: 100,
+            flush_interval: Duration::from_secs(1),
+            snapshot_size: 2,
+            compression: WalCompression::default(),
+            gen1_duration: Gen1Duration::new_1m(),
+        };
+        let wal = WalLocalDisk::new_without_replay(
+            dir.path(),
+            Arc::clone(&object_store),
+            "my_host",
+            Arc::clone(&notifier),
+            wal_config,
+            None,
+            None,
+            UploadRetryConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        // two writes buffered before a flush go into one segment
+        wal.buffer_op_unconfirmed(write_op(1)).await.unwrap();
+        wal.buffer_op_unconfirmed(write_op(2)).await.unwrap();
+        assert!(wal.flush_buffer().await.is_none());
+        wal.buffer_op_unconfirmed(write_op(62_000_000_000))
+            .await
+            .unwrap();
+        assert!(wal.flush_buffer().await.is_none());
+        wal.sync_shipper().await;
+
+        let first = WalFileSequenceNumber::new(1);
+        let second = WalFileSequenceNumber::new(2);
+        for wal_file_number in [first, second] {
+            let local = tokio::fs::read(segment_path(dir.path(), wal_file_number))
+                .await
+                .unwrap();
+            let remote = object_store
+                .get(&wal_path("my_host", wal_file_number))
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap();
+            assert_eq!(local, remote.to_vec());
+        }
+        let notified = notified_writes(&notifier);
+        assert_eq!(notified.len(), 2);
+        assert_eq!(notified[0].wal_file_number, first);
+        assert_eq!(notified[0].min_timestamp_ns, 1);
+        assert_eq!(notified[0].max_timestamp_ns, 2);
+
+        // a new wal on the same directory replays the local segments
+        let replay_notifier: Arc<dyn WalFileNotifier> = Arc::new(TestNotifier::default());
+        let replay_wal = WalLocalDisk::new_without_replay(
+            dir.path(),
+            Arc::new(InMemory::new()),
+            "my_host",
+            Arc::clone(&replay_notifier),
+            wal_config,
+            None,
+            None,
+            UploadRetryConfig::default(),
+        )
+        .await
+        .unwrap();
+        replay_wal.replay().await.unwrap();
+        assert_eq!(notified_writes(&replay_notifier), notified);
+        assert_eq!(replay_wal.last_wal_sequence_number().await, second);
+
+        // the third flush triggers a snapshot of the first two segments, which are then removed
+        // locally and from object store
+        wal.buffer_op_unconfirmed(write_op(128_000_000_000))
+            .await
+            .unwrap();
+        let (snapshot_done, snapshot_info, snapshot_permit) = wal.flush_buffer().await.unwrap();
+        let snapshot_details = snapshot_done.await.unwrap();
+        assert_eq!(
+            snapshot_details.snapshot_sequence_number,
+            SnapshotSequenceNumber::new(1)
+        );
+        assert_eq!(snapshot_details.last_wal_sequence_number, second);
+        wal.cleanup_snapshot(snapshot_info, snapshot_permit).await;
+        wal.sync_shipper().await;
+
+        for wal_file_number in [first, second] {
+            assert!(!segment_path(dir.path(), wal_file_number).exists());
+            assert!(object_store
+                .get(&wal_path("my_host", wal_file_number))
+                .await
+                .is_err());
+        }
+        assert!(segment_path(dir.path(), WalFileSequenceNumber::new(3)).exists());
+    }
+
+    #[tokio::test]
+    async fn incomplete_segments_are_not_replayed() {
+        let dir = test_helpers::tmp_dir().unwrap();
+        let notifier: Arc<dyn WalFileNotifier> = Arc::new(TestNotifier::default());
+        let temp_path = segment_path(dir.path(), WalFileSequenceNumber::new(1))
+            .with_extension(TEMP_SEGMENT_FILE_EXTENSION);
+        tokio::fs::write(&temp_path, b"idb3.002partial")
+            .await
+            .unwrap();
+
+        let wal = WalLocalDisk::new_without_replay(
+            dir.path(),
+            Arc::new(InMemory::new()),
+            "my_host",
+            Arc::clone(&notifier),
+            WalConfig::test_config(),
+            None,
+            None,
+            UploadRetryConfig::default(),
+        )
+        .await
+        .unwrap();
+        wal.replay().await.unwrap();
+
+        assert!(notified_writes(&notifier).is_empty());
+        assert!(!temp_path.exists());
+    }
+
+    #[tokio::test]
+    async fn files_not_named_after_a_sequence_number_are_skipped() {
+        let dir = test_helpers::tmp_dir().unwrap();
+        let notifier: Arc<dyn WalFileNotifier> = Arc::new(TestNotifier::default());
+        let stray_path = dir.path().join("00000000001 (copy).wal");
+        tokio::fs::write(&stray_path, b"idb3.002partial")
+            .await
+            .unwrap();
+
+        let wal = WalLocalDisk::new_without_replay(
+            dir.path(),
+            Arc::new(InMemory::new()),
+            "my_host",
+            Arc::clone(&notifier),
+            WalConfig::test_config(),
+            None,
+            None,
+            UploadRetryConfig::default(),
+        )
+        .await
+        .unwrap();
+        wal.replay().await.unwrap();
+
+        assert!(notified_writes(&notifier).is_empty());
+        assert!(stray_path.exists());
+    }
+
+    #[tokio::test]
+    async fn replay_reads_segments_missing_from_local_disk() {
+        let dir = test_helpers::tmp_dir().unwrap();
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let notifier: Arc<dyn WalFileNotifier> = Arc::new(TestNotifier::default());
+        let wal = WalLocalDisk::new_without_replay(
+            dir.path(),
+            Arc::clone(&object_store),
+            "my_host",
+            Arc::clone(&notifier),
+            WalConfig::test_config(),
+            None,
+            None,
+            UploadRetryConfig::default(),
+        )
+        .await
+        .unwrap();
+        for time in [1, 2, 3] {
+            wal.buffer_op_unconfirmed(write_op(time)).await.unwrap();
+            assert!(wal.flush_buffer().await.is_none());
+        }
+        wal.sync_shipper().await;
+
+        // the first segment is only in object store, and the last only on local disk:
+        tokio::fs::remove_file(segment_path(dir.path(), WalFileSequenceNumber::new(1)))
+            .await
+            .unwrap();
+        object_store
+            .delete(&wal_path("my_host", WalFileSequenceNumber::new(3)))
+            .await
+            .unwrap();
+
+        // every segment is replayed once, in order:
+        let replay_notifier: Arc<dyn WalFileNotifier> = Arc::new(TestNotifier::default());
+        let replay_wal = WalLocalDisk::new_without_replay(
+            dir.path(),
+            Arc::clone(&object_store),
+            "my_host",
+            Arc::clone(&replay_notifier),
+            WalConfig::test_config(),
+            None,
+            None,
+            UploadRetryConfig::default(),
+        )
+        .await
+        .unwrap();
+        replay_wal.replay().await.unwrap();
+        assert_eq!(
+            notified_writes(&replay_notifier),
+            notified_writes(&notifier)
+        );
+        assert_eq!(
+            replay_wal.last_wal_sequence_number().await,
+            WalFileSequenceNumber::new(3)
+        );
+
+        // and the segment that was only on local disk is shipped:
+        replay_wal.sync_shipper().await;
+        assert!(object_store
+            .get(&wal_path("my_host", WalFileSequenceNumber::new(3)))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn stuck_uploads_do_not_block_snapshot_cleanup() {
+        let dir = test_helpers::tmp_dir().unwrap();
+        // uploads never complete:
+        let object_store: Arc<dyn ObjectStore> = Arc::new(ThrottledStore::new(
+            InMemory::new(),
+            ThrottleConfig {
+                wait_put_per_call: Duration::from_secs(3600),
+                ..Default::default()
+            },
+        ));
+        let notifier: Arc<dyn WalFileNotifier> = Arc::new(TestNotifier::default());
+        let wal_config = WalConfig {
+            max_write_buffer_size: 100,
+            flush_interval: Duration::from_secs(1),
+            snapshot_size: 2,
+            compression: WalCompression::default(),
+            gen1_duration: Gen1Duration::new_1m(),
+        };
+        let wal = WalLocalDisk::new_without_replay(
+            dir.path(),
+            object_store,
+            "my_host",
+            Arc::clone(&notifier),
+            wal_config,
+            None,
+            None,
+            UploadRetryConfig {
+                max_attempts: 1_000,
+                attempt_timeout: Duration::from_millis(10),
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(10),
+            },
+        )
+        .await
+        .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            for time in [1, 62_000_000_000] {
+                wal.buffer_op_unconfirmed(write_op(time)).await.unwrap();
+                assert!(wal.flush_buffer().await.is_none());
+            }
+            wal.sync_shipper().await;
+
+            // the snapshot's segments are removed while their uploads are still being retried:
+            wal.buffer_op_unconfirmed(write_op(128_000_000_000))
+                .await
+                .unwrap();
+            let (snapshot_done, snapshot_info, snapshot_permit) = wal.flush_buffer().await.unwrap();
+            snapshot_done.await.unwrap();
+            wal.cleanup_snapshot(snapshot_info, snapshot_permit).await;
+            wal.sync_shipper().await;
+            for wal_file_number in [1, 2] {
+                assert!(
+                    !segment_path(dir.path(), WalFileSequenceNumber::new(wal_file_number)).exists()
+                );
+            }
+
+            // and shutdown doesn't wait for the upload that can't complete:
+            wal.shutdown().await;
+        })
+        .await
+        .expect("snapshot cleanup and shutdown should not wait for uploads");
+
+        // the segment that was not uploaded is kept on local disk, to be shipped on restart:
+        assert!(segment_path(dir.path(), WalFileSequenceNumber::new(3)).exists());
+    }
+
+    fn write_op(time: i64) -> WalOp {
+        WalOp::Write(WriteBatch {
+            database_id: DbId::from(0),
+            database_name: "db1".into(),
+            table_chunks: IndexMap::from([(
+                TableId::from(0),
+                TableChunks {
+                    min_time: time,
+                    max_time: time,
+                    chunk_time_to_chunk: Default::default(),
+                },
+            )])
+            .into(),
+            min_time_ns: time,
+            max_time_ns: time,
+        })
+    }
+
+    fn notified_writes(notifier: &Arc<dyn WalFileNotifier>) -> Vec<WalContents> {
+        notifier
+            .as_any()
+            .downcast_ref::<TestNotifier>()
+            .unwrap()
+            .notified_writes
+            .lock()
+            .clone()
+    }
+
+    #[derive(Debug, Default)]
+    struct TestNotifier {
+        notified_writes: parking_lot::Mutex<Vec<WalContents>>,
+    }
+
+    #[async_trait]
+    impl WalFileNotifier for TestNotifier {
+        fn notify(&self, write: WalContents) {
+            self.notified_writes.lock().push(write);
+        }
+
+        async fn notify_and_snapshot(
+            &self,
+            write: WalContents,
+            snapshot_details: SnapshotDetails,
+        ) -> Receiver<SnapshotDetails> {
+            self.notified_writes.lock().push(write);
+
+            let (sender, receiver) = tokio::sync::oneshot::channel();
+            sender.send(snapshot_details).unwrap();
+            receiver
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+}
@@ -1,15 +1,16 @@
 use crate::serialize::verify_file_type_and_deserialize;
 use crate::snapshot_tracker::{SnapshotInfo, SnapshotTracker, WalPeriod};
 use crate::{
-    background_wal_flush, CatalogBatch, SnapshotDetails, SnapshotSequenceNumber, Wal, WalConfig,
-    WalContents, WalFileNotifier, WalFileSequenceNumber, WalOp, WriteBatch,
+    background_wal_flush, CatalogBatch, SnapshotDetails, SnapshotSequenceNumber, Wal,
+    WalCompression, WalConfig, WalContents, WalFileNotifier, WalFileSequenceNumber, WalOp,
+    WriteBatch,
 };
 use bytes::Bytes;
 use data_types::Timestamp;
 use futures_util::stream::StreamExt;
 use hashbrown::HashMap;
 use object_store::path::{Path, PathPart};
-use object_store::{ObjectStore, PutPayload};
+use object_store::{ObjectMeta, ObjectStore, PutPayload};
 use observability_deps::tracing::{debug, error, info};
 use std::sync::Arc;
 use std::time::Duration;
@@ -21,6 +22,7 @@ pub struct WalObjectStore {
     object_store: Arc<dyn ObjectStore>,
     host_identifier_prefix: String,
     file_notifier: Arc<dyn WalFileNotifier>,
+    compression: WalCompression,
     /// Buffered wal ops go in here along with the state to track when to snapshot
     flush_buffer: Mutex<FlushBuffer>,
 }
@@ -66,16 +68,9 @@ impl WalObjectStore {
             object_store,
             host_identifier_prefix: host_identifier_prefix.into(),
             file_notifier,
+            compression: config.compression,
             flush_buffer: Mutex::new(FlushBuffer::new(
-                WalBuffer {
-                    is_shutdown: false,
-                    wal_file_sequence_number,
-                    op_limit: config.max_write_buffer_size,
-                    op_count: 0,
-                    database_to_write_batch: Default::default(),
-                    catalog_batches: vec![],
-                    write_op_responses: vec![],
-                },
+                WalBuffer::new(wal_file_sequence_number, config.max_write_buffer_size),
                 SnapshotTracker::new(
                     config.snapshot_size,
                     config.gen1_duration,
@@ -208,7 +203,7 @@ impl WalObjectStore {
         );
 
         let wal_path = wal_path(&self.host_identifier_prefix, wal_contents.wal_file_number);
-        let data = crate::serialize::serialize_to_file_bytes(&wal_contents)
+        let data = crate::serialize::serialize_to_file_bytes(&wal_contents, self.compression)
             .expect("unable to serialize wal contents into bytes for file");
         let data = Bytes::from(data);
 
@@ -279,31 +274,13 @@ impl WalObjectStore {
     }
 
     async fn load_existing_wal_file_paths(&self) -> crate::Result<Vec<Path>> {
-        let mut paths = Vec::new();
-        let mut offset: Option<Path> = None;
-        let path = Path::from(format!("{host}/wal", host = self.host_identifier_prefix));
-        loop {
-            let mut listing = if let Some(offset) = offset {
-                self.object_store.list_with_offset(Some(&path), &offset)
-            } else {
-                self.object_store.list(Some(&path))
-            };
-            let path_count = paths.len();
-
-            while let Some(item) = listing.next().await {
-                paths.push(item?.location);
-            }
-
-            if path_count == paths.len() {
-                break;
-            }
-
-            paths.sort();
-            offset = Some(paths.last().unwrap().clone())
-        }
-        paths.sort();
-
-        Ok(paths)
+        Ok(
+            list_wal_files(self.object_store.as_ref(), &self.host_identifier_prefix)
+                .await?
+                .into_iter()
+                .map(|meta| meta.location)
+                .collect(),
+        )
     }
 
     async fn remove_snapshot_wal_files(
@@ -382,20 +359,24 @@ impl Wal for WalObjectStore {
             .last_snapshot_sequence_number()
     }
 
+    async fn reserve_snapshot(&self) -> (SnapshotSequenceNumber, OwnedSemaphorePermit) {
+        self.flush_buffer.lock().await.reserve_snapshot().await
+    }
+
     async fn shutdown(&self) {
         self.shutdown().await
     }
 }
 
 #[derive(Debug)]
-struct FlushBuffer {
-    wal_buffer: WalBuffer,
-    snapshot_tracker: SnapshotTracker,
-    snapshot_semaphore: Arc<Semaphore>,
+pub(crate) struct FlushBuffer {
+    pub(crate) wal_buffer: WalBuffer,
+    pub(crate) snapshot_tracker: SnapshotTracker,
+    pub(crate) snapshot_semaphore: Arc<Semaphore>,
 }
 
 impl FlushBuffer {
-    fn new(wal_buffer: WalBuffer, snapshot_tracker: SnapshotTracker) -> Self {
+    pub(crate) fn new(wal_buffer: WalBuffer, snapshot_tracker: SnapshotTracker) -> Self {
         Self {
             wal_buffer,
             snapshot_tracker,
@@ -403,14 +384,14 @@ impl FlushBuffer {
         }
     }
 
-    fn replay_wal_period(&mut self, wal_period: WalPeriod) {
+    pub(crate) fn replay_wal_period(&mut self, wal_period: WalPeriod) {
         self.wal_buffer.wal_file_sequence_number = wal_period.wal_file_number.next();
         self.snapshot_tracker.add_wal_period(wal_period);
     }
 
     /// Converts the wal_buffer into contents and resets it. Returns the channels waiting for
     /// responses. If a snapshot should occur with this flush, a semaphore permit is also returned.
-    async fn flush_buffer_into_contents_and_responses(
+    pub(crate) async fn flush_buffer_into_contents_and_responses(
         &mut self,
     ) -> (
         WalContents,
@@ -437,6 +418,18 @@ impl FlushBuffer {
         (wal_contents, responses, snapshot)
     }
 
+    /// Waits for the snapshot in progress, if any, to finish and reserves the next snapshot
+    /// sequence number for a snapshot persisted outside of the WAL
+    pub(crate) async fn reserve_snapshot(
+        &mut self,
+    ) -> (SnapshotSequenceNumber, OwnedSemaphorePermit) {
+        let permit = self.acquire_snapshot_permit().await;
+        (
+            self.snapshot_tracker.increment_snapshot_sequence_number(),
+            permit,
+        )
+    }
+
     async fn acquire_snapshot_permit(&self) -> OwnedSemaphorePermit {
         let snapshot_semaphore = Arc::clone(&self.snapshot_semaphore);
         snapshot_semaphore
@@ -461,7 +454,7 @@ impl FlushBuffer {
         new_buffer.into_wal_contents_and_responses()
     }
 
-    async fn flush_buffer_with_failure(&mut self, error: WriteResult) {
+    pub(crate) async fn flush_buffer_with_failure(&mut self, error: WriteResult) {
         let (_, responses) = self.flush_buffer_with_responses();
         for response in responses {
             let _ = response.send(error.clone());
@@ -470,18 +463,30 @@ impl FlushBuffer {
 }
 
 #[derive(Debug, Default)]
-struct WalBuffer {
-    is_shutdown: bool,
-    wal_file_sequence_number: WalFileSequenceNumber,
-    op_limit: usize,
-    op_count: usize,
+pub(crate) struct WalBuffer {
+    pub(crate) is_shutdown: bool,
+    pub(crate) wal_file_sequence_number: WalFileSequenceNumber,
+    pub(crate) op_limit: usize,
+    pub(crate) op_count: usize,
     database_to_write_batch: HashMap<Arc<str>, WriteBatch>,
     catalog_batches: Vec<CatalogBatch>,
     write_op_responses: Vec<oneshot::Sender<WriteResult>>,
 }
 
 impl WalBuffer {
-    fn is_empty(&self) -> bool {
+    pub(crate) fn new(wal_file_sequence_number: WalFileSequenceNumber, op_limit: usize) -> Self {
+        Self {
+            is_shutdown: false,
+            wal_file_sequence_number,
+            op_limit,
+            op_count: 0,
+            database_to_write_batch: Default::default(),
+            catalog_batches: vec![],
+            write_op_responses: vec![],
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
         self.database_to_write_batch.is_empty() && self.catalog_batches.is_empty()
     }
 }
@@ -496,7 +501,7 @@ pub enum WriteResult {
 }
 
 impl WalBuffer {
-    fn buffer_op_unconfirmed(&mut self, op: WalOp) -> crate::Result<(), crate::Error> {
+    pub(crate) fn buffer_op_unconfirmed(&mut self, op: WalOp) -> crate::Result<(), crate::Error> {
         if self.op_count >= self.op_limit {
             return Err(crate::Error::BufferFull(self.op_count));
         }
@@ -530,7 +535,7 @@ impl WalBuffer {
         Ok(())
     }
 
-    fn buffer_ops_with_response(
+    pub(crate) fn buffer_ops_with_response(
         &mut self,
         ops: Vec<WalOp>,
         response: oneshot::Sender<WriteResult>,
@@ -583,6 +588,38 @@ impl WalBuffer {
     }
 }
 
+/// Lists the WAL files in object store for the given host, ordered by their sequence number.
+pub async fn list_wal_files(
+    object_store: &dyn ObjectStore,
+    host_identifier_prefix: &str,
+) -> crate::Result<Vec<ObjectMeta>> {
+    let mut files: Vec<ObjectMeta> = Vec::new();
+    let mut offset: Option<Path> = None;
+    let path = Path::from(format!("{host_identifier_prefix}/wal"));
+    loop {
+        let mut listing = if let Some(offset) = offset {
+            object_store.list_with_offset(Some(&path), &offset)
+        } else {
+            object_store.list(Some(&path))
+        };
+        let file_count = files.len();
+
+        while let Some(item) = listing.next().await {
+            files.push(item?);
+        }
+
+        if file_count == files.len() {
+            break;
+        }
+
+        files.sort_by(|a, b| a.location.cmp(&b.location));
+        offset = Some(files.last().unwrap().location.clone())
+    }
+    files.sort_by(|a, b| a.location.cmp(&b.location));
+
+    Ok(files)
+}
+
 pub fn wal_path(host_identifier_prefix: &str, wal_file_number: WalFileSequenceNumber) -> Path {
     Path::from(format!(
         "{host_identifier_prefix}/wal/{:011}.wal",
@@ -627,6 +664,7 @@ mod tests {
             max_write_buffer_size: 100,
             flush_interval: Duration::from_secs(1),
             snapshot_size: 2,
+            compression: WalCompression::default(),
             gen1_duration: Gen1Duration::new_1m(),
         };
         let wal = WalObjectStore::new_without_replay(
@@ -847,6 +885,7 @@ mod tests {
                 max_write_buffer_size: 10,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 2,
+                compression: WalCompression::default(),
             },
             None,
             None,
@@ -1020,6 +1059,7 @@ mod tests {
             max_write_buffer_size: 100,
             flush_interval: Duration::from_secs(1),
             snapshot_size: 2,
+            compression: WalCompression::default(),
             gen1_duration: Gen1Duration::new_1m(),
         };
         let wal = WalObjectStore::new_without_replay(
@@ -1,8 +1,19 @@
 //! Module for serializing and deserializing the contents of a single WAL file. Since the WAL is
 //! buffered in memory before writing it in a single PUT operation to object store, this works
 //! a little differently than a traditional WAL that appends.
+//!
+//! WAL files start with a file type identifier that determines how the rest of the file is laid
+//! out:
+//!
+//! * `idb3.001`: a crc32 checksum, followed by the JSON serialized [`WalContents`]
+//! * `idb3.002`: a crc32 checksum, followed by a byte identifying the [`WalCompression`] used,
+//!   and the MessagePack serialized [`WalContents`], compressed accordingly. The checksum covers
+//!   everything after it.
+//!
+//! Only the latest version is written, but all versions can be read, so that WAL files written
+//! before an upgrade can still be replayed.
 
-use crate::WalContents;
+use crate::{WalCompression, WalContents};
 use byteorder::{BigEndian, ReadBytesExt};
 use bytes::Bytes;
 use std::io::Cursor;
@@ -20,6 +31,18 @@ pub enum Error {
     #[error("Serde error: {0}")]
     Serde(#[from] serde_json::Error),
 
+    #[error("error encoding wal contents: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+
+    #[error("error decoding wal contents: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+
+    #[error("snappy compression error: {0}")]
+    Snappy(#[from] snap::Error),
+
+    #[error("unknown wal compression identifier: {0}")]
+    UnknownCompression(u8),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -29,23 +52,30 @@ pub enum Error {
 
 pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// The identifier of the original WAL file format, which holds JSON serialized contents.
+const FILE_TYPE_IDENTIFIER_V1: &[u8] = b"idb3.001";
+
 /// The first bytes written into a wal file to identify it and its version.
-const FILE_TYPE_IDENTIFIER: &[u8] = b"idb3.001";
+const FILE_TYPE_IDENTIFIER: &[u8] = b"idb3.002";
+
+const CHECKSUM_LEN: usize = size_of::<u32>();
+
+/// The zstd compression level used for WAL files, which favours speed, since WAL files are
+/// written on the write path.
+const ZSTD_LEVEL: i32 = 1;
 
 pub fn verify_file_type_and_deserialize(b: Bytes) -> Result<WalContents> {
     let contents = b.to_vec();
 
     let pos = FILE_TYPE_IDENTIFIER.len();
-
-    // Read and verify the file type identifier
-    let file_type = &contents[..pos];
-
-    if file_type != FILE_TYPE_IDENTIFIER {
+    if contents.len() < pos + CHECKSUM_LEN {
         return Err(Error::InvalidWalFile);
     }
 
+    // Read the file type identifier, which is the same length for all versions
+    let file_type = &contents[..pos];
+
     // Read the crc32 checksum
-    const CHECKSUM_LEN: usize = size_of::<u32>();
     let checksum_slice = &contents[pos..pos + CHECKSUM_LEN]; // Ensure this slice covers the 4 bytes for the checksum
     let mut cursor = Cursor::new(checksum_slice);
     let crc32_checksum = cursor.read_u32::<BigEndian>().unwrap();
@@ -61,17 +91,41 @@ pub fn verify_file_type_and_deserialize(b: Bytes) -> Result<WalContents> {
     }
 
     // Deserialize the data into a WalContents
-    let contents: WalContents = serde_json::from_slice(data)?;
-
-    Ok(contents)
+    if file_type == FILE_TYPE_IDENTIFIER {
+        let (&compression, data) = data.split_first().ok_or(Error::InvalidWalFile)?;
+        let data = match WalCompression::from_id(compression)? {
+            WalCompression::None => data.to_vec(),
+            WalCompression::Snappy => snap::raw::Decoder::new().decompress_vec(data)?,
+            WalCompression::Zstd => zstd::stream::decode_all(data)?,
+        };
+        Ok(rmp_serde::from_slice(&data)?)
+    } else if file_type == FILE_TYPE_IDENTIFIER_V1 {
+        Ok(serde_json::from_slice(data)?)
+    } else {
+        Err(Error::InvalidWalFile)
+    }
 }
 
-pub(crate) fn serialize_to_file_bytes(contents: &WalContents) -> Result<Vec<u8>> {
+pub(crate) fn serialize_to_file_bytes(
+    contents: &WalContents,
+    compression: WalCompression,
+) -> Result<Vec<u8>> {
     let mut buf = Vec::new();
     buf.extend_from_slice(FILE_TYPE_IDENTIFIER);
 
-    // serialize the contents into json bytes
-    let data = serde_json::to_vec(contents)?;
+    // serialize the contents into MessagePack bytes, and compress them. Structs are encoded as
+    // maps, rather than arrays, so that serde attributes like internally tagged enums work:
+    let encoded = rmp_serde::to_vec_named(contents)?;
+    let mut data = vec![compression.id()];
+    match compression {
+        WalCompression::None => data.extend_from_slice(&encoded),
+        WalCompression::Snappy => {
+            data.extend_from_slice(&snap::raw::Encoder::new().compress_vec(&encoded)?)
+        }
+        WalCompression::Zstd => {
+            data.extend_from_slice(&zstd::stream::encode_all(encoded.as_slice(), ZSTD_LEVEL)?)
+        }
+    }
 
     // calculate the crc32 checksum
     let mut hasher = crc32fast::Hasher::new();
@@ -85,16 +139,36 @@ pub(crate) fn serialize_to_file_bytes(contents: &WalContents) -> Result<Vec<u8>>
     Ok(buf)
 }
 
+impl WalCompression {
+    /// The byte used to identify the compression in a WAL file
+    fn id(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Snappy => 1,
+            Self::Zstd => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Snappy),
+            2 => Ok(Self::Zstd),
+            _ => Err(Error::UnknownCompression(id)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        Field, FieldData, Row, TableChunk, TableChunks, WalFileSequenceNumber, WalOp, WriteBatch,
+        create, Field, FieldData, Row, TableChunk, TableChunks, WalFileSequenceNumber, WalOp,
+        WriteBatch,
     };
     use influxdb3_id::{ColumnId, DbId, SerdeVecMap, TableId};
 
-    #[test]
-    fn test_serialize_deserialize() {
+    fn test_contents() -> WalContents {
         let chunk = TableChunk {
             rows: vec![Row {
                 time: 1,
@@ -119,23 +193,74 @@ mod tests {
         let mut table_chunks = SerdeVecMap::new();
         table_chunks.insert(table_id, chunks);
 
-        let contents = WalContents {
+        WalContents {
             min_timestamp_ns: 0,
             max_timestamp_ns: 10,
             wal_file_number: WalFileSequenceNumber::new(1),
-            ops: vec![WalOp::Write(WriteBatch {
-                database_id: DbId::from(0),
-                database_name: "foo".into(),
-                table_chunks,
-                min_time_ns: 0,
-                max_time_ns: 10,
-            })],
+            ops: vec![
+                WalOp::Write(WriteBatch {
+                    database_id: DbId::from(0),
+                    database_name: "foo".into(),
+                    table_chunks,
+                    min_time_ns: 0,
+                    max_time_ns: 10,
+                }),
+                create::catalog_batch_op(
+                    DbId::from(0),
+                    "foo",
+                    0,
+                    [
+                        create::create_last_cache_op_builder(table_id, "bar", "cache", []).build(),
+                        create::set_schema_locked_op(DbId::from(0), "foo", None, true),
+                    ],
+                ),
+            ],
             snapshot: None,
-        };
+        }
+    }
 
-        let bytes = serialize_to_file_bytes(&contents).unwrap();
-        let deserialized = verify_file_type_and_deserialize(Bytes::from(bytes)).unwrap();
+    #[test]
+    fn test_serialize_deserialize() {
+        let contents = test_contents();
+        for compression in [
+            WalCompression::None,
+            WalCompression::Snappy,
+            WalCompression::Zstd,
+        ] {
+            let bytes = serialize_to_file_bytes(&contents, compression).unwrap();
+            assert_eq!(FILE_TYPE_IDENTIFIER, &bytes[..FILE_TYPE_IDENTIFIER.len()]);
+            let deserialized = verify_file_type_and_deserialize(Bytes::from(bytes)).unwrap();
+
+            assert_eq!(contents, deserialized);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_v1_json() {
+        let contents = test_contents();
+        let data = serde_json::to_vec(&contents).unwrap();
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&data);
+        let mut bytes = FILE_TYPE_IDENTIFIER_V1.to_vec();
+        bytes.extend_from_slice(&hasher.finalize().to_be_bytes());
+        bytes.extend_from_slice(&data);
 
+        let deserialized = verify_file_type_and_deserialize(Bytes::from(bytes)).unwrap();
         assert_eq!(contents, deserialized);
     }
+
+    #[test]
+    fn test_deserialize_corrupt_files() {
+        let mut bytes = serialize_to_file_bytes(&test_contents(), WalCompression::Zstd).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(matches!(
+            verify_file_type_and_deserialize(Bytes::from(bytes)),
+            Err(Error::Crc32Mismatch)
+        ));
+        assert!(matches!(
+            verify_file_type_and_deserialize(Bytes::from_static(b"idb3.00")),
+            Err(Error::InvalidWalFile)
+        ));
+    }
 }
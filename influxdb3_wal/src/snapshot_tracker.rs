@@ -74,7 +74,7 @@ impl SnapshotTracker {
                 .map(|period| period.max_time)
                 .max()
                 .unwrap();
-            let t = max_time - (max_time.get() % self.gen1_duration.as_nanos())
+            let t = max_time - max_time.get().rem_euclid(self.gen1_duration.as_nanos())
                 + self.gen1_duration.as_nanos();
             let last_wal_sequence_number = wal_periods.last().unwrap().wal_file_number;
 
@@ -92,7 +92,7 @@ impl SnapshotTracker {
 
         let t = self.wal_periods.last().unwrap().max_time;
         // round the last timestamp down to the gen1_duration
-        let t = t - (t.get() % self.gen1_duration.as_nanos());
+        let t = t - t.get().rem_euclid(self.gen1_duration.as_nanos());
 
         // any wal period that has data before this time can be snapshot
         let periods_to_snapshot = self
@@ -134,7 +134,7 @@ impl SnapshotTracker {
         self.last_snapshot_sequence_number
     }
 
-    fn increment_snapshot_sequence_number(&mut self) -> SnapshotSequenceNumber {
+    pub(crate) fn increment_snapshot_sequence_number(&mut self) -> SnapshotSequenceNumber {
         self.last_snapshot_sequence_number = self.last_snapshot_sequence_number.next();
         self.last_snapshot_sequence_number
     }
@@ -315,4 +315,41 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn snapshot_rounds_times_before_the_epoch_down() {
+        let mut tracker = SnapshotTracker::new(2, Gen1Duration::new_1m(), None);
+        let p1 = WalPeriod::new(
+            WalFileSequenceNumber::new(1),
+            Timestamp::new(-120_000000000),
+            Timestamp::new(-90_000000000),
+        );
+        let p2 = WalPeriod::new(
+            WalFileSequenceNumber::new(2),
+            Timestamp::new(-90_000000000),
+            Timestamp::new(-61_000000000),
+        );
+        let p3 = WalPeriod::new(
+            WalFileSequenceNumber::new(3),
+            Timestamp::new(-60_000000000),
+            Timestamp::new(-30_500000000),
+        );
+
+        tracker.add_wal_period(p1.clone());
+        tracker.add_wal_period(p2.clone());
+        tracker.add_wal_period(p3.clone());
+
+        // the last period is in the chunk starting at -60s, so it is not snapshotted:
+        assert_eq!(
+            tracker.snapshot(),
+            Some(SnapshotInfo {
+                snapshot_details: SnapshotDetails {
+                    snapshot_sequence_number: SnapshotSequenceNumber::new(1),
+                    end_time_marker: -60_000000000,
+                    last_wal_sequence_number: WalFileSequenceNumber::new(2)
+                },
+                wal_periods: vec![p1, p2]
+            })
+        );
+    }
 }
@@ -0,0 +1,912 @@
+//! Backup and restore of the durable state of a host.
+//!
+//! A backup pins the newest snapshot and the catalog persisted with or after it, and copies every
+//! object needed to rebuild the host at that point into a backup store: the catalog, the
+//! snapshots loaded on start, the parquet files that those snapshots still reference, and the
+//! WAL files written since the pinned snapshot. If a newer snapshot removes any of those objects
+//! while they are copied, the objects are selected again from the newer snapshot. The copied
+//! objects are listed in a [`BackupManifest`].
+//!
+//! The backup store is laid out as:
+//!
+//! ```text
+//! manifests/<inverted backup sequence number>.json
+//! files/<path relative to the host prefix>
+//! ```
+//!
+//! Every object that a host persists is immutable once written, so a backup only copies the
+//! objects that are not already listed in the previous manifest, and a restore can rehydrate
+//! any backup from the shared `files/` directory.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use influxdb3_catalog::catalog::{Catalog, CatalogSequenceNumber};
+use influxdb3_wal::{object_store::list_wal_files, SnapshotSequenceNumber, WalFileSequenceNumber};
+use iox_time::{SystemProvider, TimeProvider};
+use object_store::path::Path as ObjPath;
+use object_store::{ObjectStore, PutPayload};
+use observability_deps::tracing::info;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::paths::{CatalogFilePath, SnapshotInfoFilePath};
+use crate::persister::Persister;
+use crate::write_buffer::N_SNAPSHOTS_TO_LOAD_ON_START;
+use crate::PersistedSnapshot;
+
+/// The number of objects that are copied concurrently during a backup or restore
+const COPY_CONCURRENCY: usize = 10;
+
+/// The number of times the objects of a backup are selected again, after objects that were
+/// selected are removed by a snapshot persisted during the backup
+const MAX_BACKUP_ATTEMPTS: usize = 5;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("object_store error: {0}")]
+    ObjectStore(#[from] object_store::Error),
+
+    #[error("serde_json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+
+    #[error("persister error: {0}")]
+    Persister(#[from] crate::persister::Error),
+
+    #[error("wal error: {0}")]
+    Wal(#[from] influxdb3_wal::Error),
+
+    #[error("no catalog has been persisted for host {0}")]
+    NoCatalog(String),
+
+    #[error(
+        "the backup store holds backups of host {existing}, which cannot be mixed with backups \
+        of host {requested}"
+    )]
+    HostMismatch { existing: String, requested: String },
+
+    #[error("no backup found with sequence number {0}")]
+    BackupNotFound(u64),
+
+    #[error("the backup store does not hold any backups")]
+    NoBackups,
+
+    #[error("host {0} already has objects in the target object store")]
+    HostNotEmpty(String),
+
+    #[error("invalid path {path} in backup manifest: {source}")]
+    InvalidPath {
+        path: String,
+        source: object_store::path::Error,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The record of the objects copied by a backup, which is enough to restore the host as of the
+/// pinned catalog and snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Increases by one with every backup taken into the same backup store
+    pub backup_sequence_number: u64,
+    /// The host that was backed up
+    pub host_identifier_prefix: String,
+    /// When the backup was taken, in nanoseconds since the epoch
+    pub created_at_ns: i64,
+    /// The sequence number of the catalog in the backup
+    pub catalog_sequence_number: CatalogSequenceNumber,
+    /// The sequence number of the newest snapshot in the backup, or `None` if the host had not
+    /// persisted a snapshot
+    pub snapshot_sequence_number: Option<SnapshotSequenceNumber>,
+    /// Every object in the backup, including those that were copied by earlier backups
+    pub files: Vec<BackupFile>,
+}
+
+/// An object in a backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupFile {
+    /// The path of the object, relative to the host prefix
+    pub path: String,
+    pub size_bytes: u64,
+}
+
+impl BackupManifest {
+    fn path(backup_sequence_number: u64) -> ObjPath {
+        ObjPath::from(format!(
+            "manifests/{:020}.json",
+            u64::MAX - backup_sequence_number
+        ))
+    }
+}
+
+/// Back up the host with the given prefix in `object_store` into `backup_store`. Unless `full`
+/// is set, objects that were copied by the previous backup are not copied again.
+pub async fn backup(
+    object_store: Arc<dyn ObjectStore>,
+    host_identifier_prefix: &str,
+    backup_store: Arc<dyn ObjectStore>,
+    full: bool,
+) -> Result<BackupManifest> {
+    let previous = latest_manifest(backup_store.as_ref()).await?;
+    if let Some(previous) = &previous {
+        if previous.host_identifier_prefix != host_identifier_prefix {
+            return Err(Error::HostMismatch {
+                existing: previous.host_identifier_prefix.clone(),
+                requested: host_identifier_prefix.to_string(),
+            });
+        }
+    }
+
+    let persister = Persister::new(Arc::clone(&object_store), host_identifier_prefix);
+    let host_prefix = ObjPath::from(host_identifier_prefix);
+    // the sizes of objects that are already in the backup store, by their relative path:
+    let mut copied: HashMap<String, u64> = match (&previous, full) {
+        (Some(previous), false) => previous
+            .files
+            .iter()
+            .map(|f| (f.path.clone(), f.size_bytes))
+            .collect(),
+        _ => HashMap::new(),
+    };
+    let n_previously_copied = copied.len();
+
+    // a snapshot persisted while the backup runs can delete WAL files and parquet files that were
+    // selected for the backup, in which case the objects are selected again from the new snapshot:
+    let mut attempt = 1;
+    let (sources, files) = loop {
+        let sources = BackupSources::load(&persister, object_store.as_ref()).await?;
+        let results: Vec<Result<BackupFile>> = stream::iter(&sources.paths)
+            .map(|source| {
+                let relative = relative_path(&host_prefix, source);
+                let size_bytes = copied.get(&relative).copied();
+                let object_store = Arc::clone(&object_store);
+                let backup_store = Arc::clone(&backup_store);
+                async move {
+                    let size_bytes = match size_bytes {
+                        Some(size_bytes) => size_bytes,
+                        None => {
+                            let bytes = object_store.get(source).await?.bytes().await?;
+                            let size_bytes = bytes.len() as u64;
+                            backup_store
+                                .put(&backup_file_path(&relative)?, PutPayload::from(bytes))
+                                .await?;
+                            size_bytes
+                        }
+                    };
+                    Ok::<_, Error>(BackupFile {
+                        path: relative,
+                        size_bytes,
+                    })
+                }
+            })
+            .buffered(COPY_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut files = Vec::with_capacity(results.len());
+        let mut error = None;
+        for result in results {
+            match result {
+                Ok(file) => {
+                    copied.insert(file.path.clone(), file.size_bytes);
+                    files.push(file);
+                }
+                Err(e) => error = error.or(Some(e)),
+            }
+        }
+        match error {
+            None => break (sources, files),
+            Some(Error::ObjectStore(object_store::Error::NotFound { path, .. }))
+                if attempt < MAX_BACKUP_ATTEMPTS =>
+            {
+                info!(
+                    %path,
+                    attempt,
+                    "object was removed during backup, selecting objects from the latest snapshot"
+                );
+                attempt += 1;
+            }
+            Some(e) => return Err(e),
+        }
+    };
+    let n_copied = copied.len() - n_previously_copied;
+    let BackupSources {
+        catalog_sequence_number,
+        snapshot_sequence_number,
+        ..
+    } = sources;
+
+    let manifest = BackupManifest {
+        backup_sequence_number: previous.map_or(1, |p| p.backup_sequence_number + 1),
+        host_identifier_prefix: host_identifier_prefix.to_string(),
+        created_at_ns: SystemProvider::new().now().timestamp_nanos(),
+        catalog_sequence_number,
+        snapshot_sequence_number,
+        files,
+    };
+    backup_store
+        .put(
+            &BackupManifest::path(manifest.backup_sequence_number),
+            serde_json::to_vec_pretty(&manifest)?.into(),
+        )
+        .await?;
+    info!(
+        backup_sequence_number = manifest.backup_sequence_number,
+        catalog_sequence_number = catalog_sequence_number.as_u32(),
+        n_files = manifest.files.len(),
+        n_copied,
+        "completed backup"
+    );
+
+    Ok(manifest)
+}
+
+/// The objects needed to restore a host as of its newest snapshot
+#[derive(Debug)]
+struct BackupSources {
+    catalog_sequence_number: CatalogSequenceNumber,
+    snapshot_sequence_number: Option<SnapshotSequenceNumber>,
+    paths: Vec<ObjPath>,
+}
+
+impl BackupSources {
+    async fn load(persister: &Persister, object_store: &dyn ObjectStore) -> Result<Self> {
+        let host_identifier_prefix = persister.host_identifier_prefix();
+        // the snapshots are loaded before the catalog, because the catalog is persisted before
+        // each snapshot, so the catalog loaded after them covers all of their tables and columns:
+        let snapshots = persister
+            .load_snapshots(N_SNAPSHOTS_TO_LOAD_ON_START)
+            .await?;
+        let catalog = persister
+            .load_catalog()
+            .await?
+            .map(Catalog::from_inner)
+            .ok_or_else(|| Error::NoCatalog(host_identifier_prefix.to_string()))?;
+        let catalog_sequence_number = catalog.sequence_number();
+        let mut paths = vec![
+            CatalogFilePath::new(host_identifier_prefix, catalog_sequence_number)
+                .as_ref()
+                .clone(),
+        ];
+
+        let snapshot_sequence_number = snapshots.first().map(|s| s.snapshot_sequence_number);
+        let last_wal_file_number = snapshots.first().map(|s| s.wal_file_sequence_number);
+
+        // the snapshots are ordered from newest to oldest, so files removed by a newer snapshot
+        // are known before they are seen in an older one:
+        let mut removed_file_ids = HashSet::new();
+        for snapshot in &snapshots {
+            paths.push(
+                SnapshotInfoFilePath::new(
+                    host_identifier_prefix,
+                    snapshot.snapshot_sequence_number,
+                )
+                .as_ref()
+                .clone(),
+            );
+            removed_file_ids.extend(
+                snapshot
+                    .removed_files
+                    .values()
+                    .flat_map(|db| db.tables.values().flatten())
+                    .map(|f| f.id),
+            );
+            paths.extend(
+                snapshot
+                    .databases
+                    .values()
+                    .flat_map(|db| db.tables.values().flatten())
+                    .filter(|f| !removed_file_ids.contains(&f.id))
+                    .map(|f| ObjPath::from(f.path.as_str())),
+            );
+        }
+
+        // WAL files written since the newest snapshot hold data that has not been persisted yet:
+        for file in list_wal_files(object_store, host_identifier_prefix).await? {
+            let Ok(wal_file_number) = WalFileSequenceNumber::try_from(&file.location) else {
+                continue;
+            };
+            // `None` is less than any file number, so all WAL files are included without a
+            // snapshot:
+            if Some(wal_file_number) > last_wal_file_number {
+                paths.push(file.location);
+            }
+        }
+
+        Ok(Self {
+            catalog_sequence_number,
+            snapshot_sequence_number,
+            paths,
+        })
+    }
+}
+
+/// Restore the backup with the given sequence number, or the latest backup, from `backup_store`
+/// into `object_store` as the host with the given prefix, which must not have any objects yet.
+pub async fn restore(
+    backup_store: Arc<dyn ObjectStore>,
+    backup_sequence_number: Option<u64>,
+    object_store: Arc<dyn ObjectStore>,
+    host_identifier_prefix: &str,
+) -> Result<BackupManifest> {
+    let manifest = match backup_sequence_number {
+        Some(n) => load_manifest(backup_store.as_ref(), n)
+            .await?
+            .ok_or(Error::BackupNotFound(n))?,
+        None => latest_manifest(backup_store.as_ref())
+            .await?
+            .ok_or(Error::NoBackups)?,
+    };
+
+    let host_prefix = ObjPath::from(host_identifier_prefix);
+    if let Some(existing) = object_store.list(Some(&host_prefix)).next().await {
+        existing?;
+        return Err(Error::HostNotEmpty(host_identifier_prefix.to_string()));
+    }
+
+    let old_host = manifest.host_identifier_prefix.as_str();
+    stream::iter(&manifest.files)
+        .map(|file| {
+            let backup_store = Arc::clone(&backup_store);
+            let object_store = Arc::clone(&object_store);
+            let host_prefix = host_prefix.clone();
+            async move {
+                let relative = ObjPath::parse(&file.path).map_err(|source| Error::InvalidPath {
+                    path: file.path.clone(),
+                    source,
+                })?;
+                let bytes = backup_store
+                    .get(&backup_file_path(&file.path)?)
+                    .await?
+                    .bytes()
+                    .await?;
+                // the catalog and snapshots record the host that wrote them, and snapshots also
+                // record the full path of each parquet file, so they are moved to the new host:
+                let bytes = match relative.parts().next().as_ref().map(|p| p.as_ref()) {
+                    Some("catalogs") => rehost_catalog(&bytes, host_identifier_prefix)?,
+                    Some("snapshots") => rehost_snapshot(&bytes, old_host, host_identifier_prefix)?,
+                    _ => bytes,
+                };
+                let target = ObjPath::from_iter(host_prefix.parts().chain(relative.parts()));
+                object_store.put(&target, PutPayload::from(bytes)).await?;
+                Ok::<_, Error>(())
+            }
+        })
+        .buffer_unordered(COPY_CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await?;
+    info!(
+        backup_sequence_number = manifest.backup_sequence_number,
+        from_host = old_host,
+        to_host = host_identifier_prefix,
+        n_files = manifest.files.len(),
+        "restored backup"
+    );
+
+    Ok(manifest)
+}
+
+/// List the manifests of all backups in `backup_store`, from newest to oldest
+pub async fn list_backups(backup_store: &dyn ObjectStore) -> Result<Vec<BackupManifest>> {
+    let mut paths = backup_store
+        .list(Some(&ObjPath::from("manifests")))
+        .map_ok(|meta| meta.location)
+        .try_collect::<Vec<_>>()
+        .await?;
+    paths.sort();
+
+    let mut manifests = Vec::with_capacity(paths.len());
+    for path in paths {
+        let bytes = backup_store.get(&path).await?.bytes().await?;
+        manifests.push(serde_json::from_slice(&bytes)?);
+    }
+    Ok(manifests)
+}
+
+async fn latest_manifest(backup_store: &dyn ObjectStore) -> Result<Option<BackupManifest>> {
+    // manifests are named by their inverted sequence number, so the first is the latest
+    let mut paths = backup_store
+        .list(Some(&ObjPath::from("manifests")))
+        .map_ok(|meta| meta.location)
+        .try_collect::<Vec<_>>()
+        .await?;
+    paths.sort();
+    match paths.first() {
+        Some(path) => {
+            let bytes = backup_store.get(path).await?.bytes().await?;
+            Ok(Some(serde_json::from_slice(&bytes)?))
+        }
+        None => Ok(None),
+    }
+}
+
+async fn load_manifest(
+    backup_store: &dyn ObjectStore,
+    backup_sequence_number: u64,
+) -> Result<Option<BackupManifest>> {
+    match backup_store
+        .get(&BackupManifest::path(backup_sequence_number))
+        .await
+    {
+        Ok(get) => Ok(Some(serde_json::from_slice(&get.bytes().await?)?)),
+        Err(object_store::Error::NotFound { .. }) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The path of `source` relative to the host prefix
+fn relative_path(host_prefix: &ObjPath, source: &ObjPath) -> String {
+    match source.prefix_match(host_prefix) {
+        Some(parts) => ObjPath::from_iter(parts).to_string(),
+        None => source.to_string(),
+    }
+}
+
+fn backup_file_path(relative: &str) -> Result<ObjPath> {
+    let relative = ObjPath::parse(relative).map_err(|source| Error::InvalidPath {
+        path: relative.to_string(),
+        source,
+    })?;
+    Ok(ObjPath::from_iter(
+        ObjPath::from("files").parts().chain(relative.parts()),
+    ))
+}
+
+fn rehost_catalog(bytes: &[u8], host_identifier_prefix: &str) -> Result<Bytes> {
+    let mut catalog: serde_json::Value = serde_json::from_slice(bytes)?;
+    if let Some(host_id) = catalog.get_mut("host_id") {
+        *host_id = host_identifier_prefix.into();
+    }
+    Ok(serde_json::to_vec_pretty(&catalog)?.into())
+}
+
+fn rehost_snapshot(bytes: &[u8], old_host: &str, host_identifier_prefix: &str) -> Result<Bytes> {
+    let mut snapshot: PersistedSnapshot = serde_json::from_slice(bytes)?;
+    snapshot.host_id = host_identifier_prefix.to_string();
+    let old_host = ObjPath::from(old_host);
+    let new_host = ObjPath::from(host_identifier_prefix);
+    for file in snapshot
+        .databases
+        .iter_mut()
+        .chain(snapshot.removed_files.iter_mut())
+        .flat_map(|(_, db)| db.tables.iter_mut())
+        .flat_map(|(_, files)| files.iter_mut())
+    {
+        let path = ObjPath::from(file.path.as_str());
+        if let Some(parts) = path.prefix_match(&old_host) {
+            file.path = ObjPath::from_iter(new_host.parts().chain(parts)).to_string();
+        }
+    }
+    Ok(serde_json::to_vec_pretty(&snapshot)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use futures::stream::BoxStream;
+    use influxdb3_id::{ColumnId, DbId, TableId};
+    use influxdb3_wal::{create, object_store::wal_path, FieldDataType, WalOp};
+    use object_store::memory::InMemory;
+    use object_store::{
+        GetOptions, GetResult, ListResult, MultipartUpload, ObjectMeta, PutMultipartOpts,
+        PutOptions, PutResult,
+    };
+
+    use super::*;
+    use crate::{ParquetFile, PersistedSnapshot};
+
+    /// An object store that makes changes to the inner store before the first `get` of the
+    /// `trigger` path, to change the host while a backup is copying its objects. A change with
+    /// `None` deletes the object.
+    #[derive(Debug)]
+    struct ChangeOnGet {
+        inner: Arc<dyn ObjectStore>,
+        trigger: ObjPath,
+        changes: parking_lot::Mutex<Option<Vec<(ObjPath, Option<Bytes>)>>>,
+    }
+
+    impl std::fmt::Display for ChangeOnGet {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "ChangeOnGet({})", self.inner)
+        }
+    }
+
+    #[async_trait]
+    impl ObjectStore for ChangeOnGet {
+        async fn put_opts(
+            &self,
+            location: &ObjPath,
+            payload: PutPayload,
+            opts: PutOptions,
+        ) -> object_store::Result<PutResult> {
+            self.inner.put_opts(location, payload, opts).await
+        }
+
+        async fn put_multipart_opts(
+            &self,
+            location: &ObjPath,
+            opts: PutMultipartOpts,
+        ) -> object_store::Result<Box<dyn MultipartUpload>> {
+            self.inner.put_multipart_opts(location, opts).await
+        }
+
+        async fn get_opts(
+            &self,
+            location: &ObjPath,
+            options: GetOptions,
+        ) -> object_store::Result<GetResult> {
+            if location == &self.trigger {
+                let changes = self.changes.lock().take();
+                for (path, bytes) in changes.into_iter().flatten() {
+                    match bytes {
+                        Some(bytes) => {
+                            self.inner.put(&path, bytes.into()).await?;
+                        }
+                        None => self.inner.delete(&path).await?,
+                    }
+                }
+            }
+            self.inner.get_opts(location, options).await
+        }
+
+        async fn delete(&self, location: &ObjPath) -> object_store::Result<()> {
+            self.inner.delete(location).await
+        }
+
+        fn list(
+            &self,
+            prefix: Option<&ObjPath>,
+        ) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+            self.inner.list(prefix)
+        }
+
+        async fn list_with_delimiter(
+            &self,
+            prefix: Option<&ObjPath>,
+        ) -> object_store::Result<ListResult> {
+            self.inner.list_with_delimiter(prefix).await
+        }
+
+        async fn copy(&self, from: &ObjPath, to: &ObjPath) -> object_store::Result<()> {
+            self.inner.copy(from, to).await
+        }
+
+        async fn copy_if_not_exists(
+            &self,
+            from: &ObjPath,
+            to: &ObjPath,
+        ) -> object_store::Result<()> {
+            self.inner.copy_if_not_exists(from, to).await
+        }
+    }
+
+    /// Persist a catalog with a single table for the host, returning the ids of the table
+    async fn create_catalog(persister: &Persister) -> (Catalog, DbId, TableId) {
+        let catalog = Catalog::new("host".into(), "instance".into());
+        let db_id = DbId::new();
+        let table_id = TableId::new();
+        let WalOp::Catalog(batch) = create::catalog_batch_op(
+            db_id,
+            "foo",
+            0,
+            [create::create_table_op(
+                db_id,
+                "foo",
+                table_id,
+                "cpu",
+                [create::field_def(
+                    ColumnId::from(0),
+                    "time",
+                    FieldDataType::Timestamp,
+                )],
+            )],
+        ) else {
+            unreachable!()
+        };
+        catalog.apply_catalog_batch(&batch).unwrap();
+        persister.persist_catalog(&catalog).await.unwrap();
+        (catalog, db_id, table_id)
+    }
+
+    async fn put(object_store: &dyn ObjectStore, path: &str) {
+        object_store
+            .put(&ObjPath::from(path), Bytes::from_static(b"data").into())
+            .await
+            .unwrap();
+    }
+
+    async fn persist_snapshot(
+        persister: &Persister,
+        catalog: &Catalog,
+        n: u64,
+        db_id: DbId,
+        table_id: TableId,
+        files: &[ParquetFile],
+    ) {
+        let mut snapshot = PersistedSnapshot::new(
+            "host".to_string(),
+            SnapshotSequenceNumber::new(n),
+            WalFileSequenceNumber::new(n),
+            catalog.sequence_number(),
+        );
+        for file in files {
+            put(persister.object_store().as_ref(), &file.path).await;
+            snapshot.add_parquet_file(db_id, table_id, file.clone());
+        }
+        persister.persist_snapshot(&snapshot).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn backup_incrementally_and_restore_to_new_host() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let backup_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Persister::new(Arc::clone(&object_store), "host");
+        let (catalog, db_id, table_id) = create_catalog(&persister).await;
+
+        let file_1 =
+            ParquetFile::create_for_test("host/dbs/foo-0/cpu-0/1970-01-01/00-00/1.parquet");
+        let file_2 =
+            ParquetFile::create_for_test("host/dbs/foo-0/cpu-0/1970-01-01/00-01/2.parquet");
+        persist_snapshot(&persister, &catalog, 1, db_id, table_id, &[file_1.clone()]).await;
+        persist_snapshot(&persister, &catalog, 2, db_id, table_id, &[file_2.clone()]).await;
+        // the first WAL file was persisted by the snapshots, the third has not been yet:
+        put(
+            object_store.as_ref(),
+            wal_path("host", WalFileSequenceNumber::new(1)).as_ref(),
+        )
+        .await;
+        put(
+            object_store.as_ref(),
+            wal_path("host", WalFileSequenceNumber::new(3)).as_ref(),
+        )
+        .await;
+
+        let first = backup(
+            Arc::clone(&object_store),
+            "host",
+            Arc::clone(&backup_store),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, first.backup_sequence_number);
+        assert_eq!(
+            Some(SnapshotSequenceNumber::new(2)),
+            first.snapshot_sequence_number
+        );
+        let mut paths: Vec<String> = first.files.iter().map(|f| f.path.clone()).collect();
+        paths.sort();
+        let mut expected = vec![
+            format!(
+                "catalogs/{:020}.json",
+                u64::MAX - catalog.sequence_number().as_u32() as u64
+            ),
+            "dbs/foo-0/cpu-0/1970-01-01/00-00/1.parquet".to_string(),
+            "dbs/foo-0/cpu-0/1970-01-01/00-01/2.parquet".to_string(),
+            format!("snapshots/{:020}.info.json", u64::MAX - 1),
+            format!("snapshots/{:020}.info.json", u64::MAX - 2),
+            "wal/00000000003.wal".to_string(),
+        ];
+        expected.sort();
+        assert_eq!(expected, paths);
+
+        // the second backup only copies the objects persisted since the first:
+        let file_3 =
+            ParquetFile::create_for_test("host/dbs/foo-0/cpu-0/1970-01-01/00-02/3.parquet");
+        persist_snapshot(&persister, &catalog, 3, db_id, table_id, &[file_3.clone()]).await;
+        backup_store
+            .delete(&backup_file_path("dbs/foo-0/cpu-0/1970-01-01/00-00/1.parquet").unwrap())
+            .await
+            .unwrap();
+        let second = backup(
+            Arc::clone(&object_store),
+            "host",
+            Arc::clone(&backup_store),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, second.backup_sequence_number);
+        assert!(backup_store
+            .head(&backup_file_path("dbs/foo-0/cpu-0/1970-01-01/00-02/3.parquet").unwrap())
+            .await
+            .is_ok());
+        assert!(backup_store
+            .head(&backup_file_path("dbs/foo-0/cpu-0/1970-01-01/00-00/1.parquet").unwrap())
+            .await
+            .is_err());
+        assert_eq!(2, list_backups(backup_store.as_ref()).await.unwrap().len());
+
+        // a full backup copies everything again:
+        backup(
+            Arc::clone(&object_store),
+            "host",
+            Arc::clone(&backup_store),
+            true,
+        )
+        .await
+        .unwrap();
+
+        // backups of another host cannot be taken into the same store:
+        assert!(matches!(
+            backup(
+                Arc::clone(&object_store),
+                "other",
+                Arc::clone(&backup_store),
+                false
+            )
+            .await,
+            Err(Error::HostMismatch { .. })
+        ));
+
+        let restored_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        restore(
+            Arc::clone(&backup_store),
+            Some(2),
+            Arc::clone(&restored_store),
+            "restored",
+        )
+        .await
+        .unwrap();
+        let restored = Persister::new(Arc::clone(&restored_store), "restored");
+        let restored_catalog = Catalog::from_inner(restored.load_catalog().await.unwrap().unwrap());
+        assert_eq!("restored", restored_catalog.host_id().as_ref());
+        assert_eq!(
+            catalog.sequence_number(),
+            restored_catalog.sequence_number()
+        );
+        let snapshots = restored.load_snapshots(10).await.unwrap();
+        assert_eq!(3, snapshots.len());
+        for snapshot in snapshots {
+            assert_eq!("restored", snapshot.host_id);
+            for file in snapshot
+                .databases
+                .values()
+                .flat_map(|db| db.tables.values().flatten())
+            {
+                assert!(file.path.starts_with("restored/dbs/"));
+                restored_store
+                    .head(&ObjPath::from(file.path.as_str()))
+                    .await
+                    .unwrap();
+            }
+        }
+        restored_store
+            .head(&wal_path("restored", WalFileSequenceNumber::new(3)))
+            .await
+            .unwrap();
+
+        // a host that already has objects is not overwritten:
+        assert!(matches!(
+            restore(backup_store, None, restored_store, "restored").await,
+            Err(Error::HostNotEmpty(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn backup_selects_objects_again_after_a_concurrent_snapshot() {
+        let inner: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let backup_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Persister::new(Arc::clone(&inner), "host");
+        let (catalog, db_id, table_id) = create_catalog(&persister).await;
+        let file_1 =
+            ParquetFile::create_for_test("host/dbs/foo-0/cpu-0/1970-01-01/00-00/1.parquet");
+        persist_snapshot(&persister, &catalog, 1, db_id, table_id, &[file_1.clone()]).await;
+        let wal_2 = wal_path("host", WalFileSequenceNumber::new(2));
+        put(inner.as_ref(), wal_2.as_ref()).await;
+
+        // a snapshot persists the data of the second WAL file and deletes it while it is being
+        // copied:
+        let file_2 =
+            ParquetFile::create_for_test("host/dbs/foo-0/cpu-0/1970-01-01/00-01/2.parquet");
+        let mut snapshot = PersistedSnapshot::new(
+            "host".to_string(),
+            SnapshotSequenceNumber::new(2),
+            WalFileSequenceNumber::new(2),
+            catalog.sequence_number(),
+        );
+        snapshot.add_parquet_file(db_id, table_id, file_2.clone());
+        let object_store: Arc<dyn ObjectStore> = Arc::new(ChangeOnGet {
+            inner: Arc::clone(&inner),
+            trigger: wal_2.clone(),
+            changes: parking_lot::Mutex::new(Some(vec![
+                (
+                    ObjPath::from(file_2.path.as_str()),
+                    Some(Bytes::from_static(b"data")),
+                ),
+                (
+                    SnapshotInfoFilePath::new("host", SnapshotSequenceNumber::new(2))
+                        .as_ref()
+                        .clone(),
+                    Some(serde_json::to_vec_pretty(&snapshot).unwrap().into()),
+                ),
+                (wal_2, None),
+            ])),
+        });
+
+        let manifest = backup(object_store, "host", Arc::clone(&backup_store), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(SnapshotSequenceNumber::new(2)),
+            manifest.snapshot_sequence_number
+        );
+        let mut paths: Vec<String> = manifest.files.iter().map(|f| f.path.clone()).collect();
+        paths.sort();
+        let mut expected = vec![
+            format!(
+                "catalogs/{:020}.json",
+                u64::MAX - catalog.sequence_number().as_u32() as u64
+            ),
+            "dbs/foo-0/cpu-0/1970-01-01/00-00/1.parquet".to_string(),
+            "dbs/foo-0/cpu-0/1970-01-01/00-01/2.parquet".to_string(),
+            format!("snapshots/{:020}.info.json", u64::MAX - 1),
+            format!("snapshots/{:020}.info.json", u64::MAX - 2),
+        ];
+        expected.sort();
+        assert_eq!(expected, paths);
+    }
+
+    #[tokio::test]
+    async fn backup_skips_files_removed_by_a_concurrent_snapshot() {
+        let inner: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let backup_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Persister::new(Arc::clone(&inner), "host");
+        let (catalog, db_id, table_id) = create_catalog(&persister).await;
+        let file_1 =
+            ParquetFile::create_for_test("host/dbs/foo-0/cpu-0/1970-01-01/00-00/1.parquet");
+        let file_2 =
+            ParquetFile::create_for_test("host/dbs/foo-0/cpu-0/1970-01-01/00-01/2.parquet");
+        persist_snapshot(
+            &persister,
+            &catalog,
+            1,
+            db_id,
+            table_id,
+            &[file_1.clone(), file_2.clone()],
+        )
+        .await;
+
+        // the first file expires, and is removed and deleted while it is being copied:
+        let mut snapshot = PersistedSnapshot::new(
+            "host".to_string(),
+            SnapshotSequenceNumber::new(2),
+            WalFileSequenceNumber::new(1),
+            catalog.sequence_number(),
+        );
+        snapshot
+            .removed_files
+            .entry(db_id)
+            .or_default()
+            .tables
+            .entry(table_id)
+            .or_default()
+            .push(file_1.clone());
+        let object_store: Arc<dyn ObjectStore> = Arc::new(ChangeOnGet {
+            inner: Arc::clone(&inner),
+            trigger: ObjPath::from(file_1.path.as_str()),
+            changes: parking_lot::Mutex::new(Some(vec![
+                (
+                    SnapshotInfoFilePath::new("host", SnapshotSequenceNumber::new(2))
+                        .as_ref()
+                        .clone(),
+                    Some(serde_json::to_vec_pretty(&snapshot).unwrap().into()),
+                ),
+                (ObjPath::from(file_1.path.as_str()), None),
+            ])),
+        });
+
+        let manifest = backup(object_store, "host", Arc::clone(&backup_store), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(SnapshotSequenceNumber::new(2)),
+            manifest.snapshot_sequence_number
+        );
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert!(!paths.contains(&"dbs/foo-0/cpu-0/1970-01-01/00-00/1.parquet"));
+        assert!(paths.contains(&"dbs/foo-0/cpu-0/1970-01-01/00-01/2.parquet"));
+    }
+}
@@ -0,0 +1,786 @@
+//! Consistency checks of the durable state of a host, and repair of the problems they find.
+//!
+//! [`check`] cross-references the catalog, snapshots, parquet files and WAL files of a host in
+//! object store, without trusting any of them, and reports every [`Problem`] it finds.
+//! [`repair`] then fixes those problems in a way that lets the host start and answer queries
+//! again, keeping a copy of anything it cannot read under `<host>/quarantine/`:
+//!
+//! * corrupt catalog, snapshot and WAL files are moved to the quarantine,
+//! * parquet files that are missing, or that do not match their snapshot, are removed from the
+//!   host's persisted files by a new snapshot, which also advances any `next_*_id` that is
+//!   behind the ids in the catalog or snapshots,
+//! * orphaned parquet files, and WAL files that were snapshotted but not removed, are deleted.
+//!
+//! Repairs should be made while the host is not running.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::TryStreamExt;
+use influxdb3_catalog::catalog::{Catalog, InnerCatalog};
+use influxdb3_id::{ColumnId, DbId, ParquetFileId, SerdeVecMap, TableId};
+use influxdb3_wal::{
+    object_store::list_wal_files, serialize::verify_file_type_and_deserialize,
+    SnapshotSequenceNumber, WalFileSequenceNumber,
+};
+use iox_time::Time;
+use object_store::path::Path as ObjPath;
+use object_store::{ObjectStore, PutMode, PutOptions};
+use observability_deps::tracing::info;
+use parquet::arrow::async_reader::{AsyncFileReader, ParquetObjectReader};
+use thiserror::Error;
+
+use crate::paths::{CatalogFilePath, SnapshotInfoFilePath};
+use crate::{DatabaseTables, ParquetFile, PersistedSnapshot};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("object_store error: {0}")]
+    ObjectStore(#[from] object_store::Error),
+
+    #[error("serde_json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+
+    #[error("wal error: {0}")]
+    Wal(#[from] influxdb3_wal::Error),
+
+    #[error("cannot repair parquet files without a readable snapshot to build on")]
+    NoSnapshot,
+
+    #[error("snapshot {0} was persisted since the check, is the server for the host running?")]
+    SnapshotPersistedSinceCheck(ObjPath),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A problem found by [`check`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// A catalog file that cannot be deserialized
+    CorruptCatalog { path: ObjPath, error: String },
+    /// A snapshot file that cannot be deserialized
+    CorruptSnapshot { path: ObjPath, error: String },
+    /// A WAL file that has not been snapshotted and cannot be deserialized, so cannot be replayed
+    CorruptWalFile { path: ObjPath, error: String },
+    /// A parquet file that is referenced by a snapshot but does not exist
+    MissingParquetFile(ParquetFileProblem),
+    /// A parquet file whose size differs from the size recorded in its snapshot
+    ParquetFileSizeMismatch {
+        file: ParquetFileProblem,
+        actual: u64,
+    },
+    /// A parquet file whose row count differs from the row count recorded in its snapshot
+    ParquetFileRowCountMismatch {
+        file: ParquetFileProblem,
+        actual: u64,
+    },
+    /// A parquet file whose metadata cannot be read
+    UnreadableParquetFile {
+        file: ParquetFileProblem,
+        error: String,
+    },
+    /// The next id of the given kind recorded in the latest snapshot, which is used on start,
+    /// is not greater than an id that is already in use
+    NextIdBehind {
+        kind: IdKind,
+        next_id: u64,
+        max_id: u64,
+    },
+    /// A parquet file that is not referenced by any snapshot, or that was removed by a snapshot
+    /// but not deleted
+    OrphanedParquetFile { path: ObjPath, size_bytes: u64 },
+    /// A WAL file that was persisted by a snapshot but not removed
+    SnapshottedWalFile { path: ObjPath },
+}
+
+/// A parquet file referenced by a snapshot, and where it is referenced
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParquetFileProblem {
+    pub snapshot_sequence_number: SnapshotSequenceNumber,
+    pub db_id: DbId,
+    pub table_id: TableId,
+    pub file: ParquetFile,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdKind {
+    Db,
+    Table,
+    Column,
+    ParquetFile,
+}
+
+impl Display for IdKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Db => write!(f, "database"),
+            Self::Table => write!(f, "table"),
+            Self::Column => write!(f, "column"),
+            Self::ParquetFile => write!(f, "parquet file"),
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CorruptCatalog { path, error } => write!(f, "corrupt catalog {path}: {error}"),
+            Self::CorruptSnapshot { path, error } => {
+                write!(f, "corrupt snapshot {path}: {error}")
+            }
+            Self::CorruptWalFile { path, error } => write!(f, "corrupt WAL file {path}: {error}"),
+            Self::MissingParquetFile(p) => write!(
+                f,
+                "parquet file {} in snapshot {} does not exist",
+                p.file.path,
+                p.snapshot_sequence_number.as_u64()
+            ),
+            Self::ParquetFileSizeMismatch { file: p, actual } => write!(
+                f,
+                "parquet file {} is {actual} bytes, but snapshot {} records {} bytes",
+                p.file.path,
+                p.snapshot_sequence_number.as_u64(),
+                p.file.size_bytes
+            ),
+            Self::ParquetFileRowCountMismatch { file: p, actual } => write!(
+                f,
+                "parquet file {} has {actual} rows, but snapshot {} records {} rows",
+                p.file.path,
+                p.snapshot_sequence_number.as_u64(),
+                p.file.row_count
+            ),
+            Self::UnreadableParquetFile { file: p, error } => write!(
+                f,
+                "parquet file {} in snapshot {} cannot be read: {error}",
+                p.file.path,
+                p.snapshot_sequence_number.as_u64()
+            ),
+            Self::NextIdBehind {
+                kind,
+                next_id,
+                max_id,
+            } => write!(
+                f,
+                "the next {kind} id {next_id} in the latest snapshot is not greater than the \
+                {kind} id {max_id} that is already in use"
+            ),
+            Self::OrphanedParquetFile { path, size_bytes } => write!(
+                f,
+                "parquet file {path} ({size_bytes} bytes) is not referenced by any snapshot"
+            ),
+            Self::SnapshottedWalFile { path } => {
+                write!(f, "WAL file {path} was snapshotted but not removed")
+            }
+        }
+    }
+}
+
+/// The result of checking a host
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    pub catalogs_checked: usize,
+    pub snapshots_checked: usize,
+    pub parquet_files_checked: usize,
+    pub wal_files_checked: usize,
+    pub problems: Vec<Problem>,
+    /// The latest readable snapshot, which a repair builds on
+    latest_snapshot: Option<PersistedSnapshot>,
+    /// The greatest id of each kind that is in use
+    max_ids: HashMap<IdKind, u64>,
+}
+
+/// Check the durable state of the host with the given prefix. Parquet files that were modified
+/// less than `min_orphan_age` before `now` are not reported as orphaned, as they may belong to
+/// a snapshot that is still being persisted.
+pub async fn check(
+    object_store: Arc<dyn ObjectStore>,
+    host_identifier_prefix: &str,
+    min_orphan_age: Duration,
+    now: Time,
+) -> Result<CheckReport> {
+    let mut report = CheckReport::default();
+
+    // catalogs are named by their inverted sequence number, so the first readable one is the
+    // latest:
+    let mut latest_catalog: Option<Catalog> = None;
+    for path in list_sorted(
+        object_store.as_ref(),
+        CatalogFilePath::dir(host_identifier_prefix).as_ref(),
+    )
+    .await?
+    {
+        report.catalogs_checked += 1;
+        let bytes = object_store.get(&path).await?.bytes().await?;
+        match serde_json::from_slice::<InnerCatalog>(&bytes) {
+            Ok(inner) => {
+                latest_catalog.get_or_insert_with(|| Catalog::from_inner(inner));
+            }
+            Err(e) => report.problems.push(Problem::CorruptCatalog {
+                path,
+                error: e.to_string(),
+            }),
+        }
+    }
+    if let Some(catalog) = &latest_catalog {
+        for db in catalog.list_db_schema_with_deleted() {
+            report.observe_id(IdKind::Db, db.id.as_u32() as u64);
+            for table in db.tables.values() {
+                report.observe_id(IdKind::Table, table.table_id.as_u32() as u64);
+                for column_id in table.column_map.left_values() {
+                    report.observe_id(IdKind::Column, column_id.as_u32() as u64);
+                }
+            }
+        }
+    }
+
+    let mut snapshots = vec![];
+    for path in list_sorted(
+        object_store.as_ref(),
+        SnapshotInfoFilePath::dir(host_identifier_prefix).as_ref(),
+    )
+    .await?
+    {
+        report.snapshots_checked += 1;
+        let bytes = object_store.get(&path).await?.bytes().await?;
+        match serde_json::from_slice::<PersistedSnapshot>(&bytes) {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(e) => report.problems.push(Problem::CorruptSnapshot {
+                path,
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    // the snapshots are ordered from newest to oldest, so files removed by a newer snapshot are
+    // known before they are seen in an older one:
+    let mut removed_file_ids = HashSet::new();
+    for snapshot in &snapshots {
+        removed_file_ids.extend(tables_files(&snapshot.removed_files).map(|(_, _, f)| f.id));
+        for (db_id, table_id, file) in tables_files(&snapshot.databases) {
+            report.observe_id(IdKind::ParquetFile, file.id.as_u64());
+            if removed_file_ids.contains(&file.id) {
+                continue;
+            }
+            report.parquet_files_checked += 1;
+            let file = ParquetFileProblem {
+                snapshot_sequence_number: snapshot.snapshot_sequence_number,
+                db_id,
+                table_id,
+                file: file.clone(),
+            };
+            if let Some(problem) = check_parquet_file(Arc::clone(&object_store), file).await? {
+                report.problems.push(problem);
+            }
+        }
+    }
+
+    // files removed by a snapshot are kept for a while for queries that may still be reading
+    // them, so they are reported as orphaned in the same way as files that were never added:
+    let live_paths: HashSet<&str> = snapshots
+        .iter()
+        .flat_map(|s| tables_files(&s.databases))
+        .filter(|(_, _, f)| !removed_file_ids.contains(&f.id))
+        .map(|(_, _, f)| f.path.as_str())
+        .collect();
+    let min_orphan_age_ns = min_orphan_age.as_nanos() as i64;
+    let mut parquet_files = object_store.list(Some(&ObjPath::from(format!(
+        "{host_identifier_prefix}/dbs"
+    ))));
+    while let Some(meta) = parquet_files.try_next().await? {
+        let path = meta.location.to_string();
+        let age_ns = now.timestamp_nanos() - meta.last_modified.timestamp_nanos_opt().unwrap_or(0);
+        if !live_paths.contains(path.as_str()) && age_ns >= min_orphan_age_ns {
+            report.problems.push(Problem::OrphanedParquetFile {
+                path: meta.location,
+                size_bytes: meta.size as u64,
+            });
+        }
+    }
+
+    let latest_snapshot = snapshots.into_iter().next();
+    let last_wal_file_number = latest_snapshot.as_ref().map(|s| s.wal_file_sequence_number);
+    for meta in list_wal_files(object_store.as_ref(), host_identifier_prefix).await? {
+        let Ok(wal_file_number) = WalFileSequenceNumber::try_from(&meta.location) else {
+            continue;
+        };
+        report.wal_files_checked += 1;
+        // WAL files up to the one that triggered the latest snapshot are not replayed on start:
+        if Some(wal_file_number) <= last_wal_file_number {
+            report.problems.push(Problem::SnapshottedWalFile {
+                path: meta.location,
+            });
+            continue;
+        }
+        let bytes = object_store.get(&meta.location).await?.bytes().await?;
+        if let Err(e) = verify_file_type_and_deserialize(bytes) {
+            report.problems.push(Problem::CorruptWalFile {
+                path: meta.location,
+                error: e.to_string(),
+            });
+        }
+    }
+
+    if let Some(snapshot) = &latest_snapshot {
+        for (kind, next_id) in next_ids(snapshot) {
+            if let Some(&max_id) = report.max_ids.get(&kind) {
+                if next_id <= max_id {
+                    report.problems.push(Problem::NextIdBehind {
+                        kind,
+                        next_id,
+                        max_id,
+                    });
+                }
+            }
+        }
+    }
+    report.latest_snapshot = latest_snapshot;
+
+    Ok(report)
+}
+
+/// An action taken by [`repair`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RepairAction {
+    /// The object was moved to the quarantine path
+    Quarantined { path: ObjPath, quarantine: ObjPath },
+    /// The object was deleted
+    Deleted { path: ObjPath },
+    /// A snapshot was persisted that removes unusable parquet files, and advances the next ids
+    PersistedRepairSnapshot {
+        snapshot_sequence_number: SnapshotSequenceNumber,
+        removed_files: usize,
+    },
+}
+
+impl Display for RepairAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Quarantined { path, quarantine } => {
+                write!(f, "moved {path} to {quarantine}")
+            }
+            Self::Deleted { path } => write!(f, "deleted {path}"),
+            Self::PersistedRepairSnapshot {
+                snapshot_sequence_number,
+                removed_files,
+            } => write!(
+                f,
+                "persisted snapshot {} removing {removed_files} parquet files",
+                snapshot_sequence_number.as_u64()
+            ),
+        }
+    }
+}
+
+/// Repair the problems in `report`, which must be the result of checking the host with the given
+/// prefix in `object_store`.
+///
+/// Fails without making any changes if a snapshot was persisted since the check, as the server
+/// for the host is then likely still running.
+pub async fn repair(
+    object_store: Arc<dyn ObjectStore>,
+    host_identifier_prefix: &str,
+    report: &CheckReport,
+) -> Result<Vec<RepairAction>> {
+    let mut actions = vec![];
+    let host_prefix = ObjPath::from(host_identifier_prefix);
+    let corrupt_snapshots = report
+        .problems
+        .iter()
+        .any(|p| matches!(p, Problem::CorruptSnapshot { .. }));
+
+    let mut removed_files: SerdeVecMap<DbId, DatabaseTables> = SerdeVecMap::new();
+    let mut n_removed_files = 0;
+    let mut advance_ids = false;
+    for problem in &report.problems {
+        match problem {
+            Problem::MissingParquetFile(p)
+            | Problem::ParquetFileSizeMismatch { file: p, .. }
+            | Problem::ParquetFileRowCountMismatch { file: p, .. }
+            | Problem::UnreadableParquetFile { file: p, .. } => {
+                removed_files
+                    .entry(p.db_id)
+                    .or_default()
+                    .tables
+                    .entry(p.table_id)
+                    .or_default()
+                    .push(p.file.clone());
+                n_removed_files += 1;
+            }
+            Problem::NextIdBehind { .. } => advance_ids = true,
+            _ => (),
+        }
+    }
+
+    // the snapshot is persisted before anything is moved or deleted, so that a repair based on
+    // a report that is out of date fails before it changes anything:
+    if n_removed_files > 0 || advance_ids {
+        let latest = report.latest_snapshot.as_ref().ok_or(Error::NoSnapshot)?;
+        let mut snapshot = PersistedSnapshot::new(
+            host_identifier_prefix.to_string(),
+            latest.snapshot_sequence_number.next(),
+            latest.wal_file_sequence_number,
+            latest.catalog_sequence_number,
+        );
+        let next_id = |kind, current: u64| {
+            report
+                .max_ids
+                .get(&kind)
+                .map_or(current, |max| current.max(max + 1))
+        };
+        snapshot.next_db_id =
+            DbId::from(next_id(IdKind::Db, latest.next_db_id.as_u32() as u64) as u32);
+        snapshot.next_table_id =
+            TableId::from(next_id(IdKind::Table, latest.next_table_id.as_u32() as u64) as u32);
+        snapshot.next_column_id =
+            ColumnId::from(next_id(IdKind::Column, latest.next_column_id.as_u32() as u64) as u32);
+        snapshot.next_file_id =
+            ParquetFileId::from(next_id(IdKind::ParquetFile, latest.next_file_id.as_u64()));
+        snapshot.removed_files = removed_files;
+        // a snapshot at this path was persisted since the check, e.g., by a running server,
+        // which must not be overwritten:
+        let path =
+            SnapshotInfoFilePath::new(host_identifier_prefix, snapshot.snapshot_sequence_number);
+        match object_store
+            .put_opts(
+                path.as_ref(),
+                serde_json::to_vec_pretty(&snapshot)?.into(),
+                PutOptions {
+                    mode: PutMode::Create,
+                    ..Default::default()
+                },
+            )
+            .await
+        {
+            Ok(_) => {}
+            Err(object_store::Error::AlreadyExists { .. }) => {
+                return Err(Error::SnapshotPersistedSinceCheck(path.as_ref().clone()));
+            }
+            Err(error) => return Err(error.into()),
+        }
+        actions.push(RepairAction::PersistedRepairSnapshot {
+            snapshot_sequence_number: snapshot.snapshot_sequence_number,
+            removed_files: n_removed_files,
+        });
+    }
+
+    for problem in &report.problems {
+        match problem {
+            Problem::CorruptCatalog { path, .. }
+            | Problem::CorruptSnapshot { path, .. }
+            | Problem::CorruptWalFile { path, .. } => {
+                let quarantine = quarantine_path(&host_prefix, path);
+                object_store.copy(path, &quarantine).await?;
+                object_store.delete(path).await?;
+                actions.push(RepairAction::Quarantined {
+                    path: path.clone(),
+                    quarantine,
+                });
+            }
+            // the files referenced by a corrupt snapshot look orphaned, so they are kept in case
+            // the snapshot can be recovered by hand:
+            Problem::OrphanedParquetFile { .. } if corrupt_snapshots => (),
+            Problem::OrphanedParquetFile { path, .. } | Problem::SnapshottedWalFile { path } => {
+                object_store.delete(path).await?;
+                actions.push(RepairAction::Deleted { path: path.clone() });
+            }
+            Problem::MissingParquetFile(_)
+            | Problem::ParquetFileSizeMismatch { .. }
+            | Problem::ParquetFileRowCountMismatch { .. }
+            | Problem::UnreadableParquetFile { .. }
+            | Problem::NextIdBehind { .. } => (),
+        }
+    }
+
+    for action in &actions {
+        info!(%action, "repaired");
+    }
+
+    Ok(actions)
+}
+
+impl CheckReport {
+    fn observe_id(&mut self, kind: IdKind, id: u64) {
+        let max = self.max_ids.entry(kind).or_insert(id);
+        *max = (*max).max(id);
+    }
+}
+
+async fn check_parquet_file(
+    object_store: Arc<dyn ObjectStore>,
+    file: ParquetFileProblem,
+) -> Result<Option<Problem>> {
+    let meta = match object_store
+        .head(&ObjPath::from(file.file.path.as_str()))
+        .await
+    {
+        Ok(meta) => meta,
+        Err(object_store::Error::NotFound { .. }) => {
+            return Ok(Some(Problem::MissingParquetFile(file)));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if meta.size as u64 != file.file.size_bytes {
+        return Ok(Some(Problem::ParquetFileSizeMismatch {
+            file,
+            actual: meta.size as u64,
+        }));
+    }
+
+    let mut reader = ParquetObjectReader::new(object_store, meta);
+    match reader.get_metadata().await {
+        Ok(metadata) => {
+            let rows = metadata.file_metadata().num_rows() as u64;
+            if rows != file.file.row_count {
+                return Ok(Some(Problem::ParquetFileRowCountMismatch {
+                    file,
+                    actual: rows,
+                }));
+            }
+        }
+        Err(e) => {
+            return Ok(Some(Problem::UnreadableParquetFile {
+                file,
+                error: e.to_string(),
+            }))
+        }
+    }
+
+    Ok(None)
+}
+
+fn next_ids(snapshot: &PersistedSnapshot) -> [(IdKind, u64); 4] {
+    [
+        (IdKind::Db, snapshot.next_db_id.as_u32() as u64),
+        (IdKind::Table, snapshot.next_table_id.as_u32() as u64),
+        (IdKind::Column, snapshot.next_column_id.as_u32() as u64),
+        (IdKind::ParquetFile, snapshot.next_file_id.as_u64()),
+    ]
+}
+
+fn tables_files(
+    databases: &SerdeVecMap<DbId, DatabaseTables>,
+) -> impl Iterator<Item = (DbId, TableId, &ParquetFile)> {
+    databases.iter().flat_map(|(db_id, db)| {
+        db.tables
+            .iter()
+            .flat_map(move |(table_id, files)| files.iter().map(move |f| (*db_id, *table_id, f)))
+    })
+}
+
+async fn list_sorted(object_store: &dyn ObjectStore, dir: &ObjPath) -> Result<Vec<ObjPath>> {
+    let mut paths = object_store
+        .list(Some(dir))
+        .map_ok(|meta| meta.location)
+        .try_collect::<Vec<_>>()
+        .await?;
+    paths.sort();
+    Ok(paths)
+}
+
+fn quarantine_path(host_prefix: &ObjPath, path: &ObjPath) -> ObjPath {
+    let relative = path.prefix_match(host_prefix).into_iter().flatten();
+    ObjPath::from_iter(
+        host_prefix
+            .parts()
+            .chain(std::iter::once("quarantine".into()))
+            .chain(relative),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use influxdb3_wal::object_store::wal_path;
+    use object_store::memory::InMemory;
+
+    use super::*;
+    use crate::persister::Persister;
+
+    #[tokio::test]
+    async fn check_reports_and_repairs_problems() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Persister::new(Arc::clone(&object_store), "host");
+        let catalog = Catalog::new("host".into(), "instance".into());
+        persister.persist_catalog(&catalog).await.unwrap();
+
+        let db_id = DbId::from(0);
+        let table_id = TableId::from(0);
+        let missing =
+            ParquetFile::create_for_test("host/dbs/foo-0/cpu-0/1970-01-01/00-00/1.parquet");
+        let truncated =
+            ParquetFile::create_for_test("host/dbs/foo-0/cpu-0/1970-01-01/00-01/2.parquet");
+        object_store
+            .put(
+                &ObjPath::from(truncated.path.as_str()),
+                Bytes::from_static(b"not a parquet file").into(),
+            )
+            .await
+            .unwrap();
+        let mut snapshot = PersistedSnapshot::new(
+            "host".to_string(),
+            SnapshotSequenceNumber::new(1),
+            WalFileSequenceNumber::new(1),
+            catalog.sequence_number(),
+        );
+        snapshot.add_parquet_file(db_id, table_id, missing.clone());
+        snapshot.add_parquet_file(db_id, table_id, truncated.clone());
+        snapshot.next_file_id = ParquetFileId::from(0);
+        persister.persist_snapshot(&snapshot).await.unwrap();
+
+        let orphan = ObjPath::from("host/dbs/foo-0/cpu-0/1970-01-01/00-02/3.parquet");
+        let corrupt_snapshot = SnapshotInfoFilePath::new("host", SnapshotSequenceNumber::new(0))
+            .as_ref()
+            .clone();
+        let snapshotted_wal = wal_path("host", WalFileSequenceNumber::new(1));
+        let corrupt_wal = wal_path("host", WalFileSequenceNumber::new(2));
+        for path in [&orphan, &corrupt_snapshot, &snapshotted_wal, &corrupt_wal] {
+            object_store
+                .put(path, Bytes::from_static(b"garbage").into())
+                .await
+                .unwrap();
+        }
+
+        let report = check(
+            Arc::clone(&object_store),
+            "host",
+            Duration::ZERO,
+            Time::from_timestamp_nanos(i64::MAX),
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, report.catalogs_checked);
+        assert_eq!(2, report.snapshots_checked);
+        assert_eq!(2, report.parquet_files_checked);
+        assert_eq!(2, report.wal_files_checked);
+        let problem_names: Vec<String> = report
+            .problems
+            .iter()
+            .map(|p| {
+                format!("{p:?}")
+                    .split([' ', '('])
+                    .next()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "CorruptSnapshot",
+                "MissingParquetFile",
+                "ParquetFileSizeMismatch",
+                "OrphanedParquetFile",
+                "SnapshottedWalFile",
+                "CorruptWalFile",
+                "NextIdBehind",
+            ],
+            problem_names
+        );
+
+        let actions = repair(Arc::clone(&object_store), "host", &report)
+            .await
+            .unwrap();
+        // the orphan is kept, because the corrupt snapshot may have referenced it:
+        assert!(object_store.head(&orphan).await.is_ok());
+        assert!(object_store.head(&snapshotted_wal).await.is_err());
+        assert!(object_store.head(&corrupt_wal).await.is_err());
+        assert!(object_store
+            .head(&ObjPath::from("host/quarantine/wal/00000000002.wal"))
+            .await
+            .is_ok());
+        assert!(actions.contains(&RepairAction::PersistedRepairSnapshot {
+            snapshot_sequence_number: SnapshotSequenceNumber::new(2),
+            removed_files: 2,
+        }));
+
+        // after the repair, the orphan and the removed file that still exists are left, which
+        // are now safe to delete:
+        let report = check(
+            Arc::clone(&object_store),
+            "host",
+            Duration::ZERO,
+            Time::from_timestamp_nanos(i64::MAX),
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, report.problems.len(), "{:?}", report.problems);
+        assert!(report
+            .problems
+            .iter()
+            .all(|p| matches!(p, Problem::OrphanedParquetFile { .. })));
+        repair(Arc::clone(&object_store), "host", &report)
+            .await
+            .unwrap();
+        let report = check(
+            object_store,
+            "host",
+            Duration::ZERO,
+            Time::from_timestamp_nanos(i64::MAX),
+        )
+        .await
+        .unwrap();
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+    }
+
+    #[tokio::test]
+    async fn repair_does_not_overwrite_snapshot_persisted_since_check() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let persister = Persister::new(Arc::clone(&object_store), "host");
+        let catalog = Catalog::new("host".into(), "instance".into());
+        persister.persist_catalog(&catalog).await.unwrap();
+
+        let missing =
+            ParquetFile::create_for_test("host/dbs/foo-0/cpu-0/1970-01-01/00-00/1.parquet");
+        let mut snapshot = PersistedSnapshot::new(
+            "host".to_string(),
+            SnapshotSequenceNumber::new(1),
+            WalFileSequenceNumber::new(1),
+            catalog.sequence_number(),
+        );
+        snapshot.add_parquet_file(DbId::from(0), TableId::from(0), missing);
+        persister.persist_snapshot(&snapshot).await.unwrap();
+        let orphan = ObjPath::from("host/dbs/foo-0/cpu-0/1970-01-01/00-02/3.parquet");
+        object_store
+            .put(&orphan, Bytes::from_static(b"garbage").into())
+            .await
+            .unwrap();
+
+        let report = check(
+            Arc::clone(&object_store),
+            "host",
+            Duration::ZERO,
+            Time::from_timestamp_nanos(i64::MAX),
+        )
+        .await
+        .unwrap();
+        assert!(report
+            .problems
+            .iter()
+            .any(|p| matches!(p, Problem::MissingParquetFile(_))));
+
+        // a server that is still running persists the next snapshot after the check:
+        let next_snapshot = PersistedSnapshot::new(
+            "host".to_string(),
+            SnapshotSequenceNumber::new(2),
+            WalFileSequenceNumber::new(2),
+            catalog.sequence_number(),
+        );
+        persister.persist_snapshot(&next_snapshot).await.unwrap();
+
+        let err = repair(Arc::clone(&object_store), "host", &report)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::SnapshotPersistedSinceCheck(_)),
+            "{err:?}"
+        );
+        // nothing was changed:
+        let persisted = persister.load_snapshots(1).await.unwrap().pop().unwrap();
+        assert_eq!(
+            WalFileSequenceNumber::new(2),
+            persisted.wal_file_sequence_number
+        );
+        assert!(object_store.head(&orphan).await.is_ok());
+    }
+}
@@ -492,6 +492,22 @@ impl LastCacheProvider {
         Ok(())
     }
 
+    /// Delete all caches for a database, e.g., when the database is deleted
+    pub fn delete_caches_for_db(&self, db_id: DbId) {
+        self.cache_map.write().remove(&db_id);
+    }
+
+    /// Delete all caches for a table, e.g., when the table is deleted
+    pub fn delete_caches_for_table(&self, db_id: DbId, table_id: TableId) {
+        let mut lock = self.cache_map.write();
+        if let Some(db) = lock.get_mut(&db_id) {
+            db.remove(&table_id);
+            if db.is_empty() {
+                lock.remove(&db_id);
+            }
+        }
+    }
+
     /// Write the contents from a wal file into the cache by iterating over its database and table batches
     /// to find entries that belong in the cache.
     ///
@@ -1752,6 +1768,7 @@ mod tests {
     use arrow_util::{assert_batches_eq, assert_batches_sorted_eq};
     use bimap::BiHashMap;
     use data_types::NamespaceName;
+    use influxdb3_cache::meta_cache::MetaCacheProvider;
     use influxdb3_catalog::catalog::{Catalog, DatabaseSchema, TableDefinition};
     use influxdb3_id::{ColumnId, DbId, SerdeVecMap, TableId};
     use influxdb3_wal::{LastCacheDefinition, WalConfig};
@@ -1771,11 +1788,14 @@ mod tests {
         WriteBufferImpl::new(
             persister,
             Arc::clone(&catalog),
-            LastCacheProvider::new_from_catalog(catalog as _).unwrap(),
+            LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap(),
+            MetaCacheProvider::new_from_catalog(Arc::clone(&time_provider), catalog).unwrap(),
             time_provider,
             crate::test_help::make_exec(),
             WalConfig::test_config(),
+            None,
             Some(parquet_cache),
+            Arc::new(metric::Registry::new()),
         )
         .await
         .unwrap()
@@ -3310,6 +3330,9 @@ mod tests {
                 map.insert(TableId::from(1), "test_table_2".into());
                 map
             },
+            retention_period: None,
+            deleted: false,
+            schema_locked: false,
         };
         let table_id = TableId::from(0);
         use schema::InfluxColumnType::*;
@@ -4,33 +4,44 @@
 //! data into parquet files that are persisted to object storage. A snapshot file is written that contains the
 //! metadata of the parquet files that were written in that snapshot.
 
+pub mod backup;
+pub mod check;
 pub mod chunk;
 pub mod last_cache;
+pub mod line_protocol;
 pub mod parquet_cache;
 pub mod paths;
 pub mod persister;
 pub mod write_buffer;
 
+use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
 use data_types::{NamespaceName, TimestampMinMax};
 use datafusion::catalog::Session;
 use datafusion::error::DataFusionError;
 use datafusion::prelude::Expr;
+use influxdb3_cache::meta_cache::{CreateMetaCacheArgs, MetaCacheProvider};
 use influxdb3_catalog::catalog::Catalog;
 use influxdb3_catalog::catalog::CatalogSequenceNumber;
+use influxdb3_catalog::catalog::TokenDefinition;
 use influxdb3_id::ParquetFileId;
 use influxdb3_id::SerdeVecMap;
 use influxdb3_id::TableId;
 use influxdb3_id::{ColumnId, DbId};
-use influxdb3_wal::{LastCacheDefinition, SnapshotSequenceNumber, WalFileSequenceNumber};
+use influxdb3_wal::{
+    LastCacheDefinition, MetaCacheDefinition, SnapshotSequenceNumber, WalFileSequenceNumber,
+};
 use iox_query::QueryChunk;
 use iox_time::Time;
 use last_cache::LastCacheProvider;
+use schema::InfluxFieldType;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
+use write_buffer::import::ImportSummary;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -49,7 +60,10 @@ pub enum Error {
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
-pub trait WriteBuffer: Bufferer + ChunkContainer + LastCacheManager {}
+pub trait WriteBuffer:
+    Bufferer + ChunkContainer + LastCacheManager + MetaCacheManager + DatabaseManager + TokenManager
+{
+}
 
 /// The buffer is for buffering data in memory and in the wal before it is persisted as parquet files in storage.
 #[async_trait]
@@ -75,6 +89,19 @@ pub trait Bufferer: Debug + Send + Sync + 'static {
         precision: Precision,
     ) -> write_buffer::Result<BufferedWriteRequest>;
 
+    /// Import a record batch into a table, creating the table or adding any new columns to it,
+    /// without converting the rows to line protocol. The batch must have a `time` column.
+    ///
+    /// Each batch is written to the WAL on its own, so large imports should be split into
+    /// batches of a bounded size.
+    async fn import_record_batch(
+        &self,
+        database: NamespaceName<'static>,
+        table_name: &str,
+        batch: RecordBatch,
+        ingest_time: Time,
+    ) -> write_buffer::Result<ImportSummary>;
+
     /// Returns the database schema provider
     fn catalog(&self) -> Arc<Catalog>;
 
@@ -131,6 +158,101 @@ pub trait LastCacheManager: Debug + Send + Sync + 'static {
     ) -> Result<(), write_buffer::Error>;
 }
 
+/// [`MetaCacheManager`] is used to manage interaction with a [`MetaCacheProvider`]. This enables
+/// cache creation, deletion, and getting access to existing metadata caches.
+/// It is important that the state of the cache is also maintained in the catalog.
+#[async_trait::async_trait]
+pub trait MetaCacheManager: Debug + Send + Sync + 'static {
+    /// Get a reference to the metadata cache provider
+    fn meta_cache_provider(&self) -> Arc<MetaCacheProvider>;
+    /// Create a new metadata cache
+    ///
+    /// This should handle updating the catalog with the cache information, so that it will be
+    /// preserved on server restarts.
+    async fn create_meta_cache(
+        &self,
+        db_id: DbId,
+        cache_name: Option<String>,
+        args: CreateMetaCacheArgs,
+    ) -> Result<Option<MetaCacheDefinition>, write_buffer::Error>;
+    /// Delete a metadata cache
+    ///
+    /// This should handle removal of the cache's information from the catalog as well
+    async fn delete_meta_cache(
+        &self,
+        db_id: &DbId,
+        tbl_id: &TableId,
+        cache_name: &str,
+    ) -> Result<(), write_buffer::Error>;
+}
+
+/// [`DatabaseManager`] is used to manage databases and tables, and database level configuration,
+/// such as the retention period. Changes are recorded in the catalog so that they are preserved
+/// on server restarts.
+#[async_trait::async_trait]
+pub trait DatabaseManager: Debug + Send + Sync + 'static {
+    /// Create a new, empty database, failing if a database with the same name exists
+    ///
+    /// Data in the database is retained for the `retention_period`, if one is given, or
+    /// indefinitely otherwise.
+    async fn create_database(
+        &self,
+        db_name: NamespaceName<'static>,
+        retention_period: Option<Duration>,
+    ) -> Result<(), write_buffer::Error>;
+    /// Create a new table in an existing database, with its schema declared up front
+    ///
+    /// The table has no series key, like tables created by a write of line protocol, so that
+    /// line protocol can be written to it. A `time` column is always added to the table.
+    async fn create_table(
+        &self,
+        db_name: &str,
+        table_name: &str,
+        tags: Vec<String>,
+        fields: Vec<(String, InfluxFieldType)>,
+    ) -> Result<(), write_buffer::Error>;
+    /// Set, or clear with `None`, the retention period of an existing database
+    async fn set_retention_period(
+        &self,
+        db_name: NamespaceName<'static>,
+        retention_period: Option<Duration>,
+    ) -> Result<(), write_buffer::Error>;
+    /// Lock, or unlock, the schema of a database, or of one of its tables if a table name is
+    /// given
+    ///
+    /// While a schema is locked, writes that would add new tables or columns to it are rejected.
+    async fn set_schema_locked(
+        &self,
+        db_name: &str,
+        table_name: Option<&str>,
+        locked: bool,
+    ) -> Result<(), write_buffer::Error>;
+    /// Soft delete a database, so that its name can be reused
+    ///
+    /// Buffered data and caches for the database are dropped, and its persisted files are
+    /// removed in the background.
+    async fn delete_database(&self, db_name: &str) -> Result<(), write_buffer::Error>;
+    /// Soft delete a table, so that its name can be reused
+    ///
+    /// Buffered data and caches for the table are dropped, and its persisted files are removed
+    /// in the background.
+    async fn delete_table(
+        &self,
+        db_name: &str,
+        table_name: &str,
+    ) -> Result<(), write_buffer::Error>;
+}
+
+/// [`TokenManager`] is used to manage the named tokens that grant access to the server. Tokens
+/// are stored in the catalog, which is persisted as soon as a token is created or revoked.
+#[async_trait::async_trait]
+pub trait TokenManager: Debug + Send + Sync + 'static {
+    /// Add a new token to the catalog
+    async fn create_token(&self, token: TokenDefinition) -> Result<(), write_buffer::Error>;
+    /// Remove the token with the given name from the catalog, so that it can no longer be used
+    async fn revoke_token(&self, name: &str) -> Result<(), write_buffer::Error>;
+}
+
 /// A single write request can have many lines in it. A writer can request to accept all lines that are valid, while
 /// returning an error for any invalid lines. This is the error information for a single invalid line.
 #[derive(Debug, Serialize)]
@@ -181,6 +303,17 @@ pub struct PersistedSnapshot {
     /// The collection of databases that had tables persisted in this snapshot. The tables will then have their
     /// name and the parquet file.
     pub databases: SerdeVecMap<DbId, DatabaseTables>,
+    /// The parquet files that were removed in this snapshot, e.g., because they fell outside of
+    /// their database's retention period. These will not be loaded from earlier snapshots.
+    #[serde(default, skip_serializing_if = "is_empty_map")]
+    pub removed_files: SerdeVecMap<DbId, DatabaseTables>,
+    /// The time the `removed_files` were removed, in nanoseconds since the epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removed_at_ns: Option<i64>,
+}
+
+fn is_empty_map(map: &SerdeVecMap<DbId, DatabaseTables>) -> bool {
+    map.is_empty()
 }
 
 impl PersistedSnapshot {
@@ -204,6 +337,8 @@ impl PersistedSnapshot {
             min_time: i64::MAX,
             max_time: i64::MIN,
             databases: SerdeVecMap::new(),
+            removed_files: SerdeVecMap::new(),
+            removed_at_ns: None,
         }
     }
 
@@ -245,6 +380,24 @@ pub struct ParquetFile {
     pub chunk_time: i64,
     pub min_time: i64,
     pub max_time: i64,
+    /// The min and max values of each tag column in the file, used to prune the file at query
+    /// time when a query filters on a tag value that falls outside of that range
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_stats: BTreeMap<ColumnId, ColumnStats>,
+}
+
+/// The min and max value of a string column in a persisted parquet file.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct ColumnStats {
+    pub min: String,
+    pub max: String,
+}
+
+impl ColumnStats {
+    /// Check if the given value could be contained in the column that these stats describe
+    pub fn may_contain(&self, value: &str) -> bool {
+        self.min.as_str() <= value && value <= self.max.as_str()
+    }
 }
 
 impl ParquetFile {
@@ -267,6 +420,7 @@ impl ParquetFile {
             chunk_time: 0,
             min_time: 0,
             max_time: 1,
+            column_stats: Default::default(),
         }
     }
 }
@@ -0,0 +1,168 @@
+//! Writing the rows of a table back out as line protocol, e.g., to export them
+
+use std::fmt::Write;
+
+use influxdb3_catalog::catalog::TableDefinition;
+
+/// The value of a field in a line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldValue<'a> {
+    String(&'a str),
+    Integer(i64),
+    UInteger(u64),
+    Float(f64),
+    Boolean(bool),
+}
+
+/// Builds the lines of a table, one per row, by adding the tags and fields of a row and then
+/// finishing its line. Rows of tables with a series key are written in the v3 line protocol
+/// format, for which the tags must be added in the order of the series key.
+#[derive(Debug)]
+pub struct LineBuilder {
+    measurement: String,
+    v3: bool,
+    tags: String,
+    fields: String,
+}
+
+impl LineBuilder {
+    pub fn new(table_def: &TableDefinition) -> Self {
+        Self {
+            measurement: escape(&table_def.table_name, &[',', ' ']),
+            v3: table_def.series_key.is_some(),
+            tags: String::new(),
+            fields: String::new(),
+        }
+    }
+
+    pub fn tag(&mut self, name: &str, value: &str) {
+        if self.v3 {
+            // v3 series keys are written as `table,key1/value1/key2/value2`
+            let separator = if self.tags.is_empty() { ',' } else { '/' };
+            let _ = write!(
+                self.tags,
+                "{separator}{}/{}",
+                escape(name, &[',', '=', ' ', '/']),
+                escape(value, &[',', '=', ' ', '/'])
+            );
+        } else {
+            let _ = write!(
+                self.tags,
+                ",{}={}",
+                escape(name, &[',', '=', ' ']),
+                escape(value, &[',', '=', ' '])
+            );
+        }
+    }
+
+    pub fn field(&mut self, name: &str, value: FieldValue<'_>) {
+        let separator = if self.fields.is_empty() { ' ' } else { ',' };
+        let name = escape(name, &[',', '=', ' ']);
+        let _ = match value {
+            FieldValue::String(s) => {
+                write!(self.fields, "{separator}{name}=\"{}\"", escape(s, &['"']))
+            }
+            FieldValue::Integer(i) => write!(self.fields, "{separator}{name}={i}i"),
+            FieldValue::UInteger(u) => write!(self.fields, "{separator}{name}={u}u"),
+            FieldValue::Float(f) => write!(self.fields, "{separator}{name}={f}"),
+            FieldValue::Boolean(b) => write!(self.fields, "{separator}{name}={b}"),
+        };
+    }
+
+    /// Write the line for the tags and fields added since the last line to `lp`, and start the
+    /// next line. A row without any fields cannot be represented in line protocol, so its line
+    /// is skipped, in which case `false` is returned.
+    pub fn finish_line(&mut self, lp: &mut String, time: i64) -> bool {
+        let written = !self.fields.is_empty();
+        if written {
+            let _ = writeln!(
+                lp,
+                "{}{}{} {time}",
+                self.measurement, self.tags, self.fields
+            );
+        }
+        self.tags.clear();
+        self.fields.clear();
+        written
+    }
+}
+
+/// Escapes backslashes and the given special characters with a backslash
+fn escape(s: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == '\\' || special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use influxdb3_id::{ColumnId, TableId};
+    use schema::{InfluxColumnType, InfluxFieldType};
+
+    use super::*;
+
+    fn table_def(series_key: Option<Vec<ColumnId>>) -> TableDefinition {
+        TableDefinition::new(
+            TableId::from(0),
+            "cpu load".into(),
+            vec![
+                (ColumnId::from(0), "host".into(), InfluxColumnType::Tag),
+                (ColumnId::from(1), "region".into(), InfluxColumnType::Tag),
+                (
+                    ColumnId::from(2),
+                    "usage".into(),
+                    InfluxColumnType::Field(InfluxFieldType::Float),
+                ),
+                (
+                    ColumnId::from(3),
+                    "time".into(),
+                    InfluxColumnType::Timestamp,
+                ),
+            ],
+            series_key,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn lines_are_escaped() {
+        let mut lp = String::new();
+        let mut line = LineBuilder::new(&table_def(None));
+        line.tag("host", "a,b");
+        line.tag("region", "us west");
+        line.field("usage", FieldValue::Float(0.5));
+        line.field("label", FieldValue::String("say \"hi\" \\o/"));
+        line.field("count", FieldValue::Integer(-1));
+        line.field("total", FieldValue::UInteger(2));
+        line.field("up", FieldValue::Boolean(true));
+        assert!(line.finish_line(&mut lp, 1));
+        // a row without fields is skipped:
+        line.tag("host", "c");
+        assert!(!line.finish_line(&mut lp, 2));
+        line.field("usage", FieldValue::Float(1.0));
+        assert!(line.finish_line(&mut lp, 3));
+        assert_eq!(
+            lp,
+            "cpu\\ load,host=a\\,b,region=us\\ west \
+            usage=0.5,label=\"say \\\"hi\\\" \\\\o/\",count=-1i,total=2u,up=true 1\n\
+            cpu\\ load usage=1 3\n"
+        );
+    }
+
+    #[test]
+    fn v3_lines_use_series_key_format() {
+        let mut lp = String::new();
+        let mut line =
+            LineBuilder::new(&table_def(Some(vec![ColumnId::from(1), ColumnId::from(0)])));
+        line.tag("region", "us/west");
+        line.tag("host", "a");
+        line.field("usage", FieldValue::Float(0.5));
+        line.finish_line(&mut lp, 1);
+        assert_eq!(lp, "cpu\\ load,region/us\\/west/host/a usage=0.5 1\n");
+    }
+}
@@ -1,5 +1,6 @@
 use chrono::prelude::*;
 use influxdb3_catalog::catalog::CatalogSequenceNumber;
+use influxdb3_id::ParquetFileId;
 use influxdb3_wal::{SnapshotSequenceNumber, WalFileSequenceNumber};
 use object_store::path::Path as ObjPath;
 use std::ops::Deref;
@@ -54,7 +55,9 @@ pub struct ParquetFilePath(ObjPath);
 
 impl ParquetFilePath {
     /// Generate a parquet file path using the given arguments. This will convert the provided
-    /// `chunk_time` into a date time string with format `'YYYY-MM-DD/HH-MM'`
+    /// `chunk_time` into a date time string with format `'YYYY-MM-DD/HH-MM'`, or
+    /// `'YYYY-MM-DD/HH-MM-SS'` for chunks that do not start on a minute, so that the chunks of a
+    /// sub-minute `Gen1Duration` persisted in the same snapshot do not share a path
     pub fn new(
         host_prefix: &str,
         db_name: &str,
@@ -65,14 +68,41 @@ impl ParquetFilePath {
         wal_file_sequence_number: WalFileSequenceNumber,
     ) -> Self {
         let date_time = DateTime::<Utc>::from_timestamp_nanos(chunk_time);
+        let date_format = if date_time.second() == 0 {
+            "%Y-%m-%d/%H-%M"
+        } else {
+            "%Y-%m-%d/%H-%M-%S"
+        };
         let path = ObjPath::from(format!(
             "{host_prefix}/dbs/{db_name}-{db_id}/{table_name}-{table_id}/{date_string}/{wal_seq:010}.{ext}",
-            date_string = date_time.format("%Y-%m-%d/%H-%M"),
+            date_string = date_time.format(date_format),
             wal_seq = wal_file_sequence_number.as_u64(),
             ext = PARQUET_FILE_EXTENSION
         ));
         Self(path)
     }
+
+    /// Generate the path of a parquet file that holds the compacted data of a table for the
+    /// generation starting at `generation_start`. The file is named after its `ParquetFileId`,
+    /// so that compacting the same generation again never overwrites a file in use.
+    pub fn new_compacted(
+        host_prefix: &str,
+        db_name: &str,
+        db_id: u32,
+        table_name: &str,
+        table_id: u32,
+        generation_start: i64,
+        file_id: ParquetFileId,
+    ) -> Self {
+        let date_time = DateTime::<Utc>::from_timestamp_nanos(generation_start);
+        let path = ObjPath::from(format!(
+            "{host_prefix}/dbs/{db_name}-{db_id}/{table_name}-{table_id}/{date_string}/compacted-{file_id:010}.{ext}",
+            date_string = date_time.format("%Y-%m-%d/%H-%M"),
+            file_id = file_id.as_u64(),
+            ext = PARQUET_FILE_EXTENSION
+        ));
+        Self(path)
+    }
 }
 
 impl Deref for ParquetFilePath {
@@ -138,7 +168,7 @@ fn parquet_file_path_new() {
             0,
             "my_table",
             0,
-            Utc.with_ymd_and_hms(2038, 1, 19, 3, 14, 7)
+            Utc.with_ymd_and_hms(2038, 1, 19, 3, 14, 0)
                 .unwrap()
                 .timestamp_nanos_opt()
                 .unwrap(),
@@ -146,6 +176,43 @@ fn parquet_file_path_new() {
         ),
         ObjPath::from("my_host/dbs/my_db-0/my_table-0/2038-01-19/03-14/0000001337.parquet")
     );
+    // chunks that do not start on a minute include the seconds:
+    assert_eq!(
+        *ParquetFilePath::new(
+            "my_host",
+            "my_db",
+            0,
+            "my_table",
+            0,
+            Utc.with_ymd_and_hms(2038, 1, 19, 3, 14, 7)
+                .unwrap()
+                .timestamp_nanos_opt()
+                .unwrap(),
+            WalFileSequenceNumber::new(1337),
+        ),
+        ObjPath::from("my_host/dbs/my_db-0/my_table-0/2038-01-19/03-14-07/0000001337.parquet")
+    );
+}
+
+#[test]
+fn parquet_file_path_new_compacted() {
+    assert_eq!(
+        *ParquetFilePath::new_compacted(
+            "my_host",
+            "my_db",
+            0,
+            "my_table",
+            0,
+            Utc.with_ymd_and_hms(2038, 1, 19, 3, 0, 0)
+                .unwrap()
+                .timestamp_nanos_opt()
+                .unwrap(),
+            ParquetFileId::from(42),
+        ),
+        ObjPath::from(
+            "my_host/dbs/my_db-0/my_table-0/2038-01-19/03-00/compacted-0000000042.parquet"
+        )
+    );
 }
 
 #[test]
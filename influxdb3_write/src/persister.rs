@@ -383,6 +383,14 @@ impl<W: Write + Send> TrackedMemoryArrowWriter<W> {
         Ok(())
     }
 
+    /// Get a mutable reference to the underlying sink
+    ///
+    /// This can be used to take the bytes of flushed row groups out of an in-memory sink
+    /// while writing is still in progress.
+    pub fn inner_mut(&mut self) -> &mut W {
+        self.inner.inner_mut()
+    }
+
     /// closes the writer, flushing any remaining data and returning
     /// the written [`FileMetaData`]
     ///
@@ -391,6 +399,13 @@ impl<W: Write + Send> TrackedMemoryArrowWriter<W> {
         // reservation is returned on drop
         Ok(self.inner.close()?)
     }
+
+    /// closes the writer, flushing any remaining data and returning
+    /// the underlying sink
+    pub fn into_inner(self) -> Result<W> {
+        // reservation is returned on drop
+        Ok(self.inner.into_inner()?)
+    }
 }
 
 #[cfg(test)]
@@ -471,6 +486,8 @@ mod tests {
             max_time: 1,
             row_count: 0,
             parquet_size_bytes: 0,
+            removed_files: SerdeVecMap::new(),
+            removed_at_ns: None,
         };
 
         persister.persist_snapshot(&info_file).await.unwrap();
@@ -495,6 +512,8 @@ mod tests {
             max_time: 1,
             row_count: 0,
             parquet_size_bytes: 0,
+            removed_files: SerdeVecMap::new(),
+            removed_at_ns: None,
         };
         let info_file_2 = PersistedSnapshot {
             host_id: "test_host".to_string(),
@@ -510,6 +529,8 @@ mod tests {
             min_time: 0,
             row_count: 0,
             parquet_size_bytes: 0,
+            removed_files: SerdeVecMap::new(),
+            removed_at_ns: None,
         };
         let info_file_3 = PersistedSnapshot {
             host_id: "test_host".to_string(),
@@ -525,6 +546,8 @@ mod tests {
             max_time: 1,
             row_count: 0,
             parquet_size_bytes: 0,
+            removed_files: SerdeVecMap::new(),
+            removed_at_ns: None,
         };
 
         persister.persist_snapshot(&info_file).await.unwrap();
@@ -561,6 +584,8 @@ mod tests {
             max_time: 1,
             row_count: 0,
             parquet_size_bytes: 0,
+            removed_files: SerdeVecMap::new(),
+            removed_at_ns: None,
         };
         persister.persist_snapshot(&info_file).await.unwrap();
         let snapshots = persister.load_snapshots(2).await.unwrap();
@@ -590,6 +615,8 @@ mod tests {
                 max_time: 1,
                 row_count: 0,
                 parquet_size_bytes: 0,
+                removed_files: SerdeVecMap::new(),
+                removed_at_ns: None,
             };
             persister.persist_snapshot(&info_file).await.unwrap();
         }
@@ -633,6 +660,7 @@ mod tests {
                 chunk_time: 5,
                 min_time: 0,
                 max_time: 1,
+                column_stats: Default::default(),
             },
         );
         persister.persist_snapshot(&info_file).await.unwrap();
@@ -717,6 +745,8 @@ mod tests {
             min_time: 0,
             max_time: 1,
             databases,
+            removed_files: SerdeVecMap::new(),
+            removed_at_ns: None,
         };
         insta::assert_json_snapshot!(snapshot);
     }
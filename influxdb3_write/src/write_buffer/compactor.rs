@@ -0,0 +1,342 @@
+//! Compaction of the gen1 parquet files persisted by snapshots into larger generations.
+//!
+//! Every snapshot persists a file per table for each `Gen1Duration` chunk that it covers, so a
+//! busy table builds up many small files. The compactor merges the gen1 files of a table whose
+//! chunk times fall in the same generation, e.g., the same hour or day, into a single file that
+//! is sorted and deduplicated in the same way as the gen1 files. The replacement is recorded in a
+//! new [`PersistedSnapshot`](crate::PersistedSnapshot), so the superseded files are not loaded again on restart, and the
+//! superseded files are deleted from object store once queries can no longer be reading them,
+//! see [`REMOVED_FILE_DELETE_DELAY`](super::REMOVED_FILE_DELETE_DELAY).
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use datafusion::common::DataFusionError;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use futures::TryStreamExt;
+use hashbrown::HashMap;
+use influxdb3_catalog::catalog::{DatabaseSchema, TableDefinition};
+use influxdb3_id::{DbId, ParquetFileId, TableId};
+use iox_query::frontend::reorg::ReorgPlanner;
+use iox_query::QueryChunk;
+use object_store::path::Path as ObjPath;
+use observability_deps::tracing::{error, info, warn};
+use schema::sort::SortKey;
+use thiserror::Error;
+
+use crate::parquet_cache::CacheRequest;
+use crate::paths::ParquetFilePath;
+use crate::write_buffer::parquet_chunk_from_file;
+use crate::write_buffer::queryable_buffer::TagColumnStats;
+use crate::write_buffer::WriteBufferImpl;
+use crate::ParquetFile;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("error planning compaction of table {table_name}: {source}")]
+    Plan {
+        table_name: Arc<str>,
+        source: iox_query::frontend::reorg::Error,
+    },
+
+    #[error("error executing compaction of table {table_name}: {source}")]
+    Execute {
+        table_name: Arc<str>,
+        source: DataFusionError,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The compacted file of a generation of a table, along with the gen1 files that it replaces
+#[derive(Debug)]
+pub(super) struct Compaction {
+    pub(super) db_id: DbId,
+    pub(super) table_id: TableId,
+    pub(super) files: Vec<ParquetFile>,
+    pub(super) compacted: ParquetFile,
+}
+
+impl WriteBufferImpl {
+    /// Compact the gen1 files of every table into one file per generation of the given
+    /// duration. Only generations that have ended and that have more than one file are
+    /// compacted, so a generation that receives late data is compacted again on a later run.
+    ///
+    /// Also deletes the files superseded by earlier compactions once they have been replaced
+    /// for at least [`REMOVED_FILE_DELETE_DELAY`](super::REMOVED_FILE_DELETE_DELAY).
+    pub async fn compact_gen1_files(&self, generation_duration: Duration) -> super::Result<()> {
+        self.delete_removed_files().await;
+
+        let compactions = self.compact_generations(generation_duration).await?;
+        self.replace_compacted_files(compactions).await
+    }
+
+    /// Write the compacted file of every generation that is ready to be compacted, without
+    /// replacing the gen1 files with it yet
+    pub(super) async fn compact_generations(
+        &self,
+        generation_duration: Duration,
+    ) -> super::Result<Vec<Compaction>> {
+        let generation_ns = generation_duration.as_nanos() as i64;
+        let now_ns = self.time_provider.now().timestamp_nanos();
+        let mut compactions = vec![];
+        for db_schema in self.catalog.list_db_schema() {
+            for table_def in db_schema.tables() {
+                let mut generations: HashMap<i64, Vec<ParquetFile>> = HashMap::new();
+                for file in self
+                    .persisted_files
+                    .get_files(db_schema.id, table_def.table_id)
+                {
+                    let generation_start =
+                        file.chunk_time - file.chunk_time.rem_euclid(generation_ns);
+                    generations.entry(generation_start).or_default().push(file);
+                }
+
+                for (generation_start, files) in generations {
+                    if files.len() < 2 || generation_start + generation_ns > now_ns {
+                        continue;
+                    }
+                    let compacted = self
+                        .compact_files(&db_schema, &table_def, generation_start, &files)
+                        .await?;
+                    compactions.push(Compaction {
+                        db_id: db_schema.id,
+                        table_id: table_def.table_id,
+                        files,
+                        compacted,
+                    });
+                }
+            }
+        }
+
+        Ok(compactions)
+    }
+
+    /// Replace the gen1 files of each compaction with its compacted file, and record the
+    /// replacements in a snapshot.
+    ///
+    /// The gen1 files may have been removed by retention, or with their database or table, while
+    /// they were being compacted. Such a compaction is dropped, and its compacted file deleted, as
+    /// it would bring the removed data back.
+    pub(super) async fn replace_compacted_files(
+        &self,
+        compactions: Vec<Compaction>,
+    ) -> super::Result<()> {
+        if compactions.is_empty() {
+            return Ok(());
+        }
+
+        let mut replaced = vec![];
+        let mut dropped = vec![];
+        let snapshot = self
+            .persist_files_snapshot(|snapshot| {
+                // the files are replaced while the snapshot permit is held, so that a removal of
+                // them is either seen here or made after the replacement and recorded in a later
+                // snapshot:
+                for compaction in compactions {
+                    let replaced_ids: HashSet<ParquetFileId> =
+                        compaction.files.iter().map(|f| f.id).collect();
+                    if !self.persisted_files.replace_files(
+                        compaction.db_id,
+                        compaction.table_id,
+                        &replaced_ids,
+                        compaction.compacted.clone(),
+                    ) {
+                        dropped.push(compaction);
+                        continue;
+                    }
+                    snapshot.add_parquet_file(
+                        compaction.db_id,
+                        compaction.table_id,
+                        compaction.compacted.clone(),
+                    );
+                    snapshot
+                        .removed_files
+                        .entry(compaction.db_id)
+                        .or_default()
+                        .tables
+                        .entry(compaction.table_id)
+                        .or_default()
+                        .extend(compaction.files.iter().cloned());
+                    replaced.push(compaction);
+                }
+            })
+            .await?;
+
+        let removed_at_ns = snapshot
+            .removed_at_ns
+            .unwrap_or_else(|| self.time_provider.now().timestamp_nanos());
+        for Compaction {
+            db_id,
+            table_id,
+            files,
+            compacted,
+        } in replaced
+        {
+            info!(
+                %db_id,
+                %table_id,
+                n_files = files.len(),
+                path = %compacted.path,
+                "replaced gen1 parquet files with compacted file"
+            );
+            for file in files {
+                self.queue_removed_file(removed_at_ns, file);
+            }
+        }
+
+        // the compacted file of a dropped compaction was never visible to queries:
+        let object_store = self.persister.object_store();
+        for Compaction {
+            db_id,
+            table_id,
+            compacted,
+            ..
+        } in dropped
+        {
+            info!(
+                %db_id,
+                %table_id,
+                path = %compacted.path,
+                "dropped compaction of gen1 parquet files that were removed while being compacted"
+            );
+            if let Err(error) = object_store.delete(&ObjPath::from(compacted.path)).await {
+                warn!(%error, "failed to delete compacted parquet file");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Merge the given files of a table into a single sorted and deduplicated file. Where rows
+    /// in different files have the same primary key, the row from the newest file is kept.
+    ///
+    /// The files are read from object store as the merge is executed, so the data of a whole
+    /// generation is never loaded into memory at once.
+    async fn compact_files(
+        &self,
+        db_schema: &DatabaseSchema,
+        table_def: &Arc<TableDefinition>,
+        generation_start: i64,
+        files: &[ParquetFile],
+    ) -> super::Result<ParquetFile> {
+        let mut files = files.to_vec();
+        files.sort_by_key(|f| f.id);
+
+        let schema = table_def.influx_schema().clone();
+        // newer files get a higher chunk order, so that their rows are kept when deduplicating:
+        let chunks: Vec<Arc<dyn QueryChunk>> = files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| {
+                Arc::new(parquet_chunk_from_file(
+                    file,
+                    &schema,
+                    self.persister.object_store_url().clone(),
+                    self.persister.object_store(),
+                    i as i64 + 1,
+                )) as Arc<dyn QueryChunk>
+            })
+            .collect();
+        let sort_key = SortKey::from(
+            schema
+                .primary_key()
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>(),
+        );
+
+        let table_name = Arc::clone(&table_def.table_name);
+        let execute_error = |source| Error::Execute {
+            table_name: Arc::clone(&table_name),
+            source,
+        };
+        let ctx = self.buffer.executor.new_context();
+        let logical_plan = ReorgPlanner::new()
+            .compact_plan(
+                data_types::TableId::new(0),
+                Arc::clone(&table_name),
+                &schema,
+                chunks,
+                sort_key,
+            )
+            .map_err(|source| Error::Plan {
+                table_name: Arc::clone(&table_name),
+                source,
+            })?;
+        let physical_plan = ctx
+            .create_physical_plan(&logical_plan)
+            .await
+            .map_err(execute_error)?;
+        let stream = ctx
+            .execute_stream(physical_plan)
+            .await
+            .map_err(execute_error)?;
+
+        // the tag column stats are gathered as the merged batches are written:
+        let stats = Arc::new(parking_lot::Mutex::new(TagColumnStats::new(
+            table_def, &schema,
+        )));
+        let stream = {
+            let stats = Arc::clone(&stats);
+            Box::pin(RecordBatchStreamAdapter::new(
+                stream.schema(),
+                stream.inspect_ok(move |batch| stats.lock().update(batch)),
+            ))
+        };
+
+        let id = ParquetFileId::new();
+        let path = ParquetFilePath::new_compacted(
+            self.persister.host_identifier_prefix(),
+            db_schema.name.as_ref(),
+            db_schema.id.as_u32(),
+            table_def.table_name.as_ref(),
+            table_def.table_id.as_u32(),
+            generation_start,
+            id,
+        );
+        let (size_bytes, meta) = self
+            .persister
+            .persist_parquet_file(path.clone(), stream)
+            .await?;
+        if let Some(parquet_cache) = &self.parquet_cache {
+            let (cache_request, _) = CacheRequest::create(ObjPath::from(path.to_string()));
+            parquet_cache.register(cache_request);
+        }
+
+        let min_time = files.iter().map(|f| f.min_time).min().unwrap_or(i64::MAX);
+        let max_time = files.iter().map(|f| f.max_time).max().unwrap_or(i64::MIN);
+        let column_stats = stats.lock().finish();
+        Ok(ParquetFile {
+            id,
+            path: path.to_string(),
+            size_bytes,
+            row_count: meta.num_rows as u64,
+            chunk_time: generation_start,
+            min_time,
+            max_time,
+            column_stats,
+        })
+    }
+}
+
+/// Periodically compact the gen1 files of all tables into generations of `generation_duration`
+pub fn background_compaction_process(
+    write_buffer: Arc<WriteBufferImpl>,
+    generation_duration: Duration,
+    check_interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(check_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(error) = write_buffer.compact_gen1_files(generation_duration).await {
+                error!(%error, "failed to compact gen1 parquet files");
+            }
+        }
+    })
+}
@@ -0,0 +1,352 @@
+//! Import of [`RecordBatch`]es directly into the write buffer.
+//!
+//! Bulk loads of historical data, e.g., from Parquet or CSV files, are converted straight into
+//! the rows of a [`WriteBatch`] and written to the WAL, rather than being converted to line
+//! protocol and going through the per-line parsing and validation of a write. The schema of the
+//! imported batches is checked against the catalog once per batch, with the types of columns
+//! that already exist in the catalog taking precedence over those of the batch.
+
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::compute::{cast_with_options, CastOptions};
+use arrow::datatypes::{
+    DataType, Float64Type, Int64Type, TimeUnit, TimestampNanosecondType, UInt64Type,
+};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use data_types::Timestamp;
+use indexmap::IndexMap;
+use influxdb3_catalog::catalog::{Catalog, DatabaseSchema};
+use influxdb3_id::{ColumnId, TableId};
+use influxdb3_wal::{
+    CatalogBatch, CatalogOp, Field, FieldAdditions, FieldData, FieldDefinition, Gen1Duration, Row,
+    TableChunks, WriteBatch,
+};
+use schema::{InfluxColumnType, InfluxFieldType, TIME_COLUMN_NAME};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("the data imported into table {0} has no '{TIME_COLUMN_NAME}' column")]
+    MissingTimeColumn(String),
+
+    #[error("column {column} has data type {data_type}, which cannot be imported")]
+    UnsupportedDataType { column: String, data_type: DataType },
+
+    #[error("column {column} cannot be imported as {column_type}: {source}")]
+    Cast {
+        column: String,
+        column_type: InfluxColumnType,
+        source: ArrowError,
+    },
+
+    #[error(
+        "import would add new columns [{columns}] to table {table_name}, but the schema of \
+        {locked} is locked"
+    )]
+    SchemaLocked {
+        table_name: String,
+        columns: String,
+        locked: String,
+    },
+
+    #[error("import would add tag column {column} to table {table_name}, which has a series key")]
+    TagNotInSeriesKey { table_name: String, column: String },
+
+    #[error("catalog update error: {0}")]
+    Catalog(#[from] influxdb3_catalog::catalog::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The number of rows imported, and the number that were skipped because they had no time,
+/// no field values, were missing a series key value, or were older than the database's
+/// retention period
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub row_count: usize,
+    pub skipped_row_count: usize,
+}
+
+/// An imported [`RecordBatch`], ready to be written to the WAL
+#[derive(Debug)]
+pub(crate) struct ImportedBatch {
+    /// The catalog updates made by the import, which have already been applied to the catalog
+    pub(crate) catalog_batch: Option<CatalogBatch>,
+    pub(crate) write_batch: WriteBatch,
+    pub(crate) summary: ImportSummary,
+}
+
+/// Convert the batch into rows of the named table, creating the table, or adding any new
+/// columns to it, in the catalog
+pub(crate) fn import_record_batch(
+    catalog: &Catalog,
+    db_schema: &DatabaseSchema,
+    table_name: &str,
+    batch: &RecordBatch,
+    gen1_duration: Gen1Duration,
+    time_now_ns: i64,
+) -> Result<ImportedBatch> {
+    let existing = db_schema.table_definition(table_name);
+    let table_id = existing
+        .as_ref()
+        .map(|t| t.table_id)
+        .unwrap_or_else(TableId::new);
+
+    let schema = batch.schema();
+    // the types of the columns in the catalog take precedence, so that, e.g., a string column
+    // can be imported into an existing tag:
+    let mut columns = Vec::with_capacity(schema.fields().len());
+    let mut new_columns = vec![];
+    for field in schema.fields() {
+        let name = field.name().as_str();
+        let existing_column = existing.as_ref().and_then(|t| t.column_def_and_id(name));
+        let (column_id, column_type) = match existing_column {
+            Some((id, def)) => (id, def.data_type),
+            None => {
+                let column_type = influx_column_type(name, field.data_type())?;
+                let id = ColumnId::new();
+                new_columns.push((id, Arc::<str>::from(name), column_type));
+                (id, column_type)
+            }
+        };
+        columns.push((name, column_id, column_type));
+    }
+    let series_key = existing.as_ref().and_then(|t| t.series_key.clone());
+
+    // the arrays are cast before the catalog is updated, so that the catalog is left unchanged
+    // if the batch cannot be imported:
+    let mut values = Vec::with_capacity(columns.len());
+    let mut times = None;
+    for ((name, column_id, column_type), array) in columns.into_iter().zip(batch.columns()) {
+        // values that cannot be cast are an error, rather than being imported as nulls:
+        let options = CastOptions {
+            safe: false,
+            ..Default::default()
+        };
+        let array =
+            cast_with_options(array, &arrow_type(column_type), &options).map_err(|source| {
+                Error::Cast {
+                    column: name.to_string(),
+                    column_type,
+                    source,
+                }
+            })?;
+        if column_type == InfluxColumnType::Timestamp {
+            times = Some((column_id, array));
+            continue;
+        }
+        let is_key = series_key
+            .as_ref()
+            .is_some_and(|key| key.contains(&column_id));
+        values.push((column_id, column_type, is_key, array));
+    }
+    let (time_column_id, times) =
+        times.ok_or_else(|| Error::MissingTimeColumn(table_name.to_string()))?;
+    let times = times.as_primitive::<TimestampNanosecondType>();
+
+    let catalog_op = match &existing {
+        Some(table_def) if !new_columns.is_empty() => {
+            if db_schema.schema_locked || table_def.schema_locked {
+                return Err(schema_locked_error(db_schema, table_name, &new_columns));
+            }
+            if table_def.series_key.is_some() {
+                if let Some((_, name, _)) = new_columns
+                    .iter()
+                    .find(|(_, _, t)| *t == InfluxColumnType::Tag)
+                {
+                    return Err(Error::TagNotInSeriesKey {
+                        table_name: table_name.to_string(),
+                        column: name.to_string(),
+                    });
+                }
+            }
+            Some(CatalogOp::AddFields(FieldAdditions {
+                database_name: Arc::clone(&db_schema.name),
+                database_id: db_schema.id,
+                table_id,
+                table_name: Arc::clone(&table_def.table_name),
+                field_definitions: field_definitions(&new_columns),
+            }))
+        }
+        Some(_) => None,
+        None => {
+            if db_schema.schema_locked {
+                return Err(schema_locked_error(db_schema, table_name, &new_columns));
+            }
+            Some(CatalogOp::CreateTable(influxdb3_wal::TableDefinition {
+                table_id,
+                database_id: db_schema.id,
+                database_name: Arc::clone(&db_schema.name),
+                table_name: table_name.into(),
+                field_definitions: field_definitions(&new_columns),
+                key: None,
+            }))
+        }
+    };
+    let catalog_batch = catalog_op
+        .map(|op| -> Result<CatalogBatch> {
+            let catalog_batch = CatalogBatch {
+                database_id: db_schema.id,
+                database_name: Arc::clone(&db_schema.name),
+                time_ns: time_now_ns,
+                ops: vec![op],
+            };
+            catalog.apply_catalog_batch(&catalog_batch)?;
+            Ok(catalog_batch)
+        })
+        .transpose()?;
+
+    let retention_cutoff_ns = db_schema.retention_cutoff_ns(time_now_ns);
+    let key_count = series_key.as_ref().map(Vec::len).unwrap_or_default();
+    let mut table_chunks = TableChunks::default();
+    let mut summary = ImportSummary::default();
+    for row in 0..batch.num_rows() {
+        let time = times.value(row);
+        if times.is_null(row) || retention_cutoff_ns.is_some_and(|cutoff| time < cutoff) {
+            summary.skipped_row_count += 1;
+            continue;
+        }
+
+        let mut fields = Vec::with_capacity(values.len() + 1);
+        let mut field_count = 0;
+        let mut key_values = 0;
+        for (column_id, column_type, is_key, array) in &values {
+            if array.is_null(row) {
+                continue;
+            }
+            let value = field_data(*column_type, *is_key, array, row);
+            match value {
+                FieldData::Key(_) => key_values += 1,
+                FieldData::Tag(_) => (),
+                _ => field_count += 1,
+            }
+            fields.push(Field::new(*column_id, value));
+        }
+        if field_count == 0 || key_values < key_count {
+            summary.skipped_row_count += 1;
+            continue;
+        }
+        fields.push(Field::new(time_column_id, FieldData::Timestamp(time)));
+
+        let chunk_time = gen1_duration.chunk_time_for_timestamp(Timestamp::new(time));
+        table_chunks.push_row(chunk_time, Row { time, fields });
+        summary.row_count += 1;
+    }
+
+    let mut chunks = IndexMap::new();
+    if summary.row_count > 0 {
+        chunks.insert(table_id, table_chunks);
+    }
+    Ok(ImportedBatch {
+        catalog_batch,
+        write_batch: WriteBatch::new(db_schema.id, Arc::clone(&db_schema.name), chunks),
+        summary,
+    })
+}
+
+/// The type of a new column, from the data type of the imported array. Dictionary-encoded
+/// strings are tags, as they are in the Parquet files persisted by the write buffer.
+fn influx_column_type(name: &str, data_type: &DataType) -> Result<InfluxColumnType> {
+    if name == TIME_COLUMN_NAME {
+        return Ok(InfluxColumnType::Timestamp);
+    }
+    Ok(match data_type {
+        DataType::Dictionary(_, value) if value.is_string() => InfluxColumnType::Tag,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+            InfluxColumnType::Field(InfluxFieldType::String)
+        }
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => {
+            InfluxColumnType::Field(InfluxFieldType::Integer)
+        }
+        DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => {
+            InfluxColumnType::Field(InfluxFieldType::UInteger)
+        }
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => {
+            InfluxColumnType::Field(InfluxFieldType::Float)
+        }
+        DataType::Boolean => InfluxColumnType::Field(InfluxFieldType::Boolean),
+        data_type => {
+            return Err(Error::UnsupportedDataType {
+                column: name.to_string(),
+                data_type: data_type.clone(),
+            })
+        }
+    })
+}
+
+/// The data type that arrays are cast to before they are converted into rows
+fn arrow_type(column_type: InfluxColumnType) -> DataType {
+    match column_type {
+        InfluxColumnType::Tag | InfluxColumnType::Field(InfluxFieldType::String) => DataType::Utf8,
+        InfluxColumnType::Field(InfluxFieldType::Integer) => DataType::Int64,
+        InfluxColumnType::Field(InfluxFieldType::UInteger) => DataType::UInt64,
+        InfluxColumnType::Field(InfluxFieldType::Float) => DataType::Float64,
+        InfluxColumnType::Field(InfluxFieldType::Boolean) => DataType::Boolean,
+        InfluxColumnType::Timestamp => DataType::Timestamp(TimeUnit::Nanosecond, None),
+    }
+}
+
+fn field_data(
+    column_type: InfluxColumnType,
+    is_key: bool,
+    array: &ArrayRef,
+    row: usize,
+) -> FieldData {
+    match column_type {
+        InfluxColumnType::Tag if is_key => {
+            FieldData::Key(array.as_string::<i32>().value(row).to_string())
+        }
+        InfluxColumnType::Tag => FieldData::Tag(array.as_string::<i32>().value(row).to_string()),
+        InfluxColumnType::Field(InfluxFieldType::String) => {
+            FieldData::String(array.as_string::<i32>().value(row).to_string())
+        }
+        InfluxColumnType::Field(InfluxFieldType::Integer) => {
+            FieldData::Integer(array.as_primitive::<Int64Type>().value(row))
+        }
+        InfluxColumnType::Field(InfluxFieldType::UInteger) => {
+            FieldData::UInteger(array.as_primitive::<UInt64Type>().value(row))
+        }
+        InfluxColumnType::Field(InfluxFieldType::Float) => {
+            FieldData::Float(array.as_primitive::<Float64Type>().value(row))
+        }
+        InfluxColumnType::Field(InfluxFieldType::Boolean) => {
+            FieldData::Boolean(array.as_boolean().value(row))
+        }
+        InfluxColumnType::Timestamp => {
+            FieldData::Timestamp(array.as_primitive::<TimestampNanosecondType>().value(row))
+        }
+    }
+}
+
+fn field_definitions(columns: &[(ColumnId, Arc<str>, InfluxColumnType)]) -> Vec<FieldDefinition> {
+    columns
+        .iter()
+        .map(|(id, name, column_type)| FieldDefinition::new(*id, Arc::clone(name), column_type))
+        .collect()
+}
+
+fn schema_locked_error(
+    db_schema: &DatabaseSchema,
+    table_name: &str,
+    columns: &[(ColumnId, Arc<str>, InfluxColumnType)],
+) -> Error {
+    let table_locked = db_schema
+        .table_definition(table_name)
+        .is_some_and(|t| t.schema_locked);
+    Error::SchemaLocked {
+        table_name: table_name.to_string(),
+        columns: columns
+            .iter()
+            .map(|(_, name, _)| name.as_ref())
+            .collect::<Vec<_>>()
+            .join(", "),
+        locked: if table_locked {
+            format!("table {table_name}")
+        } else {
+            format!("database {}", db_schema.name)
+        },
+    }
+}
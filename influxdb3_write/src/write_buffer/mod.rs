@@ -1,6 +1,9 @@
 //! Implementation of an in-memory buffer for writes that persists data into a wal if it is configured.
 
+pub mod compactor;
+pub mod import;
 pub mod persisted_files;
+mod pruning;
 pub mod queryable_buffer;
 mod table_buffer;
 pub mod validator;
@@ -9,13 +12,17 @@ use crate::chunk::ParquetChunk;
 use crate::last_cache::{self, CreateCacheArguments, LastCacheProvider};
 use crate::parquet_cache::ParquetCacheOracle;
 use crate::persister::Persister;
+use crate::write_buffer::import::ImportSummary;
 use crate::write_buffer::persisted_files::PersistedFiles;
+use crate::write_buffer::pruning::ParquetFilePruner;
 use crate::write_buffer::queryable_buffer::QueryableBuffer;
 use crate::write_buffer::validator::WriteValidator;
 use crate::{
-    BufferedWriteRequest, Bufferer, ChunkContainer, LastCacheManager, ParquetFile,
-    PersistedSnapshot, Precision, WriteBuffer, WriteLineError,
+    BufferedWriteRequest, Bufferer, ChunkContainer, DatabaseManager, DatabaseTables,
+    LastCacheManager, MetaCacheManager, ParquetFile, PersistedSnapshot, Precision, TokenManager,
+    WriteBuffer, WriteLineError,
 };
+use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
 use data_types::{
     ChunkId, ChunkOrder, ColumnType, NamespaceName, NamespaceNameError, PartitionHashId,
@@ -25,22 +32,29 @@ use datafusion::catalog::Session;
 use datafusion::common::DataFusionError;
 use datafusion::datasource::object_store::ObjectStoreUrl;
 use datafusion::logical_expr::Expr;
-use influxdb3_catalog::catalog::Catalog;
-use influxdb3_id::{ColumnId, DbId, TableId};
+use influxdb3_cache::meta_cache::{self, CreateMetaCacheArgs, MetaCacheProvider};
+use influxdb3_catalog::catalog::{Catalog, TableDefinition, TokenDefinition, TIME_COLUMN_NAME};
+use influxdb3_id::{ColumnId, DbId, SerdeVecMap, TableId};
+use influxdb3_wal::local_disk::WalLocalDisk;
 use influxdb3_wal::object_store::WalObjectStore;
 use influxdb3_wal::CatalogOp::CreateLastCache;
 use influxdb3_wal::{
-    CatalogBatch, CatalogOp, LastCacheDefinition, LastCacheDelete, Wal, WalConfig, WalFileNotifier,
-    WalOp,
+    CatalogBatch, CatalogOp, DatabaseDefinition, DeleteDatabaseDefinition, DeleteTableDefinition,
+    DeleteTokenDefinition, FieldDefinition, LastCacheDefinition, LastCacheDelete,
+    MetaCacheDefinition, MetaCacheDelete, RetentionPeriodDefinition, SchemaLockDefinition, Wal,
+    WalConfig, WalFileNotifier, WalOp,
 };
 use iox_query::chunk_statistics::{create_chunk_statistics, NoColumnRanges};
 use iox_query::QueryChunk;
 use iox_time::{Time, TimeProvider};
+use metric::{Registry, U64Counter};
 use object_store::path::Path as ObjPath;
 use object_store::{ObjectMeta, ObjectStore};
-use observability_deps::tracing::{debug, error};
+use observability_deps::tracing::{debug, error, info, warn};
 use parquet_file::storage::ParquetExecInput;
-use schema::Schema;
+use schema::{InfluxColumnType, InfluxFieldType, Schema};
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
@@ -76,12 +90,24 @@ pub enum Error {
     #[error("error in last cache: {0}")]
     LastCacheError(#[from] last_cache::Error),
 
+    #[error("error in metadata cache: {0}")]
+    MetaCacheError(#[from] meta_cache::ProviderError),
+
     #[error("tried accessing database and table that do not exist")]
     DbDoesNotExist,
 
     #[error("tried accessing database and table that do not exist")]
     TableDoesNotExist,
 
+    #[error("database {0} already exists")]
+    DbAlreadyExists(String),
+
+    #[error("table {table_name} already exists in database {db_name}")]
+    TableAlreadyExists { db_name: String, table_name: String },
+
+    #[error("invalid table definition: {0}")]
+    InvalidTableDefinition(String),
+
     #[error("tried accessing column with name ({0}) that does not exist")]
     ColumnDoesNotExist(String),
 
@@ -96,6 +122,12 @@ pub enum Error {
 
     #[error("cannot write to a read-only server")]
     NoWriteInReadOnly,
+
+    #[error("error compacting parquet files: {0}")]
+    CompactionError(#[from] compactor::Error),
+
+    #[error("error importing data: {0}")]
+    ImportError(#[from] import::Error),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -121,20 +153,38 @@ pub struct WriteBufferImpl {
     wal: Arc<dyn Wal>,
     time_provider: Arc<dyn TimeProvider>,
     last_cache: Arc<LastCacheProvider>,
+    meta_cache: Arc<MetaCacheProvider>,
+    /// The number of persisted parquet files that were skipped by queries because their time
+    /// range or column statistics could not match the query's filters
+    parquet_files_pruned: U64Counter,
+    /// Files that have been removed from the persisted files, because their data expired, was
+    /// deleted, or was compacted into another file, along with the time they were removed, that
+    /// are waiting to be deleted from object store
+    removed_files: parking_lot::Mutex<Vec<(i64, ParquetFile)>>,
 }
 
 /// The maximum number of snapshots to load on start
 pub const N_SNAPSHOTS_TO_LOAD_ON_START: usize = 1_000;
 
+/// How long files that have been removed from the persisted files are kept in object store, so
+/// that queries that were planned against them before they were removed can finish reading them.
+pub const REMOVED_FILE_DELETE_DELAY: Duration = Duration::from_secs(10 * 60);
+
 impl WriteBufferImpl {
+    /// Creates the write buffer, replaying the WAL into it. If `wal_dir` is set, the WAL is
+    /// written to that directory on local disk and shipped to object store in the background,
+    /// otherwise WAL files are written directly to object store.
     pub async fn new(
         persister: Arc<Persister>,
         catalog: Arc<Catalog>,
         last_cache: Arc<LastCacheProvider>,
+        meta_cache: Arc<MetaCacheProvider>,
         time_provider: Arc<dyn TimeProvider>,
         executor: Arc<iox_query::exec::Executor>,
         wal_config: WalConfig,
+        wal_dir: Option<PathBuf>,
         parquet_cache: Option<Arc<dyn ParquetCacheOracle>>,
+        metric_registry: Arc<Registry>,
     ) -> Result<Self> {
         // load snapshots and replay the wal into the in memory buffer
         let persisted_snapshots = persister
@@ -166,6 +216,27 @@ impl WriteBufferImpl {
             .first()
             .map(|s| s.next_file_id.set_next_id())
             .unwrap_or(());
+        // files that were removed shortly before a restart may not have been deleted from object
+        // store yet, so they are queued for deletion again. Files removed earlier than that have
+        // already been deleted, unless the server was down when their delay passed, in which case
+        // they are left for `influxdb3 check --repair` to clean up:
+        let cutoff_ns =
+            time_provider.now().timestamp_nanos() - REMOVED_FILE_DELETE_DELAY.as_nanos() as i64;
+        let removed_files: Vec<(i64, ParquetFile)> = persisted_snapshots
+            .iter()
+            .filter_map(|s| {
+                s.removed_at_ns
+                    .filter(|removed_at_ns| *removed_at_ns > cutoff_ns)
+                    .map(|removed_at_ns| (removed_at_ns, s))
+            })
+            .flat_map(|(removed_at_ns, s)| {
+                s.removed_files
+                    .iter()
+                    .flat_map(|(_, tables)| tables.tables.iter())
+                    .flat_map(|(_, files)| files.iter().cloned())
+                    .map(move |file| (removed_at_ns, file))
+            })
+            .collect();
         let persisted_files = Arc::new(PersistedFiles::new_from_persisted_snapshots(
             persisted_snapshots,
         ));
@@ -174,21 +245,46 @@ impl WriteBufferImpl {
             Arc::clone(&catalog),
             Arc::clone(&persister),
             Arc::clone(&last_cache),
+            Arc::clone(&meta_cache),
             Arc::clone(&persisted_files),
             parquet_cache.clone(),
         ));
 
         // create the wal instance, which will replay into the queryable buffer and start
         // the background flush task.
-        let wal = WalObjectStore::new(
-            persister.object_store(),
-            persister.host_identifier_prefix(),
-            Arc::clone(&queryable_buffer) as Arc<dyn WalFileNotifier>,
-            wal_config,
-            last_wal_sequence_number,
-            last_snapshot_sequence_number,
-        )
-        .await?;
+        let wal: Arc<dyn Wal> = match wal_dir {
+            Some(wal_dir) => {
+                WalLocalDisk::new(
+                    wal_dir,
+                    persister.object_store(),
+                    persister.host_identifier_prefix(),
+                    Arc::clone(&queryable_buffer) as Arc<dyn WalFileNotifier>,
+                    wal_config,
+                    last_wal_sequence_number,
+                    last_snapshot_sequence_number,
+                )
+                .await?
+            }
+            None => {
+                WalObjectStore::new(
+                    persister.object_store(),
+                    persister.host_identifier_prefix(),
+                    Arc::clone(&queryable_buffer) as Arc<dyn WalFileNotifier>,
+                    wal_config,
+                    last_wal_sequence_number,
+                    last_snapshot_sequence_number,
+                )
+                .await?
+            }
+        };
+
+        let parquet_files_pruned = metric_registry
+            .register_metric::<U64Counter>(
+                "influxdb3_parquet_files_pruned",
+                "number of persisted parquet files pruned from queries using their time range and \
+                column statistics",
+            )
+            .recorder(&[]);
 
         Ok(Self {
             catalog,
@@ -198,8 +294,11 @@ impl WriteBufferImpl {
             wal,
             time_provider,
             last_cache,
+            meta_cache,
             persisted_files,
             buffer: queryable_buffer,
+            parquet_files_pruned,
+            removed_files: parking_lot::Mutex::new(removed_files),
         })
     }
 
@@ -297,6 +396,38 @@ impl WriteBufferImpl {
         })
     }
 
+    async fn import_record_batch(
+        &self,
+        db_name: NamespaceName<'static>,
+        table_name: &str,
+        batch: RecordBatch,
+        ingest_time: Time,
+    ) -> Result<ImportSummary> {
+        debug!(%db_name, table_name, rows = batch.num_rows(), "import into writebuffer");
+        let db_schema = self.catalog.db_or_create(db_name.as_str())?;
+        let imported = import::import_record_batch(
+            &self.catalog,
+            &db_schema,
+            table_name,
+            &batch,
+            self.wal_config.gen1_duration,
+            ingest_time.timestamp_nanos(),
+        )?;
+
+        let mut ops = vec![];
+        if let Some(catalog_batch) = imported.catalog_batch {
+            ops.push(WalOp::Catalog(catalog_batch));
+        }
+        if imported.summary.row_count > 0 {
+            ops.push(WalOp::Write(imported.write_batch));
+        }
+        if !ops.is_empty() {
+            self.wal.write_ops(ops).await?;
+        }
+
+        Ok(imported.summary)
+    }
+
     fn get_table_chunks(
         &self,
         database_name: &str,
@@ -309,13 +440,16 @@ impl WriteBufferImpl {
             DataFusionError::Execution(format!("database {} not found", database_name))
         })?;
 
-        let (table_id, table_schema) =
-            db_schema.table_schema_and_id(table_name).ok_or_else(|| {
-                DataFusionError::Execution(format!(
-                    "table {} not found in db {}",
-                    table_name, database_name
-                ))
-            })?;
+        let (table_id, table_def) =
+            db_schema
+                .table_definition_and_id(table_name)
+                .ok_or_else(|| {
+                    DataFusionError::Execution(format!(
+                        "table {} not found in db {}",
+                        table_name, database_name
+                    ))
+                })?;
+        let table_schema = table_def.influx_schema();
 
         let mut chunks = self.buffer.get_table_chunks(
             Arc::clone(&db_schema),
@@ -325,14 +459,28 @@ impl WriteBufferImpl {
             ctx,
         )?;
 
-        let parquet_files = self.persisted_files.get_files(db_schema.id, table_id);
+        let pruner = ParquetFilePruner::new(&table_def, filters);
+        let (parquet_files, pruned): (Vec<_>, Vec<_>) = self
+            .persisted_files
+            .get_files(db_schema.id, table_id)
+            .into_iter()
+            .partition(|f| !pruner.should_prune(f));
+        if !pruned.is_empty() {
+            debug!(
+                table_name,
+                pruned = pruned.len(),
+                remaining = parquet_files.len(),
+                "pruned parquet files for query"
+            );
+            self.parquet_files_pruned.inc(pruned.len() as u64);
+        }
 
         let mut chunk_order = chunks.len() as i64;
 
         for parquet_file in parquet_files {
             let parquet_chunk = parquet_chunk_from_file(
                 &parquet_file,
-                &table_schema,
+                table_schema,
                 self.persister.object_store_url().clone(),
                 self.persister.object_store(),
                 chunk_order,
@@ -345,6 +493,143 @@ impl WriteBufferImpl {
 
         Ok(chunks)
     }
+
+    /// Remove persisted files whose data is entirely older than their database's retention
+    /// period, along with all files of deleted databases and tables.
+    ///
+    /// The removal is recorded in a new snapshot, so that the files are not loaded again on
+    /// restart, and the files are deleted from the object store once queries can no longer be
+    /// reading them, see [`REMOVED_FILE_DELETE_DELAY`].
+    pub async fn remove_expired_and_deleted_files(&self) -> Result<()> {
+        self.delete_removed_files().await;
+
+        let now_ns = self.time_provider.now().timestamp_nanos();
+        let mut removed_files = SerdeVecMap::new();
+        for db_schema in self.catalog.list_db_schema_with_deleted() {
+            let cutoff_ns = db_schema.retention_cutoff_ns(now_ns);
+            let removed =
+                self.persisted_files
+                    .remove_files_where(db_schema.id, |table_id, file| {
+                        db_schema.deleted
+                            || db_schema
+                                .table_definition_by_id(table_id)
+                                .is_some_and(|table_def| table_def.deleted)
+                            || cutoff_ns.is_some_and(|cutoff_ns| file.max_time < cutoff_ns)
+                    });
+            if !removed.is_empty() {
+                removed_files.insert(
+                    db_schema.id,
+                    DatabaseTables {
+                        tables: removed.into_iter().collect(),
+                    },
+                );
+            }
+        }
+        if removed_files.is_empty() {
+            return Ok(());
+        }
+
+        let snapshot = self
+            .persist_files_snapshot(|snapshot| snapshot.removed_files = removed_files)
+            .await?;
+
+        for (db_id, tables) in snapshot.removed_files {
+            for file in tables.tables.into_values().flatten() {
+                info!(%db_id, path = %file.path, "removed expired or deleted parquet file");
+                self.queue_removed_file(now_ns, file);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Persist a snapshot that only records changes to the persisted files, made by `update`,
+    /// such as files removed by retention or replaced by compaction.
+    ///
+    /// The snapshot does not persist any buffered data, so it carries over the WAL and catalog
+    /// sequence numbers of the last persisted snapshot, to not change where replay starts. The
+    /// WAL's snapshot permit is held until it is persisted, so that the last persisted snapshot
+    /// can not be superseded by a WAL snapshot in the meantime.
+    async fn persist_files_snapshot(
+        &self,
+        update: impl FnOnce(&mut PersistedSnapshot),
+    ) -> Result<PersistedSnapshot> {
+        let (snapshot_sequence_number, snapshot_permit) = self.wal.reserve_snapshot().await;
+
+        let last_persisted = self.buffer.persisted_snapshot_notify_rx().borrow().clone();
+        let last_snapshot = match last_persisted {
+            Some(snapshot) => Some(snapshot),
+            None => self.persister.load_snapshots(1).await?.pop(),
+        };
+        let (wal_file_sequence_number, catalog_sequence_number) = last_snapshot
+            .map(|s| (s.wal_file_sequence_number, s.catalog_sequence_number))
+            .unwrap_or_default();
+        let mut snapshot = PersistedSnapshot::new(
+            self.persister.host_identifier_prefix().to_string(),
+            snapshot_sequence_number,
+            wal_file_sequence_number,
+            catalog_sequence_number,
+        );
+        update(&mut snapshot);
+        if !snapshot.removed_files.is_empty() {
+            snapshot.removed_at_ns = Some(self.time_provider.now().timestamp_nanos());
+        }
+        self.persister.persist_snapshot(&snapshot).await?;
+        drop(snapshot_permit);
+
+        Ok(snapshot)
+    }
+
+    /// Queue a file that was removed from the persisted files at `removed_at_ns` to be deleted
+    /// from object store once [`REMOVED_FILE_DELETE_DELAY`] has passed
+    fn queue_removed_file(&self, removed_at_ns: i64, file: ParquetFile) {
+        self.removed_files.lock().push((removed_at_ns, file));
+    }
+
+    /// Delete the files that were removed from the persisted files at least
+    /// [`REMOVED_FILE_DELETE_DELAY`] ago
+    async fn delete_removed_files(&self) {
+        let cutoff_ns = self.time_provider.now().timestamp_nanos()
+            - REMOVED_FILE_DELETE_DELAY.as_nanos() as i64;
+        let expired: Vec<ParquetFile> = {
+            let mut removed = self.removed_files.lock();
+            let (expired, retained) = removed
+                .drain(..)
+                .partition(|(removed_at, _)| *removed_at <= cutoff_ns);
+            *removed = retained;
+            expired.into_iter().map(|(_, file)| file).collect()
+        };
+
+        let object_store = self.persister.object_store();
+        for file in expired {
+            info!(path = %file.path, "deleting removed parquet file");
+            match object_store.delete(&ObjPath::from(file.path)).await {
+                // the file may have been deleted before a restart
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+                Err(error) => warn!(%error, "failed to delete removed parquet file"),
+            }
+        }
+    }
+}
+
+/// Periodically remove persisted files that are outside of their database's retention period,
+/// or that belong to a deleted database or table
+pub fn background_retention_process(
+    write_buffer: Arc<WriteBufferImpl>,
+    check_interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(check_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(error) = write_buffer.remove_expired_and_deleted_files().await {
+                error!(%error, "failed to remove expired or deleted parquet files");
+            }
+        }
+    })
 }
 
 pub fn parquet_chunk_from_file(
@@ -421,6 +706,17 @@ impl Bufferer for WriteBufferImpl {
             .await
     }
 
+    async fn import_record_batch(
+        &self,
+        database: NamespaceName<'static>,
+        table_name: &str,
+        batch: RecordBatch,
+        ingest_time: Time,
+    ) -> Result<ImportSummary> {
+        self.import_record_batch(database, table_name, batch, ingest_time)
+            .await
+    }
+
     fn catalog(&self) -> Arc<Catalog> {
         self.catalog()
     }
@@ -532,6 +828,376 @@ impl LastCacheManager for WriteBufferImpl {
     }
 }
 
+#[async_trait::async_trait]
+impl MetaCacheManager for WriteBufferImpl {
+    fn meta_cache_provider(&self) -> Arc<MetaCacheProvider> {
+        Arc::clone(&self.meta_cache)
+    }
+
+    /// Create a new metadata cache in the specified database, along with the given parameters.
+    ///
+    /// Returns the definition of the newly created cache, or `None` if a cache was not created,
+    /// but the provided parameters match those of an existing cache.
+    async fn create_meta_cache(
+        &self,
+        db_id: DbId,
+        cache_name: Option<String>,
+        args: CreateMetaCacheArgs,
+    ) -> Result<Option<MetaCacheDefinition>, Error> {
+        let db_schema = self
+            .catalog
+            .db_schema_by_id(&db_id)
+            .ok_or(Error::DbDoesNotExist)?;
+        if let Some(info) = self
+            .meta_cache
+            .create_cache(db_id, cache_name.map(Into::into), args)?
+        {
+            self.catalog
+                .add_meta_cache(db_id, info.table_id, info.clone());
+            let add_cache_catalog_batch = WalOp::Catalog(CatalogBatch {
+                time_ns: self.time_provider.now().timestamp_nanos(),
+                database_id: db_schema.id,
+                database_name: Arc::clone(&db_schema.name),
+                ops: vec![CatalogOp::CreateMetaCache(info.clone())],
+            });
+            self.wal.write_ops(vec![add_cache_catalog_batch]).await?;
+
+            Ok(Some(info))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn delete_meta_cache(
+        &self,
+        db_id: &DbId,
+        tbl_id: &TableId,
+        cache_name: &str,
+    ) -> Result<(), Error> {
+        let catalog = self.catalog();
+        let db_schema = catalog
+            .db_schema_by_id(db_id)
+            .ok_or(Error::DbDoesNotExist)?;
+        let table_name = db_schema
+            .table_id_to_name(tbl_id)
+            .ok_or(Error::TableDoesNotExist)?;
+        self.meta_cache.delete_cache(db_id, tbl_id, cache_name)?;
+        catalog.delete_meta_cache(*db_id, *tbl_id, cache_name);
+
+        // NOTE: if this fails then the cache will be gone from the running server, but will be
+        // resurrected on server restart.
+        self.wal
+            .write_ops(vec![WalOp::Catalog(CatalogBatch {
+                time_ns: self.time_provider.now().timestamp_nanos(),
+                database_id: *db_id,
+                database_name: Arc::clone(&db_schema.name),
+                ops: vec![CatalogOp::DeleteMetaCache(MetaCacheDelete {
+                    table_name,
+                    table_id: *tbl_id,
+                    cache_name: cache_name.into(),
+                })],
+            })])
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl DatabaseManager for WriteBufferImpl {
+    async fn create_database(
+        &self,
+        db_name: NamespaceName<'static>,
+        retention_period: Option<Duration>,
+    ) -> Result<(), Error> {
+        if self.catalog.db_schema(db_name.as_str()).is_some() {
+            return Err(Error::DbAlreadyExists(db_name.to_string()));
+        }
+        let database_id = DbId::new();
+        let database_name: Arc<str> = db_name.as_str().into();
+        let mut ops = vec![CatalogOp::CreateDatabase(DatabaseDefinition {
+            database_id,
+            database_name: Arc::clone(&database_name),
+        })];
+        if let Some(retention_period) = retention_period {
+            ops.push(CatalogOp::SetRetentionPeriod(RetentionPeriodDefinition {
+                database_id,
+                database_name: Arc::clone(&database_name),
+                retention_period_seconds: Some(retention_period.as_secs()),
+            }));
+        }
+        let catalog_batch = CatalogBatch {
+            time_ns: self.time_provider.now().timestamp_nanos(),
+            database_id,
+            database_name,
+            ops,
+        };
+        info!(%db_name, "creating database");
+        self.catalog.apply_catalog_batch(&catalog_batch)?;
+        self.wal
+            .write_ops(vec![WalOp::Catalog(catalog_batch)])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn create_table(
+        &self,
+        db_name: &str,
+        table_name: &str,
+        tags: Vec<String>,
+        fields: Vec<(String, InfluxFieldType)>,
+    ) -> Result<(), Error> {
+        let (db_id, db_schema) = self
+            .catalog
+            .db_schema_and_id(db_name)
+            .ok_or(Error::DbDoesNotExist)?;
+        if db_schema.table_definition(table_name).is_some() {
+            return Err(Error::TableAlreadyExists {
+                db_name: db_name.to_string(),
+                table_name: table_name.to_string(),
+            });
+        }
+
+        if table_name.is_empty() {
+            return Err(Error::InvalidTableDefinition(
+                "table name cannot be empty".to_string(),
+            ));
+        }
+        let mut names = HashSet::with_capacity(tags.len() + fields.len() + 1);
+        names.insert(TIME_COLUMN_NAME);
+        for name in tags.iter().chain(fields.iter().map(|(name, _)| name)) {
+            if name.is_empty() {
+                return Err(Error::InvalidTableDefinition(
+                    "column names cannot be empty".to_string(),
+                ));
+            }
+            if !names.insert(name.as_str()) {
+                return Err(Error::InvalidTableDefinition(format!(
+                    "column name {name} is either reserved or declared more than once"
+                )));
+            }
+        }
+
+        // Columns are ordered the same way as tables created on write: tags in the order given,
+        // then fields, with time last. There is no series key, as for tables created by a v1
+        // write, so that the table accepts line protocol:
+        let mut columns = Vec::with_capacity(tags.len() + fields.len() + 1);
+        for tag in tags {
+            columns.push((
+                ColumnId::new(),
+                Arc::<str>::from(tag),
+                InfluxColumnType::Tag,
+            ));
+        }
+        for (field, field_type) in fields {
+            columns.push((
+                ColumnId::new(),
+                Arc::<str>::from(field),
+                InfluxColumnType::Field(field_type),
+            ));
+        }
+        columns.push((
+            ColumnId::new(),
+            Arc::from(TIME_COLUMN_NAME),
+            InfluxColumnType::Timestamp,
+        ));
+
+        let table_id = TableId::new();
+        let table_name: Arc<str> = table_name.into();
+        let field_definitions = columns
+            .iter()
+            .map(|(id, name, influx_type)| FieldDefinition::new(*id, Arc::clone(name), influx_type))
+            .collect();
+        // check the definition against the column limit before it is applied to the catalog:
+        TableDefinition::new(table_id, Arc::clone(&table_name), columns, None)?;
+
+        let catalog_batch = CatalogBatch {
+            time_ns: self.time_provider.now().timestamp_nanos(),
+            database_id: db_id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::CreateTable(influxdb3_wal::TableDefinition {
+                database_id: db_id,
+                database_name: Arc::clone(&db_schema.name),
+                table_name: Arc::clone(&table_name),
+                table_id,
+                field_definitions,
+                key: None,
+            })],
+        };
+        info!(db_name, %table_name, "creating table");
+        self.catalog.apply_catalog_batch(&catalog_batch)?;
+        self.wal
+            .write_ops(vec![WalOp::Catalog(catalog_batch)])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_retention_period(
+        &self,
+        db_name: NamespaceName<'static>,
+        retention_period: Option<Duration>,
+    ) -> Result<(), Error> {
+        let db_schema = self
+            .catalog
+            .db_schema(db_name.as_str())
+            .ok_or(Error::DbDoesNotExist)?;
+        let catalog_batch = CatalogBatch {
+            time_ns: self.time_provider.now().timestamp_nanos(),
+            database_id: db_schema.id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::SetRetentionPeriod(RetentionPeriodDefinition {
+                database_id: db_schema.id,
+                database_name: Arc::clone(&db_schema.name),
+                retention_period_seconds: retention_period.map(|d| d.as_secs()),
+            })],
+        };
+        self.catalog.apply_catalog_batch(&catalog_batch)?;
+        self.wal
+            .write_ops(vec![WalOp::Catalog(catalog_batch)])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_schema_locked(
+        &self,
+        db_name: &str,
+        table_name: Option<&str>,
+        locked: bool,
+    ) -> Result<(), Error> {
+        let (db_id, db_schema) = self
+            .catalog
+            .db_schema_and_id(db_name)
+            .ok_or(Error::DbDoesNotExist)?;
+        let table = table_name
+            .map(|table_name| {
+                db_schema
+                    .table_definition(table_name)
+                    .map(|table_def| (table_def.table_id, Arc::clone(&table_def.table_name)))
+                    .ok_or(Error::TableDoesNotExist)
+            })
+            .transpose()?;
+        let catalog_batch = CatalogBatch {
+            time_ns: self.time_provider.now().timestamp_nanos(),
+            database_id: db_id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::SetSchemaLocked(SchemaLockDefinition {
+                database_id: db_id,
+                database_name: Arc::clone(&db_schema.name),
+                table,
+                locked,
+            })],
+        };
+        info!(db_name, ?table_name, locked, "setting schema lock");
+        self.catalog.apply_catalog_batch(&catalog_batch)?;
+        self.wal
+            .write_ops(vec![WalOp::Catalog(catalog_batch)])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_database(&self, db_name: &str) -> Result<(), Error> {
+        let (db_id, db_schema) = self
+            .catalog
+            .db_schema_and_id(db_name)
+            .ok_or(Error::DbDoesNotExist)?;
+        let deletion_time = self.time_provider.now().timestamp_nanos();
+        let catalog_batch = CatalogBatch {
+            time_ns: deletion_time,
+            database_id: db_id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::DeleteDatabase(DeleteDatabaseDefinition {
+                database_id: db_id,
+                database_name: Arc::clone(&db_schema.name),
+                deletion_time,
+            })],
+        };
+        info!(db_name, "deleting database");
+        self.catalog.apply_catalog_batch(&catalog_batch)?;
+        self.wal
+            .write_ops(vec![WalOp::Catalog(catalog_batch)])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_table(&self, db_name: &str, table_name: &str) -> Result<(), Error> {
+        let (db_id, db_schema) = self
+            .catalog
+            .db_schema_and_id(db_name)
+            .ok_or(Error::DbDoesNotExist)?;
+        let table_def = db_schema
+            .table_definition(table_name)
+            .ok_or(Error::TableDoesNotExist)?;
+        let deletion_time = self.time_provider.now().timestamp_nanos();
+        let catalog_batch = CatalogBatch {
+            time_ns: deletion_time,
+            database_id: db_id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::DeleteTable(DeleteTableDefinition {
+                database_id: db_id,
+                database_name: Arc::clone(&db_schema.name),
+                table_id: table_def.table_id,
+                table_name: Arc::clone(&table_def.table_name),
+                deletion_time,
+            })],
+        };
+        info!(db_name, table_name, "deleting table");
+        self.catalog.apply_catalog_batch(&catalog_batch)?;
+        self.wal
+            .write_ops(vec![WalOp::Catalog(catalog_batch)])
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenManager for WriteBufferImpl {
+    async fn create_token(&self, token: TokenDefinition) -> Result<(), Error> {
+        info!(name = %token.name, "creating token");
+        let catalog_batch = CatalogBatch::tokens(
+            self.time_provider.now().timestamp_nanos(),
+            [CatalogOp::CreateToken(token)],
+        );
+        self.catalog.apply_catalog_batch(&catalog_batch)?;
+        self.wal
+            .write_ops(vec![WalOp::Catalog(catalog_batch)])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_token(&self, name: &str) -> Result<(), Error> {
+        info!(name, "revoking token");
+        // deleting a token that does not exist is a no-op when the batch is applied, so check
+        // for it here:
+        if !self
+            .catalog
+            .list_tokens()
+            .iter()
+            .any(|t| t.name.as_ref() == name)
+        {
+            return Err(influxdb3_catalog::catalog::Error::TokenNotFound(name.into()).into());
+        }
+        let catalog_batch = CatalogBatch::tokens(
+            self.time_provider.now().timestamp_nanos(),
+            [CatalogOp::DeleteToken(DeleteTokenDefinition {
+                name: name.into(),
+            })],
+        );
+        self.catalog.apply_catalog_batch(&catalog_batch)?;
+        self.wal
+            .write_ops(vec![WalOp::Catalog(catalog_batch)])
+            .await?;
+
+        Ok(())
+    }
+}
+
 impl WriteBuffer for WriteBufferImpl {}
 
 #[cfg(test)]
@@ -545,14 +1211,19 @@ mod tests {
     use arrow::record_batch::RecordBatch;
     use arrow_util::{assert_batches_eq, assert_batches_sorted_eq};
     use bytes::Bytes;
+    use datafusion::prelude::{col, lit};
+    use datafusion::scalar::ScalarValue;
     use datafusion_util::config::register_iox_object_store;
     use futures_util::StreamExt;
-    use influxdb3_catalog::catalog::CatalogSequenceNumber;
+    use influxdb3_catalog::catalog::{CatalogSequenceNumber, TokenAction, TokenPermission};
     use influxdb3_id::{DbId, ParquetFileId};
     use influxdb3_test_helpers::object_store::RequestCountedObjectStore;
-    use influxdb3_wal::{Gen1Duration, SnapshotSequenceNumber, WalFileSequenceNumber};
+    use influxdb3_wal::{
+        Gen1Duration, SnapshotSequenceNumber, WalCompression, WalFileSequenceNumber,
+    };
     use iox_query::exec::IOxSessionContext;
     use iox_time::{MockProvider, Time};
+    use metric::{Attributes, Metric};
     use object_store::local::LocalFileSystem;
     use object_store::memory::InMemory;
     use object_store::{ObjectStore, PutPayload};
@@ -594,14 +1265,20 @@ mod tests {
         let persister = Arc::new(Persister::new(Arc::clone(&object_store), "test_host"));
         let catalog = Arc::new(persister.load_or_create_catalog().await.unwrap());
         let last_cache = LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap();
+        let meta_cache =
+            MetaCacheProvider::new_from_catalog(Arc::clone(&time_provider), Arc::clone(&catalog))
+                .unwrap();
         let write_buffer = WriteBufferImpl::new(
             Arc::clone(&persister),
             catalog,
             last_cache,
+            meta_cache,
             Arc::clone(&time_provider),
             crate::test_help::make_exec(),
             WalConfig::test_config(),
+            None,
             Some(Arc::clone(&parquet_cache)),
+            Arc::new(metric::Registry::new()),
         )
         .await
         .unwrap();
@@ -668,10 +1345,14 @@ mod tests {
         // now load a new buffer from object storage
         let catalog = Arc::new(persister.load_or_create_catalog().await.unwrap());
         let last_cache = LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap();
+        let meta_cache =
+            MetaCacheProvider::new_from_catalog(Arc::clone(&time_provider), Arc::clone(&catalog))
+                .unwrap();
         let write_buffer = WriteBufferImpl::new(
             Arc::clone(&persister),
             catalog,
             last_cache,
+            meta_cache,
             Arc::clone(&time_provider),
             crate::test_help::make_exec(),
             WalConfig {
@@ -679,8 +1360,11 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(50),
                 snapshot_size: 100,
+                compression: WalCompression::default(),
             },
+            None,
             Some(Arc::clone(&parquet_cache)),
+            Arc::new(metric::Registry::new()),
         )
         .await
         .unwrap();
@@ -700,6 +1384,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -726,10 +1411,16 @@ mod tests {
         // load a new write buffer to ensure its durable
         let catalog = Arc::new(wbuf.persister.load_or_create_catalog().await.unwrap());
         let last_cache = LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap();
+        let meta_cache = MetaCacheProvider::new_from_catalog(
+            Arc::clone(&wbuf.time_provider),
+            Arc::clone(&catalog),
+        )
+        .unwrap();
         let wbuf = WriteBufferImpl::new(
             Arc::clone(&wbuf.persister),
             catalog,
             last_cache,
+            meta_cache,
             Arc::clone(&wbuf.time_provider),
             Arc::clone(&wbuf.buffer.executor),
             WalConfig {
@@ -737,8 +1428,11 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
+            None,
             wbuf.parquet_cache.clone(),
+            Arc::new(metric::Registry::new()),
         )
         .await
         .unwrap();
@@ -764,10 +1458,16 @@ mod tests {
         // and do another replay and verification
         let catalog = Arc::new(wbuf.persister.load_or_create_catalog().await.unwrap());
         let last_cache = LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap();
+        let meta_cache = MetaCacheProvider::new_from_catalog(
+            Arc::clone(&wbuf.time_provider),
+            Arc::clone(&catalog),
+        )
+        .unwrap();
         let wbuf = WriteBufferImpl::new(
             Arc::clone(&wbuf.persister),
             catalog,
             last_cache,
+            meta_cache,
             Arc::clone(&wbuf.time_provider),
             Arc::clone(&wbuf.buffer.executor),
             WalConfig {
@@ -775,8 +1475,11 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
+            None,
             wbuf.parquet_cache.clone(),
+            Arc::new(metric::Registry::new()),
         )
         .await
         .unwrap();
@@ -821,10 +1524,16 @@ mod tests {
         // do another reload and verify it's gone
         let catalog = Arc::new(wbuf.persister.load_or_create_catalog().await.unwrap());
         let last_cache = LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap();
+        let meta_cache = MetaCacheProvider::new_from_catalog(
+            Arc::clone(&wbuf.time_provider),
+            Arc::clone(&catalog),
+        )
+        .unwrap();
         let wbuf = WriteBufferImpl::new(
             Arc::clone(&wbuf.persister),
             catalog,
             last_cache,
+            meta_cache,
             Arc::clone(&wbuf.time_provider),
             Arc::clone(&wbuf.buffer.executor),
             WalConfig {
@@ -832,8 +1541,11 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
+            None,
             wbuf.parquet_cache.clone(),
+            Arc::new(metric::Registry::new()),
         )
         .await
         .unwrap();
@@ -854,6 +1566,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 2,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -977,10 +1690,16 @@ mod tests {
                 .unwrap(),
         );
         let last_cache = LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap();
+        let meta_cache = MetaCacheProvider::new_from_catalog(
+            Arc::clone(&write_buffer.time_provider),
+            Arc::clone(&catalog),
+        )
+        .unwrap();
         let write_buffer = WriteBufferImpl::new(
             Arc::clone(&write_buffer.persister),
             catalog,
             last_cache,
+            meta_cache,
             Arc::clone(&write_buffer.time_provider),
             Arc::clone(&write_buffer.buffer.executor),
             WalConfig {
@@ -988,8 +1707,11 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 2,
+                compression: WalCompression::default(),
             },
+            None,
             write_buffer.parquet_cache.clone(),
+            Arc::new(metric::Registry::new()),
         )
         .await
         .unwrap();
@@ -1046,18 +1768,118 @@ mod tests {
     }
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-    async fn catalog_snapshots_only_if_updated() {
-        let (write_buffer, _ctx) = setup(
-            Time::from_timestamp_nanos(0),
-            Arc::new(InMemory::new()),
+    async fn prunes_parquet_files_using_time_and_tag_filters() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let time_provider: Arc<dyn TimeProvider> =
+            Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let persister = Arc::new(Persister::new(Arc::clone(&object_store), "test_host"));
+        let catalog = Arc::new(persister.load_or_create_catalog().await.unwrap());
+        let metric_registry = Arc::new(metric::Registry::new());
+        let write_buffer = WriteBufferImpl::new(
+            Arc::clone(&persister),
+            Arc::clone(&catalog),
+            LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap(),
+            MetaCacheProvider::new_from_catalog(Arc::clone(&time_provider), catalog).unwrap(),
+            Arc::clone(&time_provider),
+            crate::test_help::make_exec(),
             WalConfig {
                 gen1_duration: Gen1Duration::new_1m(),
                 max_write_buffer_size: 100,
-                flush_interval: Duration::from_millis(5),
-                snapshot_size: 1,
+                flush_interval: Duration::from_millis(10),
+                snapshot_size: 2,
+                compression: WalCompression::default(),
             },
+            None,
+            None,
+            Arc::clone(&metric_registry),
         )
-        .await;
+        .await
+        .unwrap();
+        let ctx = IOxSessionContext::with_testing();
+
+        // write into two different gen1 chunks, then trigger a snapshot with a third write so
+        // that the first two are persisted as separate parquet files:
+        do_writes(
+            "foo",
+            &write_buffer,
+            &[
+                TestWrite {
+                    lp: "cpu,host=a bar=1 10000000000",
+                    time_seconds: 10,
+                },
+                TestWrite {
+                    lp: "cpu,host=b bar=2 65000000000",
+                    time_seconds: 65,
+                },
+                TestWrite {
+                    lp: "cpu,host=c bar=3 147000000000",
+                    time_seconds: 147,
+                },
+            ],
+        )
+        .await;
+        verify_snapshot_count(1, &persister).await;
+
+        // the tag column stats should be recorded for each file in the snapshot:
+        let snapshot = persister.load_snapshots(1).await.unwrap().remove(0);
+        let db_id = write_buffer.catalog().db_name_to_id("foo").unwrap();
+        let files = snapshot.databases[&db_id].tables.values().next().unwrap();
+        assert_eq!(2, files.len());
+        let mut host_stats = files
+            .iter()
+            .flat_map(|f| f.column_stats.values())
+            .map(|s| (s.min.as_str(), s.max.as_str()))
+            .collect::<Vec<_>>();
+        host_stats.sort();
+        assert_eq!(vec![("a", "a"), ("b", "b")], host_stats);
+
+        let parquet_chunk_count = |filters: &[Expr]| {
+            write_buffer
+                .get_table_chunks("foo", "cpu", filters, None, &ctx.inner().state())
+                .unwrap()
+                .iter()
+                .filter(|c| c.chunk_type() == "Parquet")
+                .count()
+        };
+        let ts = |seconds: i64| {
+            lit(ScalarValue::TimestampNanosecond(
+                Some(seconds * 1_000_000_000),
+                None,
+            ))
+        };
+
+        assert_eq!(2, parquet_chunk_count(&[]));
+        assert_eq!(1, parquet_chunk_count(&[col("time").gt(ts(60))]));
+        assert_eq!(0, parquet_chunk_count(&[col("time").gt(ts(120))]));
+        assert_eq!(1, parquet_chunk_count(&[col("host").eq(lit("a"))]));
+        assert_eq!(
+            0,
+            parquet_chunk_count(&[col("host").eq(lit("a")), col("time").gt(ts(60))])
+        );
+
+        let pruned = metric_registry
+            .get_instrument::<Metric<U64Counter>>("influxdb3_parquet_files_pruned")
+            .unwrap()
+            .get_observer(&Attributes::from(&[]))
+            .unwrap()
+            .fetch();
+        assert_eq!(6, pruned);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn catalog_snapshots_only_if_updated() {
+        let (write_buffer, _ctx) = setup(
+            Time::from_timestamp_nanos(0),
+            Arc::new(InMemory::new()),
+            WalConfig {
+                gen1_duration: Gen1Duration::new_1m(),
+                max_write_buffer_size: 100,
+                flush_interval: Duration::from_millis(5),
+                snapshot_size: 1,
+                compression: WalCompression::default(),
+            },
+        )
+        .await;
 
         let db_name = "foo";
         // do three writes to force a snapshot
@@ -1168,6 +1990,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(5),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1272,6 +2095,7 @@ mod tests {
                     chunk_time: 1,
                     min_time: 0,
                     max_time: 1,
+                    column_stats: Default::default(),
                 },
             );
         }
@@ -1309,6 +2133,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(5),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1331,6 +2156,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1372,6 +2198,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1403,6 +2230,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 2,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1455,6 +2283,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 2,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1489,6 +2318,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1533,6 +2363,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1564,6 +2395,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1613,6 +2445,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1663,6 +2496,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
         )
         .await;
@@ -1685,6 +2519,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
             true,
         )
@@ -1791,6 +2626,7 @@ mod tests {
                 max_write_buffer_size: 100,
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
+                compression: WalCompression::default(),
             },
             false,
         )
@@ -1884,6 +2720,657 @@ mod tests {
         assert_eq!(0, test_store.head_request_count(&path));
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn retention_period_removes_expired_parquet_files() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let wal_config = WalConfig {
+            gen1_duration: Gen1Duration::new_1m(),
+            max_write_buffer_size: 100,
+            flush_interval: Duration::from_millis(10),
+            snapshot_size: 2,
+            compression: WalCompression::default(),
+        };
+        let persister = Arc::new(Persister::new(Arc::clone(&object_store), "test_host"));
+        let catalog = Arc::new(persister.load_or_create_catalog().await.unwrap());
+        let write_buffer = WriteBufferImpl::new(
+            Arc::clone(&persister),
+            Arc::clone(&catalog),
+            LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap(),
+            MetaCacheProvider::new_from_catalog(
+                Arc::clone(&time_provider) as _,
+                Arc::clone(&catalog),
+            )
+            .unwrap(),
+            Arc::clone(&time_provider) as _,
+            crate::test_help::make_exec(),
+            wal_config,
+            None,
+            None,
+            Arc::new(metric::Registry::new()),
+        )
+        .await
+        .unwrap();
+
+        // write into two different gen1 chunks, then trigger a snapshot with a third write so
+        // that the first two are persisted as separate parquet files:
+        do_writes(
+            "foo",
+            &write_buffer,
+            &[
+                TestWrite {
+                    lp: "cpu,host=a bar=1 10000000000",
+                    time_seconds: 10,
+                },
+                TestWrite {
+                    lp: "cpu,host=b bar=2 65000000000",
+                    time_seconds: 65,
+                },
+                TestWrite {
+                    lp: "cpu,host=c bar=3 147000000000",
+                    time_seconds: 147,
+                },
+            ],
+        )
+        .await;
+        verify_snapshot_count(1, &persister).await;
+
+        let db_id = catalog.db_name_to_id("foo").unwrap();
+        let table_id = catalog
+            .db_schema("foo")
+            .unwrap()
+            .table_name_to_id("cpu")
+            .unwrap();
+        let files = write_buffer.persisted_files().get_files(db_id, table_id);
+        assert_eq!(2, files.len());
+        let expired_path = ObjPath::from(files[1].path.clone());
+
+        // without a retention period nothing is removed:
+        write_buffer
+            .remove_expired_and_deleted_files()
+            .await
+            .unwrap();
+        assert_eq!(
+            2,
+            write_buffer
+                .persisted_files()
+                .get_files(db_id, table_id)
+                .len()
+        );
+
+        // at 100s with a 60s retention period, only the file with data at 10s is expired:
+        write_buffer
+            .set_retention_period(
+                NamespaceName::new("foo").unwrap(),
+                Some(Duration::from_secs(60)),
+            )
+            .await
+            .unwrap();
+        time_provider.set(Time::from_timestamp_nanos(100_000_000_000));
+        write_buffer
+            .remove_expired_and_deleted_files()
+            .await
+            .unwrap();
+
+        let files = write_buffer.persisted_files().get_files(db_id, table_id);
+        assert_eq!(1, files.len());
+        assert_eq!(65_000_000_000, files[0].min_time);
+        // the expired file is kept in object store for queries that may still be reading it:
+        object_store.head(&expired_path).await.unwrap();
+
+        // writes that are older than the retention period are rejected:
+        let err = write_buffer
+            .write_lp(
+                NamespaceName::new("foo").unwrap(),
+                "cpu,host=d bar=4 20000000000",
+                Time::from_timestamp_nanos(100_000_000_000),
+                false,
+                Precision::Nanosecond,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ParseError(_)));
+
+        // the expired file is deleted once queries can no longer be reading it:
+        time_provider.set(Time::from_timestamp_nanos(
+            100_000_000_000 + REMOVED_FILE_DELETE_DELAY.as_nanos() as i64,
+        ));
+        write_buffer.delete_removed_files().await;
+        assert!(matches!(
+            object_store.head(&expired_path).await,
+            Err(object_store::Error::NotFound { .. })
+        ));
+
+        // the removal is recorded in a snapshot, so the file is not loaded again on restart:
+        verify_snapshot_count(2, &persister).await;
+        drop(write_buffer);
+        let (write_buffer, _) = setup(
+            Time::from_timestamp_nanos(100_000_000_000),
+            Arc::clone(&object_store),
+            wal_config,
+        )
+        .await;
+        let files = write_buffer.persisted_files().get_files(db_id, table_id);
+        assert_eq!(1, files.len());
+        assert_eq!(
+            Some(Duration::from_secs(60)),
+            write_buffer
+                .catalog()
+                .db_schema("foo")
+                .unwrap()
+                .retention_period
+        );
+    }
+
+    #[tokio::test]
+    async fn deleted_tables_and_databases_are_removed() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let wal_config = WalConfig {
+            gen1_duration: Gen1Duration::new_1m(),
+            max_write_buffer_size: 100,
+            flush_interval: Duration::from_millis(10),
+            snapshot_size: 2,
+            compression: WalCompression::default(),
+        };
+        let (write_buffer, ctx) = setup(
+            Time::from_timestamp_nanos(0),
+            Arc::clone(&object_store),
+            wal_config,
+        )
+        .await;
+
+        // persist a file for each of the cpu and mem tables, and leave some cpu data buffered:
+        do_writes(
+            "foo",
+            &write_buffer,
+            &[
+                TestWrite {
+                    lp: "cpu,host=a bar=1 10000000000",
+                    time_seconds: 10,
+                },
+                TestWrite {
+                    lp: "mem,host=b bar=2 65000000000",
+                    time_seconds: 65,
+                },
+                TestWrite {
+                    lp: "cpu,host=c bar=3 147000000000",
+                    time_seconds: 147,
+                },
+            ],
+        )
+        .await;
+        verify_snapshot_count(1, &write_buffer.persister).await;
+        let catalog = write_buffer.catalog();
+        let db_id = catalog.db_name_to_id("foo").unwrap();
+        let db_schema = catalog.db_schema("foo").unwrap();
+        let cpu_id = db_schema.table_name_to_id("cpu").unwrap();
+        let mem_id = db_schema.table_name_to_id("mem").unwrap();
+        assert_eq!(
+            1,
+            write_buffer
+                .persisted_files()
+                .get_files(db_id, cpu_id)
+                .len()
+        );
+
+        write_buffer.delete_table("foo", "cpu").await.unwrap();
+        assert!(matches!(
+            write_buffer.delete_table("foo", "cpu").await,
+            Err(Error::TableDoesNotExist)
+        ));
+        assert!(write_buffer
+            .get_table_chunks("foo", "cpu", &[], None, &ctx.inner().state())
+            .is_err());
+
+        // a new table can be created with the same name, which has none of the old data:
+        do_writes(
+            "foo",
+            &write_buffer,
+            &[TestWrite {
+                lp: "cpu,host=d bar=4 150000000000",
+                time_seconds: 150,
+            }],
+        )
+        .await;
+        let expected = [
+            "+-----+------+----------------------+",
+            "| bar | host | time                 |",
+            "+-----+------+----------------------+",
+            "| 4.0 | d    | 1970-01-01T00:02:30Z |",
+            "+-----+------+----------------------+",
+        ];
+        let actual = get_table_batches(&write_buffer, "foo", "cpu", &ctx).await;
+        assert_batches_sorted_eq!(&expected, &actual);
+        assert_ne!(
+            cpu_id,
+            catalog
+                .db_schema("foo")
+                .unwrap()
+                .table_name_to_id("cpu")
+                .unwrap()
+        );
+
+        // the deleted table's files are removed, but not those of other tables:
+        write_buffer
+            .remove_expired_and_deleted_files()
+            .await
+            .unwrap();
+        assert!(write_buffer
+            .persisted_files()
+            .get_files(db_id, cpu_id)
+            .is_empty());
+        assert_eq!(
+            1,
+            write_buffer
+                .persisted_files()
+                .get_files(db_id, mem_id)
+                .len()
+        );
+
+        // deleting the database removes the rest of its files:
+        write_buffer.delete_database("foo").await.unwrap();
+        assert!(catalog.db_schema("foo").is_none());
+        assert!(matches!(
+            write_buffer.delete_database("foo").await,
+            Err(Error::DbDoesNotExist)
+        ));
+        write_buffer
+            .remove_expired_and_deleted_files()
+            .await
+            .unwrap();
+        assert!(write_buffer
+            .persisted_files()
+            .get_files(db_id, mem_id)
+            .is_empty());
+        // the removed files are only deleted from object store after a delay, for queries that
+        // may still be reading them:
+        assert_eq!(2, write_buffer.removed_files.lock().len());
+    }
+
+    #[tokio::test]
+    async fn databases_and_tables_are_created_with_declared_schemas() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let wal_config = WalConfig {
+            gen1_duration: Gen1Duration::new_1m(),
+            max_write_buffer_size: 100,
+            flush_interval: Duration::from_millis(10),
+            snapshot_size: 100,
+            compression: WalCompression::default(),
+        };
+        let (write_buffer, ctx) = setup(
+            Time::from_timestamp_nanos(0),
+            Arc::clone(&object_store),
+            wal_config,
+        )
+        .await;
+
+        let db_name = NamespaceName::new("foo").unwrap();
+        write_buffer
+            .create_database(db_name.clone(), None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            write_buffer.create_database(db_name, None).await,
+            Err(Error::DbAlreadyExists(_))
+        ));
+        // a database can be created with a retention period:
+        write_buffer
+            .create_database(
+                NamespaceName::new("baz").unwrap(),
+                Some(Duration::from_secs(3600)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(Duration::from_secs(3600)),
+            write_buffer
+                .catalog()
+                .db_schema("baz")
+                .unwrap()
+                .retention_period
+        );
+        assert!(matches!(
+            write_buffer
+                .create_table("bar", "cpu", vec![], vec![])
+                .await,
+            Err(Error::DbDoesNotExist)
+        ));
+        assert!(matches!(
+            write_buffer
+                .create_table(
+                    "foo",
+                    "cpu",
+                    vec!["time".to_string()],
+                    vec![("usage".to_string(), InfluxFieldType::Float)],
+                )
+                .await,
+            Err(Error::InvalidTableDefinition(_))
+        ));
+        write_buffer
+            .create_table(
+                "foo",
+                "cpu",
+                vec!["region".to_string(), "host".to_string()],
+                vec![
+                    ("usage".to_string(), InfluxFieldType::Float),
+                    ("up".to_string(), InfluxFieldType::Boolean),
+                ],
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            write_buffer
+                .create_table("foo", "cpu", vec![], vec![])
+                .await,
+            Err(Error::TableAlreadyExists { .. })
+        ));
+
+        let table_def = write_buffer
+            .catalog()
+            .db_schema("foo")
+            .unwrap()
+            .table_definition("cpu")
+            .unwrap();
+        // the table has no series key, like a table created by a write of line protocol:
+        assert!(!table_def.is_v3());
+        assert_eq!(
+            Some(InfluxColumnType::Tag),
+            table_def.field_type_by_name("region")
+        );
+        assert_eq!(
+            Some(InfluxColumnType::Field(InfluxFieldType::Boolean)),
+            table_def.field_type_by_name("up")
+        );
+
+        // so line protocol can be written to it:
+        write_buffer
+            .write_lp(
+                NamespaceName::new("foo").unwrap(),
+                "cpu,region=us,host=a usage=0.5,up=true 10",
+                Time::from_timestamp_nanos(0),
+                false,
+                Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let expected = [
+            "+------+--------+--------------------------------+------+-------+",
+            "| host | region | time                           | up   | usage |",
+            "+------+--------+--------------------------------+------+-------+",
+            "| a    | us     | 1970-01-01T00:00:00.000000010Z | true | 0.5   |",
+            "+------+--------+--------------------------------+------+-------+",
+        ];
+        let actual = get_table_batches(&write_buffer, "foo", "cpu", &ctx).await;
+        assert_batches_sorted_eq!(&expected, &actual);
+
+        // the new database and table are replayed from the WAL on restart:
+        drop(write_buffer);
+        let (write_buffer, _) = setup(
+            Time::from_timestamp_nanos(0),
+            Arc::clone(&object_store),
+            wal_config,
+        )
+        .await;
+        let db_schema = write_buffer.catalog().db_schema("foo").unwrap();
+        assert_eq!(table_def, db_schema.table_definition("cpu").unwrap());
+    }
+
+    #[tokio::test]
+    async fn tokens_are_restored_from_the_wal() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let wal_config = WalConfig {
+            gen1_duration: Gen1Duration::new_1m(),
+            max_write_buffer_size: 100,
+            flush_interval: Duration::from_millis(10),
+            snapshot_size: 100,
+            compression: WalCompression::default(),
+        };
+        let (write_buffer, _) = setup(
+            Time::from_timestamp_nanos(0),
+            Arc::clone(&object_store),
+            wal_config,
+        )
+        .await;
+        let token = |name: &str, hash: &str| TokenDefinition {
+            name: name.into(),
+            hash: hash.into(),
+            permissions: vec![TokenPermission {
+                database: "foo".into(),
+                actions: vec![TokenAction::Read],
+            }],
+            description: None,
+            created_at_ns: 0,
+            expires_at_ns: None,
+        };
+        write_buffer.create_token(token("a", "aaa")).await.unwrap();
+        write_buffer.create_token(token("b", "bbb")).await.unwrap();
+        write_buffer.revoke_token("a").await.unwrap();
+        assert!(matches!(
+            write_buffer.revoke_token("a").await,
+            Err(Error::CatalogUpdateError(
+                influxdb3_catalog::catalog::Error::TokenNotFound(_)
+            ))
+        ));
+
+        // the token operations are replayed from the WAL on restart, without a snapshot:
+        drop(write_buffer);
+        let (write_buffer, _) =
+            setup(Time::from_timestamp_nanos(0), object_store, wal_config).await;
+        let tokens = write_buffer.catalog().list_tokens();
+        assert_eq!(1, tokens.len());
+        assert_eq!("b", tokens[0].name.as_ref());
+    }
+
+    #[tokio::test]
+    async fn gen1_files_are_compacted_into_generations() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let wal_config = WalConfig {
+            gen1_duration: Gen1Duration::new_1m(),
+            max_write_buffer_size: 100,
+            flush_interval: Duration::from_millis(10),
+            snapshot_size: 2,
+            compression: WalCompression::default(),
+        };
+        // the clock is at the end of the first hour, so that its generation can be compacted:
+        let (write_buffer, ctx) = setup(
+            Time::from_timestamp_nanos(3_600_000_000_000),
+            Arc::clone(&object_store),
+            wal_config,
+        )
+        .await;
+        do_writes(
+            "foo",
+            &write_buffer,
+            &[
+                TestWrite {
+                    lp: "cpu,host=a bar=1 10000000000",
+                    time_seconds: 10,
+                },
+                // the second gen1 file has a column that the first one does not:
+                TestWrite {
+                    lp: "cpu,host=b bar=2,baz=true 65000000000",
+                    time_seconds: 65,
+                },
+                // This write will trigger the snapshot:
+                TestWrite {
+                    lp: "cpu,host=c bar=3 147000000000",
+                    time_seconds: 147,
+                },
+            ],
+        )
+        .await;
+        verify_snapshot_count(1, &write_buffer.persister).await;
+
+        let catalog = write_buffer.catalog();
+        let db_id = catalog.db_name_to_id("foo").unwrap();
+        let table_id = catalog
+            .db_schema("foo")
+            .unwrap()
+            .table_name_to_id("cpu")
+            .unwrap();
+        let gen1_files = write_buffer.persisted_files().get_files(db_id, table_id);
+        assert_eq!(2, gen1_files.len());
+
+        write_buffer
+            .compact_gen1_files(Duration::from_secs(3600))
+            .await
+            .unwrap();
+        let files = write_buffer.persisted_files().get_files(db_id, table_id);
+        assert_eq!(1, files.len());
+        assert_eq!(0, files[0].chunk_time);
+        assert_eq!(2, files[0].row_count);
+        assert_eq!(10_000_000_000, files[0].min_time);
+        assert_eq!(65_000_000_000, files[0].max_time);
+        assert!(files[0].path.contains("compacted-"));
+
+        let expected = [
+            "+------+-----+----------------------+------+",
+            "| host | bar | time                 | baz  |",
+            "+------+-----+----------------------+------+",
+            "| a    | 1.0 | 1970-01-01T00:00:10Z |      |",
+            "| b    | 2.0 | 1970-01-01T00:01:05Z | true |",
+            "| c    | 3.0 | 1970-01-01T00:02:27Z |      |",
+            "+------+-----+----------------------+------+",
+        ];
+        let actual = get_table_batches(&write_buffer, "foo", "cpu", &ctx).await;
+        assert_batches_sorted_eq!(&expected, &actual);
+
+        // the replacement is recorded in a snapshot, so the gen1 files are not loaded on restart,
+        // while they are kept in object store for queries that may still be reading them:
+        verify_snapshot_count(2, &write_buffer.persister).await;
+        for file in &gen1_files {
+            object_store
+                .head(&ObjPath::from(file.path.as_str()))
+                .await
+                .unwrap();
+        }
+        drop(write_buffer);
+        let (write_buffer, _) = setup(
+            Time::from_timestamp_nanos(3_600_000_000_000),
+            Arc::clone(&object_store),
+            wal_config,
+        )
+        .await;
+        let files = write_buffer.persisted_files().get_files(db_id, table_id);
+        assert_eq!(1, files.len());
+        assert!(files[0].path.contains("compacted-"));
+        // the gen1 files are queued to be deleted again after the restart, at the time they were
+        // removed:
+        let mut queued: Vec<(i64, String)> = write_buffer
+            .removed_files
+            .lock()
+            .iter()
+            .map(|(removed_at_ns, f)| (*removed_at_ns, f.path.clone()))
+            .collect();
+        queued.sort();
+        let mut expected: Vec<(i64, String)> = gen1_files
+            .iter()
+            .map(|f| (3_600_000_000_000, f.path.clone()))
+            .collect();
+        expected.sort();
+        assert_eq!(expected, queued);
+
+        // files that were removed longer ago than the delete delay are not queued again:
+        drop(write_buffer);
+        let (write_buffer, _) = setup(
+            Time::from_timestamp_nanos(
+                3_600_000_000_000 + REMOVED_FILE_DELETE_DELAY.as_nanos() as i64,
+            ),
+            object_store,
+            wal_config,
+        )
+        .await;
+        assert!(write_buffer.removed_files.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn compaction_of_files_expired_while_being_compacted_is_dropped() {
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let wal_config = WalConfig {
+            gen1_duration: Gen1Duration::new_1m(),
+            max_write_buffer_size: 100,
+            flush_interval: Duration::from_millis(10),
+            snapshot_size: 2,
+            compression: WalCompression::default(),
+        };
+        let (write_buffer, _) = setup(
+            Time::from_timestamp_nanos(3_600_000_000_000),
+            Arc::clone(&object_store),
+            wal_config,
+        )
+        .await;
+        do_writes(
+            "foo",
+            &write_buffer,
+            &[
+                TestWrite {
+                    lp: "cpu,host=a bar=1 10000000000",
+                    time_seconds: 10,
+                },
+                TestWrite {
+                    lp: "cpu,host=b bar=2 65000000000",
+                    time_seconds: 65,
+                },
+                // This write will trigger the snapshot:
+                TestWrite {
+                    lp: "cpu,host=c bar=3 147000000000",
+                    time_seconds: 147,
+                },
+            ],
+        )
+        .await;
+        verify_snapshot_count(1, &write_buffer.persister).await;
+
+        let catalog = write_buffer.catalog();
+        let db_id = catalog.db_name_to_id("foo").unwrap();
+        let table_id = catalog
+            .db_schema("foo")
+            .unwrap()
+            .table_name_to_id("cpu")
+            .unwrap();
+        let compactions = write_buffer
+            .compact_generations(Duration::from_secs(3600))
+            .await
+            .unwrap();
+        assert_eq!(1, compactions.len());
+        let compacted_path = ObjPath::from(compactions[0].compacted.path.as_str());
+        object_store.head(&compacted_path).await.unwrap();
+
+        // the gen1 files expire after they were compacted, but before they are replaced:
+        write_buffer
+            .set_retention_period(
+                NamespaceName::new("foo").unwrap(),
+                Some(Duration::from_secs(1800)),
+            )
+            .await
+            .unwrap();
+        write_buffer
+            .remove_expired_and_deleted_files()
+            .await
+            .unwrap();
+        assert!(write_buffer
+            .persisted_files()
+            .get_files(db_id, table_id)
+            .is_empty());
+
+        write_buffer
+            .replace_compacted_files(compactions)
+            .await
+            .unwrap();
+
+        // the compacted file does not bring back the expired data, and is deleted:
+        assert!(write_buffer
+            .persisted_files()
+            .get_files(db_id, table_id)
+            .is_empty());
+        assert!(matches!(
+            object_store.head(&compacted_path).await,
+            Err(object_store::Error::NotFound { .. })
+        ));
+        // the expired files are only queued for deletion once, by retention:
+        assert_eq!(2, write_buffer.removed_files.lock().len());
+    }
+
     struct TestWrite<LP> {
         lp: LP,
         time_seconds: i64,
@@ -1955,6 +3442,143 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn record_batches_are_imported() {
+        use arrow::array::{
+            ArrayRef, DictionaryArray, Int64Array, StringArray, TimestampNanosecondArray,
+        };
+        use arrow::datatypes::Int32Type;
+
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let (write_buffer, ctx) = setup(
+            Time::from_timestamp_nanos(0),
+            Arc::clone(&object_store),
+            WalConfig {
+                gen1_duration: Gen1Duration::new_1m(),
+                max_write_buffer_size: 100,
+                flush_interval: Duration::from_millis(10),
+                snapshot_size: 100,
+                compression: WalCompression::default(),
+            },
+        )
+        .await;
+        write_buffer
+            .write_lp(
+                NamespaceName::new("foo").unwrap(),
+                "cpu,host=a usage=0.5 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        // host and usage are imported as the tag and float in the catalog, region and count
+        // are added to the table:
+        let batch = RecordBatch::try_from_iter([
+            (
+                "host",
+                Arc::new(StringArray::from(vec!["b", "c", "d", "e"])) as ArrayRef,
+            ),
+            (
+                "region",
+                Arc::new(
+                    vec![Some("us"), None, Some("eu"), Some("eu")]
+                        .into_iter()
+                        .collect::<DictionaryArray<Int32Type>>(),
+                ),
+            ),
+            (
+                "usage",
+                Arc::new(Int64Array::from(vec![Some(1), None, Some(3), None])),
+            ),
+            (
+                "count",
+                Arc::new(Int64Array::from(vec![Some(10), Some(20), Some(30), None])),
+            ),
+            (
+                "time",
+                Arc::new(TimestampNanosecondArray::from(vec![
+                    Some(2),
+                    None,
+                    Some(3),
+                    Some(4),
+                ])),
+            ),
+        ])
+        .unwrap();
+        // the row without a time, and the row without any fields, are skipped:
+        let summary = write_buffer
+            .import_record_batch(
+                NamespaceName::new("foo").unwrap(),
+                "cpu",
+                batch,
+                Time::from_timestamp_nanos(0),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            ImportSummary {
+                row_count: 2,
+                skipped_row_count: 2
+            },
+            summary
+        );
+
+        let expected = [
+            "+-------+------+--------+--------------------------------+-------+",
+            "| count | host | region | time                           | usage |",
+            "+-------+------+--------+--------------------------------+-------+",
+            "|       | a    |        | 1970-01-01T00:00:00.000000001Z | 0.5   |",
+            "| 10    | b    | us     | 1970-01-01T00:00:00.000000002Z | 1.0   |",
+            "| 30    | d    | eu     | 1970-01-01T00:00:00.000000003Z | 3.0   |",
+            "+-------+------+--------+--------------------------------+-------+",
+        ];
+        let actual = get_table_batches(&write_buffer, "foo", "cpu", &ctx).await;
+        assert_batches_sorted_eq!(expected, &actual);
+
+        // the import was written to the wal, along with the catalog updates, so it is
+        // replayed on restart:
+        drop(write_buffer);
+        let (write_buffer, ctx) = setup(
+            Time::from_timestamp_nanos(0),
+            Arc::clone(&object_store),
+            WalConfig {
+                gen1_duration: Gen1Duration::new_1m(),
+                max_write_buffer_size: 100,
+                flush_interval: Duration::from_millis(10),
+                snapshot_size: 100,
+                compression: WalCompression::default(),
+            },
+        )
+        .await;
+        let actual = get_table_batches(&write_buffer, "foo", "cpu", &ctx).await;
+        assert_batches_sorted_eq!(expected, &actual);
+
+        // a column that cannot be cast to its type in the catalog fails the import:
+        let batch = RecordBatch::try_from_iter([
+            (
+                "usage",
+                Arc::new(StringArray::from(vec!["high"])) as ArrayRef,
+            ),
+            ("time", Arc::new(TimestampNanosecondArray::from(vec![5]))),
+        ])
+        .unwrap();
+        let err = write_buffer
+            .import_record_batch(
+                NamespaceName::new("foo").unwrap(),
+                "cpu",
+                batch,
+                Time::from_timestamp_nanos(0),
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::ImportError(import::Error::Cast { .. })),
+            "unexpected error: {err}"
+        );
+    }
+
     fn catalog_to_json(catalog: &Catalog) -> serde_json::Value {
         let bytes = serde_json::to_vec_pretty(catalog).unwrap();
         serde_json::from_slice::<serde_json::Value>(&bytes).expect("parse bytes as JSON")
@@ -1985,14 +3609,28 @@ mod tests {
         let persister = Arc::new(Persister::new(Arc::clone(&object_store), "test_host"));
         let catalog = Arc::new(persister.load_or_create_catalog().await.unwrap());
         let last_cache = LastCacheProvider::new_from_catalog(Arc::clone(&catalog) as _).unwrap();
+        let meta_cache =
+            MetaCacheProvider::new_from_catalog(Arc::clone(&time_provider), Arc::clone(&catalog))
+                .unwrap();
+        // the object store is registered with the executor as it is by the server, so that the
+        // compactor can read persisted files:
+        let exec = crate::test_help::make_exec();
+        register_iox_object_store(
+            exec.new_context().inner().runtime_env(),
+            "influxdb3",
+            Arc::clone(&object_store),
+        );
         let wbuf = WriteBufferImpl::new(
             Arc::clone(&persister),
             catalog,
             last_cache,
+            meta_cache,
             Arc::clone(&time_provider),
-            crate::test_help::make_exec(),
+            exec,
             wal_config,
+            None,
             parquet_cache,
+            Arc::new(metric::Registry::new()),
         )
         .await
         .unwrap();
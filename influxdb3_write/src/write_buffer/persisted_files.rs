@@ -3,8 +3,9 @@
 //! the persisted files to get the full set of data to query.
 
 use crate::{ParquetFile, PersistedSnapshot};
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use influxdb3_id::DbId;
+use influxdb3_id::ParquetFileId;
 use influxdb3_id::TableId;
 use influxdb3_telemetry::ParquetMetrics;
 use parking_lot::RwLock;
@@ -56,6 +57,88 @@ impl PersistedFiles {
 
         files
     }
+
+    /// Remove all files for the given database whose data is entirely older than `cutoff_ns`,
+    /// returning the removed files by table
+    pub fn remove_files_older_than(&self, db_id: DbId, cutoff_ns: i64) -> TableToFiles {
+        self.remove_files_where(db_id, |_, f| f.max_time < cutoff_ns)
+    }
+
+    /// Remove all files for the given database that match the `predicate`, returning the
+    /// removed files by table
+    pub fn remove_files_where(
+        &self,
+        db_id: DbId,
+        predicate: impl Fn(&TableId, &ParquetFile) -> bool,
+    ) -> TableToFiles {
+        let mut inner = self.inner.write();
+        let Some(tables) = inner.files.get_mut(&db_id) else {
+            return TableToFiles::new();
+        };
+        let mut removed = TableToFiles::new();
+        for (table_id, files) in tables.iter_mut() {
+            let (matched, retained): (Vec<_>, Vec<_>) =
+                files.drain(..).partition(|f| predicate(table_id, f));
+            *files = retained;
+            if !matched.is_empty() {
+                removed.insert(*table_id, matched);
+            }
+        }
+        for file in removed.values().flatten() {
+            inner.parquet_files_count = inner.parquet_files_count.saturating_sub(1);
+            inner.parquet_files_row_count =
+                inner.parquet_files_row_count.saturating_sub(file.row_count);
+            inner.parquet_files_size_mb =
+                (inner.parquet_files_size_mb - as_mb(file.size_bytes)).max(0.0);
+        }
+        removed
+    }
+
+    /// Replace the files of a table that have the given ids with `new_file`, which holds their
+    /// compacted data. The swap is made under a single lock, so queries either see the original
+    /// files or the new one, never both or neither.
+    ///
+    /// If any of the files has been removed in the meantime, e.g., by retention, nothing is
+    /// replaced and `false` is returned, as `new_file` would bring back the removed data.
+    pub fn replace_files(
+        &self,
+        db_id: DbId,
+        table_id: TableId,
+        replaced_ids: &HashSet<ParquetFileId>,
+        new_file: ParquetFile,
+    ) -> bool {
+        let mut inner = self.inner.write();
+        let Some(files) = inner
+            .files
+            .get_mut(&db_id)
+            .and_then(|tables| tables.get_mut(&table_id))
+        else {
+            return false;
+        };
+        let live_count = files
+            .iter()
+            .filter(|f| replaced_ids.contains(&f.id))
+            .count();
+        if live_count != replaced_ids.len() {
+            return false;
+        }
+        let (replaced, retained): (Vec<_>, Vec<_>) =
+            files.drain(..).partition(|f| replaced_ids.contains(&f.id));
+        *files = retained;
+        files.push(new_file.clone());
+
+        for file in &replaced {
+            inner.parquet_files_count = inner.parquet_files_count.saturating_sub(1);
+            inner.parquet_files_row_count =
+                inner.parquet_files_row_count.saturating_sub(file.row_count);
+            inner.parquet_files_size_mb =
+                (inner.parquet_files_size_mb - as_mb(file.size_bytes)).max(0.0);
+        }
+        inner.parquet_files_count += 1;
+        inner.parquet_files_row_count += new_file.row_count;
+        inner.parquet_files_size_mb += as_mb(new_file.size_bytes);
+        true
+    }
 }
 
 impl ParquetMetrics for PersistedFiles {
@@ -83,16 +166,30 @@ struct Inner {
 }
 
 impl Inner {
+    /// Build from the given snapshots, which are expected to be ordered from newest to oldest,
+    /// so that files removed by a newer snapshot are not loaded from an older one.
     pub fn new_from_persisted_snapshots(persisted_snapshots: Vec<PersistedSnapshot>) -> Self {
         let mut file_count = 0;
         let mut size_in_mb = 0.0;
         let mut row_count = 0;
+        let mut removed_file_ids = HashSet::new();
 
         let files = persisted_snapshots.into_iter().fold(
             hashbrown::HashMap::new(),
-            |mut files, persisted_snapshot| {
+            |mut files, mut persisted_snapshot| {
                 size_in_mb += as_mb(persisted_snapshot.parquet_size_bytes);
                 row_count += persisted_snapshot.row_count;
+                for file in remove_files(&mut persisted_snapshot, &removed_file_ids) {
+                    size_in_mb -= as_mb(file.size_bytes);
+                    row_count = row_count.saturating_sub(file.row_count);
+                }
+                removed_file_ids.extend(
+                    persisted_snapshot
+                        .removed_files
+                        .values()
+                        .flat_map(|db| db.tables.values().flatten())
+                        .map(|f| f.id),
+                );
                 let parquet_files_added =
                     update_persisted_files_with_snapshot(true, persisted_snapshot, &mut files);
                 file_count += parquet_files_added;
@@ -122,6 +219,26 @@ fn as_mb(bytes: u64) -> f64 {
     bytes as f64 / factor
 }
 
+/// Remove the files with the given ids from the snapshot, returning the removed files
+fn remove_files(
+    persisted_snapshot: &mut PersistedSnapshot,
+    file_ids: &HashSet<ParquetFileId>,
+) -> Vec<ParquetFile> {
+    if file_ids.is_empty() {
+        return vec![];
+    }
+    let mut removed = vec![];
+    for (_, tables) in persisted_snapshot.databases.iter_mut() {
+        for (_, files) in tables.tables.iter_mut() {
+            let (r, kept): (Vec<_>, Vec<_>) =
+                files.drain(..).partition(|f| file_ids.contains(&f.id));
+            *files = kept;
+            removed.extend(r);
+        }
+    }
+    removed
+}
+
 fn update_persisted_files_with_snapshot(
     initial_load: bool,
     persisted_snapshot: PersistedSnapshot,
@@ -238,6 +355,106 @@ mod tests {
         assert_eq!(150, row_count);
     }
 
+    #[test_log::test(test)]
+    fn test_remove_files_older_than() {
+        let all_persisted_snapshot_files = build_persisted_snapshots();
+        let persisted_file =
+            PersistedFiles::new_from_persisted_snapshots(all_persisted_snapshot_files);
+
+        // nothing is removed if the cutoff is not after the max time of any file:
+        let removed = persisted_file.remove_files_older_than(DbId::from(0), 200);
+        assert!(removed.is_empty());
+
+        let removed = persisted_file.remove_files_older_than(DbId::from(0), 201);
+        assert_eq!(10, removed[&TableId::from(0)].len());
+        assert!(persisted_file
+            .get_files(DbId::from(0), TableId::from(0))
+            .is_empty());
+        let (file_count, _, row_count) = persisted_file.get_metrics();
+        assert_eq!((0, 0), (file_count, row_count));
+    }
+
+    #[test_log::test(test)]
+    fn test_removed_files_are_not_loaded_from_older_snapshots() {
+        let mut all_persisted_snapshot_files = build_persisted_snapshots();
+        let removed_file = all_persisted_snapshot_files[1].databases[&DbId::from(0)].tables
+            [&TableId::from(0)][0]
+            .clone();
+        let mut removal_snapshot = build_snapshot(vec![], 3, 2, 2);
+        removal_snapshot
+            .removed_files
+            .entry(DbId::from(0))
+            .or_default()
+            .tables
+            .insert(TableId::from(0), vec![removed_file.clone()]);
+        // snapshots are loaded newest first:
+        all_persisted_snapshot_files.reverse();
+        all_persisted_snapshot_files.insert(0, removal_snapshot);
+
+        let persisted_file =
+            PersistedFiles::new_from_persisted_snapshots(all_persisted_snapshot_files);
+        let files = persisted_file.get_files(DbId::from(0), TableId::from(0));
+        assert_eq!(9, files.len());
+        assert!(!files.contains(&removed_file));
+        let (file_count, _, row_count) = persisted_file.get_metrics();
+        assert_eq!((9, 90), (file_count, row_count));
+    }
+
+    #[test_log::test(test)]
+    fn test_replace_files() {
+        let all_persisted_snapshot_files = build_persisted_snapshots();
+        let persisted_file =
+            PersistedFiles::new_from_persisted_snapshots(all_persisted_snapshot_files);
+        let files = persisted_file.get_files(DbId::from(0), TableId::from(0));
+        let replaced_ids: HashSet<ParquetFileId> = files.iter().take(4).map(|f| f.id).collect();
+        let compacted_file = ParquetFile {
+            row_count: 40,
+            size_bytes: 100_000,
+            ..build_parquet_files(1).remove(0)
+        };
+
+        assert!(persisted_file.replace_files(
+            DbId::from(0),
+            TableId::from(0),
+            &replaced_ids,
+            compacted_file.clone(),
+        ));
+
+        let files = persisted_file.get_files(DbId::from(0), TableId::from(0));
+        assert_eq!(7, files.len());
+        assert!(files.contains(&compacted_file));
+        assert!(!files.iter().any(|f| replaced_ids.contains(&f.id)));
+        let (file_count, size_in_mb, row_count) = persisted_file.get_metrics();
+        assert_eq!((7, 100), (file_count, row_count));
+        assert!((size_in_mb - 0.4).abs() < 1e-9);
+    }
+
+    #[test_log::test(test)]
+    fn test_replace_files_that_were_removed() {
+        let all_persisted_snapshot_files = build_persisted_snapshots();
+        let persisted_file =
+            PersistedFiles::new_from_persisted_snapshots(all_persisted_snapshot_files);
+        let files = persisted_file.get_files(DbId::from(0), TableId::from(0));
+        let replaced_ids: HashSet<ParquetFileId> = files.iter().take(4).map(|f| f.id).collect();
+        let removed_id = files[0].id;
+        persisted_file.remove_files_where(DbId::from(0), |_, f| f.id == removed_id);
+        let compacted_file = build_parquet_files(1).remove(0);
+
+        assert!(!persisted_file.replace_files(
+            DbId::from(0),
+            TableId::from(0),
+            &replaced_ids,
+            compacted_file.clone(),
+        ));
+
+        // the files that were not removed are kept, and the compacted file is not added:
+        let files = persisted_file.get_files(DbId::from(0), TableId::from(0));
+        assert_eq!(9, files.len());
+        assert!(!files.contains(&compacted_file));
+        let (file_count, _, row_count) = persisted_file.get_metrics();
+        assert_eq!((9, 90), (file_count, row_count));
+    }
+
     fn build_persisted_snapshots() -> Vec<PersistedSnapshot> {
         let mut all_persisted_snapshot_files = Vec::new();
         let parquet_files_1 = build_parquet_files(5);
@@ -279,6 +496,7 @@ mod tests {
                 chunk_time: 10,
                 min_time: 10,
                 max_time: 200,
+                column_stats: Default::default(),
             })
             .collect();
         parquet_files
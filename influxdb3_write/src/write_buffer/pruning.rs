@@ -0,0 +1,310 @@
+//! Pruning of persisted parquet files at query time, using the time range and tag column
+//! statistics that are recorded for each file in the snapshot.
+
+use std::collections::HashMap;
+
+use datafusion::logical_expr::{expr::InList, utils::split_conjunction, Between, BinaryExpr};
+use datafusion::logical_expr::{Expr, Operator};
+use datafusion::scalar::ScalarValue;
+use influxdb3_catalog::catalog::TableDefinition;
+use influxdb3_id::ColumnId;
+use schema::TIME_COLUMN_NAME;
+
+use crate::ParquetFile;
+
+/// Decides which persisted [`ParquetFile`]s can be skipped for a query, given the filters that
+/// DataFusion pushed down to the table scan.
+///
+/// Filters that cannot be used for pruning are ignored, so a file is only pruned if it is
+/// guaranteed not to contain any rows that match the filters.
+#[derive(Debug, Default)]
+pub(crate) struct ParquetFilePruner {
+    /// Inclusive lower bound on the `time` column
+    min_time: Option<i64>,
+    /// Inclusive upper bound on the `time` column
+    max_time: Option<i64>,
+    /// The set of values that a tag column must equal one of
+    tag_values: HashMap<ColumnId, Vec<String>>,
+}
+
+impl ParquetFilePruner {
+    pub(crate) fn new(table_def: &TableDefinition, filters: &[Expr]) -> Self {
+        let mut pruner = Self::default();
+        for expr in filters.iter().flat_map(split_conjunction) {
+            match expr {
+                Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                    if let (Expr::Column(c), Expr::Literal(v)) = (left.as_ref(), right.as_ref()) {
+                        pruner.add_comparison(table_def, c.name(), *op, v);
+                    } else if let (Expr::Literal(v), Expr::Column(c)) =
+                        (left.as_ref(), right.as_ref())
+                    {
+                        if let Some(op) = op.swap() {
+                            pruner.add_comparison(table_def, c.name(), op, v);
+                        }
+                    }
+                }
+                Expr::Between(Between {
+                    expr,
+                    negated: false,
+                    low,
+                    high,
+                }) => {
+                    if let (Expr::Column(c), Expr::Literal(low), Expr::Literal(high)) =
+                        (expr.as_ref(), low.as_ref(), high.as_ref())
+                    {
+                        pruner.add_comparison(table_def, c.name(), Operator::GtEq, low);
+                        pruner.add_comparison(table_def, c.name(), Operator::LtEq, high);
+                    }
+                }
+                Expr::InList(InList {
+                    expr,
+                    list,
+                    negated: false,
+                }) => {
+                    let Expr::Column(c) = expr.as_ref() else {
+                        continue;
+                    };
+                    let Some(values) = list
+                        .iter()
+                        .map(|e| match e {
+                            Expr::Literal(v) => string_value(v),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>()
+                    else {
+                        continue;
+                    };
+                    pruner.add_tag_values(table_def, c.name(), values);
+                }
+                _ => (),
+            }
+        }
+        pruner
+    }
+
+    fn add_comparison(
+        &mut self,
+        table_def: &TableDefinition,
+        column_name: &str,
+        op: Operator,
+        value: &ScalarValue,
+    ) {
+        if column_name == TIME_COLUMN_NAME {
+            let Some(t) = timestamp_nanos(value) else {
+                return;
+            };
+            match op {
+                Operator::Eq => {
+                    self.set_min_time(t);
+                    self.set_max_time(t);
+                }
+                Operator::Gt => self.set_min_time(t.saturating_add(1)),
+                Operator::GtEq => self.set_min_time(t),
+                Operator::Lt => self.set_max_time(t.saturating_sub(1)),
+                Operator::LtEq => self.set_max_time(t),
+                _ => (),
+            }
+        } else if op == Operator::Eq {
+            if let Some(v) = string_value(value) {
+                self.add_tag_values(table_def, column_name, vec![v]);
+            }
+        }
+    }
+
+    fn set_min_time(&mut self, t: i64) {
+        self.min_time = Some(self.min_time.map_or(t, |min| min.max(t)));
+    }
+
+    fn set_max_time(&mut self, t: i64) {
+        self.max_time = Some(self.max_time.map_or(t, |max| max.min(t)));
+    }
+
+    fn add_tag_values(
+        &mut self,
+        table_def: &TableDefinition,
+        column_name: &str,
+        values: Vec<String>,
+    ) {
+        let Some(column_id) = table_def.column_name_to_id(column_name) else {
+            return;
+        };
+        // multiple constraints on the same column are combined with AND, so only values that
+        // satisfy all of them are kept:
+        self.tag_values
+            .entry(column_id)
+            .and_modify(|existing| existing.retain(|v| values.contains(v)))
+            .or_insert(values);
+    }
+
+    /// Returns `true` if the given file cannot contain rows that satisfy the filters
+    pub(crate) fn should_prune(&self, file: &ParquetFile) -> bool {
+        if self.min_time.is_some_and(|min| file.max_time < min)
+            || self.max_time.is_some_and(|max| file.min_time > max)
+        {
+            return true;
+        }
+        self.tag_values.iter().any(|(column_id, values)| {
+            file.column_stats
+                .get(column_id)
+                .is_some_and(|stats| !values.iter().any(|v| stats.may_contain(v)))
+        })
+    }
+}
+
+fn timestamp_nanos(value: &ScalarValue) -> Option<i64> {
+    match value {
+        ScalarValue::TimestampNanosecond(Some(t), _) => Some(*t),
+        ScalarValue::TimestampMicrosecond(Some(t), _) => Some(t.saturating_mul(1_000)),
+        ScalarValue::TimestampMillisecond(Some(t), _) => Some(t.saturating_mul(1_000_000)),
+        ScalarValue::TimestampSecond(Some(t), _) => Some(t.saturating_mul(1_000_000_000)),
+        _ => None,
+    }
+}
+
+fn string_value(value: &ScalarValue) -> Option<String> {
+    match value {
+        ScalarValue::Utf8(Some(v))
+        | ScalarValue::Utf8View(Some(v))
+        | ScalarValue::LargeUtf8(Some(v)) => Some(v.clone()),
+        ScalarValue::Dictionary(_, v) => string_value(v),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use datafusion::prelude::{col, lit};
+    use influxdb3_catalog::catalog::TableDefinition;
+    use influxdb3_id::{ColumnId, ParquetFileId, TableId};
+    use schema::{InfluxColumnType, InfluxFieldType};
+
+    use crate::{ColumnStats, ParquetFile};
+
+    use super::ParquetFilePruner;
+
+    fn table_def() -> TableDefinition {
+        TableDefinition::new(
+            TableId::from(0),
+            "cpu".into(),
+            vec![
+                (ColumnId::from(0), "host".into(), InfluxColumnType::Tag),
+                (
+                    ColumnId::from(1),
+                    "usage".into(),
+                    InfluxColumnType::Field(InfluxFieldType::Float),
+                ),
+                (
+                    ColumnId::from(2),
+                    "time".into(),
+                    InfluxColumnType::Timestamp,
+                ),
+            ],
+            Some(vec![ColumnId::from(0)]),
+        )
+        .unwrap()
+    }
+
+    fn file(min_time: i64, max_time: i64, host_min: &str, host_max: &str) -> ParquetFile {
+        ParquetFile {
+            id: ParquetFileId::new(),
+            path: "file".into(),
+            size_bytes: 1,
+            row_count: 1,
+            chunk_time: min_time,
+            min_time,
+            max_time,
+            column_stats: BTreeMap::from([(
+                ColumnId::from(0),
+                ColumnStats {
+                    min: host_min.into(),
+                    max: host_max.into(),
+                },
+            )]),
+        }
+    }
+
+    fn ts(t: i64) -> datafusion::prelude::Expr {
+        lit(datafusion::scalar::ScalarValue::TimestampNanosecond(
+            Some(t),
+            None,
+        ))
+    }
+
+    #[test]
+    fn prune_files() {
+        let table_def = table_def();
+        let files = [
+            file(0, 99, "a", "c"),
+            file(100, 199, "d", "f"),
+            file(200, 299, "a", "z"),
+        ];
+        struct TestCase {
+            desc: &'static str,
+            filters: Vec<datafusion::prelude::Expr>,
+            expected_kept: Vec<usize>,
+        }
+        let test_cases = [
+            TestCase {
+                desc: "no filters",
+                filters: vec![],
+                expected_kept: vec![0, 1, 2],
+            },
+            TestCase {
+                desc: "time greater than",
+                filters: vec![col("time").gt(ts(150))],
+                expected_kept: vec![1, 2],
+            },
+            TestCase {
+                desc: "time range across conjunction",
+                filters: vec![col("time").gt_eq(ts(100)).and(col("time").lt(ts(200)))],
+                expected_kept: vec![1],
+            },
+            TestCase {
+                desc: "literal on left side",
+                filters: vec![ts(99).gt_eq(col("time"))],
+                expected_kept: vec![0],
+            },
+            TestCase {
+                desc: "between",
+                filters: vec![col("time").between(ts(50), ts(150))],
+                expected_kept: vec![0, 1],
+            },
+            TestCase {
+                desc: "tag equality",
+                filters: vec![col("host").eq(lit("b"))],
+                expected_kept: vec![0, 2],
+            },
+            TestCase {
+                desc: "tag in list",
+                filters: vec![col("host").in_list(vec![lit("e"), lit("y")], false)],
+                expected_kept: vec![1, 2],
+            },
+            TestCase {
+                desc: "tag and time",
+                filters: vec![col("host").eq(lit("b")), col("time").lt(ts(200))],
+                expected_kept: vec![0],
+            },
+            TestCase {
+                desc: "unsupported filters are ignored",
+                filters: vec![
+                    col("host").not_eq(lit("b")),
+                    col("usage").gt(lit(1.0)),
+                    col("time").gt(ts(50)).or(col("host").eq(lit("e"))),
+                ],
+                expected_kept: vec![0, 1, 2],
+            },
+        ];
+        for t in test_cases {
+            let pruner = ParquetFilePruner::new(&table_def, &t.filters);
+            let kept = files
+                .iter()
+                .enumerate()
+                .filter(|(_, f)| !pruner.should_prune(f))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            assert_eq!(t.expected_kept, kept, "test case failed: {}", t.desc);
+        }
+    }
+}
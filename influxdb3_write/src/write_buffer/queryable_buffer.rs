@@ -5,7 +5,10 @@ use crate::paths::ParquetFilePath;
 use crate::persister::Persister;
 use crate::write_buffer::persisted_files::PersistedFiles;
 use crate::write_buffer::table_buffer::TableBuffer;
-use crate::{ParquetFile, ParquetFileId, PersistedSnapshot};
+use crate::{ColumnStats, ParquetFile, ParquetFileId, PersistedSnapshot};
+use arrow::array::AsArray;
+use arrow::compute::{cast, max_string, min_string};
+use arrow::datatypes::DataType;
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
 use data_types::{
@@ -17,8 +20,9 @@ use datafusion::common::DataFusionError;
 use datafusion::logical_expr::Expr;
 use datafusion_util::stream_from_batches;
 use hashbrown::HashMap;
-use influxdb3_catalog::catalog::{Catalog, DatabaseSchema};
-use influxdb3_id::{DbId, TableId};
+use influxdb3_cache::meta_cache::MetaCacheProvider;
+use influxdb3_catalog::catalog::{Catalog, DatabaseSchema, TableDefinition};
+use influxdb3_id::{ColumnId, DbId, TableId};
 use influxdb3_wal::{CatalogOp, SnapshotDetails, WalContents, WalFileNotifier, WalOp, WriteBatch};
 use iox_query::chunk_statistics::{create_chunk_statistics, NoColumnRanges};
 use iox_query::exec::Executor;
@@ -29,8 +33,9 @@ use observability_deps::tracing::{error, info};
 use parking_lot::RwLock;
 use parquet::format::FileMetaData;
 use schema::sort::SortKey;
-use schema::Schema;
+use schema::{InfluxColumnType, Schema};
 use std::any::Any;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::oneshot;
@@ -41,6 +46,7 @@ pub struct QueryableBuffer {
     pub(crate) executor: Arc<Executor>,
     catalog: Arc<Catalog>,
     last_cache_provider: Arc<LastCacheProvider>,
+    meta_cache_provider: Arc<MetaCacheProvider>,
     persister: Arc<Persister>,
     persisted_files: Arc<PersistedFiles>,
     buffer: Arc<RwLock<BufferState>>,
@@ -56,6 +62,7 @@ impl QueryableBuffer {
         catalog: Arc<Catalog>,
         persister: Arc<Persister>,
         last_cache_provider: Arc<LastCacheProvider>,
+        meta_cache_provider: Arc<MetaCacheProvider>,
         persisted_files: Arc<PersistedFiles>,
         parquet_cache: Option<Arc<dyn ParquetCacheOracle>>,
     ) -> Self {
@@ -66,6 +73,7 @@ impl QueryableBuffer {
             executor,
             catalog,
             last_cache_provider,
+            meta_cache_provider,
             persister,
             persisted_files,
             buffer,
@@ -129,11 +137,17 @@ impl QueryableBuffer {
             .collect())
     }
 
-    /// Called when the wal has persisted a new file. Buffer the contents in memory and update the last cache so the data is queryable.
+    /// Called when the wal has persisted a new file. Buffer the contents in memory and update the
+    /// last and metadata caches so the data is queryable.
     fn buffer_contents(&self, write: WalContents) {
         self.last_cache_provider.write_wal_contents_to_cache(&write);
+        self.meta_cache_provider.write_wal_contents_to_cache(&write);
         let mut buffer = self.buffer.write();
-        buffer.buffer_ops(write.ops, &self.last_cache_provider);
+        buffer.buffer_ops(
+            write.ops,
+            &self.last_cache_provider,
+            &self.meta_cache_provider,
+        );
     }
 
     /// Called when the wal has written a new file and is attempting to snapshot. Kicks off persistence of
@@ -158,8 +172,8 @@ impl QueryableBuffer {
                     let table_def = db_schema
                         .table_definition_by_id(table_id)
                         .expect("table exists");
-                    let snapshot_chunks =
-                        table_buffer.snapshot(table_def, snapshot_details.end_time_marker);
+                    let snapshot_chunks = table_buffer
+                        .snapshot(Arc::clone(&table_def), snapshot_details.end_time_marker);
 
                     for chunk in snapshot_chunks {
                         let table_name =
@@ -168,6 +182,7 @@ impl QueryableBuffer {
                             database_id: *database_id,
                             table_id: *table_id,
                             table_name: Arc::clone(&table_name),
+                            table_def: Arc::clone(&table_def),
                             chunk_time: chunk.chunk_time,
                             path: ParquetFilePath::new(
                                 self.persister.host_identifier_prefix(),
@@ -178,7 +193,7 @@ impl QueryableBuffer {
                                 chunk.chunk_time,
                                 write.wal_file_number,
                             ),
-                            batch: chunk.record_batch,
+                            batches: vec![chunk.record_batch],
                             schema: chunk.schema,
                             timestamp_min_max: chunk.timestamp_min_max,
                             sort_key: table_buffer.sort_key.clone(),
@@ -191,7 +206,11 @@ impl QueryableBuffer {
 
             // we must buffer the ops after the snapshotting as this data should not be persisted
             // with this set of wal files
-            buffer.buffer_ops(write.ops, &self.last_cache_provider);
+            buffer.buffer_ops(
+                write.ops,
+                &self.last_cache_provider,
+                &self.meta_cache_provider,
+            );
 
             persisting_chunks
         };
@@ -256,7 +275,7 @@ impl QueryableBuffer {
                 let min_time = persist_job.timestamp_min_max.min;
                 let max_time = persist_job.timestamp_min_max.max;
 
-                let (size_bytes, meta, cache_notifier) = sort_dedupe_persist(
+                let (size_bytes, meta, column_stats, cache_notifier) = sort_dedupe_persist(
                     persist_job,
                     Arc::clone(&persister),
                     Arc::clone(&executor),
@@ -275,6 +294,7 @@ impl QueryableBuffer {
                         chunk_time,
                         min_time,
                         max_time,
+                        column_stats,
                     },
                 )
             }
@@ -367,7 +387,12 @@ impl BufferState {
         }
     }
 
-    pub fn buffer_ops(&mut self, ops: Vec<WalOp>, last_cache_provider: &LastCacheProvider) {
+    pub fn buffer_ops(
+        &mut self,
+        ops: Vec<WalOp>,
+        last_cache_provider: &LastCacheProvider,
+        meta_cache_provider: &MetaCacheProvider,
+    ) {
         for op in ops {
             match op {
                 WalOp::Write(write_batch) => self.add_write_batch(write_batch),
@@ -376,6 +401,11 @@ impl BufferState {
                         .apply_catalog_batch(&catalog_batch)
                         .expect("catalog batch should apply");
 
+                    // tokens are not scoped to a database, and have nothing buffered:
+                    if catalog_batch.is_token_batch() {
+                        continue;
+                    }
+
                     let db_schema = self
                         .catalog
                         .db_schema_by_id(&catalog_batch.database_id)
@@ -401,9 +431,47 @@ impl BufferState {
                                     &cache.name,
                                 );
                             }
+                            CatalogOp::CreateMetaCache(definition) => {
+                                let table_def = db_schema
+                                    .table_definition_by_id(&definition.table_id)
+                                    .expect("table should exist");
+                                meta_cache_provider.create_from_definition(
+                                    db_schema.id,
+                                    table_def,
+                                    &definition,
+                                );
+                            }
+                            CatalogOp::DeleteMetaCache(cache) => {
+                                // we can ignore it if this doesn't exist for any reason
+                                let _ = meta_cache_provider.delete_cache(
+                                    &db_schema.id,
+                                    &cache.table_id,
+                                    &cache.cache_name,
+                                );
+                            }
                             CatalogOp::AddFields(_) => (),
                             CatalogOp::CreateTable(_) => (),
                             CatalogOp::CreateDatabase(_) => (),
+                            CatalogOp::SetRetentionPeriod(_) => (),
+                            CatalogOp::SetSchemaLocked(_) => (),
+                            CatalogOp::CreateToken(_) => (),
+                            CatalogOp::DeleteToken(_) => (),
+                            CatalogOp::DeleteDatabase(_) => {
+                                // the database's persisted files are removed separately, see
+                                // `WriteBufferImpl::remove_expired_and_deleted_files`:
+                                self.db_to_table.remove(&db_schema.id);
+                                last_cache_provider.delete_caches_for_db(db_schema.id);
+                                meta_cache_provider.delete_caches_for_db(&db_schema.id);
+                            }
+                            CatalogOp::DeleteTable(definition) => {
+                                if let Some(tables) = self.db_to_table.get_mut(&db_schema.id) {
+                                    tables.remove(&definition.table_id);
+                                }
+                                last_cache_provider
+                                    .delete_caches_for_table(db_schema.id, definition.table_id);
+                                meta_cache_provider
+                                    .delete_caches_for_table(&db_schema.id, &definition.table_id);
+                            }
                         }
                     }
                 }
@@ -443,27 +511,35 @@ impl BufferState {
 }
 
 #[derive(Debug)]
-struct PersistJob {
-    database_id: DbId,
-    table_id: TableId,
-    table_name: Arc<str>,
-    chunk_time: i64,
-    path: ParquetFilePath,
-    batch: RecordBatch,
-    schema: Schema,
-    timestamp_min_max: TimestampMinMax,
-    sort_key: SortKey,
+pub(crate) struct PersistJob {
+    pub(crate) database_id: DbId,
+    pub(crate) table_id: TableId,
+    pub(crate) table_name: Arc<str>,
+    pub(crate) table_def: Arc<TableDefinition>,
+    pub(crate) chunk_time: i64,
+    pub(crate) path: ParquetFilePath,
+    /// The data to persist, all with the same `schema`. Where rows in different batches have the
+    /// same primary key, the row from the later batch is kept.
+    pub(crate) batches: Vec<RecordBatch>,
+    pub(crate) schema: Schema,
+    pub(crate) timestamp_min_max: TimestampMinMax,
+    pub(crate) sort_key: SortKey,
 }
 
-async fn sort_dedupe_persist(
+pub(crate) async fn sort_dedupe_persist(
     persist_job: PersistJob,
     persister: Arc<Persister>,
     executor: Arc<Executor>,
     parquet_cache: Option<Arc<dyn ParquetCacheOracle>>,
-) -> (u64, FileMetaData, Option<oneshot::Receiver<()>>) {
+) -> (
+    u64,
+    FileMetaData,
+    BTreeMap<ColumnId, ColumnStats>,
+    Option<oneshot::Receiver<()>>,
+) {
     // Dedupe and sort using the COMPACT query built into
     // iox_query
-    let row_count = persist_job.batch.num_rows();
+    let row_count: usize = persist_job.batches.iter().map(|b| b.num_rows()).sum();
     info!(
         "Persisting {} rows for db id {} and table id {} and chunk {} to file {}",
         row_count,
@@ -473,28 +549,35 @@ async fn sort_dedupe_persist(
         persist_job.path.to_string()
     );
 
-    let chunk_stats = create_chunk_statistics(
-        Some(row_count),
-        &persist_job.schema,
-        Some(persist_job.timestamp_min_max),
-        &NoColumnRanges,
-    );
+    let chunks: Vec<Arc<dyn QueryChunk>> = persist_job
+        .batches
+        .into_iter()
+        .enumerate()
+        .map(|(i, batch)| {
+            let chunk_stats = create_chunk_statistics(
+                Some(batch.num_rows()),
+                &persist_job.schema,
+                Some(persist_job.timestamp_min_max),
+                &NoColumnRanges,
+            );
 
-    let chunks: Vec<Arc<dyn QueryChunk>> = vec![Arc::new(BufferChunk {
-        batches: vec![persist_job.batch],
-        schema: persist_job.schema.clone(),
-        stats: Arc::new(chunk_stats),
-        partition_id: TransitionPartitionId::from_parts(
-            PartitionId::new(0),
-            Some(PartitionHashId::new(
-                data_types::TableId::new(0),
-                &PartitionKey::from(format!("{}", persist_job.chunk_time)),
-            )),
-        ),
-        sort_key: Some(persist_job.sort_key.clone()),
-        id: ChunkId::new(),
-        chunk_order: ChunkOrder::new(1),
-    })];
+            Arc::new(BufferChunk {
+                batches: vec![batch],
+                schema: persist_job.schema.clone(),
+                stats: Arc::new(chunk_stats),
+                partition_id: TransitionPartitionId::from_parts(
+                    PartitionId::new(0),
+                    Some(PartitionHashId::new(
+                        data_types::TableId::new(0),
+                        &PartitionKey::from(format!("{}", persist_job.chunk_time)),
+                    )),
+                ),
+                sort_key: Some(persist_job.sort_key.clone()),
+                id: ChunkId::new(),
+                chunk_order: ChunkOrder::new(i as i64 + 1),
+            }) as Arc<dyn QueryChunk>
+        })
+        .collect();
 
     let ctx = executor.new_context();
 
@@ -514,6 +597,8 @@ async fn sort_dedupe_persist(
     // Execute the plan and return compacted record batches
     let data = ctx.collect(physical_plan).await.unwrap();
 
+    let column_stats = tag_column_stats(&persist_job.table_def, &persist_job.schema, &data);
+
     // keep attempting to persist forever. If we can't reach the object store, we'll stop accepting
     // writes elsewhere in the system, so we need to keep trying to persist.
     loop {
@@ -529,9 +614,9 @@ async fn sort_dedupe_persist(
                     let (cache_request, cache_notify_rx) =
                         CacheRequest::create(Path::from(persist_job.path.to_string()));
                     pq.register(cache_request);
-                    return (size_bytes, meta, Some(cache_notify_rx));
+                    return (size_bytes, meta, column_stats, Some(cache_notify_rx));
                 } else {
-                    return (size_bytes, meta, None);
+                    return (size_bytes, meta, column_stats, None);
                 }
             }
             Err(e) => {
@@ -544,3 +629,80 @@ async fn sort_dedupe_persist(
         }
     }
 }
+
+/// Compute the min and max value of each tag column in the given record batches, so they can be
+/// recorded with the persisted file and used to prune it at query time
+fn tag_column_stats(
+    table_def: &TableDefinition,
+    schema: &Schema,
+    batches: &[RecordBatch],
+) -> BTreeMap<ColumnId, ColumnStats> {
+    let mut stats = TagColumnStats::new(table_def, schema);
+    for batch in batches {
+        stats.update(batch);
+    }
+    stats.finish()
+}
+
+/// Accumulates the min and max value of each tag column over a stream of record batches
+#[derive(Debug)]
+pub(crate) struct TagColumnStats {
+    columns: BTreeMap<ColumnId, (String, Option<ColumnStats>)>,
+}
+
+impl TagColumnStats {
+    pub(crate) fn new(table_def: &TableDefinition, schema: &Schema) -> Self {
+        let columns = schema
+            .iter()
+            .filter(|(col_type, _)| matches!(col_type, InfluxColumnType::Tag))
+            .filter_map(|(_, field)| {
+                table_def
+                    .column_name_to_id(field.name().as_str())
+                    .map(|column_id| (column_id, (field.name().to_string(), None)))
+            })
+            .collect();
+        Self { columns }
+    }
+
+    pub(crate) fn update(&mut self, batch: &RecordBatch) {
+        self.columns.retain(|_, (name, stats)| {
+            let Some(column) = batch.column_by_name(name) else {
+                return true;
+            };
+            // stats that do not cover every batch could be used to wrongly prune the file, so
+            // none are kept for the column if any batch cannot be summarized:
+            let Ok(values) = cast(column, &DataType::Utf8) else {
+                return false;
+            };
+            let values = values.as_string::<i32>();
+            let (Some(batch_min), Some(batch_max)) = (min_string(values), max_string(values))
+            else {
+                return true;
+            };
+            match stats {
+                Some(stats) => {
+                    if batch_min < stats.min.as_str() {
+                        stats.min = batch_min.to_string();
+                    }
+                    if batch_max > stats.max.as_str() {
+                        stats.max = batch_max.to_string();
+                    }
+                }
+                None => {
+                    *stats = Some(ColumnStats {
+                        min: batch_min.to_string(),
+                        max: batch_max.to_string(),
+                    })
+                }
+            }
+            true
+        });
+    }
+
+    pub(crate) fn finish(&mut self) -> BTreeMap<ColumnId, ColumnStats> {
+        std::mem::take(&mut self.columns)
+            .into_iter()
+            .filter_map(|(column_id, (_, stats))| stats.map(|stats| (column_id, stats)))
+            .collect()
+    }
+}
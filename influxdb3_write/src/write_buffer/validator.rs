@@ -240,6 +240,36 @@ impl WriteValidator<WithCatalog> {
 /// Type alias for storing new columns added by a write
 type ColumnTracker = Vec<(ColumnId, Arc<str>, InfluxColumnType)>;
 
+/// Produce the error for a line that would add new columns to a table whose schema is locked,
+/// either on the table itself or on its database
+fn schema_locked_error(
+    raw_line: &str,
+    line_number: usize,
+    db_schema: &DatabaseSchema,
+    table_def: &TableDefinition,
+    columns: &ColumnTracker,
+) -> WriteLineError {
+    let locked = if table_def.schema_locked {
+        format!("table {}", table_def.table_name)
+    } else {
+        format!("database {}", db_schema.name)
+    };
+    WriteLineError {
+        original_line: raw_line.to_string(),
+        line_number: line_number + 1,
+        error_message: format!(
+            "write would add new columns [{columns}] to table {table_name}, but the schema of \
+            {locked} is locked",
+            columns = columns
+                .iter()
+                .map(|(_, name, _)| name.as_ref())
+                .collect::<Vec<&str>>()
+                .join(", "),
+            table_name = table_def.table_name,
+        ),
+    }
+}
+
 /// Validate an individual line of v3 line protocol and update the database
 /// schema
 ///
@@ -262,6 +292,20 @@ fn validate_and_qualify_v3_line(
     let mut fields = Vec::with_capacity(line.column_count());
     let mut index_count = 0;
     let mut field_count = 0;
+    let timestamp_ns = line
+        .timestamp
+        .map(|ts| apply_precision_to_timestamp(precision, ts))
+        .unwrap_or(ingest_time.timestamp_nanos());
+    if db_schema
+        .retention_cutoff_ns(ingest_time.timestamp_nanos())
+        .is_some_and(|cutoff_ns| timestamp_ns < cutoff_ns)
+    {
+        return Err(WriteLineError {
+            original_line: raw_line.to_string(),
+            line_number: line_number + 1,
+            error_message: "line timestamp is older than the database retention period".to_string(),
+        });
+    }
     let qualified = if let Some(table_def) = db_schema.table_definition(table_name) {
         let table_id = table_def.table_id;
         if !table_def.is_v3() {
@@ -374,12 +418,18 @@ fn validate_and_qualify_v3_line(
                 ));
                 col_id
             });
-        let timestamp_ns = line
-            .timestamp
-            .map(|ts| apply_precision_to_timestamp(precision, ts))
-            .unwrap_or(ingest_time.timestamp_nanos());
         fields.push(Field::new(time_col_id, FieldData::Timestamp(timestamp_ns)));
 
+        if !columns.is_empty() && (db_schema.schema_locked || table_def.schema_locked) {
+            return Err(schema_locked_error(
+                raw_line,
+                line_number,
+                db_schema,
+                &table_def,
+                &columns,
+            ));
+        }
+
         // if we have new columns defined, add them to the db_schema table so that subsequent lines
         // won't try to add the same definitions. Collect these additions into a catalog op, which
         // will be applied to the catalog with any other ops after all lines in the write request
@@ -426,6 +476,17 @@ fn validate_and_qualify_v3_line(
             field_count,
         }
     } else {
+        if db_schema.schema_locked {
+            return Err(WriteLineError {
+                original_line: raw_line.to_string(),
+                line_number: line_number + 1,
+                error_message: format!(
+                    "write would create new table {table_name}, but the schema of database \
+                    {db_name} is locked",
+                    db_name = db_schema.name,
+                ),
+            });
+        }
         let table_id = TableId::new();
         let mut columns = Vec::new();
         let mut key = Vec::new();
@@ -455,10 +516,6 @@ fn validate_and_qualify_v3_line(
             Arc::from(TIME_COLUMN_NAME),
             InfluxColumnType::Timestamp,
         ));
-        let timestamp_ns = line
-            .timestamp
-            .map(|ts| apply_precision_to_timestamp(precision, ts))
-            .unwrap_or(ingest_time.timestamp_nanos());
         fields.push(Field::new(time_col_id, FieldData::Timestamp(timestamp_ns)));
 
         let table_name = table_name.into();
@@ -529,6 +586,20 @@ fn validate_and_qualify_v1_line(
     let mut fields = Vec::with_capacity(line.column_count());
     let mut index_count = 0;
     let mut field_count = 0;
+    let timestamp_ns = line
+        .timestamp
+        .map(|ts| apply_precision_to_timestamp(precision, ts))
+        .unwrap_or(ingest_time.timestamp_nanos());
+    if db_schema
+        .retention_cutoff_ns(ingest_time.timestamp_nanos())
+        .is_some_and(|cutoff_ns| timestamp_ns < cutoff_ns)
+    {
+        return Err(WriteLineError {
+            original_line: line.to_string(),
+            line_number: line_number + 1,
+            error_message: "line timestamp is older than the database retention period".to_string(),
+        });
+    }
     let qualified = if let Some(table_def) = db_schema.table_definition(table_name) {
         if table_def.is_v3() {
             return Err(WriteLineError {
@@ -594,12 +665,18 @@ fn validate_and_qualify_v1_line(
                 ));
                 col_id
             });
-        let timestamp_ns = line
-            .timestamp
-            .map(|ts| apply_precision_to_timestamp(precision, ts))
-            .unwrap_or(ingest_time.timestamp_nanos());
         fields.push(Field::new(time_col_id, FieldData::Timestamp(timestamp_ns)));
 
+        if !columns.is_empty() && (db_schema.schema_locked || table_def.schema_locked) {
+            return Err(schema_locked_error(
+                &line.to_string(),
+                line_number,
+                db_schema,
+                &table_def,
+                &columns,
+            ));
+        }
+
         // if we have new columns defined, add them to the db_schema table so that subsequent lines
         // won't try to add the same definitions. Collect these additions into a catalog op, which
         // will be applied to the catalog with any other ops after all lines in the write request
@@ -650,6 +727,17 @@ fn validate_and_qualify_v1_line(
             field_count,
         }
     } else {
+        if db_schema.schema_locked {
+            return Err(WriteLineError {
+                original_line: line.to_string(),
+                line_number: line_number + 1,
+                error_message: format!(
+                    "write would create new table {table_name}, but the schema of database \
+                    {db_name} is locked",
+                    db_name = db_schema.name,
+                ),
+            });
+        }
         let table_id = TableId::new();
         // This is a new table, so build up its columns:
         let mut columns = Vec::new();
@@ -678,10 +766,6 @@ fn validate_and_qualify_v1_line(
             Arc::from(TIME_COLUMN_NAME),
             InfluxColumnType::Timestamp,
         ));
-        let timestamp_ns = line
-            .timestamp
-            .map(|ts| apply_precision_to_timestamp(precision, ts))
-            .unwrap_or(ingest_time.timestamp_nanos());
         fields.push(Field::new(time_col_id, FieldData::Timestamp(timestamp_ns)));
 
         let table_name = table_name.into();
@@ -828,7 +912,7 @@ mod tests {
     use data_types::NamespaceName;
     use influxdb3_catalog::catalog::Catalog;
     use influxdb3_id::TableId;
-    use influxdb3_wal::Gen1Duration;
+    use influxdb3_wal::{create, Gen1Duration};
     use iox_time::Time;
 
     #[test]
@@ -895,4 +979,169 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn write_validator_rejects_lines_outside_retention_period() -> Result<(), Error> {
+        let host_id = Arc::from("sample-host-id");
+        let instance_id = Arc::from("sample-instance-id");
+        let namespace = NamespaceName::new("test").unwrap();
+        let catalog = Arc::new(Catalog::new(host_id, instance_id));
+        let db_id = catalog.db_or_create("test")?.id;
+        let catalog_batch = create::catalog_batch_op(
+            db_id,
+            "test",
+            0,
+            [create::set_retention_period_op(db_id, "test", Some(10))],
+        );
+        catalog.apply_catalog_batch(catalog_batch.as_catalog().unwrap())?;
+
+        // with an ingest time of 20s, lines older than 10s are rejected:
+        let result = WriteValidator::initialize(namespace.clone(), Arc::clone(&catalog), 0)?
+            .v1_parse_lines_and_update_schema(
+                "cpu,tag1=foo val1=1 5000000000\n\
+                cpu,tag1=foo val1=2 15000000000",
+                true,
+                Time::from_timestamp_nanos(20_000_000_000),
+                Precision::Nanosecond,
+            )?
+            .convert_lines_to_buffer(Gen1Duration::new_5m());
+
+        assert_eq!(result.line_count, 1);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].line_number, 1);
+        assert_eq!(
+            result.errors[0].error_message,
+            "line timestamp is older than the database retention period"
+        );
+
+        let result = WriteValidator::initialize(namespace, catalog, 0)?
+            .v3_parse_lines_and_update_schema(
+                "cpu_v3,tag1/foo val1=1 5000000000",
+                true,
+                Time::from_timestamp_nanos(20_000_000_000),
+                Precision::Nanosecond,
+            )?
+            .convert_lines_to_buffer(Gen1Duration::new_5m());
+
+        assert_eq!(result.line_count, 0);
+        assert_eq!(result.errors.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_validator_rejects_schema_changes_when_locked() -> Result<(), Error> {
+        let host_id = Arc::from("sample-host-id");
+        let instance_id = Arc::from("sample-instance-id");
+        let namespace = NamespaceName::new("test").unwrap();
+        let catalog = Arc::new(Catalog::new(host_id, instance_id));
+        WriteValidator::initialize(namespace.clone(), Arc::clone(&catalog), 0)?
+            .v1_parse_lines_and_update_schema(
+                "cpu,tag1=foo val1=1 1",
+                false,
+                Time::from_timestamp_nanos(0),
+                Precision::Nanosecond,
+            )?;
+        let db_schema = catalog.db_schema("test").unwrap();
+        let cpu_id = db_schema.table_name_to_id("cpu").unwrap();
+        let catalog_batch = create::catalog_batch_op(
+            db_schema.id,
+            "test",
+            0,
+            [create::set_schema_locked_op(
+                db_schema.id,
+                "test",
+                Some((cpu_id, "cpu")),
+                true,
+            )],
+        );
+        catalog.apply_catalog_batch(catalog_batch.as_catalog().unwrap())?;
+
+        // lines that add a tag or field to the locked table are rejected, but new tables can
+        // still be added to the database:
+        let result = WriteValidator::initialize(namespace.clone(), Arc::clone(&catalog), 0)?
+            .v1_parse_lines_and_update_schema(
+                "cpu,tag1=foo val1=2 2\n\
+                cpu,tag1=foo,tag2=bar val1=3 3\n\
+                cpu,tag1=foo val2=4 4\n\
+                mem,tag1=foo val1=5 5",
+                true,
+                Time::from_timestamp_nanos(0),
+                Precision::Nanosecond,
+            )?
+            .convert_lines_to_buffer(Gen1Duration::new_5m());
+        assert_eq!(result.line_count, 2);
+        assert_eq!(result.errors.len(), 2);
+        assert_eq!(result.errors[0].line_number, 2);
+        assert_eq!(
+            result.errors[0].error_message,
+            "write would add new columns [tag2] to table cpu, but the schema of table cpu is locked"
+        );
+        assert_eq!(result.errors[1].line_number, 3);
+
+        // without accept_partial, the whole batch is rejected:
+        assert!(matches!(
+            WriteValidator::initialize(namespace.clone(), Arc::clone(&catalog), 0)?
+                .v1_parse_lines_and_update_schema(
+                    "cpu,tag1=foo val1=2 2\n\
+                    cpu,tag1=foo val2=4 4",
+                    false,
+                    Time::from_timestamp_nanos(0),
+                    Precision::Nanosecond,
+                ),
+            Err(Error::ParseError(_))
+        ));
+
+        // locking the database prevents new tables, and new columns on any table:
+        let catalog_batch = create::catalog_batch_op(
+            db_schema.id,
+            "test",
+            0,
+            [create::set_schema_locked_op(
+                db_schema.id,
+                "test",
+                None,
+                true,
+            )],
+        );
+        catalog.apply_catalog_batch(catalog_batch.as_catalog().unwrap())?;
+        let result = WriteValidator::initialize(namespace.clone(), Arc::clone(&catalog), 0)?
+            .v1_parse_lines_and_update_schema(
+                "mem,tag1=foo val1=6 6\n\
+                mem,tag1=foo val2=7 7\n\
+                disk,tag1=foo val1=8 8",
+                true,
+                Time::from_timestamp_nanos(0),
+                Precision::Nanosecond,
+            )?
+            .convert_lines_to_buffer(Gen1Duration::new_5m());
+        assert_eq!(result.line_count, 1);
+        assert_eq!(result.errors.len(), 2);
+        assert_eq!(
+            result.errors[0].error_message,
+            "write would add new columns [val2] to table mem, but the schema of database test is \
+            locked"
+        );
+        assert_eq!(
+            result.errors[1].error_message,
+            "write would create new table disk, but the schema of database test is locked"
+        );
+        let result = WriteValidator::initialize(namespace, Arc::clone(&catalog), 0)?
+            .v3_parse_lines_and_update_schema(
+                "cpu_v3,tag1/foo val1=1 9",
+                true,
+                Time::from_timestamp_nanos(0),
+                Precision::Nanosecond,
+            )?
+            .convert_lines_to_buffer(Gen1Duration::new_5m());
+        assert_eq!(result.line_count, 0);
+        assert_eq!(result.errors.len(), 1);
+        assert!(catalog
+            .db_schema("test")
+            .unwrap()
+            .table_definition("disk")
+            .is_none());
+
+        Ok(())
+    }
 }